@@ -1,30 +1,348 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use crossbeam_channel::{Receiver, Sender, TryRecvError, bounded, unbounded};
 use egui::{Color32, ComboBox};
 
-use crate::audio::{self, AudioDevice, AudioPlayback};
+use crate::audio::{self, AudioDevice, AudioPlayback, PlaybackOptions};
+use crate::clock::{self, ClockMode, Corner, Stopwatch};
+use crate::keybind::{self, ACTIONS, Action};
+use crate::latency::{LatencyProbe, Marker};
 use crate::platform;
-use crate::types::{DeviceInfo, VideoFormat, VideoFrame};
+use crate::recording::Recorder;
+use crate::render::{PipLayout, SurfaceDiagnostics};
+use crate::replay::{self, ReplayBuffer};
+use crate::scope::Histogram;
+use crate::screenshot;
+use crate::settings::{self, Settings};
+use crate::signal::SignalWatch;
+use crate::stats_server::{StatsReport, StatsServer};
+use crate::types::{
+    ColorInfo, ColorMatrix, ColorRange, ColorTransfer, Deinterlace, DeviceInfo, FramePolicy,
+    VideoFormat, VideoFrame,
+};
+use crate::view::{
+    AspectMode, CropRect, CrtMask, CrtSettings, ImageAdjust, ModeMatch, Rotation, ScaleFilter,
+    ScaleMode,
+};
 
 pub struct App {
     video_devices: Vec<DeviceInfo>,
+    network_sources: Vec<DeviceInfo>,
+    network_url: String,
+    network_prompt: bool,
     audio_devices: Vec<AudioDevice>,
+    audio_outputs: Vec<AudioDevice>,
     selected_video: Option<usize>,
+    frame_rates: Vec<u32>,
+    frame_rate: Option<u32>,
+    video_controls: Vec<platform::VideoControl>,
     selected_audio: Option<usize>,
+    // None plays through the system default output.
+    selected_output: Option<usize>,
     video_capture: Option<platform::VideoCapture>,
+    // Second device drawn over a corner; its frames skip replay, stats and
+    // recording.
+    pip_capture: Option<platform::VideoCapture>,
+    pip_start: Option<VideoStart>,
+    // Held by id since a rescan can shift the device list.
+    pip_id: Option<String>,
+    pip_corner: Corner,
+    pip_size: f32,
     audio_playback: Option<AudioPlayback>,
-    last_error: Option<String>,
+    // Displayed per-channel peak, decaying between readings.
+    audio_levels: Vec<f32>,
+    audio_muted: bool,
+    audio_volume: f32,
+    video_paused: bool,
+    replay: ReplayBuffer,
+    // Buffered frame shown while paused; None is the live (last) frame.
+    replay_pos: Option<usize>,
+    replay_pending: bool,
+    errors: ErrorLog,
+    settings: Settings,
     mouse_y: f32,
     last_refresh: Instant,
+    // Set while a scan runs on the worker thread; no second one starts then.
+    device_scan: Option<Receiver<DeviceScan>>,
+    video_start: Option<VideoStart>,
+    // --video/--audio queries, matched once the first scan is in.
+    launch_devices: Option<(Option<String>, Option<String>)>,
+    // Hotplug signals; the periodic refresh stays as a fallback.
+    device_watch: Option<Receiver<()>>,
+    // Id of a video device that went away mid-capture and is being reopened.
+    reconnect_id: Option<String>,
+    reconnect_at: Instant,
+    post_shader: Option<PathBuf>,
+    effect: Effect,
+    crt: CrtSettings,
     show_stats: bool,
+    show_guides: bool,
+    show_scope: bool,
+    scope: Option<Histogram>,
+    // On-screen video rectangle in physical pixels, from the renderer.
+    video_rect: [f32; 4],
+    // Whether the renderer has shown any frame yet.
+    has_frame: bool,
+    signal_watch: SignalWatch,
+    // The capture is open but frames stopped coming.
+    no_signal: bool,
+    no_signal_timeout: f32,
+    blank_on_no_signal: bool,
     stats: StatsState,
+    // Per-device overrides for what the source signals; None follows the frame.
+    color_matrix_override: Option<ColorMatrix>,
+    color_range_override: Option<ColorRange>,
+    clock_mode: ClockMode,
+    clock_corner: Corner,
+    clock_size: f32,
+    stopwatch: Stopwatch,
+    stats_server: Option<StatsServer>,
+    latency_probe: LatencyProbe,
+    recorder: Recorder,
     target_capture_size: Option<(u32, u32)>,
-    disable_aspect_correction: bool,
+    resolution_preset: ResolutionPreset,
+    video_modes: Vec<platform::VideoInfo>,
+    video_mode: Option<platform::VideoInfo>,
+    aspect_mode: AspectMode,
+    mode_match: ModeMatch,
+    clamp_limited_range: bool,
+    // None follows the surface format.
+    srgb_output: Option<bool>,
+    // Read when the renderer is created, so changes apply on restart.
+    wide_gamut: bool,
+    output_gamma: f32,
+    image_adjust: ImageAdjust,
+    zebra: bool,
+    zebra_threshold: f32,
+    dither: bool,
+    sharpen: f32,
+    scale_mode: ScaleMode,
+    scale_filter: ScaleFilter,
+    rotation: Rotation,
+    deinterlace: Deinterlace,
+    frame_policy: FramePolicy,
+    crop: CropRect,
+    crop_adjust: bool,
+    crop_edge: CropEdge,
+    frame_latency: u32,
+    vsync: bool,
+    // Most redraws per second; 0 leaves it to the capture and the display.
+    fps_cap: u32,
+    playback_options: PlaybackOptions,
+    surface: Option<SurfaceDiagnostics>,
     fullscreen: bool,
     fullscreen_request: Option<bool>,
+    monitors: Vec<String>,
+    fullscreen_monitor: Option<String>,
+    always_on_top: bool,
+    always_on_top_request: Option<bool>,
+    compact: bool,
+    compact_request: Option<bool>,
+    fit_window_request: bool,
+    // Fit the window to each capture as it starts.
+    auto_fit: bool,
     keep_awake: bool,
+    background: [u8; 3],
     keep_awake_request: Option<bool>,
+    // Wakes the event loop when a capture has a new frame.
+    frame_waker: Option<platform::FrameWaker>,
+    // Minimized or fully covered; the captures idle until it's visible.
+    window_hidden: bool,
+    // Action whose binding is taken from the next key press.
+    rebinding: Option<Action>,
+    title_request: Option<String>,
+    screenshot_request: bool,
+    screenshot_tx: Sender<Result<PathBuf>>,
+    screenshot_rx: Receiver<Result<PathBuf>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorSource {
+    Video,
+    Audio,
+    Render,
+    StatsServer,
+    Screenshot,
+    Recording,
+    Scope,
+    Shader,
+    Pip,
+    Settings,
+}
+
+const ERROR_SOURCES: [ErrorSource; 10] = [
+    ErrorSource::Video,
+    ErrorSource::Audio,
+    ErrorSource::Render,
+    ErrorSource::StatsServer,
+    ErrorSource::Screenshot,
+    ErrorSource::Recording,
+    ErrorSource::Scope,
+    ErrorSource::Shader,
+    ErrorSource::Pip,
+    ErrorSource::Settings,
+];
+
+const ERROR_HISTORY_LEN: usize = 20;
+
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+const RESOLUTION_PRESETS: [ResolutionPreset; 5] = [
+    ResolutionPreset::Auto,
+    ResolutionPreset::Uhd,
+    ResolutionPreset::Qhd,
+    ResolutionPreset::FullHd,
+    ResolutionPreset::Hd,
+];
+
+const ASPECT_MODES: [AspectMode; 4] = [
+    AspectMode::Auto,
+    AspectMode::Force4x3,
+    AspectMode::Force16x9,
+    AspectMode::Stretch,
+];
+
+const COLOR_MATRICES: [ColorMatrix; 3] =
+    [ColorMatrix::Bt601, ColorMatrix::Bt709, ColorMatrix::Bt2020];
+
+const COLOR_RANGES: [ColorRange; 2] = [ColorRange::Limited, ColorRange::Full];
+
+const CORNERS: [Corner; 4] = [
+    Corner::TopLeft,
+    Corner::TopRight,
+    Corner::BottomLeft,
+    Corner::BottomRight,
+];
+
+// Devices and window state requested on the command line.
+#[derive(Debug, Default, PartialEq)]
+pub struct LaunchOptions {
+    pub video: Option<String>,
+    pub audio: Option<String>,
+    pub fullscreen: bool,
+    // How long to run a headless capture benchmark for instead of the UI.
+    pub bench: Option<Duration>,
+    // WGSL post-process shader, overriding the one in the settings.
+    pub shader: Option<PathBuf>,
+}
+
+// Device lists from one background enumeration. The audio lists are only
+// rescanned while empty.
+struct DeviceScan {
+    video: Result<Vec<DeviceInfo>>,
+    audio: Option<Result<Vec<AudioDevice>>>,
+    outputs: Option<Result<Vec<AudioDevice>>>,
+}
+
+// A capture being opened on a worker thread; poll_video_start installs it.
+struct VideoStart {
+    rx: Receiver<Result<platform::VideoCapture>>,
+    id: String,
+    // Reopening a lost device, which keeps retrying if this attempt fails.
+    reconnect: bool,
+    // Set once the start is replaced, so a worker still waiting its turn
+    // doesn't open the device at all.
+    cancel: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl VideoStart {
+    // Hands back the thread so the start replacing this one can wait for
+    // it to let go of the device.
+    fn replace(self) -> JoinHandle<()> {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.thread
+    }
+}
+
+// An exact id, or else the first name containing `query` ignoring case.
+pub fn find_device<'a>(
+    mut infos: impl Iterator<Item = &'a DeviceInfo> + Clone,
+    query: &str,
+) -> Option<usize> {
+    let lower = query.to_lowercase();
+    infos
+        .clone()
+        .position(|d| d.id == query)
+        .or_else(|| infos.position(|d| d.name.to_lowercase().contains(&lower)))
+}
+
+struct ErrorLog {
+    started: Instant,
+    current: [Option<String>; ERROR_SOURCES.len()],
+    history: VecDeque<(Duration, ErrorSource, String)>,
+}
+
+impl ErrorLog {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            current: Default::default(),
+            history: VecDeque::new(),
+        }
+    }
+
+    fn set(&mut self, source: ErrorSource, message: String) {
+        let slot = &mut self.current[source as usize];
+        // Repeated failures (e.g. a surface error every frame) only log once.
+        if slot.as_deref() == Some(message.as_str()) {
+            return;
+        }
+        if self.history.len() == ERROR_HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history
+            .push_back((self.started.elapsed(), source, message.clone()));
+        *slot = Some(message);
+    }
+
+    fn clear(&mut self, source: ErrorSource) {
+        self.current[source as usize] = None;
+    }
+
+    fn current(&self) -> impl Iterator<Item = (ErrorSource, &str)> {
+        ERROR_SOURCES
+            .into_iter()
+            .filter_map(|s| self.current[s as usize].as_deref().map(|m| (s, m)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CropEdge {
+    Left,
+    Top,
+    Right,
+    Bottom,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ResolutionPreset {
+    Auto,
+    Uhd,
+    Qhd,
+    FullHd,
+    Hd,
+}
+
+// Post-process pass over the finished picture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Effect {
+    None,
+    Crt,
+    // The WGSL file from --shader or the settings.
+    Custom,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LatencyPreset {
+    LowLatency,
+    Smooth,
 }
 
 struct StatsState {
@@ -35,6 +353,9 @@ struct StatsState {
     drops_per_s: f32,
     decode_us: u64,
     last_frame_format: Option<VideoFormat>,
+    last_frame_color: Option<ColorInfo>,
+    // Rolling average of frame age when handed to the renderer.
+    latency_ms: Option<f32>,
 }
 
 impl StatsState {
@@ -47,6 +368,8 @@ impl StatsState {
             drops_per_s: 0.0,
             decode_us: 0,
             last_frame_format: None,
+            last_frame_color: None,
+            latency_ms: None,
         }
     }
 
@@ -58,49 +381,225 @@ impl StatsState {
         self.drops_per_s = 0.0;
         self.decode_us = 0;
         self.last_frame_format = None;
+        self.last_frame_color = None;
+        self.latency_ms = None;
     }
 
     fn update_frame(&mut self, frame: &VideoFrame) {
         self.last_frame_format = Some(frame.format);
+        self.last_frame_color = Some(frame.color);
+        // Exponential average over roughly the last 30 frames.
+        let ms = frame.captured_at.elapsed().as_secs_f32() * 1000.0;
+        self.latency_ms = Some(self.latency_ms.map_or(ms, |avg| avg + (ms - avg) / 30.0));
     }
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
-        let mut last_error = None;
-        let video_devices = match platform::list_video_devices() {
-            Ok(v) => v,
-            Err(e) => {
-                last_error = Some(format!("Video: {e}"));
-                Vec::new()
-            }
-        };
-        let audio_devices = match audio::list_input_devices() {
-            Ok(v) => v,
-            Err(e) => {
-                last_error = Some(format!("Audio: {e}"));
-                Vec::new()
+    pub fn new(launch: LaunchOptions) -> Result<Self> {
+        let mut errors = ErrorLog::new();
+        let settings = Settings::load();
+        let vsync = settings.get_parsed("display", "vsync").unwrap_or(false);
+        let mode_match = [ModeMatch::Exact, ModeMatch::Nearest]
+            .into_iter()
+            .find(|&m| settings.get("display", "exclusive_mode") == Some(Self::mode_match_name(m)))
+            .unwrap_or(ModeMatch::Exact);
+        let always_on_top = settings
+            .get_parsed("window", "always_on_top")
+            .unwrap_or(false);
+        let auto_fit = settings.get_parsed("window", "auto_fit").unwrap_or(false);
+        let wide_gamut = settings
+            .get_parsed("display", "wide_gamut")
+            .unwrap_or(false);
+        let resample_quality = settings
+            .get_parsed::<u8>("audio", "resample_quality")
+            .map_or(0, |q| q.min(10));
+        let srgb_output = [Some(true), Some(false)]
+            .into_iter()
+            .find(|&s| settings.get("display", "srgb_output") == Some(Self::srgb_output_name(s)))
+            .flatten();
+        let fullscreen_monitor = settings
+            .get("window", "fullscreen_monitor")
+            .filter(|name| !name.is_empty())
+            .map(str::to_string);
+        let post_shader = launch.shader.or_else(|| {
+            settings
+                .get("display", "shader")
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+        });
+        let clock_mode = [ClockMode::Off, ClockMode::Clock, ClockMode::Stopwatch]
+            .into_iter()
+            .find(|&m| settings.get("clock", "mode") == Some(Self::clock_mode_name(m)))
+            .unwrap_or(ClockMode::Off);
+        let clock_corner = CORNERS
+            .into_iter()
+            .find(|&c| settings.get("clock", "corner") == Some(Self::corner_name(c)))
+            .unwrap_or(Corner::TopRight);
+        let clock_size = settings
+            .get_parsed("clock", "size")
+            .unwrap_or(24.0f32)
+            .clamp(12.0, 96.0);
+        let pip_corner = CORNERS
+            .into_iter()
+            .find(|&c| settings.get("pip", "corner") == Some(Self::corner_name(c)))
+            .unwrap_or(Corner::BottomRight);
+        let pip_size = settings
+            .get_parsed("pip", "size")
+            .unwrap_or(0.25f32)
+            .clamp(0.1, 0.5);
+        let no_signal_timeout = settings
+            .get_parsed("signal", "timeout")
+            .unwrap_or(2.0f32)
+            .clamp(0.5, 10.0);
+        let blank_on_no_signal = settings.get_parsed("signal", "blank").unwrap_or(false);
+        let fps_cap = settings
+            .get_parsed("display", "fps_cap")
+            .unwrap_or(0u32)
+            .min(240);
+        let frame_latency = settings
+            .get_parsed("display", "frame_latency")
+            .unwrap_or(1u32)
+            .clamp(1, 3);
+        let frame_policy = [FramePolicy::LowLatency, FramePolicy::Smooth]
+            .into_iter()
+            .find(|&p| settings.get("capture", "frame_policy") == Some(Self::frame_policy_name(p)))
+            .unwrap_or(FramePolicy::LowLatency);
+        // Off unless asked for: buffering copies every shown frame.
+        let replay_seconds = settings.get_parsed("replay", "seconds").unwrap_or(0.0f32);
+        let background = settings
+            .get("display", "background")
+            .and_then(settings::parse_color)
+            .unwrap_or([0, 0, 0]);
+        let stats_server = match StatsServer::from_env() {
+            Some(Ok(server)) => Some(server),
+            Some(Err(e)) => {
+                errors.set(ErrorSource::StatsServer, e.to_string());
+                None
             }
+            None => None,
         };
-        Ok(Self {
-            video_devices,
-            audio_devices,
+        let (screenshot_tx, screenshot_rx) = unbounded();
+        let mut app = Self {
+            video_devices: Vec::new(),
+            network_sources: Vec::new(),
+            network_url: String::new(),
+            network_prompt: false,
+            audio_devices: Vec::new(),
+            audio_outputs: Vec::new(),
             selected_video: None,
+            frame_rates: Vec::new(),
+            frame_rate: None,
+            video_controls: Vec::new(),
             selected_audio: None,
+            selected_output: None,
             video_capture: None,
+            pip_capture: None,
+            pip_start: None,
+            pip_id: None,
+            pip_corner,
+            pip_size,
             audio_playback: None,
-            last_error,
+            audio_levels: Vec::new(),
+            audio_muted: false,
+            audio_volume: 1.0,
+            video_paused: false,
+            replay: ReplayBuffer::new(
+                Duration::from_secs_f32(replay_seconds.clamp(0.0, 30.0)),
+                replay::DEFAULT_BUDGET,
+            ),
+            replay_pos: None,
+            replay_pending: false,
+            errors,
+            settings,
             mouse_y: 0.0,
             last_refresh: Instant::now(),
+            device_scan: None,
+            video_start: None,
+            launch_devices: Some((launch.video, launch.audio)),
+            device_watch: platform::watch_video_devices(),
+            reconnect_id: None,
+            reconnect_at: Instant::now(),
+            effect: if post_shader.is_some() {
+                Effect::Custom
+            } else {
+                Effect::None
+            },
+            post_shader,
+            crt: CrtSettings::default(),
             show_stats: false,
+            show_guides: false,
+            show_scope: false,
+            scope: None,
+            video_rect: [0.0; 4],
+            has_frame: false,
+            signal_watch: SignalWatch::new(Instant::now()),
+            no_signal: false,
+            no_signal_timeout,
+            blank_on_no_signal,
             stats: StatsState::new(),
+            clock_mode,
+            color_matrix_override: None,
+            color_range_override: None,
+            clock_corner,
+            clock_size,
+            stopwatch: Stopwatch::default(),
+            stats_server,
+            latency_probe: LatencyProbe::new(),
+            recorder: Recorder::new(),
             target_capture_size: None,
-            disable_aspect_correction: false,
+            resolution_preset: ResolutionPreset::Auto,
+            video_modes: Vec::new(),
+            video_mode: None,
+            aspect_mode: AspectMode::Auto,
+            mode_match,
+            clamp_limited_range: true,
+            srgb_output,
+            wide_gamut,
+            output_gamma: 1.0,
+            image_adjust: ImageAdjust::default(),
+            zebra: false,
+            zebra_threshold: 0.95,
+            dither: false,
+            sharpen: 0.0,
+            scale_mode: ScaleMode::Fit,
+            scale_filter: ScaleFilter::Linear,
+            rotation: Rotation::Deg0,
+            deinterlace: Deinterlace::Auto,
+            frame_policy,
+            crop: CropRect::default(),
+            crop_adjust: false,
+            crop_edge: CropEdge::Left,
+            frame_latency,
+            vsync,
+            fps_cap,
+            playback_options: PlaybackOptions {
+                resample_quality,
+                ..PlaybackOptions::default()
+            },
+            surface: None,
             fullscreen: false,
-            fullscreen_request: None,
+            fullscreen_request: launch.fullscreen.then_some(true),
+            monitors: Vec::new(),
+            fullscreen_monitor,
+            always_on_top,
+            always_on_top_request: always_on_top.then_some(true),
+            compact: false,
+            compact_request: None,
+            fit_window_request: false,
+            auto_fit,
             keep_awake: true,
+            background,
             keep_awake_request: Some(true),
-        })
+            frame_waker: None,
+            window_hidden: false,
+            rebinding: None,
+            title_request: None,
+            screenshot_request: false,
+            screenshot_tx,
+            screenshot_rx,
+        };
+        app.refresh_devices();
+        Ok(app)
     }
 
     pub fn set_mouse_y(&mut self, y: f32) {
@@ -111,216 +610,2451 @@ impl App {
         self.target_capture_size = size;
     }
 
-    pub fn aspect_correction_enabled(&self) -> bool {
-        !self.disable_aspect_correction
+    pub fn aspect_mode(&self) -> AspectMode {
+        self.aspect_mode
     }
 
-    pub fn is_fullscreen(&self) -> bool {
-        self.fullscreen
+    pub fn mode_match(&self) -> ModeMatch {
+        self.mode_match
     }
 
-    pub fn take_fullscreen_request(&mut self) -> Option<bool> {
-        self.fullscreen_request.take()
+    pub fn clamp_limited_range(&self) -> bool {
+        self.clamp_limited_range
     }
 
-    pub fn set_fullscreen_state(&mut self, fullscreen: bool) {
-        self.fullscreen = fullscreen;
+    pub fn wide_gamut(&self) -> bool {
+        self.wide_gamut
     }
 
-    pub fn take_keep_awake_request(&mut self) -> Option<bool> {
-        self.keep_awake_request.take()
+    pub fn srgb_output(&self) -> Option<bool> {
+        self.srgb_output
     }
 
-    pub fn capture_size(&self) -> Option<(u32, u32)> {
+    pub fn vsync(&self) -> bool {
+        self.vsync
+    }
+
+    pub fn background(&self) -> [u8; 3] {
+        self.background
+    }
+
+    pub fn output_gamma(&self) -> f32 {
+        self.output_gamma
+    }
+
+    // Where the preview goes; None while there's no PiP capture.
+    pub fn pip_layout(&self) -> Option<PipLayout> {
+        self.pip_capture.as_ref()?;
+        Some(PipLayout {
+            corner: self.pip_corner,
+            size: self.pip_size,
+        })
+    }
+
+    // Luma threshold for the zebra stripes, when they're on.
+    pub fn zebra(&self) -> Option<f32> {
+        self.zebra.then_some(self.zebra_threshold)
+    }
+
+    pub fn post_shader(&self) -> Option<&Path> {
+        self.post_shader
+            .as_deref()
+            .filter(|_| self.effect == Effect::Custom)
+    }
+
+    pub fn crt(&self) -> Option<CrtSettings> {
+        (self.effect == Effect::Crt).then_some(self.crt)
+    }
+
+    pub fn dither(&self) -> bool {
+        self.dither
+    }
+
+    pub fn image_adjust(&self) -> ImageAdjust {
+        self.image_adjust
+    }
+
+    pub fn scale_mode(&self) -> ScaleMode {
+        self.scale_mode
+    }
+
+    pub fn sharpen(&self) -> f32 {
+        self.sharpen
+    }
+
+    pub fn scale_filter(&self) -> ScaleFilter {
+        self.scale_filter
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    // Whether the renderer should deinterlace; the platform has already
+    // resolved the mode against what the source reports.
+    pub fn deinterlace_frames(&self) -> bool {
         self.video_capture
             .as_ref()
-            .map(|cap| (cap.info.width, cap.info.height))
+            .is_some_and(|c| c.info.deinterlace)
     }
 
-    pub fn take_latest_frame(&mut self) -> Option<VideoFrame> {
-        let cap = self.video_capture.as_ref()?;
-        let mut latest = None;
-        while let Ok(frame) = cap.rx.try_recv() {
-            latest = Some(frame);
-        }
-        if self.show_stats {
-            if let Some(frame) = latest.as_ref() {
-                self.stats.update_frame(frame);
+    pub fn crop(&self) -> CropRect {
+        self.crop
+    }
+
+    pub fn is_crop_adjust(&self) -> bool {
+        self.crop_adjust
+    }
+
+    pub fn toggle_crop_adjust(&mut self) {
+        self.crop_adjust = !self.crop_adjust;
+    }
+
+    pub fn cycle_crop_edge(&mut self) {
+        self.crop_edge = match self.crop_edge {
+            CropEdge::Left => CropEdge::Top,
+            CropEdge::Top => CropEdge::Right,
+            CropEdge::Right => CropEdge::Bottom,
+            CropEdge::Bottom => CropEdge::Left,
+        };
+    }
+
+    // dx/dy follow the arrow direction in source pixels; the active edge moves
+    // that way, so arrows along the other axis are ignored.
+    pub fn nudge_crop(&mut self, dx: i32, dy: i32) {
+        let Some((width, height)) = self.capture_size() else {
+            return;
+        };
+        let crop = &mut self.crop;
+        let (value, delta) = match self.crop_edge {
+            CropEdge::Left => (&mut crop.left, dx),
+            CropEdge::Right => (&mut crop.right, -dx),
+            CropEdge::Top => (&mut crop.top, dy),
+            CropEdge::Bottom => (&mut crop.bottom, -dy),
+        };
+        *value = value.saturating_add_signed(delta);
+        self.crop = self.crop.clamped(width, height);
+        self.save_device_pref("crop", settings::format_crop(self.crop));
+    }
+
+    // Crop insets in source pixels; the renderer maps the remaining region
+    // onto the whole quad and fits its aspect ratio.
+    fn crop_ui(&mut self, ui: &mut egui::Ui, width: u32, height: u32) {
+        let mut crop = self.crop;
+        let mut commit = false;
+        ui.horizontal(|ui| {
+            let max_x = width.saturating_sub(1);
+            let max_y = height.saturating_sub(1);
+            let responses = [
+                ui.add(egui::Slider::new(&mut crop.left, 0..=max_x).text("Left")),
+                ui.add(egui::Slider::new(&mut crop.right, 0..=max_x).text("Right")),
+                ui.add(egui::Slider::new(&mut crop.top, 0..=max_y).text("Top")),
+                ui.add(egui::Slider::new(&mut crop.bottom, 0..=max_y).text("Bottom")),
+            ];
+            commit = responses
+                .iter()
+                .any(|r| r.drag_stopped() || (r.changed() && !r.dragged()));
+            if ui.button("Reset").clicked() {
+                crop = CropRect::default();
+                commit = true;
             }
+        });
+        self.crop = crop.clamped(width, height);
+        if commit {
+            self.save_device_pref("crop", settings::format_crop(self.crop));
         }
-        latest
     }
 
-    pub fn ui(&mut self, ctx: &egui::Context) {
-        let ui_active =
-            egui::Popup::is_any_open(ctx) || ctx.is_pointer_over_area() || ctx.is_using_pointer();
-        let show_ui = self.video_capture.is_none() || self.mouse_y <= 32.0 || ui_active;
-        if show_ui {
-            egui::TopBottomPanel::top("selectors").show(ctx, |ui| {
-                ui.horizontal(|ui| {
-                    let mut vid = self.selected_video;
-                    let video_text = vid
-                        .and_then(|i| self.video_devices.get(i).map(|d| d.name.clone()))
-                        .unwrap_or_else(|| "Video: None".to_string());
-                    ComboBox::from_id_salt("video_select")
-                        .selected_text(video_text)
-                        .show_ui(ui, |ui| {
-                            ui.selectable_value(&mut vid, None, "None");
-                            for (i, dev) in self.video_devices.iter().enumerate() {
-                                ui.selectable_value(&mut vid, Some(i), &dev.name);
-                            }
-                        });
-                    if vid != self.selected_video {
-                        self.set_video(vid);
+    fn crt_ui(&mut self, ui: &mut egui::Ui) {
+        let crt = &mut self.crt;
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::Slider::new(&mut crt.scanlines, 0.0..=1.0)
+                    .text("Scanlines")
+                    .fixed_decimals(2),
+            );
+            ComboBox::from_id_salt("crt_mask")
+                .selected_text(format!("Mask: {}", Self::crt_mask_name(crt.mask)))
+                .show_ui(ui, |ui| {
+                    for m in [CrtMask::None, CrtMask::ApertureGrille, CrtMask::ShadowMask] {
+                        ui.selectable_value(&mut crt.mask, m, Self::crt_mask_name(m));
                     }
-                    let mut aud = self.selected_audio;
-                    let audio_text = aud
-                        .and_then(|i| self.audio_devices.get(i).map(|d| d.info.name.clone()))
-                        .unwrap_or_else(|| "Audio: None".to_string());
-                    ComboBox::from_id_salt("audio_select")
-                        .selected_text(audio_text)
-                        .show_ui(ui, |ui| {
-                            ui.selectable_value(&mut aud, None, "None");
-                            for (i, dev) in self.audio_devices.iter().enumerate() {
-                                ui.selectable_value(&mut aud, Some(i), &dev.info.name);
-                            }
-                        });
-                    if aud != self.selected_audio {
-                        self.set_audio_with_reinit(aud);
+                });
+            if crt.mask != CrtMask::None {
+                ui.add(
+                    egui::Slider::new(&mut crt.mask_strength, 0.0..=1.0)
+                        .text("Mask Strength")
+                        .fixed_decimals(2),
+                );
+            }
+            ui.add(
+                egui::Slider::new(&mut crt.bloom, 0.0..=1.0)
+                    .text("Bloom")
+                    .fixed_decimals(2),
+            );
+            ui.checkbox(&mut crt.curvature, "Curvature");
+        });
+    }
+
+    fn signal_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::Slider::new(&mut self.no_signal_timeout, 0.5..=10.0)
+                    .text("No signal after (s)")
+                    .fixed_decimals(1),
+            );
+            if response.changed() {
+                self.settings
+                    .set("signal", "timeout", self.no_signal_timeout);
+            }
+            if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                self.save_settings();
+            }
+            if ui
+                .checkbox(&mut self.blank_on_no_signal, "Blank on no signal")
+                .changed()
+            {
+                self.settings
+                    .set("signal", "blank", self.blank_on_no_signal);
+                self.save_settings();
+            }
+        });
+    }
+
+    fn pip_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let selected = self.pip_index();
+            let mut pip = selected;
+            let pip_text = pip
+                .and_then(|i| self.video_devices.get(i).map(|d| d.name.clone()))
+                .unwrap_or_else(|| "None".to_string());
+            ComboBox::from_id_salt("pip_select")
+                .selected_text(format!("Device: {pip_text}"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut pip, None, "None");
+                    for (i, dev) in self.video_devices.iter().enumerate() {
+                        // Most devices can only be opened once.
+                        if Some(i) != self.selected_video {
+                            ui.selectable_value(&mut pip, Some(i), &dev.name);
+                        }
                     }
-                    let mut show_stats = self.show_stats;
-                    if ui.checkbox(&mut show_stats, "Stats").changed() {
-                        self.show_stats = show_stats;
-                        self.apply_stats_enabled();
+                });
+            if pip != selected {
+                self.set_pip(pip);
+            }
+            let mut corner = self.pip_corner;
+            ComboBox::from_id_salt("pip_corner")
+                .selected_text(format!("Corner: {}", Self::corner_name(corner)))
+                .show_ui(ui, |ui| {
+                    for c in CORNERS {
+                        ui.selectable_value(&mut corner, c, Self::corner_name(c));
                     }
-                    let mut keep_awake = self.keep_awake;
-                    if ui.checkbox(&mut keep_awake, "Keep Awake").changed() {
-                        self.keep_awake = keep_awake;
-                        self.keep_awake_request = Some(keep_awake);
+                });
+            if corner != self.pip_corner {
+                self.pip_corner = corner;
+                self.settings
+                    .set("pip", "corner", Self::corner_name(corner));
+                self.save_settings();
+            }
+            let response = ui.add(
+                egui::Slider::new(&mut self.pip_size, 0.1..=0.5)
+                    .text("Size")
+                    .fixed_decimals(2),
+            );
+            if response.changed() {
+                self.settings.set("pip", "size", self.pip_size);
+            }
+            if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                self.save_settings();
+            }
+        });
+    }
+
+    fn hotkeys_ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("hotkeys_grid")
+            .num_columns(3)
+            .show(ui, |ui| {
+                for action in ACTIONS {
+                    ui.label(action.label());
+                    let text = if self.rebinding == Some(action) {
+                        "Press a key…".to_string()
+                    } else {
+                        self.binding(action).to_string()
+                    };
+                    let response = ui
+                        .button(text)
+                        .on_hover_text("Click, then press a key. Esc cancels.");
+                    if response.clicked() {
+                        // Keep Space or Enter from clicking the button again.
+                        response.surrender_focus();
+                        self.rebinding = Some(action);
                     }
-                    let mut disable_aspect = self.disable_aspect_correction;
+                    let default = action.default_binding();
                     if ui
-                        .checkbox(&mut disable_aspect, "Disable Aspect-correct Rendering")
-                        .changed()
+                        .add_enabled(
+                            self.binding(action) != default,
+                            egui::Button::new("Default"),
+                        )
+                        .clicked()
                     {
-                        self.disable_aspect_correction = disable_aspect;
+                        self.settings.set("hotkeys", action.setting(), default);
+                        self.save_settings();
                     }
-                    let button_text = if self.fullscreen {
-                        "Exit Fullscreen"
-                    } else {
-                        "Fullscreen"
-                    };
-                    if ui.button(button_text).clicked() {
-                        let next = !self.fullscreen;
-                        self.fullscreen_request = Some(next);
+                    ui.end_row();
+                }
+            });
+        let bindings: Vec<_> = ACTIONS.map(|action| (action, self.binding(action))).into();
+        for (first, second, binding) in keybind::conflicts(&bindings) {
+            ui.colored_label(
+                Color32::LIGHT_RED,
+                format!(
+                    "{binding} is bound to both {} and {}; only {} will run",
+                    first.label(),
+                    second.label(),
+                    first.label()
+                ),
+            );
+        }
+    }
+
+    fn clock_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut mode = self.clock_mode;
+            ComboBox::from_id_salt("clock_mode")
+                .selected_text(format!("Overlay: {}", Self::clock_mode_name(mode)))
+                .show_ui(ui, |ui| {
+                    for m in [ClockMode::Off, ClockMode::Clock, ClockMode::Stopwatch] {
+                        ui.selectable_value(&mut mode, m, Self::clock_mode_name(m));
                     }
                 });
-                if let Some(err) = &self.last_error {
-                    ui.colored_label(Color32::LIGHT_RED, err);
+            if mode != self.clock_mode {
+                self.clock_mode = mode;
+                self.settings
+                    .set("clock", "mode", Self::clock_mode_name(mode));
+                self.save_settings();
+            }
+            let mut corner = self.clock_corner;
+            ComboBox::from_id_salt("clock_corner")
+                .selected_text(format!("Corner: {}", Self::corner_name(corner)))
+                .show_ui(ui, |ui| {
+                    for c in CORNERS {
+                        ui.selectable_value(&mut corner, c, Self::corner_name(c));
+                    }
+                });
+            if corner != self.clock_corner {
+                self.clock_corner = corner;
+                self.settings
+                    .set("clock", "corner", Self::corner_name(corner));
+                self.save_settings();
+            }
+            let response = ui.add(
+                egui::Slider::new(&mut self.clock_size, 12.0..=96.0)
+                    .text("Size")
+                    .fixed_decimals(0),
+            );
+            if response.changed() {
+                self.settings.set("clock", "size", self.clock_size);
+            }
+            if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                self.save_settings();
+            }
+        });
+        if self.clock_mode == ClockMode::Stopwatch {
+            ui.horizontal(|ui| {
+                let running = self.stopwatch.is_running();
+                if ui.button(if running { "Stop" } else { "Start" }).clicked() {
+                    if running {
+                        self.stopwatch.stop();
+                    } else {
+                        self.stopwatch.start();
+                    }
+                }
+                if ui.button("Reset").clicked() {
+                    self.stopwatch.reset();
                 }
             });
         }
-        if self.show_stats {
-            self.update_stats();
-            if let Some(cap) = self.video_capture.as_ref() {
-                let queue_len = cap.rx.len();
-                let info = &cap.info;
-                let fps_text = info
-                    .fps
-                    .map(|v| v.to_string())
-                    .unwrap_or_else(|| "auto".to_string());
-                egui::Area::new("stats_overlay".into())
-                    .fixed_pos(egui::pos2(8.0, 40.0))
-                    .show(ctx, |ui| {
+    }
+
+    // Rule-of-thirds lines plus 90% and 95% safe-area boxes, fitted to the
+    // displayed picture rather than the window.
+    fn draw_guides(&self, ctx: &egui::Context) {
+        let [left, top, right, bottom] = self.video_rect.map(|v| v / ctx.pixels_per_point());
+        let rect = egui::Rect::from_min_max(egui::pos2(left, top), egui::pos2(right, bottom));
+        if !rect.is_positive() {
+            return;
+        }
+        let painter =
+            ctx.layer_painter(egui::LayerId::new(egui::Order::Background, "guides".into()));
+        let grid = egui::Stroke::new(1.0, Color32::from_white_alpha(110));
+        for t in [1.0 / 3.0, 2.0 / 3.0] {
+            let x = rect.left() + rect.width() * t;
+            let y = rect.top() + rect.height() * t;
+            painter.vline(x, rect.y_range(), grid);
+            painter.hline(rect.x_range(), y, grid);
+        }
+        for (fraction, color) in [
+            (0.95, Color32::from_rgb(80, 200, 255)),
+            (0.9, Color32::YELLOW),
+        ] {
+            let safe = rect.shrink2(rect.size() * (1.0 - fraction) / 2.0);
+            painter.rect_stroke(
+                safe,
+                0.0,
+                egui::Stroke::new(1.0, color),
+                egui::StrokeKind::Middle,
+            );
+        }
+    }
+
+    // RGB and luma level graph; the markers show the source's nominal black
+    // and white codes.
+    fn draw_scope(ctx: &egui::Context, histogram: &Histogram) {
+        egui::Area::new("scope_overlay".into())
+            .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(8.0, -8.0))
+            .show(ctx, |ui| {
+                egui::Frame::new()
+                    .fill(Color32::from_black_alpha(180))
+                    .inner_margin(6.0)
+                    .show(ui, |ui| {
+                        let (rect, _) =
+                            ui.allocate_exact_size(egui::vec2(256.0, 100.0), egui::Sense::hover());
+                        let painter = ui.painter_at(rect);
+                        let level_x =
+                            |level: u8| rect.left() + rect.width() * f32::from(level) / 255.0;
+                        let (black, white) = histogram.bounds();
+                        let marker = egui::Stroke::new(1.0, Color32::from_white_alpha(60));
+                        painter.vline(level_x(black), rect.y_range(), marker);
+                        painter.vline(level_x(white), rect.y_range(), marker);
+                        let peak = histogram.peak() as f32;
+                        let colors = [
+                            Color32::from_rgb(255, 70, 70),
+                            Color32::from_rgb(70, 255, 70),
+                            Color32::from_rgb(90, 130, 255),
+                            Color32::from_gray(230),
+                        ];
+                        for (bins, color) in histogram.channels.iter().zip(colors) {
+                            let points = (0..=255u8)
+                                .map(|level| {
+                                    let height = (bins[usize::from(level)] as f32 / peak).min(1.0);
+                                    let y = rect.bottom() - height * rect.height();
+                                    egui::pos2(level_x(level), y)
+                                })
+                                .collect();
+                            painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, color)));
+                        }
                         ui.label(format!(
-                            "Video: {} {}x{} @{}",
-                            info.format, info.width, info.height, fps_text
+                            "Range: {}",
+                            match histogram.range {
+                                ColorRange::Limited => "Limited (16-235)",
+                                ColorRange::Full => "Full (0-255)",
+                            }
                         ));
-                        ui.label(format!("FPS: {:.1}", self.stats.fps));
-                        ui.label(format!("Decode: {} us", self.stats.decode_us));
-                        ui.label(format!("Drops/s: {:.1}", self.stats.drops_per_s));
-                        ui.label(format!("Queue: {queue_len}"));
-                        if let Some(fmt) = self.stats.last_frame_format {
-                            ui.label(format!("Frame: {}", Self::format_name(fmt)));
-                        }
                     });
-            }
-        }
-        if show_ui && self.last_refresh.elapsed().as_secs() >= 5 {
-            self.refresh_devices();
-        }
+            });
     }
 
-    pub fn toggle_stats(&mut self) {
-        self.show_stats = !self.show_stats;
-        self.apply_stats_enabled();
+    // Drawn on its own so it stays up with the menu hidden and in fullscreen.
+    fn clock_overlay(&self, ctx: &egui::Context) {
+        let text = match self.clock_mode {
+            ClockMode::Off => return,
+            ClockMode::Clock => clock::wall_clock(),
+            ClockMode::Stopwatch => clock::format_elapsed(self.stopwatch.elapsed()),
+        };
+        let align = self.clock_corner.align();
+        // Top corners sit below the menu bar, like the other overlays.
+        let y = if align.y() == egui::Align::Min {
+            40.0
+        } else {
+            -8.0
+        };
+        let x = if align.x() == egui::Align::Min {
+            8.0
+        } else {
+            -8.0
+        };
+        egui::Area::new("clock_overlay".into())
+            .anchor(align, egui::vec2(x, y))
+            .show(ctx, |ui| {
+                egui::Frame::new()
+                    .fill(Color32::from_black_alpha(160))
+                    .inner_margin(6.0)
+                    .corner_radius(4.0)
+                    .show(ui, |ui| {
+                        ui.label(
+                            egui::RichText::new(text)
+                                .size(self.clock_size)
+                                .monospace()
+                                .color(Color32::WHITE),
+                        );
+                    });
+            });
     }
 
-    fn refresh_devices(&mut self) {
-        self.last_refresh = Instant::now();
-        if let Ok(v) = platform::list_video_devices() {
-            self.video_devices = v;
-            if let Some(idx) = self.selected_video {
-                if idx >= self.video_devices.len() {
-                    self.set_video(None);
-                }
+    // Scrubs the replay buffer; the slider shows how far each frame lies
+    // behind the newest one.
+    fn replay_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(last) = self.replay.len().checked_sub(1) else {
+            return;
+        };
+        let mut pos = self.replay_pos.unwrap_or(last).min(last);
+        let newest = self.replay.get(last).map(|f| f.captured_at);
+        ui.horizontal(|ui| {
+            if ui.button("⏴").clicked() {
+                pos = pos.saturating_sub(1);
             }
-        }
-        if self.audio_devices.is_empty() {
-            if let Ok(v) = audio::list_input_devices() {
-                self.audio_devices = v;
-                if let Some(idx) = self.selected_audio {
-                    if idx >= self.audio_devices.len() {
-                        self.set_audio(None);
-                    }
-                }
+            let replay = &self.replay;
+            ui.add(
+                egui::Slider::new(&mut pos, 0..=last)
+                    .text("Replay")
+                    .custom_formatter(|v, _| {
+                        let age = replay
+                            .get(v as usize)
+                            .zip(newest)
+                            .map(|(f, newest)| newest.duration_since(f.captured_at))
+                            .unwrap_or_default();
+                        format!("-{:.2} s", age.as_secs_f32())
+                    }),
+            );
+            if ui.button("⏵").clicked() {
+                pos = (pos + 1).min(last);
             }
+        });
+        if self.replay_pos.unwrap_or(last) != pos {
+            self.replay_pos = Some(pos);
+            self.replay_pending = true;
         }
     }
 
-    fn set_video(&mut self, sel: Option<usize>) {
-        if let Some(mut cap) = self.video_capture.take() {
-            cap.stop();
+    fn video_controls_ui(&mut self, ui: &mut egui::Ui) {
+        if self.video_controls.is_empty() {
+            return;
         }
-        self.selected_video = sel;
-        if let Some(i) = sel {
-            match platform::start_video_capture(
-                &self.video_devices[i].id,
-                self.target_capture_size,
-            ) {
-                Ok(cap) => {
-                    self.video_capture = Some(cap);
-                    self.apply_stats_enabled();
-                    self.last_error = None;
+        let mut changes = Vec::new();
+        let mut save = false;
+        ui.horizontal_wrapped(|ui| {
+            for (i, control) in self.video_controls.iter().enumerate() {
+                let mut value = control.value;
+                let response = ui.add(
+                    egui::Slider::new(&mut value, control.min..=control.max)
+                        .step_by(control.step as f64)
+                        .text(&control.name),
+                );
+                if response.changed() {
+                    changes.push((i, value));
                 }
-                Err(e) => self.last_error = Some(format!("Video: {e}")),
+                // Write once the drag ends rather than on every step.
+                if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                    save = true;
+                }
+            }
+            if ui.button("Defaults").clicked() {
+                changes = self
+                    .video_controls
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| (i, c.default))
+                    .collect();
+                save = true;
             }
+        });
+        for (i, value) in changes {
+            self.set_video_control(i, value);
+        }
+        if save {
+            self.save_settings();
         }
     }
 
-    fn apply_stats_enabled(&mut self) {
-        if let Some(cap) = self.video_capture.as_ref() {
-            cap.stats.set_enabled(self.show_stats);
-            if self.show_stats {
-                cap.stats.reset();
-                self.stats.reset();
-            }
+    fn set_video_control(&mut self, index: usize, value: i64) {
+        let Some(device) = self.selected_video.and_then(|i| self.video_devices.get(i)) else {
+            return;
+        };
+        let Some(control) = self.video_controls.get_mut(index) else {
+            return;
+        };
+        if let Err(e) = platform::set_video_control(&device.id, control.id, value) {
+            self.errors.set(
+                ErrorSource::Video,
+                format!("Failed to set {}: {e}", control.name),
+            );
+            return;
+        }
+        control.value = value;
+        let key = Self::video_control_key(control);
+        if let Some(section) = self.video_settings_section() {
+            self.settings.set(&section, &key, value);
         }
     }
 
-    fn update_stats(&mut self) {
-        let cap = match self.video_capture.as_ref() {
-            Some(c) => c,
-            None => return,
+    // Picture controls the device exposes, with values saved for this device
+    // written back to the hardware.
+    fn load_video_controls(&mut self) {
+        let Some(device) = self.selected_video.and_then(|i| self.video_devices.get(i)) else {
+            self.video_controls.clear();
+            return;
         };
-        let snap = cap.stats.snapshot();
-        let now = Instant::now();
-        let dt = now.duration_since(self.stats.last_at);
-        if dt >= Duration::from_millis(250) {
-            let df = snap.frames.saturating_sub(self.stats.last_frames);
-            let dd = snap.drops.saturating_sub(self.stats.last_drops);
-            let secs = dt.as_secs_f32().max(0.001);
-            self.stats.fps = df as f32 / secs;
+        self.video_controls = platform::list_video_controls(&device.id).unwrap_or_default();
+        let Some(section) = self.video_settings_section() else {
+            return;
+        };
+        for i in 0..self.video_controls.len() {
+            let control = &self.video_controls[i];
+            let saved = self
+                .settings
+                .get_parsed::<i64>(&section, &Self::video_control_key(control))
+                .filter(|v| (control.min..=control.max).contains(v) && *v != control.value);
+            if let Some(value) = saved {
+                self.set_video_control(i, value);
+            }
+        }
+    }
+
+    fn load_color_override(&mut self) {
+        let section = self.video_settings_section();
+        let saved = |key| section.as_ref().and_then(|s| self.settings.get(s, key));
+        self.color_matrix_override = COLOR_MATRICES
+            .into_iter()
+            .find(|&m| saved("color_matrix") == Some(Self::color_matrix_name(m)));
+        self.color_range_override = COLOR_RANGES
+            .into_iter()
+            .find(|&r| saved("color_range") == Some(Self::color_range_name(r)));
+    }
+
+    // Auto is stored as an empty value so a saved override can be cleared.
+    fn color_override_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut matrix = self.color_matrix_override;
+            ComboBox::from_id_salt("color_matrix")
+                .selected_text(format!(
+                    "Matrix: {}",
+                    matrix.map_or("Auto", Self::color_matrix_name)
+                ))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut matrix, None, "Auto");
+                    for m in COLOR_MATRICES {
+                        ui.selectable_value(&mut matrix, Some(m), Self::color_matrix_name(m));
+                    }
+                });
+            let mut range = self.color_range_override;
+            ComboBox::from_id_salt("color_range")
+                .selected_text(format!(
+                    "Range: {}",
+                    range.map_or("Auto", Self::color_range_name)
+                ))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut range, None, "Auto");
+                    for r in COLOR_RANGES {
+                        ui.selectable_value(&mut range, Some(r), Self::color_range_name(r));
+                    }
+                });
+            if matrix == self.color_matrix_override && range == self.color_range_override {
+                return;
+            }
+            self.color_matrix_override = matrix;
+            self.color_range_override = range;
+            if let Some(section) = self.video_settings_section() {
+                let matrix = matrix.map_or("", Self::color_matrix_name);
+                self.settings.set(&section, "color_matrix", matrix);
+                let range = range.map_or("", Self::color_range_name);
+                self.settings.set(&section, "color_range", range);
+                self.save_settings();
+            }
+        });
+    }
+
+    // Capture and picture choices remembered for the selected device; one
+    // with nothing saved starts from the defaults.
+    fn load_device_prefs(&mut self) {
+        let section = self.video_settings_section();
+        let saved = |key| section.as_ref().and_then(|s| self.settings.get(s, key));
+        self.resolution_preset = RESOLUTION_PRESETS
+            .into_iter()
+            .find(|&p| saved("resolution") == Some(Self::resolution_preset_name(p)))
+            .unwrap_or(ResolutionPreset::Auto);
+        self.video_mode = saved("mode").and_then(|label| {
+            self.video_modes
+                .iter()
+                .find(|m| Self::video_mode_label(m) == label)
+                .cloned()
+        });
+        self.frame_rate = saved("fps").and_then(|v| v.parse().ok());
+        self.aspect_mode = ASPECT_MODES
+            .into_iter()
+            .find(|&m| saved("aspect") == Some(Self::aspect_mode_name(m)))
+            .unwrap_or(AspectMode::Auto);
+        self.crop = saved("crop")
+            .and_then(settings::parse_crop)
+            .unwrap_or_default();
+    }
+
+    // Every picture adjustment back to its default, including what was saved
+    // for the device. Hardware controls have their own Defaults button.
+    fn reset_picture(&mut self) {
+        self.output_gamma = 1.0;
+        self.image_adjust = ImageAdjust::default();
+        self.sharpen = 0.0;
+        self.rotation = Rotation::Deg0;
+        self.aspect_mode = AspectMode::Auto;
+        self.crop = CropRect::default();
+        self.color_matrix_override = None;
+        self.color_range_override = None;
+        if let Some(section) = self.video_settings_section() {
+            for key in ["aspect", "crop", "color_matrix", "color_range"] {
+                self.settings.set(&section, key, "");
+            }
+            self.save_settings();
+        }
+    }
+
+    // Empty values stand for Auto, like the color overrides.
+    fn save_device_pref(&mut self, key: &str, value: impl ToString) {
+        if let Some(section) = self.video_settings_section() {
+            self.settings.set(&section, key, value);
+            self.save_settings();
+        }
+    }
+
+    fn video_settings_section(&self) -> Option<String> {
+        let device = self.video_devices.get(self.selected_video?)?;
+        Some(format!("video:{}", device.id))
+    }
+
+    fn video_control_key(control: &platform::VideoControl) -> String {
+        format!("control.{}", control.name.to_lowercase().replace(' ', "_"))
+    }
+
+    pub fn frame_latency(&self) -> u32 {
+        self.frame_latency
+    }
+
+    // Shortest time between redraws that new frames may cause.
+    pub fn frame_interval(&self) -> Option<Duration> {
+        (self.fps_cap > 0).then(|| Duration::from_secs(1) / self.fps_cap)
+    }
+
+    pub fn set_surface_diagnostics(&mut self, surface: SurfaceDiagnostics) {
+        self.surface = Some(surface);
+    }
+
+    pub fn set_video_rect(&mut self, rect: [f32; 4]) {
+        self.video_rect = rect;
+    }
+
+    // The renderer couldn't show a frame, e.g. a truncated buffer; it counts
+    // as a drop.
+    pub fn count_skipped_frame(&self) {
+        if let Some(cap) = self.video_capture.as_ref()
+            && cap.stats.enabled()
+        {
+            cap.stats.on_drop_enabled();
+        }
+    }
+
+    pub fn set_has_frame(&mut self, has_frame: bool) {
+        self.has_frame = has_frame;
+    }
+
+    // A paused picture stays up, since it's the replay buffer showing.
+    pub fn blank_video(&self) -> bool {
+        self.blank_on_no_signal
+            && self.no_signal
+            && !self.video_paused
+            && self.video_capture.is_some()
+    }
+
+    pub fn scope_enabled(&self) -> bool {
+        self.show_scope && self.video_capture.is_some()
+    }
+
+    pub fn update_scope(&mut self, image: &image::RgbaImage, range: ColorRange) {
+        self.scope = Some(Histogram::from_rgba(image.as_raw(), range));
+        self.errors.clear(ErrorSource::Scope);
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    pub fn take_fullscreen_request(&mut self) -> Option<bool> {
+        self.fullscreen_request.take()
+    }
+
+    pub fn set_fullscreen_state(&mut self, fullscreen: bool) {
+        self.fullscreen = fullscreen;
+    }
+
+    pub fn take_always_on_top_request(&mut self) -> Option<bool> {
+        self.always_on_top_request.take()
+    }
+
+    pub fn set_monitors(&mut self, monitors: Vec<String>) {
+        self.monitors = monitors;
+    }
+
+    // None means whichever monitor the window is on.
+    pub fn fullscreen_monitor(&self) -> Option<&str> {
+        self.fullscreen_monitor.as_deref()
+    }
+
+    pub fn take_compact_request(&mut self) -> Option<bool> {
+        self.compact_request.take()
+    }
+
+    // Fullscreen and compact mode pick their own sizes.
+    pub fn take_fit_window_request(&mut self) -> bool {
+        std::mem::take(&mut self.fit_window_request) && !self.fullscreen && !self.compact
+    }
+
+    // Width over height of the picture as shown, after crop, forced aspect
+    // and rotation.
+    pub fn picture_aspect(&self) -> Option<f32> {
+        let (width, height) = self.capture_size()?;
+        let crop = self.crop.clamped(width, height);
+        let w = width.saturating_sub(crop.left + crop.right) as f32;
+        let h = height.saturating_sub(crop.top + crop.bottom) as f32;
+        if w <= 0.0 || h <= 0.0 {
+            return None;
+        }
+        let ratio = self.aspect_mode.ratio(w / h).unwrap_or(w / h);
+        Some(if self.rotation.swaps_axes() {
+            1.0 / ratio
+        } else {
+            ratio
+        })
+    }
+
+    pub fn take_keep_awake_request(&mut self) -> Option<bool> {
+        self.keep_awake_request.take()
+    }
+
+    pub fn take_title_request(&mut self) -> Option<String> {
+        self.title_request.take()
+    }
+
+    pub fn request_screenshot(&mut self) {
+        self.screenshot_request = self.video_capture.is_some();
+    }
+
+    pub fn take_screenshot_request(&mut self) -> bool {
+        std::mem::take(&mut self.screenshot_request)
+    }
+
+    // PNG encoding of a 4K frame takes long enough to hitch playback, so it
+    // happens off the UI thread; the outcome is picked up in ui().
+    pub fn save_screenshot(&self, image: image::RgbaImage) {
+        let tx = self.screenshot_tx.clone();
+        let spawned = std::thread::Builder::new()
+            .name("screenshot".to_string())
+            .spawn(move || {
+                let _ = tx.send(screenshot::save_png(&image));
+            });
+        if let Err(e) = spawned {
+            let _ = self.screenshot_tx.send(Err(e.into()));
+        }
+    }
+
+    pub fn report_error(&mut self, source: ErrorSource, message: String) {
+        self.errors.set(source, message);
+    }
+
+    pub fn clear_error(&mut self, source: ErrorSource) {
+        self.errors.clear(source);
+    }
+
+    pub fn toggle_audio_muted(&mut self) {
+        self.set_audio_muted(!self.audio_muted);
+    }
+
+    pub fn set_audio_muted(&mut self, muted: bool) {
+        self.audio_muted = muted;
+        if let Some(playback) = self.audio_playback.as_ref() {
+            playback.set_muted(muted);
+        }
+        if let Some(section) = self.audio_settings_section() {
+            self.settings.set(&section, "muted", muted);
+            self.save_settings();
+        }
+    }
+
+    pub fn set_audio_volume(&mut self, volume: f32) {
+        self.audio_volume = volume;
+        if let Some(playback) = self.audio_playback.as_ref() {
+            playback.set_volume(volume);
+        }
+        if let Some(section) = self.audio_settings_section() {
+            self.settings.set(&section, "volume", volume);
+        }
+    }
+
+    // Remembered by name: on Linux the ids are enumeration indices.
+    fn set_audio_output(&mut self, sel: Option<usize>) {
+        self.selected_output = sel;
+        let name = sel
+            .and_then(|i| self.audio_outputs.get(i))
+            .map(|d| d.info.name.clone())
+            .unwrap_or_default();
+        self.settings.set("audio", "output", name);
+        self.save_settings();
+        if self.audio_playback.is_some() {
+            self.set_audio(self.selected_audio);
+        }
+    }
+
+    // Audio tunables are remembered per input device so switching between
+    // sources brings back whatever each one was last set to.
+    fn audio_settings_section(&self) -> Option<String> {
+        let device = self.audio_devices.get(self.selected_audio?)?;
+        Some(format!("audio:{}", device.info.id))
+    }
+
+    fn save_settings(&mut self) {
+        match self.settings.save() {
+            Ok(()) => self.errors.clear(ErrorSource::Settings),
+            Err(e) => self.errors.set(
+                ErrorSource::Settings,
+                format!("Failed to save settings: {e}"),
+            ),
+        }
+    }
+
+    // Frames keep being drained while paused so the capture thread never
+    // backs up; the renderer simply keeps showing the last uploaded frame.
+    pub fn set_video_paused(&mut self, paused: bool) {
+        self.video_paused = paused;
+        self.replay_pos = None;
+        self.replay_pending = false;
+    }
+
+    pub fn toggle_video_paused(&mut self) {
+        self.set_video_paused(!self.video_paused);
+    }
+
+    pub fn capture_size(&self) -> Option<(u32, u32)> {
+        self.video_capture
+            .as_ref()
+            .map(|cap| (cap.info.width, cap.info.height))
+    }
+
+    pub fn capture_fps(&self) -> Option<u32> {
+        self.video_capture.as_ref().and_then(|cap| cap.info.fps)
+    }
+
+    // Capture size as shown on screen, i.e. after rotation.
+    pub fn display_size(&self) -> Option<(u32, u32)> {
+        let (w, h) = self.capture_size()?;
+        Some(if self.rotation.swaps_axes() {
+            (h, w)
+        } else {
+            (w, h)
+        })
+    }
+
+    pub fn take_latest_frame(&mut self) -> Option<VideoFrame> {
+        let cap = self.video_capture.as_ref()?;
+        let mut latest = None;
+        while let Ok(frame) = cap.rx.try_recv() {
+            latest = Some(frame);
+            // Smooth shows queued frames in order, one per redraw.
+            if self.frame_policy == FramePolicy::Smooth {
+                if !cap.rx.is_empty()
+                    && let Some(wake) = self.frame_waker.as_ref()
+                {
+                    wake();
+                }
+                break;
+            }
+        }
+        let delivered = cap.stats.delivered();
+        let now = Instant::now();
+        // A hidden window idles the capture, so a quiet spell then means
+        // nothing.
+        if self.window_hidden {
+            self.signal_watch.restart(delivered, now);
+        }
+        let timeout = Duration::from_secs_f32(self.no_signal_timeout);
+        self.no_signal = self.signal_watch.update(delivered, now, timeout);
+        if self.video_paused {
+            if !std::mem::take(&mut self.replay_pending) {
+                return None;
+            }
+            let pos = self
+                .replay_pos
+                .unwrap_or(self.replay.len().saturating_sub(1));
+            return self
+                .replay
+                .get(pos)
+                .cloned()
+                .map(|frame| self.override_color(frame));
+        }
+        if let Some(frame) = latest.as_ref() {
+            self.replay.push(frame);
+            if self.show_stats {
+                self.stats.update_frame(frame);
+            }
+        }
+        latest.map(|frame| self.override_color(frame))
+    }
+
+    pub fn take_pip_frame(&mut self) -> Option<VideoFrame> {
+        let cap = self.pip_capture.as_ref()?;
+        let mut latest = None;
+        while let Ok(frame) = cap.rx.try_recv() {
+            latest = Some(frame);
+        }
+        latest
+    }
+
+    fn override_color(&self, mut frame: VideoFrame) -> VideoFrame {
+        if let Some(matrix) = self.color_matrix_override {
+            frame.color.matrix = matrix;
+        }
+        if let Some(range) = self.color_range_override {
+            frame.color.range = range;
+        }
+        frame
+    }
+
+    pub fn ui(&mut self, ctx: &egui::Context) {
+        while let Ok(result) = self.screenshot_rx.try_recv() {
+            match result {
+                Ok(_) => self.errors.clear(ErrorSource::Screenshot),
+                Err(e) => self.errors.set(ErrorSource::Screenshot, e.to_string()),
+            }
+        }
+        while let Some(result) = self.recorder.take_result() {
+            match result {
+                Ok(_) => self.errors.clear(ErrorSource::Recording),
+                Err(e) => self.errors.set(ErrorSource::Recording, e.to_string()),
+            }
+        }
+        self.poll_device_scan();
+        self.poll_video_start();
+        self.poll_pip_start();
+        self.poll_reconnect();
+        if self.pip_capture.as_ref().is_some_and(|cap| cap.is_lost()) {
+            self.set_pip(None);
+            self.errors
+                .set(ErrorSource::Pip, "PiP device lost".to_string());
+        }
+        let ui_active =
+            egui::Popup::is_any_open(ctx) || ctx.is_pointer_over_area() || ctx.is_using_pointer();
+        let idle = self.video_capture.is_none() && !self.compact;
+        let show_ui = idle || self.mouse_y <= 32.0 || ui_active;
+        if show_ui {
+            egui::TopBottomPanel::top("selectors").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let mut vid = self.selected_video;
+                    let pip = self.pip_index();
+                    let video_text = vid
+                        .and_then(|i| self.video_devices.get(i).map(|d| d.name.clone()))
+                        .unwrap_or_else(|| self.empty_device_label("Video", &self.video_devices));
+                    ComboBox::from_id_salt("video_select")
+                        .selected_text(video_text)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut vid, None, "None");
+                            for (i, dev) in self.video_devices.iter().enumerate() {
+                                // Already open as the PiP.
+                                if Some(i) != pip {
+                                    ui.selectable_value(&mut vid, Some(i), &dev.name);
+                                }
+                            }
+                            if platform::NETWORK_SOURCES_SUPPORTED
+                                && ui.selectable_label(false, "Network Source…").clicked()
+                            {
+                                self.network_prompt = true;
+                            }
+                        });
+                    if vid != self.selected_video {
+                        self.set_video(vid);
+                    }
+                    let mut preset = self.resolution_preset;
+                    ComboBox::from_id_salt("resolution_preset")
+                        .selected_text(format!(
+                            "Resolution: {}",
+                            Self::resolution_preset_name(preset)
+                        ))
+                        .show_ui(ui, |ui| {
+                            for p in RESOLUTION_PRESETS {
+                                ui.selectable_value(
+                                    &mut preset,
+                                    p,
+                                    Self::resolution_preset_name(p),
+                                );
+                            }
+                        });
+                    if preset != self.resolution_preset {
+                        self.resolution_preset = preset;
+                        self.save_device_pref("resolution", Self::resolution_preset_name(preset));
+                        if self.video_capture.is_some() && self.video_mode.is_none() {
+                            self.set_video(self.selected_video);
+                        }
+                    }
+                    if !self.video_modes.is_empty() {
+                        let mut mode = self.video_mode.clone();
+                        let mode_text = mode
+                            .as_ref()
+                            .map(Self::video_mode_label)
+                            .unwrap_or_else(|| "Auto".to_string());
+                        ComboBox::from_id_salt("video_mode")
+                            .selected_text(format!("Mode: {mode_text}"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut mode, None, "Auto");
+                                for m in &self.video_modes {
+                                    ui.selectable_value(
+                                        &mut mode,
+                                        Some(m.clone()),
+                                        Self::video_mode_label(m),
+                                    );
+                                }
+                            });
+                        if mode != self.video_mode {
+                            let label = mode.as_ref().map(Self::video_mode_label);
+                            self.save_device_pref("mode", label.unwrap_or_default());
+                            self.video_mode = mode;
+                            self.refresh_frame_rates();
+                            self.set_video(self.selected_video);
+                        }
+                    }
+                    if !self.frame_rates.is_empty() {
+                        let mut rate = self.frame_rate;
+                        let rate_text = rate
+                            .map(|v| v.to_string())
+                            .unwrap_or_else(|| "Auto".to_string());
+                        ComboBox::from_id_salt("frame_rate")
+                            .selected_text(format!("FPS: {rate_text}"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut rate, None, "Auto");
+                                for &v in &self.frame_rates {
+                                    ui.selectable_value(&mut rate, Some(v), v.to_string());
+                                }
+                            });
+                        if rate != self.frame_rate {
+                            self.frame_rate = rate;
+                            let fps = rate.map(|v| v.to_string()).unwrap_or_default();
+                            self.save_device_pref("fps", fps);
+                            self.set_video(self.selected_video);
+                        }
+                    }
+                    let mut aud = self.selected_audio;
+                    let audio_text = aud
+                        .and_then(|i| self.audio_devices.get(i).map(|d| d.info.name.clone()))
+                        .unwrap_or_else(|| self.empty_device_label("Audio", &self.audio_devices));
+                    ComboBox::from_id_salt("audio_select")
+                        .selected_text(audio_text)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut aud, None, "None");
+                            for (i, dev) in self.audio_devices.iter().enumerate() {
+                                ui.selectable_value(&mut aud, Some(i), &dev.info.name);
+                            }
+                        });
+                    if aud != self.selected_audio {
+                        self.set_audio(aud);
+                    }
+                    if !self.audio_outputs.is_empty() {
+                        let mut output = self.selected_output;
+                        let output_text = output
+                            .and_then(|i| self.audio_outputs.get(i).map(|d| d.info.name.clone()))
+                            .unwrap_or_else(|| "Default".to_string());
+                        ComboBox::from_id_salt("audio_output")
+                            .selected_text(format!("Output: {output_text}"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut output, None, "Default");
+                                for (i, dev) in self.audio_outputs.iter().enumerate() {
+                                    ui.selectable_value(&mut output, Some(i), &dev.info.name);
+                                }
+                            });
+                        if output != self.selected_output {
+                            self.set_audio_output(output);
+                        }
+                    }
+                    let mut audio_muted = self.audio_muted;
+                    if ui.checkbox(&mut audio_muted, "Mute Audio").changed() {
+                        self.set_audio_muted(audio_muted);
+                    }
+                    let mut volume = self.audio_volume;
+                    let response = ui.add(
+                        egui::Slider::new(&mut volume, 0.0..=1.0)
+                            .text("Volume")
+                            .custom_formatter(|v, _| format!("{:.0}%", v * 100.0)),
+                    );
+                    if response.changed() {
+                        self.set_audio_volume(volume);
+                    }
+                    // Write once the drag ends rather than on every step.
+                    if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                        self.save_settings();
+                    }
+                    let response = ui
+                        .add(
+                            egui::Slider::new(&mut self.playback_options.resample_quality, 0..=10)
+                                .text("Resample Quality"),
+                        )
+                        .on_hover_text("Higher sounds better when rates differ, for more CPU");
+                    if response.changed() {
+                        let quality = self.playback_options.resample_quality;
+                        self.settings.set("audio", "resample_quality", quality);
+                    }
+                    // Restarting playback mid-drag would stutter on every step.
+                    if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                        self.save_settings();
+                        if self.audio_playback.is_some() {
+                            self.set_audio(self.selected_audio);
+                        }
+                    }
+                    let mut video_paused = self.video_paused;
+                    if ui
+                        .checkbox(&mut video_paused, "Pause Video (Space)")
+                        .changed()
+                    {
+                        self.set_video_paused(video_paused);
+                    }
+                    if self.video_paused && !self.replay.is_empty() {
+                        self.replay_ui(ui);
+                    }
+                    let mut replay_seconds = self.replay.window().as_secs_f32();
+                    let response = ui.add(
+                        egui::Slider::new(&mut replay_seconds, 0.0..=30.0)
+                            .text("Replay Seconds")
+                            .fixed_decimals(0),
+                    );
+                    if response.changed() {
+                        self.replay
+                            .set_window(Duration::from_secs_f32(replay_seconds));
+                        self.settings.set("replay", "seconds", replay_seconds);
+                    }
+                    if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                        self.save_settings();
+                    }
+                    let mut show_stats = self.show_stats;
+                    if ui.checkbox(&mut show_stats, "Stats").changed() {
+                        self.show_stats = show_stats;
+                        self.apply_stats_enabled();
+                    }
+                    ui.checkbox(&mut self.show_guides, "Guides");
+                    if ui.checkbox(&mut self.show_scope, "Scope").changed() && !self.show_scope {
+                        self.scope = None;
+                        self.errors.clear(ErrorSource::Scope);
+                    }
+                    let can_test = self.video_capture.is_some() && !self.latency_probe.is_running();
+                    if ui
+                        .add_enabled(can_test, egui::Button::new("Latency Test"))
+                        .clicked()
+                    {
+                        self.latency_probe.start();
+                    }
+                    let recording = self.recorder.is_recording();
+                    let record_text = if recording {
+                        "Stop Recording"
+                    } else {
+                        "Record"
+                    };
+                    if ui
+                        .add_enabled(
+                            recording || self.video_capture.is_some(),
+                            egui::Button::new(record_text).selected(recording),
+                        )
+                        .clicked()
+                    {
+                        self.set_recording(!recording);
+                    }
+                    match self.latency_probe.result() {
+                        Some(Some(d)) => {
+                            ui.label(format!("Latency: {:.1} ms", d.as_secs_f64() * 1000.0));
+                        }
+                        Some(None) => {
+                            ui.label("Latency: flash not seen");
+                        }
+                        None => {}
+                    }
+                    let mut vsync = self.vsync;
+                    if ui.checkbox(&mut vsync, "VSync").changed() {
+                        self.set_vsync(vsync);
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Background:");
+                        if ui.color_edit_button_srgb(&mut self.background).changed() {
+                            self.settings.set(
+                                "display",
+                                "background",
+                                settings::format_color(self.background),
+                            );
+                            self.save_settings();
+                        }
+                    });
+                    let mut keep_awake = self.keep_awake;
+                    if ui.checkbox(&mut keep_awake, "Keep Awake").changed() {
+                        self.keep_awake = keep_awake;
+                        self.keep_awake_request = Some(keep_awake);
+                    }
+                    let mut aspect_mode = self.aspect_mode;
+                    ComboBox::from_id_salt("aspect_mode")
+                        .selected_text(format!("Aspect: {}", Self::aspect_mode_name(aspect_mode)))
+                        .show_ui(ui, |ui| {
+                            for mode in ASPECT_MODES {
+                                ui.selectable_value(
+                                    &mut aspect_mode,
+                                    mode,
+                                    Self::aspect_mode_name(mode),
+                                );
+                            }
+                        });
+                    if aspect_mode != self.aspect_mode {
+                        self.aspect_mode = aspect_mode;
+                        self.save_device_pref("aspect", Self::aspect_mode_name(aspect_mode));
+                    }
+                    let mut mode_match = self.mode_match;
+                    ComboBox::from_id_salt("mode_match")
+                        .selected_text(format!(
+                            "Exclusive Mode: {}",
+                            Self::mode_match_name(mode_match)
+                        ))
+                        .show_ui(ui, |ui| {
+                            for m in [ModeMatch::Exact, ModeMatch::Nearest] {
+                                ui.selectable_value(&mut mode_match, m, Self::mode_match_name(m));
+                            }
+                        });
+                    if mode_match != self.mode_match {
+                        self.mode_match = mode_match;
+                        self.settings.set(
+                            "display",
+                            "exclusive_mode",
+                            Self::mode_match_name(mode_match),
+                        );
+                        self.save_settings();
+                    }
+                    let mut clamp_limited = self.clamp_limited_range;
+                    if ui
+                        .checkbox(&mut clamp_limited, "Clamp Limited Range")
+                        .changed()
+                    {
+                        self.clamp_limited_range = clamp_limited;
+                    }
+                    let mut srgb_output = self.srgb_output;
+                    let surface_srgb = self.surface.map(|s| s.format.is_srgb());
+                    let auto_text = match surface_srgb {
+                        Some(on) => format!("Auto ({})", Self::srgb_output_name(Some(on))),
+                        None => "Auto".to_string(),
+                    };
+                    let srgb_text = match srgb_output {
+                        Some(_) => Self::srgb_output_name(srgb_output),
+                        None => auto_text.as_str(),
+                    };
+                    ComboBox::from_id_salt("srgb_output")
+                        .selected_text(format!("sRGB Decode: {srgb_text}"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut srgb_output, None, auto_text.as_str());
+                            for s in [Some(true), Some(false)] {
+                                ui.selectable_value(&mut srgb_output, s, Self::srgb_output_name(s));
+                            }
+                        });
+                    if srgb_output != self.srgb_output {
+                        self.srgb_output = srgb_output;
+                        self.settings.set(
+                            "display",
+                            "srgb_output",
+                            Self::srgb_output_name(srgb_output),
+                        );
+                        self.save_settings();
+                    }
+                    let mut wide_gamut = self.wide_gamut;
+                    let response = ui.checkbox(&mut wide_gamut, "Wide Gamut").on_hover_text(
+                        "Display P3 output for wide-gamut monitors; applies after a restart",
+                    );
+                    if response.changed() {
+                        self.wide_gamut = wide_gamut;
+                        self.settings.set("display", "wide_gamut", wide_gamut);
+                        self.save_settings();
+                    }
+                    ui.checkbox(&mut self.dither, "Dither");
+                    ui.add(
+                        egui::Slider::new(&mut self.output_gamma, 0.5..=2.0)
+                            .text("Gamma")
+                            .fixed_decimals(2),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut self.image_adjust.brightness, -0.5..=0.5)
+                            .text("Brightness")
+                            .fixed_decimals(2),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut self.image_adjust.contrast, 0.5..=2.0)
+                            .text("Contrast")
+                            .fixed_decimals(2),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut self.image_adjust.saturation, 0.0..=2.0)
+                            .text("Saturation")
+                            .fixed_decimals(2),
+                    );
+                    ui.checkbox(&mut self.zebra, "Zebra");
+                    if self.zebra {
+                        ui.add(
+                            egui::Slider::new(&mut self.zebra_threshold, 0.5..=1.0)
+                                .text("Zebra Level")
+                                .custom_formatter(|v, _| format!("{:.0}%", v * 100.0)),
+                        );
+                    }
+                    ui.add(
+                        egui::Slider::new(&mut self.sharpen, 0.0..=1.0)
+                            .text("Sharpen")
+                            .fixed_decimals(2),
+                    );
+                    let mut scale_mode = self.scale_mode;
+                    ComboBox::from_id_salt("scale_mode")
+                        .selected_text(format!("Scale: {}", Self::scale_mode_name(scale_mode)))
+                        .show_ui(ui, |ui| {
+                            for mode in [ScaleMode::Fit, ScaleMode::NoUpscale, ScaleMode::Integer] {
+                                ui.selectable_value(
+                                    &mut scale_mode,
+                                    mode,
+                                    Self::scale_mode_name(mode),
+                                );
+                            }
+                        });
+                    self.scale_mode = scale_mode;
+                    ComboBox::from_id_salt("scale_filter")
+                        .selected_text(format!(
+                            "Scaling: {}",
+                            Self::scale_filter_name(self.scale_filter)
+                        ))
+                        .show_ui(ui, |ui| {
+                            for f in [ScaleFilter::Linear, ScaleFilter::CatmullRom] {
+                                ui.selectable_value(
+                                    &mut self.scale_filter,
+                                    f,
+                                    Self::scale_filter_name(f),
+                                );
+                            }
+                        });
+                    ComboBox::from_id_salt("effect")
+                        .selected_text(format!("Effect: {}", Self::effect_name(self.effect)))
+                        .show_ui(ui, |ui| {
+                            let custom = self.post_shader.is_some().then_some(Effect::Custom);
+                            for e in [Some(Effect::None), Some(Effect::Crt), custom]
+                                .into_iter()
+                                .flatten()
+                            {
+                                ui.selectable_value(&mut self.effect, e, Self::effect_name(e));
+                            }
+                        });
+                    ComboBox::from_id_salt("rotation")
+                        .selected_text(format!("Rotate: {}", Self::rotation_name(self.rotation)))
+                        .show_ui(ui, |ui| {
+                            for r in [
+                                Rotation::Deg0,
+                                Rotation::Deg90,
+                                Rotation::Deg180,
+                                Rotation::Deg270,
+                            ] {
+                                ui.selectable_value(&mut self.rotation, r, Self::rotation_name(r));
+                            }
+                        });
+                    let reset = ui.button("Reset Picture").on_hover_text(
+                        "Gamma, brightness, contrast, saturation, sharpening, rotation, aspect, \
+                         crop and color overrides",
+                    );
+                    if reset.clicked() {
+                        self.reset_picture();
+                    }
+                    let mut deinterlace = self.deinterlace;
+                    ComboBox::from_id_salt("deinterlace")
+                        .selected_text(format!(
+                            "Deinterlace: {}",
+                            Self::deinterlace_name(deinterlace)
+                        ))
+                        .show_ui(ui, |ui| {
+                            for mode in [Deinterlace::Auto, Deinterlace::On, Deinterlace::Off] {
+                                ui.selectable_value(
+                                    &mut deinterlace,
+                                    mode,
+                                    Self::deinterlace_name(mode),
+                                );
+                            }
+                        });
+                    if deinterlace != self.deinterlace {
+                        self.deinterlace = deinterlace;
+                        // GStreamer paths deinterlace in the pipeline, which
+                        // has to be rebuilt.
+                        if self.video_capture.is_some() {
+                            self.set_video(self.selected_video);
+                        }
+                    }
+                    let preset = self.latency_preset();
+                    let preset_text = preset.map(Self::latency_preset_name).unwrap_or("Custom");
+                    let mut chosen = preset;
+                    ComboBox::from_id_salt("latency_preset")
+                        .selected_text(format!("Preset: {preset_text}"))
+                        .show_ui(ui, |ui| {
+                            for p in [LatencyPreset::LowLatency, LatencyPreset::Smooth] {
+                                ui.selectable_value(
+                                    &mut chosen,
+                                    Some(p),
+                                    Self::latency_preset_name(p),
+                                );
+                            }
+                        });
+                    if chosen != preset
+                        && let Some(p) = chosen
+                    {
+                        self.apply_latency_preset(p);
+                    }
+                    let mut frame_policy = self.frame_policy;
+                    ComboBox::from_id_salt("frame_policy")
+                        .selected_text(format!("Frames: {}", Self::frame_policy_name(frame_policy)))
+                        .show_ui(ui, |ui| {
+                            for p in [FramePolicy::LowLatency, FramePolicy::Smooth] {
+                                let name = Self::frame_policy_name(p);
+                                ui.selectable_value(&mut frame_policy, p, name);
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "Low Latency shows only the newest frame; Smooth queues a few \
+                             and shows them in order",
+                        );
+                    if frame_policy != self.frame_policy {
+                        self.set_frame_policy(frame_policy);
+                    }
+                    let mut frame_latency = self.frame_latency;
+                    ComboBox::from_id_salt("frame_latency")
+                        .selected_text(format!("Frame Latency: {frame_latency}"))
+                        .show_ui(ui, |ui| {
+                            for n in 1..=3 {
+                                ui.selectable_value(&mut frame_latency, n, n.to_string());
+                            }
+                        });
+                    self.set_frame_latency(frame_latency);
+                    let response = ui
+                        .add(
+                            egui::DragValue::new(&mut self.fps_cap)
+                                .range(0..=240)
+                                .prefix("FPS Cap: ")
+                                .custom_formatter(|v, _| {
+                                    if v == 0.0 {
+                                        "Off".to_string()
+                                    } else {
+                                        format!("{v:.0}")
+                                    }
+                                }),
+                        )
+                        .on_hover_text(
+                            "Limits how often the picture is redrawn; capture keeps its rate",
+                        );
+                    if response.changed() {
+                        self.settings.set("display", "fps_cap", self.fps_cap);
+                    }
+                    if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                        self.save_settings();
+                    }
+                    let mut always_on_top = self.always_on_top;
+                    if ui.checkbox(&mut always_on_top, "Always on Top").changed() {
+                        self.always_on_top = always_on_top;
+                        self.always_on_top_request = Some(always_on_top);
+                        self.settings.set("window", "always_on_top", always_on_top);
+                        self.save_settings();
+                    }
+                    let mut fullscreen_monitor = self.fullscreen_monitor.clone();
+                    ComboBox::from_id_salt("fullscreen_monitor")
+                        .selected_text(format!(
+                            "Fullscreen Monitor: {}",
+                            fullscreen_monitor.as_deref().unwrap_or("Current")
+                        ))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut fullscreen_monitor, None, "Current");
+                            for name in &self.monitors {
+                                ui.selectable_value(
+                                    &mut fullscreen_monitor,
+                                    Some(name.clone()),
+                                    name,
+                                );
+                            }
+                        });
+                    if fullscreen_monitor != self.fullscreen_monitor {
+                        let value = fullscreen_monitor.clone().unwrap_or_default();
+                        self.settings.set("window", "fullscreen_monitor", value);
+                        self.save_settings();
+                        self.fullscreen_monitor = fullscreen_monitor;
+                    }
+                    let mut compact = self.compact;
+                    if ui.checkbox(&mut compact, "Compact").changed() {
+                        self.compact = compact;
+                        self.compact_request = Some(compact);
+                        if compact && self.fullscreen {
+                            self.fullscreen_request = Some(false);
+                        }
+                    }
+                    let button_text = if self.fullscreen {
+                        "Exit Fullscreen"
+                    } else {
+                        "Fullscreen"
+                    };
+                    if ui.button(button_text).clicked() {
+                        self.toggle_fullscreen();
+                    }
+                    let can_fit = self.video_capture.is_some() && !self.fullscreen && !self.compact;
+                    if ui
+                        .add_enabled(can_fit, egui::Button::new("Fit to Video"))
+                        .on_hover_text("Resize the window to the picture's shape")
+                        .clicked()
+                    {
+                        self.fit_window_request = true;
+                    }
+                    if ui.checkbox(&mut self.auto_fit, "Auto Fit").changed() {
+                        self.settings.set("window", "auto_fit", self.auto_fit);
+                        self.save_settings();
+                    }
+                });
+                if self.network_prompt {
+                    ui.horizontal(|ui| {
+                        ui.label("URL:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.network_url)
+                                .hint_text("rtsp://host:8554/stream")
+                                .desired_width(320.0),
+                        );
+                        if ui.button("Connect").clicked() {
+                            self.connect_network_source();
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.network_prompt = false;
+                        }
+                    });
+                }
+                if let Some((width, height)) = self.capture_size() {
+                    egui::CollapsingHeader::new("Display")
+                        .id_salt("display")
+                        .show(ui, |ui| {
+                            self.crop_ui(ui, width, height);
+                            self.signal_ui(ui);
+                            self.video_controls_ui(ui);
+                        });
+                    egui::CollapsingHeader::new("Color")
+                        .id_salt("color")
+                        .show(ui, |ui| self.color_override_ui(ui));
+                }
+                if self.effect == Effect::Crt {
+                    egui::CollapsingHeader::new("CRT")
+                        .id_salt("crt")
+                        .show(ui, |ui| self.crt_ui(ui));
+                }
+                egui::CollapsingHeader::new("Clock")
+                    .id_salt("clock")
+                    .show(ui, |ui| self.clock_ui(ui));
+                if self.video_devices.len() > 1 || self.pip_id.is_some() {
+                    egui::CollapsingHeader::new("Picture in Picture")
+                        .id_salt("pip")
+                        .show(ui, |ui| self.pip_ui(ui));
+                }
+                egui::CollapsingHeader::new("Hotkeys")
+                    .id_salt("hotkeys")
+                    .show(ui, |ui| self.hotkeys_ui(ui));
+                for (source, err) in self.errors.current() {
+                    ui.colored_label(
+                        Color32::LIGHT_RED,
+                        format!("{}: {err}", Self::error_source_name(source)),
+                    );
+                }
+                if !self.errors.history.is_empty() {
+                    egui::CollapsingHeader::new(format!(
+                        "Recent errors ({})",
+                        self.errors.history.len()
+                    ))
+                    .id_salt("error_history")
+                    .show(ui, |ui| {
+                        for (at, source, err) in self.errors.history.iter().rev() {
+                            let secs = at.as_secs();
+                            ui.label(format!(
+                                "[{:02}:{:02}:{:02}] {}: {err}",
+                                secs / 3600,
+                                secs / 60 % 60,
+                                secs % 60,
+                                Self::error_source_name(*source)
+                            ));
+                        }
+                    });
+                }
+            });
+        }
+        if self.crop_adjust {
+            let crop = self.crop;
+            egui::Area::new("crop_overlay".into())
+                .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -16.0))
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "Crop: {} edge  L{} T{} R{} B{}",
+                        Self::crop_edge_name(self.crop_edge),
+                        crop.left,
+                        crop.top,
+                        crop.right,
+                        crop.bottom
+                    ));
+                    ui.label(format!(
+                        "Arrows: move edge (Shift: x10)  Tab: next edge  {}: done",
+                        self.binding(Action::CropAdjust)
+                    ));
+                });
+        }
+        let starting = self.video_start.is_some();
+        let status = if starting {
+            Some("Starting capture…")
+        } else if self.video_capture.is_none() {
+            None
+        } else if !self.has_frame {
+            Some("No signal / waiting…")
+        } else {
+            self.no_signal.then_some("No signal")
+        };
+        if let Some(text) = status {
+            egui::Area::new("signal_overlay".into())
+                .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        if starting {
+                            ui.spinner();
+                        }
+                        ui.label(egui::RichText::new(text).size(24.0).color(Color32::GRAY));
+                    });
+                });
+        }
+        if self.video_paused && self.video_capture.is_some() {
+            egui::Area::new("paused_overlay".into())
+                .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 40.0))
+                .show(ctx, |ui| {
+                    ui.label(
+                        egui::RichText::new("PAUSED")
+                            .strong()
+                            .color(Color32::YELLOW),
+                    );
+                });
+        }
+        if self.show_guides && self.video_capture.is_some() {
+            self.draw_guides(ctx);
+        }
+        if self.scope_enabled()
+            && let Some(histogram) = self.scope.as_ref()
+        {
+            Self::draw_scope(ctx, histogram);
+        }
+        self.clock_overlay(ctx);
+        if self.show_stats || self.stats_server.is_some() {
+            self.update_stats();
+            self.publish_stats();
+        }
+        if self.show_stats {
+            self.update_audio_levels();
+        }
+        if self.show_stats
+            && let Some(cap) = self.video_capture.as_ref()
+        {
+            let queue_len = cap.rx.len();
+            let info = &cap.info;
+            let fps_text = info
+                .fps
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "auto".to_string());
+            egui::Area::new("stats_overlay".into())
+                .fixed_pos(egui::pos2(8.0, 40.0))
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "Video: {} {}x{} @{}",
+                        info.format, info.width, info.height, fps_text
+                    ));
+                    ui.label(format!("FPS: {:.1}", self.stats.fps));
+                    ui.label(format!("Decode: {} us", self.stats.decode_us));
+                    ui.label(format!("Drops/s: {:.1}", self.stats.drops_per_s));
+                    ui.label(format!("Queue: {queue_len}"));
+                    if let Some(surface) = self.surface {
+                        ui.label(format!(
+                            "Surface: {:?} {}x{} {:?}",
+                            surface.format, surface.width, surface.height, surface.present_mode
+                        ));
+                        ui.label(format!("Frame latency: {}", surface.frame_latency));
+                        ui.label(format!(
+                            "sRGB decode: {} ({})",
+                            Self::srgb_output_name(Some(surface.srgb_output)),
+                            if self.srgb_output.is_some() {
+                                "forced"
+                            } else {
+                                "auto"
+                            }
+                        ));
+                    }
+                    if let Some(fmt) = self.stats.last_frame_format {
+                        ui.label(format!("Frame: {}", Self::format_name(fmt)));
+                    }
+                    if let Some(color) = self.stats.last_frame_color {
+                        ui.label(format!(
+                            "Color: {} {}, {}",
+                            Self::color_matrix_name(color.matrix),
+                            Self::color_range_name(color.range),
+                            Self::color_transfer_name(color.transfer)
+                        ));
+                    }
+                    if let Some(ms) = self.stats.latency_ms {
+                        ui.label(format!("Capture to render: {ms:.1} ms"));
+                    }
+                    if let Some(format) = self.audio_playback.as_ref().and_then(|p| p.format()) {
+                        ui.label(format!("Audio format: {format}"));
+                    }
+                    if !self.audio_levels.is_empty() {
+                        ui.label("Audio:");
+                        for &level in &self.audio_levels {
+                            Self::level_bar(ui, level);
+                        }
+                    }
+                });
+        }
+        if let Some(marker) = self.latency_probe.tick() {
+            let color = match marker {
+                Marker::Black => Color32::BLACK,
+                Marker::White => Color32::WHITE,
+            };
+            ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("latency_marker"),
+            ))
+            .rect_filled(ctx.content_rect(), 0.0, color);
+        }
+        let hotplug = self
+            .device_watch
+            .as_ref()
+            .is_some_and(|rx| rx.try_recv().is_ok());
+        if hotplug || (show_ui && self.last_refresh.elapsed().as_secs() >= 5) {
+            self.refresh_devices();
+        }
+    }
+
+    fn update_audio_levels(&mut self) {
+        let peaks = self
+            .audio_playback
+            .as_ref()
+            .map(|p| p.take_peaks())
+            .unwrap_or_default();
+        self.audio_levels.resize(peaks.len(), 0.0);
+        for (level, peak) in self.audio_levels.iter_mut().zip(peaks) {
+            // Fall back gradually so short peaks stay readable.
+            *level = peak.max(*level * 0.92);
+        }
+    }
+
+    // Peak meter on a -60..0 dBFS scale.
+    fn level_bar(ui: &mut egui::Ui, level: f32) {
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(160.0, 8.0), egui::Sense::hover());
+        let db = 20.0 * level.max(1e-6).log10();
+        let color = if db > -3.0 {
+            Color32::RED
+        } else if db > -12.0 {
+            Color32::YELLOW
+        } else {
+            Color32::GREEN
+        };
+        let mut bar = rect;
+        bar.set_width(rect.width() * ((db + 60.0) / 60.0).clamp(0.0, 1.0));
+        ui.painter().rect_filled(rect, 0.0, Color32::from_gray(40));
+        ui.painter().rect_filled(bar, 0.0, color);
+    }
+
+    pub fn next_video_device(&mut self) {
+        self.cycle_video_device(true);
+    }
+
+    pub fn previous_video_device(&mut self) {
+        self.cycle_video_device(false);
+    }
+
+    // None is one more stop in the cycle, between the last device and the
+    // first. The PiP device is stepped over; None never is, so the loop ends.
+    fn cycle_video_device(&mut self, forward: bool) {
+        let stops = self.video_devices.len() + 1;
+        let pip = self.pip_index().map(|i| i + 1);
+        let mut stop = self.selected_video.map_or(0, |i| i + 1);
+        loop {
+            stop = if forward {
+                (stop + 1) % stops
+            } else {
+                (stop + stops - 1) % stops
+            };
+            if Some(stop) != pip {
+                break;
+            }
+        }
+        self.set_video(stop.checked_sub(1));
+    }
+
+    // Bindings live in the [hotkeys] section.
+    pub fn binding(&self, action: Action) -> &str {
+        self.settings
+            .get("hotkeys", action.setting())
+            .unwrap_or(action.default_binding())
+    }
+
+    pub fn is_rebinding(&self) -> bool {
+        self.rebinding.is_some()
+    }
+
+    // None cancels, leaving the old binding in place.
+    pub fn finish_rebinding(&mut self, binding: Option<String>) {
+        let Some(action) = self.rebinding.take() else {
+            return;
+        };
+        if let Some(binding) = binding {
+            self.settings.set("hotkeys", action.setting(), binding);
+            self.save_settings();
+        }
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        self.fullscreen_request = Some(!self.fullscreen);
+    }
+
+    pub fn toggle_stats(&mut self) {
+        self.show_stats = !self.show_stats;
+        self.apply_stats_enabled();
+    }
+
+    fn connect_network_source(&mut self) {
+        let url = self.network_url.trim().to_string();
+        if !platform::is_network_source(&url) {
+            self.errors
+                .set(ErrorSource::Video, format!("Not a network URL: {url}"));
+            return;
+        }
+        if !self.network_sources.iter().any(|d| d.id == url) {
+            self.network_sources.push(DeviceInfo {
+                id: url.clone(),
+                name: url.clone(),
+            });
+            self.video_devices.push(DeviceInfo {
+                id: url.clone(),
+                name: url.clone(),
+            });
+        }
+        self.network_prompt = false;
+        let index = self.video_devices.iter().position(|d| d.id == url);
+        self.set_video(index);
+    }
+
+    fn poll_reconnect(&mut self) {
+        if self.video_capture.as_ref().is_some_and(|cap| cap.is_lost()) {
+            self.reconnect_id = self
+                .selected_video
+                .and_then(|i| self.video_devices.get(i))
+                .map(|d| d.id.clone());
+            self.recorder.stop();
+            self.video_capture = None;
+            self.video_controls.clear();
+            self.reconnect_at = Instant::now();
+            self.errors.set(
+                ErrorSource::Video,
+                "Video device lost, reconnecting…".to_string(),
+            );
+            self.title_request = Some(self.window_title());
+        }
+        if self.reconnect_id.is_some() && self.reconnect_at.elapsed() >= RECONNECT_INTERVAL {
+            self.refresh_devices();
+        }
+    }
+
+    // Enumeration can take hundreds of milliseconds, so it runs on a worker
+    // thread and poll_device_scan applies the result.
+    fn refresh_devices(&mut self) {
+        self.last_refresh = Instant::now();
+        if self.device_scan.is_some() {
+            return;
+        }
+        let scan_audio = self.audio_devices.is_empty();
+        let scan_outputs = self.audio_outputs.is_empty();
+        let (tx, rx) = bounded(1);
+        let spawned = std::thread::Builder::new()
+            .name("device-scan".to_string())
+            .spawn(move || {
+                let _ = tx.send(DeviceScan {
+                    video: platform::list_video_devices(),
+                    audio: scan_audio.then(audio::list_input_devices),
+                    outputs: scan_outputs.then(audio::list_output_devices),
+                });
+            });
+        if spawned.is_ok() {
+            self.device_scan = Some(rx);
+        }
+    }
+
+    fn poll_device_scan(&mut self) {
+        let Some(rx) = self.device_scan.as_ref() else {
+            return;
+        };
+        let scan = match rx.try_recv() {
+            Ok(scan) => scan,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.device_scan = None;
+                return;
+            }
+        };
+        self.device_scan = None;
+        // Enumeration errors are only reported for the scan at startup;
+        // later refreshes keep the last good lists.
+        let launch = self.launch_devices.take();
+        if launch.is_some() {
+            if let Err(e) = &scan.video {
+                self.errors.set(ErrorSource::Video, e.to_string());
+            }
+            for result in [&scan.audio, &scan.outputs].into_iter().flatten() {
+                if let Err(e) = result {
+                    self.errors.set(ErrorSource::Audio, e.to_string());
+                }
+            }
+        }
+        self.apply_device_scan(scan);
+        let Some((video, audio)) = launch else { return };
+        if let Some(query) = video {
+            match find_device(self.video_devices.iter(), &query) {
+                Some(i) => self.set_video(Some(i)),
+                None => self.errors.set(
+                    ErrorSource::Video,
+                    format!("No video device matches {query:?}"),
+                ),
+            }
+        }
+        if let Some(query) = audio {
+            match find_device(self.audio_devices.iter().map(|d| &d.info), &query) {
+                Some(i) => self.set_audio(Some(i)),
+                None => self.errors.set(
+                    ErrorSource::Audio,
+                    format!("No audio device matches {query:?}"),
+                ),
+            }
+        }
+    }
+
+    fn apply_device_scan(&mut self, scan: DeviceScan) {
+        if let Ok(mut v) = scan.video {
+            v.extend(self.network_sources.iter().cloned());
+            let selected_id = self
+                .selected_video
+                .and_then(|i| self.video_devices.get(i))
+                .map(|d| d.id.clone());
+            self.video_devices = v;
+            if let Some(id) = self.reconnect_id.clone() {
+                // Matched by id since indices shift while it's unplugged.
+                self.reconnect_at = Instant::now();
+                if let Some(i) = self.video_devices.iter().position(|d| d.id == id) {
+                    // Keeping the selection keeps the chosen mode and rate.
+                    self.selected_video = Some(i);
+                    self.set_video(Some(i));
+                    match self.video_start.as_mut() {
+                        Some(start) => start.reconnect = true,
+                        None => self.reconnect_id = Some(id),
+                    }
+                }
+            } else if let Some(id) = selected_id {
+                // Devices coming and going shift the indices, so the selection
+                // follows its id.
+                match self.video_devices.iter().position(|d| d.id == id) {
+                    Some(i) => self.selected_video = Some(i),
+                    None => self.set_video(None),
+                }
+            }
+            if self.pip_id.is_some() && self.pip_index().is_none() {
+                self.set_pip(None);
+            }
+        }
+        if self.audio_devices.is_empty() {
+            if let Some(Ok(v)) = scan.audio {
+                self.audio_devices = v;
+                if let Some(idx) = self.selected_audio {
+                    if idx >= self.audio_devices.len() {
+                        self.set_audio(None);
+                    }
+                }
+            }
+        }
+        if self.audio_outputs.is_empty()
+            && let Some(Ok(v)) = scan.outputs
+        {
+            let name = self.settings.get("audio", "output");
+            self.selected_output = name.and_then(|n| v.iter().position(|d| d.info.name == n));
+            self.audio_outputs = v;
+        }
+    }
+
+    // Shown with nothing selected; an empty list may just not be in yet.
+    fn empty_device_label<T>(&self, kind: &str, devices: &[T]) -> String {
+        if devices.is_empty() && self.device_scan.is_some() {
+            format!("{kind}: Scanning…")
+        } else {
+            format!("{kind}: None")
+        }
+    }
+
+    fn pip_index(&self) -> Option<usize> {
+        let id = self.pip_id.as_ref()?;
+        self.video_devices.iter().position(|d| &d.id == id)
+    }
+
+    // The preview is capped at 720p; it only ever fills a corner.
+    fn set_pip(&mut self, sel: Option<usize>) {
+        if let Some(mut cap) = self.pip_capture.take() {
+            cap.stop();
+        }
+        let previous: Vec<_> = self
+            .pip_start
+            .take()
+            .map(VideoStart::replace)
+            .into_iter()
+            .collect();
+        let device = sel.and_then(|i| self.video_devices.get(i));
+        self.pip_id = device.map(|d| d.id.clone());
+        let Some(device) = device else {
+            self.errors.clear(ErrorSource::Pip);
+            return;
+        };
+        let id = device.id.clone();
+        let waker = self.frame_waker.clone();
+        let open = move |id: &str| {
+            let max_size = Some((1280, 720));
+            platform::start_video_capture(id, max_size, None, None, Deinterlace::Auto, None, waker)
+        };
+        match self.spawn_start(id, previous, open) {
+            Ok(start) => self.pip_start = Some(start),
+            Err(e) => {
+                self.pip_id = None;
+                self.errors.set(ErrorSource::Pip, e.to_string());
+            }
+        }
+    }
+
+    fn poll_pip_start(&mut self) {
+        let Some(start) = self.pip_start.as_ref() else {
+            return;
+        };
+        let result = match start.rx.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err(anyhow!("PiP capture failed to start")),
+        };
+        self.pip_start = None;
+        match result {
+            Ok(cap) => {
+                self.pip_capture = Some(cap);
+                self.apply_capture_paused();
+                self.errors.clear(ErrorSource::Pip);
+            }
+            Err(e) => {
+                self.pip_id = None;
+                self.errors.set(ErrorSource::Pip, e.to_string());
+            }
+        }
+    }
+
+    pub fn set_recording(&mut self, recording: bool) {
+        if !recording {
+            self.recorder.stop();
+            self.apply_capture_paused();
+            return;
+        }
+        let fps = self.video_capture.as_ref().and_then(|cap| cap.info.fps);
+        if let Err(e) = self.recorder.start(fps) {
+            self.errors.set(ErrorSource::Recording, e.to_string());
+        }
+        self.apply_capture_paused();
+    }
+
+    pub fn set_frame_waker(&mut self, waker: platform::FrameWaker) {
+        self.frame_waker = Some(waker);
+    }
+
+    // How soon to redraw with no input and no new frame. Overlays that
+    // tick on their own need a steady rate; otherwise this only polls for
+    // devices, reconnects and errors.
+    pub fn redraw_interval(&self) -> Duration {
+        let stopwatch = self.clock_mode == ClockMode::Stopwatch && self.stopwatch.is_running();
+        let interval = if stopwatch || self.show_stats || self.latency_probe.is_running() {
+            Duration::from_millis(16)
+        } else {
+            Duration::from_millis(250)
+        };
+        interval.max(self.frame_interval().unwrap_or_default())
+    }
+
+    pub fn set_window_hidden(&mut self, hidden: bool) {
+        if self.window_hidden != hidden {
+            self.window_hidden = hidden;
+            self.apply_capture_paused();
+            // The window may not have been redrawn while hidden, which leaves
+            // the watch timing from before it was hidden.
+            if !hidden && let Some(cap) = &self.video_capture {
+                self.signal_watch
+                    .restart(cap.stats.delivered(), Instant::now());
+            }
+        }
+    }
+
+    // Nothing shows the frames while the window is hidden, but a recording
+    // still needs them.
+    fn apply_capture_paused(&self) {
+        if let Some(cap) = &self.video_capture {
+            cap.set_paused(self.window_hidden && !self.recorder.is_recording());
+        }
+        if let Some(cap) = &self.pip_capture {
+            cap.set_paused(self.window_hidden);
+        }
+    }
+
+    fn set_video(&mut self, sel: Option<usize>) {
+        // A replaced start that is already opening its device can't be
+        // stopped; the capture it opens is closed once nobody takes it. Each
+        // start waits for the ones before it to let go of the device, so a
+        // slow open delays the next start too.
+        let mut previous: Vec<_> = self
+            .video_start
+            .take()
+            .map(VideoStart::replace)
+            .into_iter()
+            .collect();
+        // Most devices can only be opened once, so the main picture takes
+        // the device over from the PiP, opening or open.
+        if sel.is_some() && sel == self.pip_index() {
+            previous.extend(self.pip_start.take().map(VideoStart::replace));
+            self.set_pip(None);
+        }
+        self.reconnect_id = None;
+        self.replay.clear();
+        self.replay_pos = None;
+        // A recording is tied to the capture's format; restarting ends it.
+        self.recorder.stop();
+        if let Some(mut cap) = self.video_capture.take() {
+            cap.stop();
+        }
+        if sel != self.selected_video {
+            self.video_modes = sel
+                .and_then(|i| platform::list_video_modes(&self.video_devices[i].id).ok())
+                .unwrap_or_default();
+            self.selected_video = sel;
+            self.load_device_prefs();
+            self.refresh_frame_rates();
+        }
+        self.selected_video = sel;
+        if let Some(i) = sel {
+            self.start_video(self.video_devices[i].id.clone(), previous);
+        }
+        self.title_request = Some(self.window_title());
+    }
+
+    fn start_video(&mut self, id: String, previous: Vec<JoinHandle<()>>) {
+        let max_size = self.capture_max_size();
+        // An explicitly chosen mode wins over the preset.
+        let mode = self.video_mode.clone().or_else(|| self.preset_mode());
+        let fps = self.frame_rate;
+        let deinterlace = self.deinterlace;
+        let on_frame = self.frame_callback();
+        let waker = self.frame_waker.clone();
+        let open = move |id: &str| {
+            platform::start_video_capture(
+                id,
+                max_size,
+                mode.as_ref(),
+                fps,
+                deinterlace,
+                Some(on_frame),
+                waker,
+            )
+        };
+        match self.spawn_start(id, previous, open) {
+            Ok(start) => self.video_start = Some(start),
+            Err(e) => self.errors.set(ErrorSource::Video, e.to_string()),
+        }
+    }
+
+    // Opening a device can take a second or more (GStreamer tries several
+    // MJPEG pipelines), so it runs on a worker thread.
+    fn spawn_start(
+        &self,
+        id: String,
+        previous: Vec<JoinHandle<()>>,
+        open: impl FnOnce(&str) -> Result<platform::VideoCapture> + Send + 'static,
+    ) -> std::io::Result<VideoStart> {
+        let waker = self.frame_waker.clone();
+        let (tx, rx) = bounded(1);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let thread_cancel = cancel.clone();
+        let thread_id = id.clone();
+        let thread = std::thread::Builder::new()
+            .name("video-start".to_string())
+            .spawn(move || {
+                // A replaced start may still hold the device open.
+                for previous in previous {
+                    let _ = previous.join();
+                }
+                if thread_cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let result = open(&thread_id);
+                // Once the start was replaced nobody is listening, and the
+                // returned capture is closed again as it drops.
+                if tx.send(result).is_ok()
+                    && let Some(wake) = waker
+                {
+                    wake();
+                }
+            })?;
+        Ok(VideoStart {
+            rx,
+            id,
+            reconnect: false,
+            cancel,
+            thread,
+        })
+    }
+
+    fn poll_video_start(&mut self) {
+        let Some(start) = self.video_start.as_ref() else {
+            return;
+        };
+        let result = match start.rx.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err(anyhow!("Video capture failed to start")),
+        };
+        let Some(start) = self.video_start.take() else {
+            return;
+        };
+        match result {
+            Ok(cap) => {
+                cap.set_frame_policy(self.frame_policy);
+                self.video_capture = Some(cap);
+                self.fit_window_request = self.auto_fit;
+                self.signal_watch = SignalWatch::new(Instant::now());
+                self.no_signal = false;
+                self.apply_capture_paused();
+                self.load_video_controls();
+                self.load_color_override();
+                self.apply_stats_enabled();
+                self.errors.clear(ErrorSource::Video);
+            }
+            Err(e) => {
+                if start.reconnect {
+                    self.reconnect_id = Some(start.id);
+                    self.reconnect_at = Instant::now();
+                }
+                self.errors.set(ErrorSource::Video, e.to_string());
+            }
+        }
+        self.title_request = Some(self.window_title());
+    }
+
+    // Runs on the capture thread for every frame.
+    fn frame_callback(&self) -> platform::FrameCallback {
+        let probe = self.latency_probe.callback();
+        let recorder = self.recorder.callback();
+        Arc::new(move |frame: &VideoFrame| {
+            probe(frame);
+            recorder(frame);
+        })
+    }
+
+    fn window_title(&self) -> String {
+        let Some(cap) = self.video_capture.as_ref() else {
+            return "CaptureCardGaming".to_string();
+        };
+        let name = self
+            .selected_video
+            .and_then(|i| self.video_devices.get(i))
+            .map(|d| d.name.as_str())
+            .unwrap_or("Unknown");
+        let info = &cap.info;
+        if info.width == 0 || info.height == 0 {
+            return format!("CaptureCardGaming — {name}");
+        }
+        let fps = info.fps.map(|v| format!("@{v}")).unwrap_or_default();
+        format!(
+            "CaptureCardGaming — {name} {}x{}{fps}",
+            info.width, info.height
+        )
+    }
+
+    // Auto follows the monitor so we never capture more than can be shown;
+    // the fixed presets cap at that resolution, see preset_mode for the
+    // exact size.
+    fn capture_max_size(&self) -> Option<(u32, u32)> {
+        match self.resolution_preset {
+            ResolutionPreset::Auto => self.target_capture_size,
+            ResolutionPreset::Uhd => Some((3840, 2160)),
+            ResolutionPreset::Qhd => Some((2560, 1440)),
+            ResolutionPreset::FullHd => Some((1920, 1080)),
+            ResolutionPreset::Hd => Some((1280, 720)),
+        }
+    }
+
+    // A fixed preset pins the device's mode at exactly its size when there
+    // is one, the fastest and best format since the list is sorted that
+    // way. Otherwise the platform picks within capture_max_size.
+    fn preset_mode(&self) -> Option<platform::VideoInfo> {
+        if self.resolution_preset == ResolutionPreset::Auto {
+            return None;
+        }
+        let size = self.capture_max_size()?;
+        self.video_modes
+            .iter()
+            .find(|m| (m.width, m.height) == size)
+            .cloned()
+    }
+
+    // Keeps the chosen rate only while the current mode still offers it.
+    fn refresh_frame_rates(&mut self) {
+        self.frame_rates = self
+            .selected_video
+            .and_then(|i| self.video_devices.get(i))
+            .and_then(|d| platform::list_frame_rates(&d.id, self.video_mode.as_ref()).ok())
+            .unwrap_or_default();
+        if self
+            .frame_rate
+            .is_some_and(|v| !self.frame_rates.contains(&v))
+        {
+            self.frame_rate = None;
+        }
+    }
+
+    fn video_mode_label(mode: &platform::VideoInfo) -> String {
+        let fps = mode.fps.map(|v| format!(" @{v}")).unwrap_or_default();
+        format!("{}x{} {}{fps}", mode.width, mode.height, mode.format)
+    }
+
+    fn resolution_preset_name(preset: ResolutionPreset) -> &'static str {
+        match preset {
+            ResolutionPreset::Auto => "Auto",
+            ResolutionPreset::Uhd => "4K",
+            ResolutionPreset::Qhd => "1440p",
+            ResolutionPreset::FullHd => "1080p",
+            ResolutionPreset::Hd => "720p",
+        }
+    }
+
+    fn apply_stats_enabled(&mut self) {
+        if let Some(cap) = self.video_capture.as_ref() {
+            cap.stats
+                .set_enabled(self.show_stats || self.stats_server.is_some());
+            if self.show_stats {
+                cap.stats.reset();
+                self.stats.reset();
+            }
+        }
+    }
+
+    fn update_stats(&mut self) {
+        let cap = match self.video_capture.as_ref() {
+            Some(c) => c,
+            None => return,
+        };
+        let snap = cap.stats.snapshot();
+        let now = Instant::now();
+        let dt = now.duration_since(self.stats.last_at);
+        if dt >= Duration::from_millis(250) {
+            let df = snap.frames.saturating_sub(self.stats.last_frames);
+            let dd = snap.drops.saturating_sub(self.stats.last_drops);
+            let secs = dt.as_secs_f32().max(0.001);
+            self.stats.fps = df as f32 / secs;
             self.stats.drops_per_s = dd as f32 / secs;
             self.stats.last_frames = snap.frames;
             self.stats.last_drops = snap.drops;
@@ -329,41 +3063,277 @@ impl App {
         self.stats.decode_us = snap.decode_us;
     }
 
+    fn publish_stats(&self) {
+        let Some(server) = self.stats_server.as_ref() else {
+            return;
+        };
+        let report = match self.video_capture.as_ref() {
+            Some(cap) => StatsReport {
+                capturing: true,
+                device: self
+                    .selected_video
+                    .and_then(|i| self.video_devices.get(i))
+                    .map(|d| d.name.clone())
+                    .unwrap_or_default(),
+                width: cap.info.width,
+                height: cap.info.height,
+                format: cap.info.format.clone(),
+                fps: self.stats.fps,
+                drops_per_s: self.stats.drops_per_s,
+                decode_us: self.stats.decode_us,
+            },
+            None => StatsReport::default(),
+        };
+        server.publish(report);
+    }
+
     fn format_name(format: VideoFormat) -> &'static str {
         match format {
             VideoFormat::Rgba => "RGBA",
             VideoFormat::Yuyv => "YUYV",
+            VideoFormat::Uyvy => "UYVY",
             VideoFormat::Nv12 => "NV12",
+            VideoFormat::I420 => "I420",
+            VideoFormat::P010 => "P010",
+            VideoFormat::Grey => "GREY",
+            VideoFormat::Rgb565 => "RGB565",
+        }
+    }
+
+    fn color_matrix_name(matrix: ColorMatrix) -> &'static str {
+        match matrix {
+            ColorMatrix::Bt601 => "BT.601",
+            ColorMatrix::Bt709 => "BT.709",
+            ColorMatrix::Bt2020 => "BT.2020",
+        }
+    }
+
+    fn color_range_name(range: ColorRange) -> &'static str {
+        match range {
+            ColorRange::Limited => "Limited",
+            ColorRange::Full => "Full",
+        }
+    }
+
+    fn color_transfer_name(transfer: ColorTransfer) -> &'static str {
+        match transfer {
+            ColorTransfer::Bt709 => "BT.709 gamma",
+            ColorTransfer::Srgb => "sRGB",
+            ColorTransfer::Pq => "PQ (HDR)",
+            ColorTransfer::Hlg => "HLG (HDR)",
+            ColorTransfer::Linear => "Linear",
+        }
+    }
+
+    fn latency_preset(&self) -> Option<LatencyPreset> {
+        let options = (
+            self.frame_latency,
+            self.playback_options.low_latency,
+            self.vsync,
+        );
+        match (options, self.frame_policy) {
+            ((1, true, false), FramePolicy::LowLatency) => Some(LatencyPreset::LowLatency),
+            ((2, false, true), FramePolicy::Smooth) => Some(LatencyPreset::Smooth),
+            _ => None,
+        }
+    }
+
+    fn apply_latency_preset(&mut self, preset: LatencyPreset) {
+        let low_latency = preset == LatencyPreset::LowLatency;
+        self.set_frame_latency(if low_latency { 1 } else { 2 });
+        self.set_vsync(!low_latency);
+        self.set_frame_policy(if low_latency {
+            FramePolicy::LowLatency
+        } else {
+            FramePolicy::Smooth
+        });
+        if self.playback_options.low_latency != low_latency {
+            self.playback_options.low_latency = low_latency;
+            if self.audio_playback.is_some() {
+                self.set_audio(self.selected_audio);
+            }
+        }
+    }
+
+    fn set_frame_latency(&mut self, frame_latency: u32) {
+        if frame_latency == self.frame_latency {
+            return;
+        }
+        self.frame_latency = frame_latency;
+        self.settings.set("display", "frame_latency", frame_latency);
+        self.save_settings();
+    }
+
+    fn set_vsync(&mut self, vsync: bool) {
+        if vsync == self.vsync {
+            return;
+        }
+        self.vsync = vsync;
+        self.settings.set("display", "vsync", vsync);
+        self.save_settings();
+    }
+
+    fn set_frame_policy(&mut self, policy: FramePolicy) {
+        if policy == self.frame_policy {
+            return;
+        }
+        self.frame_policy = policy;
+        if let Some(cap) = self.video_capture.as_ref() {
+            cap.set_frame_policy(policy);
+        }
+        self.settings
+            .set("capture", "frame_policy", Self::frame_policy_name(policy));
+        self.save_settings();
+    }
+
+    fn srgb_output_name(srgb_output: Option<bool>) -> &'static str {
+        match srgb_output {
+            None => "Auto",
+            Some(true) => "On",
+            Some(false) => "Off",
+        }
+    }
+
+    fn effect_name(effect: Effect) -> &'static str {
+        match effect {
+            Effect::None => "None",
+            Effect::Crt => "CRT",
+            Effect::Custom => "Custom Shader",
+        }
+    }
+
+    fn crt_mask_name(mask: CrtMask) -> &'static str {
+        match mask {
+            CrtMask::None => "None",
+            CrtMask::ApertureGrille => "Aperture Grille",
+            CrtMask::ShadowMask => "Shadow Mask",
+        }
+    }
+
+    fn latency_preset_name(preset: LatencyPreset) -> &'static str {
+        match preset {
+            LatencyPreset::LowLatency => "Low Latency",
+            LatencyPreset::Smooth => "Smooth",
+        }
+    }
+
+    fn error_source_name(source: ErrorSource) -> &'static str {
+        match source {
+            ErrorSource::Video => "Video",
+            ErrorSource::Audio => "Audio",
+            ErrorSource::Render => "Render",
+            ErrorSource::StatsServer => "Stats server",
+            ErrorSource::Screenshot => "Screenshot",
+            ErrorSource::Recording => "Recording",
+            ErrorSource::Scope => "Scope",
+            ErrorSource::Shader => "Shader",
+            ErrorSource::Pip => "PiP",
+            ErrorSource::Settings => "Settings",
+        }
+    }
+
+    fn crop_edge_name(edge: CropEdge) -> &'static str {
+        match edge {
+            CropEdge::Left => "Left",
+            CropEdge::Top => "Top",
+            CropEdge::Right => "Right",
+            CropEdge::Bottom => "Bottom",
+        }
+    }
+
+    fn scale_filter_name(filter: ScaleFilter) -> &'static str {
+        match filter {
+            ScaleFilter::Linear => "Linear",
+            ScaleFilter::CatmullRom => "Catmull-Rom",
+        }
+    }
+
+    fn frame_policy_name(policy: FramePolicy) -> &'static str {
+        match policy {
+            FramePolicy::LowLatency => "Low Latency",
+            FramePolicy::Smooth => "Smooth",
+        }
+    }
+
+    fn deinterlace_name(mode: Deinterlace) -> &'static str {
+        match mode {
+            Deinterlace::Auto => "Auto",
+            Deinterlace::On => "On",
+            Deinterlace::Off => "Off",
+        }
+    }
+
+    fn rotation_name(rotation: Rotation) -> &'static str {
+        match rotation {
+            Rotation::Deg0 => "0°",
+            Rotation::Deg90 => "90°",
+            Rotation::Deg180 => "180°",
+            Rotation::Deg270 => "270°",
+        }
+    }
+
+    fn aspect_mode_name(mode: AspectMode) -> &'static str {
+        match mode {
+            AspectMode::Auto => "Auto",
+            AspectMode::Force4x3 => "4:3",
+            AspectMode::Force16x9 => "16:9",
+            AspectMode::Stretch => "Stretch",
+        }
+    }
+
+    fn mode_match_name(mode: ModeMatch) -> &'static str {
+        match mode {
+            ModeMatch::Exact => "Exact",
+            ModeMatch::Nearest => "Nearest",
+        }
+    }
+
+    fn clock_mode_name(mode: ClockMode) -> &'static str {
+        match mode {
+            ClockMode::Off => "Off",
+            ClockMode::Clock => "Clock",
+            ClockMode::Stopwatch => "Stopwatch",
+        }
+    }
+
+    fn corner_name(corner: Corner) -> &'static str {
+        match corner {
+            Corner::TopLeft => "Top Left",
+            Corner::TopRight => "Top Right",
+            Corner::BottomLeft => "Bottom Left",
+            Corner::BottomRight => "Bottom Right",
+        }
+    }
+
+    fn scale_mode_name(mode: ScaleMode) -> &'static str {
+        match mode {
+            ScaleMode::Fit => "Fit",
+            ScaleMode::NoUpscale => "No Upscale",
+            ScaleMode::Integer => "Integer",
         }
     }
 
     fn set_audio(&mut self, sel: Option<usize>) {
         self.audio_playback = None;
         self.selected_audio = sel;
+        if let Some(section) = self.audio_settings_section() {
+            self.audio_muted = self.settings.get_parsed(&section, "muted").unwrap_or(false);
+            self.audio_volume = self
+                .settings
+                .get_parsed::<f32>(&section, "volume")
+                .map_or(1.0, |v| v.clamp(0.0, 1.0));
+        }
         if let Some(i) = sel {
-            match audio::start_playback(&self.audio_devices[i]) {
+            let output = self.selected_output.and_then(|o| self.audio_outputs.get(o));
+            match audio::start_playback(&self.audio_devices[i], output, &self.playback_options) {
                 Ok(playback) => {
+                    playback.set_muted(self.audio_muted);
+                    playback.set_volume(self.audio_volume);
                     self.audio_playback = Some(playback);
-                    self.last_error = None;
+                    self.errors.clear(ErrorSource::Audio);
                 }
-                Err(e) => self.last_error = Some(format!("Audio: {e}")),
-            }
-        }
-    }
-
-    // This function exists because just setting the audio device doesn't work
-    // But setting it again works
-    // Basically the issue is "selecting the audio device plays the sound for a second and then nothing... you have to then select none and then back to the audio device for it to actually play the sound"
-    fn set_audio_with_reinit(&mut self, sel: Option<usize>) {
-        match sel {
-            Some(index) => {
-                self.set_audio(Some(index));
-                self.set_audio(None);
-                self.set_audio(Some(index));
-                self.set_audio(None);
-                self.set_audio(Some(index));
+                Err(e) => self.errors.set(ErrorSource::Audio, e.to_string()),
             }
-            None => self.set_audio(None),
         }
     }
 }
@@ -1,7 +1,85 @@
+use std::fmt;
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
 use anyhow::Result;
 
 use crate::types::DeviceInfo;
 
+const METER_CHANNELS: usize = 8;
+
+// Per-channel peak amplitude (0..1) since the last read, as f32 bits. Bits
+// of non-negative floats order like the floats, so fetch_max works on them.
+#[derive(Clone, Default)]
+struct AudioLevels {
+    peaks: Arc<[AtomicU32; METER_CHANNELS]>,
+    channels: Arc<AtomicUsize>,
+}
+
+impl AudioLevels {
+    #[cfg_attr(
+        not(any(target_os = "linux", target_os = "windows", target_os = "macos")),
+        allow(dead_code)
+    )]
+    fn record(&self, channel: usize, peak: f32) {
+        if let Some(slot) = self.peaks.get(channel) {
+            slot.fetch_max(peak.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+            self.channels.fetch_max(channel + 1, Ordering::Relaxed);
+        }
+    }
+
+    #[cfg_attr(
+        not(any(target_os = "linux", target_os = "windows", target_os = "macos")),
+        allow(dead_code)
+    )]
+    fn take(&self) -> Vec<f32> {
+        let channels = self.channels.load(Ordering::Relaxed);
+        self.peaks[..channels]
+            .iter()
+            .map(|p| f32::from_bits(p.swap(0, Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+// What the output side ended up playing, for diagnostics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AudioFormat {
+    pub rate: u32,
+    pub channels: u16,
+    pub bits: u16,
+    pub float: bool,
+}
+
+impl fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let layout = match self.channels {
+            1 => "mono".to_string(),
+            2 => "stereo".to_string(),
+            n => format!("{n} ch"),
+        };
+        let kind = if self.float { "float" } else { "int" };
+        write!(f, "{} Hz, {layout}, {}-bit {kind}", self.rate, self.bits)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlaybackOptions {
+    pub low_latency: bool,
+    // 0 (fastest) to 10 (best) for sample-rate conversion.
+    pub resample_quality: u8,
+}
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        Self {
+            low_latency: true,
+            resample_quality: 0,
+        }
+    }
+}
+
 #[cfg(target_os = "linux")]
 mod gst_audio {
     use super::*;
@@ -17,6 +95,44 @@ mod gst_audio {
 
     pub struct AudioPlayback {
         pipeline: gst::Pipeline,
+        volume: gst::Element,
+        sink: gst::Element,
+        levels: AudioLevels,
+    }
+
+    impl AudioPlayback {
+        pub fn set_muted(&self, muted: bool) {
+            self.volume.set_property("mute", muted);
+        }
+
+        pub fn set_volume(&self, volume: f32) {
+            self.volume.set_property("volume", volume as f64);
+        }
+
+        // Per-channel peaks since the last call.
+        pub fn take_peaks(&self) -> Vec<f32> {
+            self.levels.take()
+        }
+
+        // Read from the caps negotiated on the sink's input.
+        pub fn format(&self) -> Option<AudioFormat> {
+            let caps = self.sink.static_pad("sink")?.current_caps()?;
+            let s = caps.structure(0)?;
+            let format = s.get::<&str>("format").ok()?;
+            // Formats read like S16LE, F32LE or S24_32LE.
+            let float = format.starts_with('F');
+            let digits = format.get(1..)?;
+            let end = digits
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(digits.len());
+            let bits = digits[..end].parse::<u16>().ok()?;
+            Some(AudioFormat {
+                rate: u32::try_from(s.get::<i32>("rate").ok()?).ok()?,
+                channels: u16::try_from(s.get::<i32>("channels").ok()?).ok()?,
+                bits,
+                float,
+            })
+        }
     }
 
     impl Drop for AudioPlayback {
@@ -26,10 +142,18 @@ mod gst_audio {
     }
 
     pub fn list_input_devices() -> Result<Vec<AudioDevice>> {
+        list_devices("Audio/Source")
+    }
+
+    pub fn list_output_devices() -> Result<Vec<AudioDevice>> {
+        list_devices("Audio/Sink")
+    }
+
+    fn list_devices(class: &str) -> Result<Vec<AudioDevice>> {
         gst::init()?;
         let monitor = gst::DeviceMonitor::new();
         let caps = gst::Caps::builder("audio/x-raw").build();
-        let _ = monitor.add_filter(Some("Audio/Source"), Some(&caps));
+        let _ = monitor.add_filter(Some(class), Some(&caps));
         monitor.start()?;
         let devices = monitor.devices();
         monitor.stop();
@@ -73,8 +197,29 @@ mod gst_audio {
         set_i64_prop(elem, "buffer-time", 20_000);
     }
 
-    fn make_audio_sink() -> Result<gst::Element> {
-        let sink = if gst::ElementFactory::find("pipewiresink").is_some() {
+    fn apply_buffering(elem: &gst::Element, options: &PlaybackOptions) {
+        if options.low_latency {
+            apply_low_latency(elem);
+        } else {
+            set_i64_prop(elem, "latency-time", 20_000);
+            set_i64_prop(elem, "buffer-time", 100_000);
+        }
+    }
+
+    fn make_audio_sink(
+        output: Option<&AudioDevice>,
+        options: &PlaybackOptions,
+    ) -> Result<gst::Element> {
+        let sink = if let Some(output) = output {
+            match output.pipewire_target.as_ref() {
+                Some(target) if gst::ElementFactory::find("pipewiresink").is_some() => {
+                    let sink = gst::ElementFactory::make("pipewiresink").build()?;
+                    sink.set_property("target-object", target);
+                    sink
+                }
+                _ => output.device.create_element(Some("audiosink"))?,
+            }
+        } else if gst::ElementFactory::find("pipewiresink").is_some() {
             gst::ElementFactory::make("pipewiresink").build()?
         } else if gst::ElementFactory::find("pulsesink").is_some() {
             gst::ElementFactory::make("pulsesink").build()?
@@ -84,11 +229,15 @@ mod gst_audio {
             gst::ElementFactory::make("autoaudiosink").build()?
         };
         set_bool_prop(&sink, "sync", false);
-        apply_low_latency(&sink);
+        apply_buffering(&sink, options);
         Ok(sink)
     }
 
-    pub fn start_playback(device: &AudioDevice) -> Result<AudioPlayback> {
+    pub fn start_playback(
+        device: &AudioDevice,
+        output: Option<&AudioDevice>,
+        options: &PlaybackOptions,
+    ) -> Result<AudioPlayback> {
         gst::init()?;
         let pipeline = gst::Pipeline::new();
         let src = if let Some(target) = device.pipewire_target.as_ref() {
@@ -103,22 +252,87 @@ mod gst_audio {
             device.device.create_element(Some("audiosrc"))?
         };
         set_bool_prop(&src, "do-timestamp", true);
-        apply_low_latency(&src);
+        apply_buffering(&src, options);
         let queue = gst::ElementFactory::make("queue").build()?;
-        queue.set_property_from_str("leaky", "downstream");
-        queue.set_property_from_str("max-size-buffers", "1");
-        queue.set_property_from_str("max-size-time", "0");
-        queue.set_property_from_str("max-size-bytes", "0");
+        if options.low_latency {
+            queue.set_property_from_str("leaky", "downstream");
+            queue.set_property_from_str("max-size-buffers", "1");
+            queue.set_property_from_str("max-size-time", "0");
+            queue.set_property_from_str("max-size-bytes", "0");
+        } else {
+            queue.set_property_from_str("max-size-buffers", "0");
+            queue.set_property_from_str("max-size-time", "100000000");
+            queue.set_property_from_str("max-size-bytes", "0");
+        }
         let convert = gst::ElementFactory::make("audioconvert").build()?;
         let resample = gst::ElementFactory::make("audioresample").build()?;
         if resample.find_property("quality").is_some() {
-            resample.set_property("quality", 0i32);
+            resample.set_property("quality", i32::from(options.resample_quality.min(10)));
+        }
+        // Metered before the volume element so the bars show the source
+        // level regardless of mute and volume.
+        let level = gst::ElementFactory::make("level")
+            .property("interval", 50_000_000u64)
+            .property("post-messages", true)
+            .build()?;
+        let volume = gst::ElementFactory::make("volume").build()?;
+        let sink = make_audio_sink(output, options)?;
+        pipeline.add_many([&src, &queue, &convert, &resample, &level, &volume, &sink])?;
+        gst::Element::link_many([&src, &queue, &convert, &resample, &level, &volume, &sink])?;
+        let levels = AudioLevels::default();
+        if let Some(bus) = pipeline.bus() {
+            let levels = levels.clone();
+            bus.set_sync_handler(move |_, msg| {
+                if let gst::MessageView::Element(element) = msg.view()
+                    && let Some(s) = element.structure()
+                    && s.name() == "level"
+                {
+                    if let Ok(peaks) = s.get::<gst::glib::ValueArray>("peak") {
+                        for (channel, db) in peaks.iter().enumerate() {
+                            if let Ok(db) = db.get::<f64>() {
+                                levels.record(channel, 10f64.powf(db / 20.0) as f32);
+                            }
+                        }
+                    }
+                    // Nothing pops this bus; don't let level messages pile up.
+                    return gst::BusSyncReply::Drop;
+                }
+                gst::BusSyncReply::Pass
+            });
+        }
+        // Pre-roll first: going straight to Playing let the sink start its
+        // clock before any data arrived, underrun, and go quiet for good.
+        if pipeline.set_state(gst::State::Paused)? == gst::StateChangeSuccess::Async {
+            wait_async_done(&pipeline)?;
         }
-        let sink = make_audio_sink()?;
-        pipeline.add_many([&src, &queue, &convert, &resample, &sink])?;
-        gst::Element::link_many([&src, &queue, &convert, &resample, &sink])?;
         pipeline.set_state(gst::State::Playing)?;
-        Ok(AudioPlayback { pipeline })
+        Ok(AudioPlayback {
+            pipeline,
+            volume,
+            sink,
+            levels,
+        })
+    }
+
+    fn wait_async_done(pipeline: &gst::Pipeline) -> Result<()> {
+        let bus = pipeline
+            .bus()
+            .ok_or_else(|| anyhow::anyhow!("Audio pipeline has no bus"))?;
+        let msg = bus.timed_pop_filtered(
+            gst::ClockTime::from_seconds(2),
+            &[gst::MessageType::AsyncDone, gst::MessageType::Error],
+        );
+        match msg.as_ref().map(|m| m.view()) {
+            Some(gst::MessageView::AsyncDone(_)) => Ok(()),
+            Some(gst::MessageView::Error(err)) => {
+                let _ = pipeline.set_state(gst::State::Null);
+                Err(anyhow::anyhow!("Audio pipeline failed: {}", err.error()))
+            }
+            _ => {
+                let _ = pipeline.set_state(gst::State::Null);
+                Err(anyhow::anyhow!("Audio pipeline failed to pre-roll"))
+            }
+        }
     }
 
     fn pipewire_target_from_props(device: &gst::Device) -> Option<String> {
@@ -147,18 +361,18 @@ mod wasapi_audio {
     use super::*;
     use anyhow::anyhow;
     use std::sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU32, Ordering},
     };
     use std::thread::JoinHandle;
-    use windows::core::{HSTRING, Interface, PWSTR};
     use windows::Win32::Foundation::*;
-    use windows::Win32::Media::Audio::*;
     use windows::Win32::Media::Audio::Endpoints::*;
-    use windows::Win32::System::Com::*;
+    use windows::Win32::Media::Audio::*;
     use windows::Win32::System::Com::StructuredStorage::*;
+    use windows::Win32::System::Com::*;
     use windows::Win32::System::Threading::*;
     use windows::Win32::UI::Shell::PropertiesSystem::*;
+    use windows::core::{GUID, HSTRING, Interface, PWSTR};
 
     pub struct AudioDevice {
         pub info: DeviceInfo,
@@ -166,9 +380,34 @@ mod wasapi_audio {
 
     pub struct AudioPlayback {
         stop: Arc<AtomicBool>,
+        muted: Arc<AtomicBool>,
+        // f32 bits, read by the audio thread for every packet.
+        volume: Arc<AtomicU32>,
+        levels: AudioLevels,
+        // Set by the audio thread once the streams are initialized.
+        format: Arc<Mutex<Option<AudioFormat>>>,
         thread: Option<JoinHandle<()>>,
     }
 
+    impl AudioPlayback {
+        pub fn set_muted(&self, muted: bool) {
+            self.muted.store(muted, Ordering::Relaxed);
+        }
+
+        pub fn set_volume(&self, volume: f32) {
+            self.volume.store(volume.to_bits(), Ordering::Relaxed);
+        }
+
+        // Per-channel peaks since the last call.
+        pub fn take_peaks(&self) -> Vec<f32> {
+            self.levels.take()
+        }
+
+        pub fn format(&self) -> Option<AudioFormat> {
+            *self.format.lock().unwrap()
+        }
+    }
+
     impl Drop for AudioPlayback {
         fn drop(&mut self) {
             self.stop.store(true, Ordering::Relaxed);
@@ -179,10 +418,18 @@ mod wasapi_audio {
     }
 
     pub fn list_input_devices() -> Result<Vec<AudioDevice>> {
+        list_devices(eCapture)
+    }
+
+    pub fn list_output_devices() -> Result<Vec<AudioDevice>> {
+        list_devices(eRender)
+    }
+
+    fn list_devices(flow: EDataFlow) -> Result<Vec<AudioDevice>> {
         let _com = ComInit::new()?;
         let enumerator: IMMDeviceEnumerator =
             unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)? };
-        let collection = enumerator.EnumAudioEndpoints(eCapture, DEVICE_STATE_ACTIVE)?;
+        let collection = enumerator.EnumAudioEndpoints(flow, DEVICE_STATE_ACTIVE)?;
         let count = collection.GetCount()?;
         let mut out = Vec::new();
         for i in 0..count {
@@ -196,20 +443,47 @@ mod wasapi_audio {
         Ok(out)
     }
 
-    pub fn start_playback(device: &AudioDevice) -> Result<AudioPlayback> {
+    pub fn start_playback(
+        device: &AudioDevice,
+        output: Option<&AudioDevice>,
+        options: &PlaybackOptions,
+    ) -> Result<AudioPlayback> {
         let id = device.info.id.clone();
+        let output_id = output.map(|d| d.info.id.clone());
+        let options = *options;
         let stop = Arc::new(AtomicBool::new(false));
+        let muted = Arc::new(AtomicBool::new(false));
+        let volume = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+        let levels = AudioLevels::default();
+        let format = Arc::new(Mutex::new(None));
         let (ready_tx, ready_rx) = std::sync::mpsc::channel();
         let stop_thread = stop.clone();
+        let muted_thread = muted.clone();
+        let volume_thread = volume.clone();
+        let levels_thread = levels.clone();
+        let format_thread = format.clone();
         let handle = std::thread::Builder::new()
             .name("wasapi-audio".to_string())
             .spawn(move || {
-                let res = run_wasapi(&id, stop_thread);
+                let res = run_wasapi(
+                    &id,
+                    output_id.as_deref(),
+                    &options,
+                    stop_thread,
+                    muted_thread,
+                    volume_thread,
+                    levels_thread,
+                    format_thread,
+                );
                 let _ = ready_tx.send(res);
             })?;
         match ready_rx.recv() {
             Ok(Ok(())) => Ok(AudioPlayback {
                 stop,
+                muted,
+                volume,
+                levels,
+                format,
                 thread: Some(handle),
             }),
             Ok(Err(e)) => {
@@ -225,37 +499,63 @@ mod wasapi_audio {
         }
     }
 
-    fn run_wasapi(id: &str, stop: Arc<AtomicBool>) -> Result<()> {
+    // `output_id` picks the render endpoint; None follows the default one.
+    fn run_wasapi(
+        id: &str,
+        output_id: Option<&str>,
+        options: &PlaybackOptions,
+        stop: Arc<AtomicBool>,
+        muted: Arc<AtomicBool>,
+        volume: Arc<AtomicU32>,
+        levels: AudioLevels,
+        format: Arc<Mutex<Option<AudioFormat>>>,
+    ) -> Result<()> {
         let _com = ComInit::new()?;
         let enumerator: IMMDeviceEnumerator =
             unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)? };
         let capture_device = enumerator.GetDevice(&HSTRING::from(id))?;
-        let render_device = enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?;
-        let capture_client: IAudioClient =
-            capture_device.Activate(CLSCTX_ALL, None)?;
-        let render_client: IAudioClient =
-            render_device.Activate(CLSCTX_ALL, None)?;
-        let format = select_format(&capture_client, &render_client)?;
-        let buffer_duration = 100_000;
+        let render_device = match output_id {
+            Some(output_id) => enumerator.GetDevice(&HSTRING::from(output_id))?,
+            None => enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?,
+        };
+        let capture_client: IAudioClient = capture_device.Activate(CLSCTX_ALL, None)?;
+        let render_client: IAudioClient = render_device.Activate(CLSCTX_ALL, None)?;
+        let formats = select_format(&capture_client, &render_client)?;
+        let (capture_format, render_format) = (&formats.capture, &formats.render);
+        let buffer_duration = if options.low_latency {
+            100_000
+        } else {
+            400_000
+        };
         let flags = AUDCLNT_STREAMFLAGS_EVENTCALLBACK | AUDCLNT_STREAMFLAGS_NOPERSIST;
+        // Without a quality asked for, the engine's converter runs in its
+        // cheaper low-latency mode.
+        let render_flags = match (formats.engine_resample, options.resample_quality) {
+            (false, _) => flags,
+            (true, 0) => flags | AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM,
+            (true, _) => {
+                flags | AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM | AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY
+            }
+        };
         unsafe {
             capture_client.Initialize(
                 AUDCLNT_SHAREMODE_SHARED,
                 flags,
                 buffer_duration,
                 0,
-                format.as_ptr(),
+                capture_format.as_ptr(),
                 std::ptr::null(),
             )?;
             render_client.Initialize(
                 AUDCLNT_SHAREMODE_SHARED,
-                flags,
+                render_flags,
                 buffer_duration,
                 0,
-                format.as_ptr(),
+                render_format.as_ptr(),
                 std::ptr::null(),
             )?;
         }
+        *format.lock().unwrap() = Some(render_format.audio_format());
         let render_frames = render_client.GetBufferSize()?;
         let capture_event = unsafe { CreateEventW(None, false, false, None)? };
         let _render_event = unsafe { CreateEventW(None, false, false, None)? };
@@ -263,11 +563,22 @@ mod wasapi_audio {
         render_client.SetEventHandle(_render_event)?;
         let capture: IAudioCaptureClient = capture_client.GetService()?;
         let render: IAudioRenderClient = render_client.GetService()?;
+        // Queue a full buffer of silence so the render side doesn't underrun
+        // while the capture side is still filling its first packet.
+        unsafe {
+            render.GetBuffer(render_frames)?;
+            render.ReleaseBuffer(render_frames, AUDCLNT_BUFFERFLAGS_SILENT.0 as u32)?;
+        }
         unsafe {
             render_client.Start()?;
             capture_client.Start()?;
         }
-        let frame_size = format.block_align();
+        // The two sides share rate and sample type; only channels may differ.
+        let frame_size = capture_format.block_align();
+        let render_frame_size = render_format.block_align();
+        let sample_type = render_format.sample_type();
+        let channels = capture_format.channels();
+        let render_channels = render_format.channels();
         while !stop.load(Ordering::Relaxed) {
             let wait = unsafe { WaitForSingleObject(capture_event, 50) };
             if wait != WAIT_OBJECT_0 {
@@ -283,6 +594,11 @@ mod wasapi_audio {
                 unsafe { capture.ReleaseBuffer(0)? };
                 continue;
             }
+            if flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32 == 0 && !data.is_null() {
+                let captured =
+                    unsafe { std::slice::from_raw_parts(data, frames as usize * frame_size) };
+                record_peaks(&levels, sample_type, channels, captured);
+            }
             let padding = render_client.GetCurrentPadding()?;
             let available = render_frames.saturating_sub(padding);
             let write_frames = frames.min(available);
@@ -290,11 +606,26 @@ mod wasapi_audio {
                 let mut out = std::ptr::null_mut();
                 unsafe {
                     render.GetBuffer(write_frames, &mut out)?;
-                    let bytes = write_frames as usize * frame_size;
-                    if flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32 != 0 || data.is_null() {
+                    let bytes = write_frames as usize * render_frame_size;
+                    let silent = flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32 != 0;
+                    if silent || data.is_null() || muted.load(Ordering::Relaxed) {
                         std::ptr::write_bytes(out, 0, bytes);
                     } else {
-                        std::ptr::copy_nonoverlapping(data, out, bytes);
+                        if channels == render_channels {
+                            std::ptr::copy_nonoverlapping(data, out, bytes);
+                        } else {
+                            let input = std::slice::from_raw_parts(
+                                data,
+                                write_frames as usize * frame_size,
+                            );
+                            let output = std::slice::from_raw_parts_mut(out, bytes);
+                            remap_channels(sample_type, input, channels, output, render_channels);
+                        }
+                        let gain = f32::from_bits(volume.load(Ordering::Relaxed));
+                        if gain != 1.0 {
+                            let samples = std::slice::from_raw_parts_mut(out, bytes);
+                            scale_samples(sample_type, samples, gain);
+                        }
                     }
                     render.ReleaseBuffer(write_frames, 0)?;
                 }
@@ -310,22 +641,167 @@ mod wasapi_audio {
         Ok(())
     }
 
-    fn select_format(
-        capture: &IAudioClient,
-        render: &IAudioClient,
-    ) -> Result<WaveFormat> {
-        let mix = unsafe { render.GetMixFormat()? };
-        let mix_fmt = unsafe { WaveFormat::from_ptr(mix) };
-        unsafe { CoTaskMemFree(Some(mix as _)) };
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum SampleType {
+        F32,
+        I16,
+        Other,
+    }
+
+    // Formats we can't scale (24/32-bit PCM) play at source level; the
+    // shared-mode mix format is f32 on practically every system.
+    fn scale_samples(sample_type: SampleType, out: &mut [u8], gain: f32) {
+        match sample_type {
+            SampleType::F32 => {
+                for s in out.chunks_exact_mut(4) {
+                    let v = f32::from_le_bytes([s[0], s[1], s[2], s[3]]) * gain;
+                    s.copy_from_slice(&v.to_le_bytes());
+                }
+            }
+            SampleType::I16 => {
+                for s in out.chunks_exact_mut(2) {
+                    let v = i16::from_le_bytes([s[0], s[1]]) as f32 * gain;
+                    s.copy_from_slice(&(v as i16).to_le_bytes());
+                }
+            }
+            SampleType::Other => {}
+        }
+    }
+
+    // Mono goes to the first two output channels, anything folds down to
+    // mono by averaging, and otherwise channels map one to one with extra
+    // inputs dropped and extra outputs silent.
+    fn remap_channels(
+        sample_type: SampleType,
+        input: &[u8],
+        in_channels: usize,
+        output: &mut [u8],
+        out_channels: usize,
+    ) {
+        let size = match sample_type {
+            SampleType::F32 => 4,
+            SampleType::I16 => 2,
+            SampleType::Other => {
+                output.fill(0);
+                return;
+            }
+        };
+        let read = |s: &[u8]| match sample_type {
+            SampleType::F32 => f32::from_le_bytes([s[0], s[1], s[2], s[3]]),
+            _ => i16::from_le_bytes([s[0], s[1]]) as f32 / 32768.0,
+        };
+        let frames = input
+            .chunks_exact(in_channels * size)
+            .zip(output.chunks_exact_mut(out_channels * size));
+        for (src, dst) in frames {
+            let sample = |c: usize| read(&src[c * size..(c + 1) * size]);
+            for (c, out) in dst.chunks_exact_mut(size).enumerate() {
+                let v = if in_channels == 1 {
+                    if c < 2 { sample(0) } else { 0.0 }
+                } else if out_channels == 1 {
+                    (0..in_channels).map(sample).sum::<f32>() / in_channels as f32
+                } else if c < in_channels {
+                    sample(c)
+                } else {
+                    0.0
+                };
+                match sample_type {
+                    SampleType::F32 => out.copy_from_slice(&v.to_le_bytes()),
+                    _ => {
+                        let v = (v * 32768.0).round().clamp(-32768.0, 32767.0) as i16;
+                        out.copy_from_slice(&v.to_le_bytes());
+                    }
+                }
+            }
+        }
+    }
+
+    fn record_peaks(levels: &AudioLevels, sample_type: SampleType, channels: usize, data: &[u8]) {
+        let (size, scale) = match sample_type {
+            SampleType::F32 => (4, 1.0),
+            SampleType::I16 => (2, 1.0 / 32768.0),
+            SampleType::Other => return,
+        };
+        let mut peaks = [0f32; METER_CHANNELS];
+        for (i, s) in data.chunks_exact(size).enumerate() {
+            let v = match sample_type {
+                SampleType::F32 => f32::from_le_bytes([s[0], s[1], s[2], s[3]]),
+                _ => i16::from_le_bytes([s[0], s[1]]) as f32,
+            };
+            if let Some(peak) = peaks.get_mut(i % channels.max(1)) {
+                *peak = peak.max(v.abs() * scale);
+            }
+        }
+        for (channel, &peak) in peaks.iter().take(channels).enumerate() {
+            levels.record(channel, peak);
+        }
+    }
+
+    const FORMAT_TAG_IEEE_FLOAT: u16 = 3;
+    const FORMAT_TAG_EXTENSIBLE: u16 = 0xfffe;
+    const SUBTYPE_IEEE_FLOAT: GUID = GUID::from_u128(0x00000003_0000_0010_8000_00aa00389b71);
+
+    struct StreamFormats {
+        capture: WaveFormat,
+        render: WaveFormat,
+        // The render side takes the capture format as is and leaves rate
+        // conversion to the audio engine.
+        engine_resample: bool,
+    }
+
+    // Capture and render formats. One format for both where possible;
+    // otherwise each side keeps its own channel count at a shared rate and
+    // sample type, and the copy remaps channels. Differing rates fall back
+    // to the engine's converter.
+    fn select_format(capture: &IAudioClient, render: &IAudioClient) -> Result<StreamFormats> {
+        let formats = |capture: WaveFormat, render: WaveFormat| StreamFormats {
+            capture,
+            render,
+            engine_resample: false,
+        };
+        let mix_fmt = mix_format(render)?;
         if supports_format(capture, &mix_fmt) && supports_format(render, &mix_fmt) {
-            return Ok(mix_fmt);
+            return Ok(formats(mix_fmt.clone(), mix_fmt));
         }
         for fmt in preferred_formats() {
             if supports_format(capture, &fmt) && supports_format(render, &fmt) {
-                return Ok(fmt);
+                return Ok(formats(fmt.clone(), fmt));
+            }
+        }
+        let capture_mix = mix_format(capture)?;
+        let capture_channels = capture_mix.channels() as u16;
+        if let Some(fmt) = mix_fmt.with_channels(capture_channels)
+            && supports_format(capture, &fmt)
+            && supports_format(render, &mix_fmt)
+        {
+            return Ok(formats(fmt, mix_fmt));
+        }
+        let render_channels = mix_fmt.channels() as u16;
+        for fmt in preferred_formats() {
+            let (Some(capture_fmt), Some(render_fmt)) = (
+                fmt.with_channels(capture_channels),
+                fmt.with_channels(render_channels),
+            ) else {
+                continue;
+            };
+            if supports_format(capture, &capture_fmt) && supports_format(render, &render_fmt) {
+                return Ok(formats(capture_fmt, render_fmt));
             }
         }
-        Err(anyhow!("No shared audio format"))
+        // IsFormatSupported doesn't account for AUTOCONVERTPCM, so this is
+        // only known to work once Initialize accepts it.
+        Ok(StreamFormats {
+            capture: capture_mix.clone(),
+            render: capture_mix,
+            engine_resample: true,
+        })
+    }
+
+    fn mix_format(client: &IAudioClient) -> Result<WaveFormat> {
+        let mix = unsafe { client.GetMixFormat()? };
+        let fmt = unsafe { WaveFormat::from_ptr(mix) };
+        unsafe { CoTaskMemFree(Some(mix as _)) };
+        Ok(fmt)
     }
 
     fn supports_format(client: &IAudioClient, fmt: &WaveFormat) -> bool {
@@ -354,6 +830,7 @@ mod wasapi_audio {
         out
     }
 
+    #[derive(Clone)]
     struct WaveFormat {
         data: Vec<u8>,
     }
@@ -399,6 +876,54 @@ mod wasapi_audio {
         fn block_align(&self) -> usize {
             unsafe { (*self.as_ptr()).nBlockAlign as usize }
         }
+
+        fn channels(&self) -> usize {
+            unsafe { (*self.as_ptr()).nChannels as usize }
+        }
+
+        // The same rate and sample type with another channel count, for the
+        // sample types the copy can remap.
+        fn with_channels(&self, channels: u16) -> Option<Self> {
+            let rate = unsafe { (*self.as_ptr()).nSamplesPerSec };
+            match self.sample_type() {
+                SampleType::F32 => Some(Self::new(rate, channels, 32, true)),
+                SampleType::I16 => Some(Self::new(rate, channels, 16, false)),
+                SampleType::Other => None,
+            }
+        }
+
+        fn is_float(&self) -> bool {
+            let fmt = unsafe { *self.as_ptr() };
+            if fmt.wFormatTag == FORMAT_TAG_EXTENSIBLE
+                && self.data.len() >= std::mem::size_of::<WAVEFORMATEXTENSIBLE>()
+            {
+                let ext = self.data.as_ptr() as *const WAVEFORMATEXTENSIBLE;
+                unsafe {
+                    std::ptr::addr_of!((*ext).SubFormat).read_unaligned() == SUBTYPE_IEEE_FLOAT
+                }
+            } else {
+                fmt.wFormatTag == FORMAT_TAG_IEEE_FLOAT
+            }
+        }
+
+        fn audio_format(&self) -> AudioFormat {
+            let fmt = unsafe { *self.as_ptr() };
+            AudioFormat {
+                rate: fmt.nSamplesPerSec,
+                channels: fmt.nChannels,
+                bits: fmt.wBitsPerSample,
+                float: self.is_float(),
+            }
+        }
+
+        fn sample_type(&self) -> SampleType {
+            let bits = unsafe { (*self.as_ptr()).wBitsPerSample };
+            match (self.is_float(), bits) {
+                (true, 32) => SampleType::F32,
+                (false, 16) => SampleType::I16,
+                _ => SampleType::Other,
+            }
+        }
     }
 
     fn device_id(device: &IMMDevice) -> Result<String> {
@@ -449,7 +974,543 @@ mod wasapi_audio {
     }
 }
 
-#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+#[cfg(target_os = "macos")]
+mod coreaudio_audio {
+    use super::*;
+    use anyhow::{anyhow, bail};
+    use objc2_audio_toolbox::*;
+    use objc2_core_audio::*;
+    use objc2_core_audio_types::*;
+    use objc2_core_foundation::{CFRetained, CFString};
+    use std::collections::VecDeque;
+    use std::ffi::c_void;
+    use std::ptr::{self, NonNull};
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    // AUHAL buses: element 1 carries the device input, element 0 the output.
+    const INPUT_BUS: u32 = 1;
+    const OUTPUT_BUS: u32 = 0;
+
+    pub struct AudioDevice {
+        pub info: DeviceInfo,
+    }
+
+    // Fields drop in order, so both units are stopped and disposed before
+    // the state their callbacks point at is freed.
+    pub struct AudioPlayback {
+        _input: Unit,
+        _output: Unit,
+        shared: Box<Shared>,
+        format: AudioFormat,
+    }
+
+    impl AudioPlayback {
+        pub fn set_muted(&self, muted: bool) {
+            self.shared.muted.store(muted, Ordering::Relaxed);
+        }
+
+        pub fn set_volume(&self, volume: f32) {
+            self.shared
+                .volume
+                .store(volume.to_bits(), Ordering::Relaxed);
+        }
+
+        // Per-channel peaks since the last call.
+        pub fn take_peaks(&self) -> Vec<f32> {
+            self.shared.levels.take()
+        }
+
+        // The stream handed to the output unit, which converts it on to the
+        // device itself.
+        pub fn format(&self) -> Option<AudioFormat> {
+            Some(self.format)
+        }
+    }
+
+    // Handed to both render callbacks. The input callback renders into
+    // `scratch` and appends to `queue`; the output callback drains it.
+    struct Shared {
+        input_unit: AudioUnit,
+        channels: usize,
+        scratch: Mutex<Vec<f32>>,
+        queue: Mutex<VecDeque<f32>>,
+        // Samples beyond this are dropped oldest first so latency can't
+        // creep up when the output clock runs slower than the input's.
+        max_queued: usize,
+        muted: AtomicBool,
+        // f32 bits, read by the output callback for every buffer.
+        volume: AtomicU32,
+        levels: AudioLevels,
+    }
+
+    struct Unit(AudioUnit);
+
+    impl Unit {
+        fn new_hal() -> Result<Self> {
+            let desc = AudioComponentDescription {
+                componentType: kAudioUnitType_Output,
+                componentSubType: kAudioUnitSubType_HALOutput,
+                componentManufacturer: kAudioUnitManufacturer_Apple,
+                componentFlags: 0,
+                componentFlagsMask: 0,
+            };
+            let component =
+                unsafe { AudioComponentFindNext(ptr::null_mut(), NonNull::from(&desc)) };
+            if component.is_null() {
+                bail!("No HAL output audio unit");
+            }
+            let mut unit = ptr::null_mut();
+            check(
+                unsafe { AudioComponentInstanceNew(component, NonNull::from(&mut unit)) },
+                "AudioComponentInstanceNew",
+            )?;
+            Ok(Self(unit))
+        }
+
+        fn set<T>(
+            &self,
+            property: AudioUnitPropertyID,
+            scope: AudioUnitScope,
+            bus: u32,
+            value: &T,
+        ) -> Result<()> {
+            let status = unsafe {
+                AudioUnitSetProperty(
+                    self.0,
+                    property,
+                    scope,
+                    bus,
+                    (value as *const T).cast(),
+                    size_of::<T>() as u32,
+                )
+            };
+            check(status, "AudioUnitSetProperty")
+        }
+
+        fn stream_format(
+            &self,
+            scope: AudioUnitScope,
+            bus: u32,
+        ) -> Result<AudioStreamBasicDescription> {
+            let mut format = float_format(0.0, 0);
+            let mut size = size_of::<AudioStreamBasicDescription>() as u32;
+            let status = unsafe {
+                AudioUnitGetProperty(
+                    self.0,
+                    kAudioUnitProperty_StreamFormat,
+                    scope,
+                    bus,
+                    NonNull::from(&mut format).cast(),
+                    NonNull::from(&mut size),
+                )
+            };
+            check(status, "AudioUnitGetProperty")?;
+            Ok(format)
+        }
+
+        fn start(&self) -> Result<()> {
+            check(
+                unsafe { AudioUnitInitialize(self.0) },
+                "AudioUnitInitialize",
+            )?;
+            check(
+                unsafe { AudioOutputUnitStart(self.0) },
+                "AudioOutputUnitStart",
+            )
+        }
+    }
+
+    impl Drop for Unit {
+        fn drop(&mut self) {
+            unsafe {
+                AudioOutputUnitStop(self.0);
+                AudioUnitUninitialize(self.0);
+                AudioComponentInstanceDispose(self.0);
+            }
+        }
+    }
+
+    pub fn list_input_devices() -> Result<Vec<AudioDevice>> {
+        list_devices(kAudioObjectPropertyScopeInput)
+    }
+
+    pub fn list_output_devices() -> Result<Vec<AudioDevice>> {
+        list_devices(kAudioObjectPropertyScopeOutput)
+    }
+
+    // Devices with at least one channel in `scope`, keyed by their UID,
+    // which stays the same across reconnects and reboots.
+    fn list_devices(scope: AudioObjectPropertyScope) -> Result<Vec<AudioDevice>> {
+        let mut out = Vec::new();
+        for device in device_ids()? {
+            if channel_count(device, scope) == 0 {
+                continue;
+            }
+            let Some(id) = string_property(device, kAudioDevicePropertyDeviceUID) else {
+                continue;
+            };
+            let name =
+                string_property(device, kAudioObjectPropertyName).unwrap_or_else(|| id.clone());
+            out.push(AudioDevice {
+                info: DeviceInfo { id, name },
+            });
+        }
+        Ok(out)
+    }
+
+    // `output` picks the playback device; None plays through the current
+    // default output.
+    pub fn start_playback(
+        device: &AudioDevice,
+        output: Option<&AudioDevice>,
+        options: &PlaybackOptions,
+    ) -> Result<AudioPlayback> {
+        let input_id = find_device(&device.info.id)?;
+        let output_id = output.map(|d| find_device(&d.info.id)).transpose()?;
+        // Created first so it outlives the units on every early return.
+        let mut shared = Box::new(Shared {
+            input_unit: ptr::null_mut(),
+            channels: 0,
+            scratch: Mutex::new(Vec::new()),
+            queue: Mutex::new(VecDeque::new()),
+            max_queued: 0,
+            muted: AtomicBool::new(false),
+            volume: AtomicU32::new(1.0f32.to_bits()),
+            levels: AudioLevels::default(),
+        });
+        let input = Unit::new_hal()?;
+        input.set(
+            kAudioOutputUnitProperty_EnableIO,
+            kAudioUnitScope_Input,
+            INPUT_BUS,
+            &1u32,
+        )?;
+        input.set(
+            kAudioOutputUnitProperty_EnableIO,
+            kAudioUnitScope_Output,
+            OUTPUT_BUS,
+            &0u32,
+        )?;
+        input.set(
+            kAudioOutputUnitProperty_CurrentDevice,
+            kAudioUnitScope_Global,
+            0,
+            &input_id,
+        )?;
+        // The input side can't resample, so the client format keeps the
+        // device's rate and only converts to interleaved f32.
+        let hardware = input.stream_format(kAudioUnitScope_Input, INPUT_BUS)?;
+        let channels = hardware.mChannelsPerFrame.clamp(1, METER_CHANNELS as u32);
+        let format = float_format(hardware.mSampleRate, channels);
+        input.set(
+            kAudioUnitProperty_StreamFormat,
+            kAudioUnitScope_Output,
+            INPUT_BUS,
+            &format,
+        )?;
+        let output_unit = Unit::new_hal()?;
+        if let Some(id) = output_id {
+            output_unit.set(
+                kAudioOutputUnitProperty_CurrentDevice,
+                kAudioUnitScope_Global,
+                0,
+                &id,
+            )?;
+        }
+        // The output unit converts rate and channel layout to its device.
+        output_unit.set(
+            kAudioUnitProperty_StreamFormat,
+            kAudioUnitScope_Input,
+            OUTPUT_BUS,
+            &format,
+        )?;
+        let latency = if options.low_latency { 0.03 } else { 0.12 };
+        shared.input_unit = input.0;
+        shared.channels = channels as usize;
+        shared.max_queued = (format.mSampleRate * latency) as usize * channels as usize;
+        let refcon = ptr::from_ref::<Shared>(&shared).cast_mut().cast::<c_void>();
+        input.set(
+            kAudioOutputUnitProperty_SetInputCallback,
+            kAudioUnitScope_Global,
+            0,
+            &AURenderCallbackStruct {
+                inputProc: Some(input_callback),
+                inputProcRefCon: refcon,
+            },
+        )?;
+        output_unit.set(
+            kAudioUnitProperty_SetRenderCallback,
+            kAudioUnitScope_Input,
+            OUTPUT_BUS,
+            &AURenderCallbackStruct {
+                inputProc: Some(output_callback),
+                inputProcRefCon: refcon,
+            },
+        )?;
+        output_unit.start()?;
+        input.start()?;
+        Ok(AudioPlayback {
+            _input: input,
+            _output: output_unit,
+            shared,
+            format: AudioFormat {
+                rate: format.mSampleRate as u32,
+                channels: channels as u16,
+                bits: 32,
+                float: true,
+            },
+        })
+    }
+
+    unsafe extern "C-unwind" fn input_callback(
+        refcon: NonNull<c_void>,
+        flags: NonNull<AudioUnitRenderActionFlags>,
+        timestamp: NonNull<AudioTimeStamp>,
+        bus: u32,
+        frames: u32,
+        _: *mut AudioBufferList,
+    ) -> i32 {
+        let shared = unsafe { refcon.cast::<Shared>().as_ref() };
+        let Ok(mut scratch) = shared.scratch.lock() else {
+            return 0;
+        };
+        scratch.resize(frames as usize * shared.channels, 0.0);
+        let mut list = AudioBufferList {
+            mNumberBuffers: 1,
+            mBuffers: [AudioBuffer {
+                mNumberChannels: shared.channels as u32,
+                mDataByteSize: (scratch.len() * size_of::<f32>()) as u32,
+                mData: scratch.as_mut_ptr().cast(),
+            }],
+        };
+        let status = unsafe {
+            AudioUnitRender(
+                shared.input_unit,
+                flags.as_ptr(),
+                timestamp,
+                bus,
+                frames,
+                NonNull::from(&mut list),
+            )
+        };
+        if status != 0 {
+            return status;
+        }
+        for channel in 0..shared.channels {
+            let peak = scratch
+                .iter()
+                .skip(channel)
+                .step_by(shared.channels)
+                .fold(0.0f32, |peak, s| peak.max(s.abs()));
+            shared.levels.record(channel, peak);
+        }
+        let Ok(mut queue) = shared.queue.lock() else {
+            return 0;
+        };
+        queue.extend(scratch.iter().copied());
+        // Whole frames go in, so the excess stays frame aligned.
+        let excess = queue.len().saturating_sub(shared.max_queued);
+        queue.drain(..excess);
+        0
+    }
+
+    unsafe extern "C-unwind" fn output_callback(
+        refcon: NonNull<c_void>,
+        _: NonNull<AudioUnitRenderActionFlags>,
+        _: NonNull<AudioTimeStamp>,
+        _: u32,
+        _: u32,
+        data: *mut AudioBufferList,
+    ) -> i32 {
+        let shared = unsafe { refcon.cast::<Shared>().as_ref() };
+        let Some(list) = (unsafe { data.as_mut() }) else {
+            return 0;
+        };
+        let buffer = &mut list.mBuffers[0];
+        if buffer.mData.is_null() {
+            return 0;
+        }
+        let out = unsafe {
+            std::slice::from_raw_parts_mut(
+                buffer.mData.cast::<f32>(),
+                buffer.mDataByteSize as usize / size_of::<f32>(),
+            )
+        };
+        let gain = if shared.muted.load(Ordering::Relaxed) {
+            0.0
+        } else {
+            f32::from_bits(shared.volume.load(Ordering::Relaxed))
+        };
+        let Ok(mut queue) = shared.queue.lock() else {
+            out.fill(0.0);
+            return 0;
+        };
+        // An underrun plays silence until the input catches up.
+        for sample in out.iter_mut() {
+            *sample = queue.pop_front().map_or(0.0, |s| s * gain);
+        }
+        0
+    }
+
+    fn float_format(sample_rate: f64, channels: u32) -> AudioStreamBasicDescription {
+        let bytes_per_frame = channels * size_of::<f32>() as u32;
+        AudioStreamBasicDescription {
+            mSampleRate: sample_rate,
+            mFormatID: kAudioFormatLinearPCM,
+            mFormatFlags: kAudioFormatFlagIsFloat | kAudioFormatFlagIsPacked,
+            mBytesPerPacket: bytes_per_frame,
+            mFramesPerPacket: 1,
+            mBytesPerFrame: bytes_per_frame,
+            mChannelsPerFrame: channels,
+            mBitsPerChannel: 32,
+            mReserved: 0,
+        }
+    }
+
+    fn check(status: i32, what: &str) -> Result<()> {
+        if status == 0 {
+            Ok(())
+        } else {
+            Err(anyhow!("{what} failed ({status})"))
+        }
+    }
+
+    fn address(
+        selector: AudioObjectPropertySelector,
+        scope: AudioObjectPropertyScope,
+    ) -> AudioObjectPropertyAddress {
+        AudioObjectPropertyAddress {
+            mSelector: selector,
+            mScope: scope,
+            mElement: kAudioObjectPropertyElementMain,
+        }
+    }
+
+    // Variable-sized property data. u64 storage keeps it aligned for the
+    // pointer-bearing structs some properties return.
+    fn property_data(
+        object: AudioObjectID,
+        address: &AudioObjectPropertyAddress,
+    ) -> Result<Vec<u64>> {
+        let mut size = 0u32;
+        check(
+            unsafe {
+                AudioObjectGetPropertyDataSize(
+                    object,
+                    NonNull::from(address),
+                    0,
+                    ptr::null(),
+                    NonNull::from(&mut size),
+                )
+            },
+            "AudioObjectGetPropertyDataSize",
+        )?;
+        let mut data = vec![0u64; (size as usize).div_ceil(size_of::<u64>()).max(1)];
+        check(
+            unsafe {
+                AudioObjectGetPropertyData(
+                    object,
+                    NonNull::from(address),
+                    0,
+                    ptr::null(),
+                    NonNull::from(&mut size),
+                    NonNull::from(data.as_mut_slice()).cast(),
+                )
+            },
+            "AudioObjectGetPropertyData",
+        )?;
+        data.truncate((size as usize).div_ceil(size_of::<u64>()).max(1));
+        Ok(data)
+    }
+
+    fn device_ids() -> Result<Vec<AudioObjectID>> {
+        let address = address(
+            kAudioHardwarePropertyDevices,
+            kAudioObjectPropertyScopeGlobal,
+        );
+        let mut size = 0u32;
+        check(
+            unsafe {
+                AudioObjectGetPropertyDataSize(
+                    kAudioObjectSystemObject as AudioObjectID,
+                    NonNull::from(&address),
+                    0,
+                    ptr::null(),
+                    NonNull::from(&mut size),
+                )
+            },
+            "AudioObjectGetPropertyDataSize",
+        )?;
+        let mut ids: Vec<AudioObjectID> = vec![0; size as usize / size_of::<AudioObjectID>()];
+        if ids.is_empty() {
+            return Ok(ids);
+        }
+        check(
+            unsafe {
+                AudioObjectGetPropertyData(
+                    kAudioObjectSystemObject as AudioObjectID,
+                    NonNull::from(&address),
+                    0,
+                    ptr::null(),
+                    NonNull::from(&mut size),
+                    NonNull::from(ids.as_mut_slice()).cast(),
+                )
+            },
+            "AudioObjectGetPropertyData",
+        )?;
+        ids.truncate(size as usize / size_of::<AudioObjectID>());
+        Ok(ids)
+    }
+
+    fn find_device(uid: &str) -> Result<AudioObjectID> {
+        device_ids()?
+            .into_iter()
+            .find(|&device| {
+                string_property(device, kAudioDevicePropertyDeviceUID).as_deref() == Some(uid)
+            })
+            .ok_or_else(|| anyhow!("Audio device {uid} not found"))
+    }
+
+    // Channels across all of the device's streams in `scope`.
+    fn channel_count(device: AudioObjectID, scope: AudioObjectPropertyScope) -> u32 {
+        let address = address(kAudioDevicePropertyStreamConfiguration, scope);
+        let Ok(data) = property_data(device, &address) else {
+            return 0;
+        };
+        let list = data.as_ptr().cast::<AudioBufferList>();
+        unsafe {
+            let count = (*list).mNumberBuffers as usize;
+            let buffers = std::slice::from_raw_parts((*list).mBuffers.as_ptr(), count);
+            buffers.iter().map(|b| b.mNumberChannels).sum()
+        }
+    }
+
+    fn string_property(
+        device: AudioObjectID,
+        selector: AudioObjectPropertySelector,
+    ) -> Option<String> {
+        let address = address(selector, kAudioObjectPropertyScopeGlobal);
+        let mut value: *const CFString = ptr::null();
+        let mut size = size_of::<*const CFString>() as u32;
+        let status = unsafe {
+            AudioObjectGetPropertyData(
+                device,
+                NonNull::from(&address),
+                0,
+                ptr::null(),
+                NonNull::from(&mut size),
+                NonNull::from(&mut value).cast(),
+            )
+        };
+        let value = NonNull::new(value.cast_mut()).filter(|_| status == 0)?;
+        // The property hands over a +1 reference.
+        let value = unsafe { CFRetained::from_raw(value) };
+        Some(value.to_string())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
 mod stub_audio {
     use super::*;
     use anyhow::anyhow;
@@ -460,18 +1521,50 @@ mod stub_audio {
 
     pub struct AudioPlayback;
 
+    impl AudioPlayback {
+        pub fn set_muted(&self, _: bool) {}
+
+        pub fn set_volume(&self, _: f32) {}
+
+        pub fn take_peaks(&self) -> Vec<f32> {
+            Vec::new()
+        }
+
+        pub fn format(&self) -> Option<AudioFormat> {
+            None
+        }
+    }
+
     pub fn list_input_devices() -> Result<Vec<AudioDevice>> {
         Ok(Vec::new())
     }
 
-    pub fn start_playback(_: &AudioDevice) -> Result<AudioPlayback> {
+    pub fn list_output_devices() -> Result<Vec<AudioDevice>> {
+        Ok(Vec::new())
+    }
+
+    pub fn start_playback(
+        _: &AudioDevice,
+        _: Option<&AudioDevice>,
+        _: &PlaybackOptions,
+    ) -> Result<AudioPlayback> {
         Err(anyhow!("Audio capture unsupported on this platform"))
     }
 }
 
+#[cfg(target_os = "macos")]
+pub use coreaudio_audio::{
+    AudioDevice, AudioPlayback, list_input_devices, list_output_devices, start_playback,
+};
 #[cfg(target_os = "linux")]
-pub use gst_audio::{list_input_devices, start_playback, AudioDevice, AudioPlayback};
+pub use gst_audio::{
+    AudioDevice, AudioPlayback, list_input_devices, list_output_devices, start_playback,
+};
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+pub use stub_audio::{
+    AudioDevice, AudioPlayback, list_input_devices, list_output_devices, start_playback,
+};
 #[cfg(target_os = "windows")]
-pub use wasapi_audio::{list_input_devices, start_playback, AudioDevice, AudioPlayback};
-#[cfg(not(any(target_os = "linux", target_os = "windows")))]
-pub use stub_audio::{list_input_devices, start_playback, AudioDevice, AudioPlayback};
+pub use wasapi_audio::{
+    AudioDevice, AudioPlayback, list_input_devices, list_output_devices, start_playback,
+};
@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+
+use crate::app::find_device;
+use crate::platform;
+use crate::types::Deinterlace;
+
+// Runs a capture for `duration` with nothing drawing the frames, to measure
+// what the capture and decode path alone sustain. `video` picks the device
+// like --video; the first device is used otherwise.
+pub fn run(video: Option<&str>, duration: Duration) -> Result<()> {
+    let devices = platform::list_video_devices()?;
+    let index = match video {
+        Some(query) => find_device(devices.iter(), query)
+            .ok_or_else(|| anyhow!("No video device matches {query:?}"))?,
+        None if devices.is_empty() => return Err(anyhow!("No video devices found")),
+        None => 0,
+    };
+    let device = &devices[index];
+    let cap =
+        platform::start_video_capture(&device.id, None, None, None, Deinterlace::Auto, None, None)?;
+    cap.stats.set_enabled(true);
+    println!(
+        "Benchmarking {} ({}x{} {}) for {}s",
+        device.name,
+        cap.info.width,
+        cap.info.height,
+        cap.info.format,
+        duration.as_secs_f64()
+    );
+    let started = Instant::now();
+    let deadline = started
+        .checked_add(duration)
+        .ok_or_else(|| anyhow!("Benchmark duration is too long"))?;
+    let mut received = 0u64;
+    let mut decode_us_total = 0u64;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        if cap.is_lost() {
+            return Err(anyhow!("Video device was lost"));
+        }
+        // decode_us only holds the latest frame's time, so sample it per frame.
+        if cap.rx.recv_timeout(remaining).is_ok() {
+            received += 1;
+            decode_us_total += cap.stats.snapshot().decode_us;
+        }
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+    let stats = cap.stats.snapshot();
+    println!("Frames:      {}", stats.frames);
+    println!("Received:    {received}");
+    println!("Dropped:     {}", stats.drops);
+    println!("FPS:         {:.1}", stats.frames as f64 / elapsed);
+    println!(
+        "Avg decode:  {} us",
+        decode_us_total.checked_div(received).unwrap_or(0)
+    );
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockMode {
+    Off,
+    // Local wall-clock time.
+    Clock,
+    Stopwatch,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub fn align(self) -> egui::Align2 {
+        match self {
+            Corner::TopLeft => egui::Align2::LEFT_TOP,
+            Corner::TopRight => egui::Align2::RIGHT_TOP,
+            Corner::BottomLeft => egui::Align2::LEFT_BOTTOM,
+            Corner::BottomRight => egui::Align2::RIGHT_BOTTOM,
+        }
+    }
+}
+
+// Start/stop timer that keeps its running total across stops.
+#[derive(Default)]
+pub struct Stopwatch {
+    started: Option<Instant>,
+    banked: Duration,
+}
+
+impl Stopwatch {
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    pub fn start(&mut self) {
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(started) = self.started.take() {
+            self.banked += started.elapsed();
+        }
+    }
+
+    // Back to zero; a running stopwatch keeps running from there.
+    pub fn reset(&mut self) {
+        self.banked = Duration::ZERO;
+        if self.started.is_some() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.banked + self.started.map_or(Duration::ZERO, |s| s.elapsed())
+    }
+}
+
+// M:SS.cc, with hours in front once there are any.
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let centis = elapsed.as_millis() / 10;
+    let secs = centis / 100;
+    let (h, m, s, c) = (secs / 3600, secs / 60 % 60, secs % 60, centis % 100);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}.{c:02}")
+    } else {
+        format!("{m}:{s:02}.{c:02}")
+    }
+}
+
+pub fn wall_clock() -> String {
+    chrono::Local::now().format("%H:%M:%S").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elapsed_formatting() {
+        assert_eq!(format_elapsed(Duration::ZERO), "0:00.00");
+        assert_eq!(format_elapsed(Duration::from_millis(61_239)), "1:01.23");
+        assert_eq!(format_elapsed(Duration::from_secs(3600 + 5)), "1:00:05.00");
+    }
+
+    #[test]
+    fn stopwatch_banks_time_across_stops() {
+        let mut watch = Stopwatch::default();
+        assert_eq!(watch.elapsed(), Duration::ZERO);
+        watch.start();
+        std::thread::sleep(Duration::from_millis(5));
+        watch.stop();
+        let banked = watch.elapsed();
+        assert!(banked >= Duration::from_millis(5));
+        assert_eq!(watch.elapsed(), banked);
+        watch.reset();
+        assert!(!watch.is_running());
+        assert_eq!(watch.elapsed(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,221 @@
+// Zero-copy upload for dmabuf-backed GStreamer buffers. Each plane of the
+// buffer's dmabuf is imported as a linear Vulkan image through external
+// memory and wrapped as a wgpu texture, so the GPU samples the capture
+// buffer directly instead of a mapped copy. Only single-memory NV12 whose
+// strides match the driver's linear layout is handled; anything else is left
+// to the write_texture path.
+
+use std::os::fd::{AsRawFd, BorrowedFd, IntoRawFd, RawFd};
+
+use ash::vk;
+use gstreamer as gst;
+use gstreamer_allocators::DmaBufMemory;
+use wgpu::hal::api::Vulkan;
+
+use crate::types::{VideoFormat, VideoFrame};
+
+struct Plane {
+    offset: u64,
+    stride: u32,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+}
+
+// True when `device` runs on Vulkan with dmabuf import enabled; wgpu turns
+// the extensions on by itself wherever the driver offers them.
+pub fn supported(device: &wgpu::Device) -> bool {
+    let Some(hal_device) = (unsafe { device.as_hal::<Vulkan>() }) else {
+        return false;
+    };
+    let extensions = hal_device.enabled_device_extensions();
+    extensions.contains(&ash::khr::external_memory_fd::NAME)
+        && extensions.contains(&ash::ext::external_memory_dma_buf::NAME)
+}
+
+pub fn is_dmabuf(buffer: &gst::Buffer) -> bool {
+    buffer.n_memory() == 1 && buffer.peek_memory(0).is_memory_type::<DmaBufMemory>()
+}
+
+// Y and UV textures aliasing the frame's dmabuf. Both hold a reference to
+// the buffer, so it isn't handed back to the driver until wgpu is done
+// sampling them.
+pub fn import_nv12(
+    device: &wgpu::Device,
+    frame: &VideoFrame,
+    buffer: &gst::Buffer,
+) -> Option<(wgpu::Texture, wgpu::Texture)> {
+    if frame.format != VideoFormat::Nv12 || buffer.n_memory() != 1 {
+        return None;
+    }
+    let memory = buffer.peek_memory(0);
+    let fd = memory.downcast_memory_ref::<DmaBufMemory>()?.fd();
+    let y_bytes = frame.stride * frame.height as usize;
+    let [y_offset, uv_offset, _] = frame.plane_offsets.unwrap_or([0, y_bytes, 0]);
+    let base = memory.offset() as u64;
+    let size = memory.maxsize() as u64;
+    let y = Plane {
+        offset: base + y_offset as u64,
+        stride: frame.stride as u32,
+        width: frame.width,
+        height: frame.height,
+        format: wgpu::TextureFormat::R8Unorm,
+    };
+    let uv = Plane {
+        offset: base + uv_offset as u64,
+        stride: frame.uv_stride as u32,
+        width: frame.width.div_ceil(2),
+        height: frame.height.div_ceil(2),
+        format: wgpu::TextureFormat::Rg8Unorm,
+    };
+    let y_texture = unsafe { import_plane(device, fd, size, &y, buffer.clone())? };
+    let uv_texture = unsafe { import_plane(device, fd, size, &uv, buffer.clone())? };
+    Some((y_texture, uv_texture))
+}
+
+unsafe fn import_plane(
+    device: &wgpu::Device,
+    fd: RawFd,
+    size: u64,
+    plane: &Plane,
+    keep: gst::Buffer,
+) -> Option<wgpu::Texture> {
+    let vk_format = match plane.format {
+        wgpu::TextureFormat::R8Unorm => vk::Format::R8_UNORM,
+        wgpu::TextureFormat::Rg8Unorm => vk::Format::R8G8_UNORM,
+        _ => return None,
+    };
+    let extent = wgpu::Extent3d {
+        width: plane.width,
+        height: plane.height,
+        depth_or_array_layers: 1,
+    };
+    let hal_texture = {
+        let hal_device = unsafe { device.as_hal::<Vulkan>()? };
+        let raw = hal_device.raw_device().clone();
+        let instance = hal_device.shared_instance().raw_instance();
+        let mut external = vk::ExternalMemoryImageCreateInfo::default()
+            .handle_types(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT);
+        let info = vk::ImageCreateInfo::default()
+            .push_next(&mut external)
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(vk_format)
+            .extent(vk::Extent3D {
+                width: plane.width,
+                height: plane.height,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::LINEAR)
+            .usage(vk::ImageUsageFlags::SAMPLED)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+        let image = unsafe { raw.create_image(&info, None).ok()? };
+        let Some(memory) = (unsafe { bind_dmabuf(&raw, instance, image, fd, size, plane) }) else {
+            unsafe { raw.destroy_image(image, None) };
+            return None;
+        };
+        let desc = wgpu::hal::TextureDescriptor {
+            label: Some("video_dmabuf"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: plane.format,
+            usage: wgpu::TextureUses::RESOURCE,
+            memory_flags: wgpu::hal::MemoryFlags::empty(),
+            view_formats: Vec::new(),
+        };
+        let release: wgpu::hal::DropCallback = Box::new(move || {
+            unsafe {
+                raw.destroy_image(image, None);
+                raw.free_memory(memory, None);
+            }
+            drop(keep);
+        });
+        unsafe { hal_device.texture_from_raw(image, &desc, Some(release)) }
+    };
+    let texture = unsafe {
+        device.create_texture_from_hal::<Vulkan>(
+            hal_texture,
+            &wgpu::TextureDescriptor {
+                label: Some("video_dmabuf"),
+                size: extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: plane.format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        )
+    };
+    Some(texture)
+}
+
+// Imports a duplicate of `fd` and binds it to `image` at the plane's offset.
+// Fails when the driver's linear row pitch differs from the plane stride,
+// since plain linear images can't be given an explicit layout.
+unsafe fn bind_dmabuf(
+    raw: &ash::Device,
+    instance: &ash::Instance,
+    image: vk::Image,
+    fd: RawFd,
+    size: u64,
+    plane: &Plane,
+) -> Option<vk::DeviceMemory> {
+    let layout = unsafe {
+        raw.get_image_subresource_layout(
+            image,
+            vk::ImageSubresource {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                array_layer: 0,
+            },
+        )
+    };
+    if layout.offset != 0 || layout.row_pitch != plane.stride as u64 {
+        return None;
+    }
+    let requirements = unsafe { raw.get_image_memory_requirements(image) };
+    if !plane.offset.is_multiple_of(requirements.alignment.max(1))
+        || plane.offset + requirements.size > size
+    {
+        return None;
+    }
+    let fd_device = ash::khr::external_memory_fd::Device::new(instance, raw);
+    let mut properties = vk::MemoryFdPropertiesKHR::default();
+    unsafe {
+        fd_device
+            .get_memory_fd_properties(
+                vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT,
+                fd,
+                &mut properties,
+            )
+            .ok()?
+    };
+    let types = requirements.memory_type_bits & properties.memory_type_bits;
+    if types == 0 {
+        return None;
+    }
+    // The driver owns the duplicate only once the import succeeds.
+    let owned = unsafe { BorrowedFd::borrow_raw(fd) }
+        .try_clone_to_owned()
+        .ok()?;
+    let mut import = vk::ImportMemoryFdInfoKHR::default()
+        .handle_type(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT)
+        .fd(owned.as_raw_fd());
+    let info = vk::MemoryAllocateInfo::default()
+        .push_next(&mut import)
+        .allocation_size(size)
+        .memory_type_index(types.trailing_zeros());
+    let memory = unsafe { raw.allocate_memory(&info, None).ok()? };
+    let _ = owned.into_raw_fd();
+    if unsafe { raw.bind_image_memory(image, memory, plane.offset) }.is_err() {
+        unsafe { raw.free_memory(memory, None) };
+        return None;
+    }
+    Some(memory)
+}
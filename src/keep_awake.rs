@@ -0,0 +1,158 @@
+// Holds off the screensaver and sleep while video is showing; the inhibit
+// lasts as long as the value.
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::process::{Child, Command, Stdio};
+
+    pub struct KeepAwake {
+        inhibitor: Inhibitor,
+    }
+
+    enum Inhibitor {
+        // systemd-inhibit holds the lock for as long as it runs.
+        Systemd(Child),
+        // The screensaver drops the inhibit when its connection closes, so the
+        // connection is kept open alongside the cookie.
+        ScreenSaver(zbus::blocking::Connection, u32),
+        // X11 window id handed to `xdg-screensaver suspend`.
+        XdgScreensaver(String),
+    }
+
+    const SCREENSAVER_NAME: &str = "org.freedesktop.ScreenSaver";
+    const SCREENSAVER_PATH: &str = "/org/freedesktop/ScreenSaver";
+
+    impl KeepAwake {
+        // Tries systemd-inhibit, then the freedesktop ScreenSaver D-Bus service,
+        // then xdg-screensaver, which only works for an X11 window.
+        pub fn new(window: &winit::window::Window) -> Option<Self> {
+            let inhibitor = match Self::spawn_systemd_inhibit() {
+                Ok(child) => Inhibitor::Systemd(child),
+                Err(_) => match Self::inhibit_screensaver() {
+                    Ok((connection, cookie)) => Inhibitor::ScreenSaver(connection, cookie),
+                    Err(_) => {
+                        let window = format!("{:#x}", Self::x11_window_id(window)?);
+                        if !Self::run_xdg_screensaver("suspend", &window) {
+                            return None;
+                        }
+                        Inhibitor::XdgScreensaver(window)
+                    }
+                },
+            };
+            Some(Self { inhibitor })
+        }
+
+        fn spawn_systemd_inhibit() -> std::io::Result<Child> {
+            Command::new("systemd-inhibit")
+                .arg("--what=idle:sleep")
+                .arg("--mode=block")
+                .arg("--who=CaptureCardGaming")
+                .arg("--why=CaptureCardGaming")
+                .arg("sleep")
+                .arg("infinity")
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+        }
+
+        fn inhibit_screensaver() -> zbus::Result<(zbus::blocking::Connection, u32)> {
+            let connection = zbus::blocking::Connection::session()?;
+            let reply = connection.call_method(
+                Some(SCREENSAVER_NAME),
+                SCREENSAVER_PATH,
+                Some(SCREENSAVER_NAME),
+                "Inhibit",
+                &("CaptureCardGaming", "Playing captured video"),
+            )?;
+            let cookie: u32 = reply.body().deserialize()?;
+            Ok((connection, cookie))
+        }
+
+        fn x11_window_id(window: &winit::window::Window) -> Option<u64> {
+            use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
+            match window.window_handle().ok()?.as_raw() {
+                RawWindowHandle::Xlib(handle) => Some(handle.window),
+                RawWindowHandle::Xcb(handle) => Some(u64::from(handle.window.get())),
+                _ => None,
+            }
+        }
+
+        fn run_xdg_screensaver(action: &str, window: &str) -> bool {
+            Command::new("xdg-screensaver")
+                .arg(action)
+                .arg(window)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success())
+        }
+    }
+
+    impl Drop for KeepAwake {
+        fn drop(&mut self) {
+            match &mut self.inhibitor {
+                Inhibitor::Systemd(child) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+                Inhibitor::ScreenSaver(connection, cookie) => {
+                    let _ = connection.call_method(
+                        Some(SCREENSAVER_NAME),
+                        SCREENSAVER_PATH,
+                        Some(SCREENSAVER_NAME),
+                        "UnInhibit",
+                        &(*cookie,),
+                    );
+                }
+                Inhibitor::XdgScreensaver(window) => {
+                    Self::run_xdg_screensaver("resume", window);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod windows {
+    use windows::Win32::System::Power::{
+        ES_CONTINUOUS, ES_DISPLAY_REQUIRED, ES_SYSTEM_REQUIRED, SetThreadExecutionState,
+    };
+
+    pub struct KeepAwake;
+
+    impl KeepAwake {
+        pub fn new(_window: &winit::window::Window) -> Option<Self> {
+            let flags = ES_CONTINUOUS | ES_SYSTEM_REQUIRED | ES_DISPLAY_REQUIRED;
+            let ok = unsafe { SetThreadExecutionState(flags) };
+            if ok.0 == 0 { None } else { Some(Self) }
+        }
+    }
+
+    impl Drop for KeepAwake {
+        fn drop(&mut self) {
+            unsafe {
+                SetThreadExecutionState(ES_CONTINUOUS);
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod stub {
+    pub struct KeepAwake;
+
+    impl KeepAwake {
+        pub fn new(_window: &winit::window::Window) -> Option<Self> {
+            None
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use linux::KeepAwake;
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub use stub::KeepAwake;
+#[cfg(target_os = "windows")]
+pub use windows::KeepAwake;
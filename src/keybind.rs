@@ -0,0 +1,134 @@
+use winit::keyboard::Key;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    ToggleStats,
+    CropAdjust,
+    Screenshot,
+    TogglePause,
+    ToggleFullscreen,
+    NextVideo,
+    PreviousVideo,
+    ToggleMute,
+}
+
+pub const ACTIONS: [Action; 8] = [
+    Action::ToggleStats,
+    Action::CropAdjust,
+    Action::Screenshot,
+    Action::TogglePause,
+    Action::ToggleFullscreen,
+    Action::NextVideo,
+    Action::PreviousVideo,
+    Action::ToggleMute,
+];
+
+impl Action {
+    // Key in the [hotkeys] settings section.
+    pub fn setting(self) -> &'static str {
+        match self {
+            Action::ToggleStats => "toggle_stats",
+            Action::CropAdjust => "crop_adjust",
+            Action::Screenshot => "screenshot",
+            Action::TogglePause => "toggle_pause",
+            Action::ToggleFullscreen => "toggle_fullscreen",
+            Action::NextVideo => "next_video",
+            Action::PreviousVideo => "previous_video",
+            Action::ToggleMute => "toggle_mute",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::ToggleStats => "Stats",
+            Action::CropAdjust => "Crop Adjust",
+            Action::Screenshot => "Screenshot",
+            Action::TogglePause => "Pause",
+            Action::ToggleFullscreen => "Fullscreen",
+            Action::NextVideo => "Next Device",
+            Action::PreviousVideo => "Previous Device",
+            Action::ToggleMute => "Mute",
+        }
+    }
+
+    pub fn default_binding(self) -> &'static str {
+        match self {
+            Action::ToggleStats => "F3",
+            Action::CropAdjust => "F4",
+            Action::Screenshot => "F12",
+            Action::TogglePause => "Space",
+            Action::ToggleFullscreen => "F11",
+            Action::NextVideo => "]",
+            Action::PreviousVideo => "[",
+            Action::ToggleMute => "m",
+        }
+    }
+}
+
+// A binding is a single character or a key name such as "F5" or "PageDown",
+// compared without regard to case.
+pub fn key_matches(binding: &str, key: &Key) -> bool {
+    binding_name(key).is_some_and(|name| name.eq_ignore_ascii_case(binding))
+}
+
+// How a pressed key is written as a binding; None for keys that can't be
+// named.
+pub fn binding_name(key: &Key) -> Option<String> {
+    match key {
+        // winit reports Space as a named key on most platforms and as " "
+        // on others.
+        Key::Character(c) if c.as_str() == " " => Some("Space".to_string()),
+        Key::Character(c) => Some(c.to_lowercase()),
+        Key::Named(named) => Some(format!("{named:?}")),
+        _ => None,
+    }
+}
+
+// Pairs of actions bound to the same key; the first one listed wins.
+pub fn conflicts<'a>(bindings: &[(Action, &'a str)]) -> Vec<(Action, Action, &'a str)> {
+    let mut out = Vec::new();
+    for (i, &(first, binding)) in bindings.iter().enumerate() {
+        for &(second, other) in &bindings[i + 1..] {
+            if binding.eq_ignore_ascii_case(other) {
+                out.push((first, second, binding));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::keyboard::NamedKey;
+
+    #[test]
+    fn bindings_match_keys() {
+        assert!(key_matches("]", &Key::Character("]".into())));
+        assert!(key_matches("m", &Key::Character("M".into())));
+        assert!(key_matches("F5", &Key::Named(NamedKey::F5)));
+        assert!(key_matches("pagedown", &Key::Named(NamedKey::PageDown)));
+        assert!(key_matches("Space", &Key::Named(NamedKey::Space)));
+        assert!(key_matches("space", &Key::Character(" ".into())));
+        assert!(!key_matches("F5", &Key::Named(NamedKey::F6)));
+        assert!(!key_matches("[", &Key::Character("]".into())));
+        assert_eq!(
+            binding_name(&Key::Character("Q".into())).as_deref(),
+            Some("q")
+        );
+    }
+
+    #[test]
+    fn shared_bindings_conflict() {
+        let bindings = [
+            (Action::ToggleStats, "F3"),
+            (Action::Screenshot, "f3"),
+            (Action::ToggleMute, "m"),
+        ];
+        assert_eq!(
+            conflicts(&bindings),
+            vec![(Action::ToggleStats, Action::Screenshot, "F3")]
+        );
+        assert!(conflicts(&bindings[1..]).is_empty());
+    }
+}
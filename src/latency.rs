@@ -0,0 +1,137 @@
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, Ordering},
+};
+use std::time::{Duration, Instant};
+
+use crate::pixel;
+use crate::platform::FrameCallback;
+use crate::types::{FrameData, VideoFormat, VideoFrame};
+
+const PRIME_TIME: Duration = Duration::from_millis(500);
+const TIMEOUT: Duration = Duration::from_secs(2);
+const LUMA_THRESHOLD: f32 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Phase {
+    Idle,
+    // A black marker is shown while the darkest-expected luma is sampled.
+    Priming { until: Instant, baseline: f32 },
+    Flashing { since: Instant, baseline: f32 },
+    Done(Option<Duration>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Marker {
+    Black,
+    White,
+}
+
+// Measures capture round-trip by flashing the window white and timing how
+// long it takes for the flash to show up in captured frames. Needs the
+// capture source to see this display (loopback cable, camera, photodiode rig).
+pub struct LatencyProbe {
+    active: Arc<AtomicBool>,
+    phase: Arc<Mutex<Phase>>,
+}
+
+impl LatencyProbe {
+    pub fn new() -> Self {
+        Self {
+            active: Arc::new(AtomicBool::new(false)),
+            phase: Arc::new(Mutex::new(Phase::Idle)),
+        }
+    }
+
+    pub fn callback(&self) -> FrameCallback {
+        let active = self.active.clone();
+        let phase = self.phase.clone();
+        Arc::new(move |frame: &VideoFrame| {
+            if !active.load(Ordering::Relaxed) {
+                return;
+            }
+            let Some(luma) = frame_luma(frame) else {
+                return;
+            };
+            let Ok(mut phase) = phase.lock() else { return };
+            match &mut *phase {
+                Phase::Priming { baseline, .. } => *baseline = baseline.max(luma),
+                Phase::Flashing { since, baseline } if luma > *baseline + LUMA_THRESHOLD => {
+                    *phase = Phase::Done(Some(since.elapsed()));
+                    active.store(false, Ordering::Relaxed);
+                }
+                _ => {}
+            }
+        })
+    }
+
+    pub fn start(&self) {
+        if let Ok(mut phase) = self.phase.lock() {
+            *phase = Phase::Priming {
+                until: Instant::now() + PRIME_TIME,
+                baseline: 0.0,
+            };
+            self.active.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    // Advances the timeline; call once per UI frame. Returns the marker to
+    // draw over the whole window, if any.
+    pub fn tick(&self) -> Option<Marker> {
+        let mut phase = self.phase.lock().ok()?;
+        let now = Instant::now();
+        match *phase {
+            Phase::Priming { until, baseline } if now >= until => {
+                *phase = Phase::Flashing {
+                    since: now,
+                    baseline,
+                };
+                Some(Marker::White)
+            }
+            Phase::Priming { .. } => Some(Marker::Black),
+            Phase::Flashing { since, .. } if now.duration_since(since) >= TIMEOUT => {
+                *phase = Phase::Done(None);
+                self.active.store(false, Ordering::Relaxed);
+                None
+            }
+            Phase::Flashing { .. } => Some(Marker::White),
+            _ => None,
+        }
+    }
+
+    // None while no test has finished; Some(None) when the flash was never seen.
+    pub fn result(&self) -> Option<Option<Duration>> {
+        match *self.phase.lock().ok()? {
+            Phase::Done(result) => Some(result),
+            _ => None,
+        }
+    }
+}
+
+fn frame_luma(frame: &VideoFrame) -> Option<f32> {
+    let luma = |data: &[u8]| {
+        let start = match frame.format {
+            VideoFormat::Nv12 | VideoFormat::I420 | VideoFormat::P010 => {
+                frame.plane_offsets.map(|o| o[0]).unwrap_or(0)
+            }
+            _ => 0,
+        };
+        pixel::mean_luma(
+            frame.format,
+            frame.width,
+            frame.height,
+            frame.stride,
+            data.get(start..)?,
+        )
+    };
+    match &frame.data {
+        FrameData::Owned(data) => luma(data),
+        FrameData::Planes(planes) => luma(planes.first()?),
+        #[cfg(target_os = "linux")]
+        FrameData::Gst(buffer) => luma(buffer.map_readable().ok()?.as_slice()),
+    }
+}
@@ -0,0 +1,20 @@
+// The capture side of the viewer, usable without its window and renderer:
+// device discovery, capture threads and the frames they deliver.
+//
+//     let devices = platform::list_video_devices()?;
+//     let cap = platform::start_video_capture(
+//         &devices[0].id, None, None, None, Deinterlace::Auto, None, None,
+//     )?;
+//     while let Ok(frame) = cap.rx.recv() {
+//         // frame.data holds the pixels, laid out as frame.format says.
+//     }
+//
+// By default `rx` holds only the newest frame; a consumer that falls behind
+// finds the older ones dropped. `set_frame_policy(FramePolicy::Smooth)`
+// queues a few frames instead. The `on_frame` callback sees every frame on the
+// capture thread before it's queued, and `waker` is called once it is.
+// Dropping the VideoCapture stops the capture and closes the device.
+
+pub mod pixel;
+pub mod platform;
+pub mod types;
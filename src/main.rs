@@ -1,21 +1,43 @@
 mod app;
 mod audio;
-mod pixel;
-mod platform;
+mod bench;
+mod clock;
+#[cfg(target_os = "linux")]
+mod dmabuf;
+mod keep_awake;
+mod keybind;
+mod latency;
+mod recording;
 mod render;
-mod types;
+mod replay;
+mod scope;
+mod screenshot;
+mod settings;
+mod signal;
+mod stats_server;
+mod view;
 
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
-use app::App;
+use anyhow::{Result, anyhow};
+use app::{App, ErrorSource, LaunchOptions};
+use capture_card_gaming::{pixel, platform, types};
 use egui_winit::State as EguiWinitState;
 use winit::application::ApplicationHandler;
-use winit::event::{ElementState, KeyEvent, WindowEvent};
-use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::keyboard::{Key, NamedKey};
-use winit::monitor::VideoModeHandle;
-use winit::window::{Window, WindowId};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{ElementState, KeyEvent, MouseButton, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy};
+use winit::keyboard::{Key, ModifiersState, NamedKey};
+use winit::monitor::{MonitorHandle, VideoModeHandle};
+use winit::window::{Window, WindowId, WindowLevel};
+
+use keybind::{ACTIONS, Action, key_matches};
+use view::{AspectMode, ModeMatch, Rotation};
+
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(500);
+// Physical pixels the pointer may move between the two clicks.
+const DOUBLE_CLICK_DISTANCE: f64 = 8.0;
 
 struct MainState {
     window: Option<Arc<Window>>,
@@ -25,40 +47,156 @@ struct MainState {
     egui_ctx: egui::Context,
     app: App,
     fullscreen_aspect: Option<bool>,
+    // Rotation the exclusive mode was matched for.
+    fullscreen_rotation: Rotation,
+    // Picked monitor the fullscreen window was placed on.
+    fullscreen_monitor: Option<String>,
+    fullscreen_mode_match: ModeMatch,
     fullscreen_exclusive: bool,
-    keep_awake: Option<platform::KeepAwake>,
+    compact_restore_size: Option<PhysicalSize<u32>>,
+    modifiers: ModifiersState,
+    keep_awake: Option<keep_awake::KeepAwake>,
+    occluded: bool,
+    // Frames wake the loop as they arrive; otherwise it sleeps until this.
+    next_redraw: Instant,
+    last_redraw: Instant,
+    cursor: PhysicalPosition<f64>,
+    // Time and place of a click on the video that could start a double click.
+    last_click: Option<(Instant, PhysicalPosition<f64>)>,
 }
 
 impl MainState {
-    fn new() -> Result<Self> {
+    fn new(launch: LaunchOptions, proxy: EventLoopProxy<()>) -> Result<Self> {
+        let mut app = App::new(launch)?;
+        app.set_frame_waker(Arc::new(move || {
+            let _ = proxy.send_event(());
+        }));
         Ok(Self {
             window: None,
             render: None,
             egui_state: None,
             egui_renderer: None,
             egui_ctx: egui::Context::default(),
-            app: App::new()?,
+            app,
             fullscreen_aspect: None,
+            fullscreen_rotation: Rotation::Deg0,
+            fullscreen_monitor: None,
+            fullscreen_mode_match: ModeMatch::Exact,
             fullscreen_exclusive: false,
+            compact_restore_size: None,
+            modifiers: ModifiersState::empty(),
             keep_awake: None,
+            occluded: false,
+            next_redraw: Instant::now(),
+            last_redraw: Instant::now(),
+            cursor: PhysicalPosition::new(0.0, 0.0),
+            last_click: None,
         })
     }
 
+    // False when `key` isn't bound to anything.
+    fn run_hotkey(&mut self, key: &Key) -> bool {
+        let Some(action) = ACTIONS
+            .into_iter()
+            .find(|&action| key_matches(self.app.binding(action), key))
+        else {
+            return false;
+        };
+        match action {
+            Action::ToggleStats => self.app.toggle_stats(),
+            Action::CropAdjust => self.app.toggle_crop_adjust(),
+            Action::Screenshot => self.app.request_screenshot(),
+            Action::TogglePause => self.app.toggle_video_paused(),
+            Action::ToggleFullscreen => self.app.toggle_fullscreen(),
+            Action::NextVideo => self.app.next_video_device(),
+            Action::PreviousVideo => self.app.previous_video_device(),
+            Action::ToggleMute => self.app.toggle_audio_muted(),
+        }
+        true
+    }
+
+    // Occlusion isn't reported everywhere, so minimizing is also checked
+    // whenever the window resizes.
+    fn update_window_hidden(&mut self, window: &Window) {
+        let minimized = window.is_minimized().unwrap_or(false);
+        self.app.set_window_hidden(self.occluded || minimized);
+    }
+
     fn redraw(&mut self, window: &Window) {
+        self.last_redraw = Instant::now();
+        self.sync_surface_size(window);
         self.apply_fullscreen(window);
-        self.apply_keep_awake();
+        self.apply_window_mode(window);
+        self.apply_keep_awake(window);
         let Some(render) = self.render.as_mut() else { return };
         let Some(egui_state) = self.egui_state.as_mut() else { return };
         let Some(egui_renderer) = self.egui_renderer.as_mut() else { return };
+        self.app.set_surface_diagnostics(render.diagnostics());
+        self.app.set_video_rect(render.video_rect());
+        self.app.set_has_frame(render.has_frame());
         let raw_input = egui_state.take_egui_input(window);
         let full_output = self.egui_ctx.run(raw_input, |ctx| self.app.ui(ctx));
+        let repaint_delay = full_output
+            .viewport_output
+            .get(&egui::ViewportId::ROOT)
+            .map_or(Duration::MAX, |viewport| viewport.repaint_delay);
+        self.next_redraw = Instant::now() + repaint_delay.min(self.app.redraw_interval());
         egui_state.handle_platform_output(window, full_output.platform_output);
-        if let Some(frame) = self.app.take_latest_frame() {
-            render.update_frame(&frame);
+        let new_frame = self.app.take_latest_frame();
+        if let Some(frame) = new_frame.as_ref()
+            && !render.update_frame(frame)
+        {
+            self.app.count_skipped_frame();
+        }
+        render.set_blank_video(self.app.blank_video());
+        render.set_pip(self.app.pip_layout());
+        if let Some(frame) = self.app.take_pip_frame() {
+            render.update_pip_frame(&frame);
+        }
+        // Exclusive fullscreen already runs at the capture's own mode.
+        let aspect = match self.app.aspect_mode() {
+            AspectMode::Auto if self.app.is_fullscreen() && self.fullscreen_exclusive => {
+                AspectMode::Stretch
+            }
+            mode => mode,
+        };
+        render.set_aspect_mode(aspect);
+        render.set_scale_mode(self.app.scale_mode());
+        render.set_scale_filter(self.app.scale_filter());
+        render.set_sharpen(self.app.sharpen());
+        render.set_rotation(self.app.rotation());
+        render.set_deinterlace(self.app.deinterlace_frames());
+        render.set_crop(self.app.crop());
+        render.set_clamp_limited_range(self.app.clamp_limited_range());
+        render.set_srgb_output(self.app.srgb_output());
+        render.set_output_gamma(self.app.output_gamma());
+        render.set_image_adjust(self.app.image_adjust());
+        render.set_zebra(self.app.zebra());
+        render.set_dither(self.app.dither());
+        render.set_frame_latency(self.app.frame_latency());
+        render.set_vsync(self.app.vsync());
+        render.set_background(self.app.background());
+        render.set_crt(self.app.crt());
+        match render.set_post_shader(self.app.post_shader()) {
+            Some(Ok(())) => self.app.clear_error(ErrorSource::Shader),
+            Some(Err(e)) => self.app.report_error(ErrorSource::Shader, e.to_string()),
+            None => {}
+        }
+        // Only new frames change the levels; reading back costs a GPU sync.
+        if new_frame.is_some() && self.app.scope_enabled() {
+            match render.scope_frame() {
+                Ok(image) => self.app.update_scope(&image, render.color_info().range),
+                Err(e) => self.app.report_error(ErrorSource::Scope, e.to_string()),
+            }
+        }
+        if self.app.take_screenshot_request() {
+            match render.capture_frame() {
+                Ok(image) => self.app.save_screenshot(image),
+                Err(e) => self
+                    .app
+                    .report_error(ErrorSource::Screenshot, e.to_string()),
+            }
         }
-        let aspect = self.app.aspect_correction_enabled()
-            && (!self.app.is_fullscreen() || !self.fullscreen_exclusive);
-        render.set_aspect_correction(aspect);
         let clipped = if full_output.shapes.is_empty() {
             Vec::new()
         } else {
@@ -66,76 +204,192 @@ impl MainState {
                 .tessellate(full_output.shapes, full_output.pixels_per_point)
         };
         let pixels_per_point = egui_winit::pixels_per_point(&self.egui_ctx, window);
-        let _ = render.render(
+        let result = render.render(
             window,
             egui_renderer,
             &full_output.textures_delta,
             &clipped,
             pixels_per_point,
         );
+        match result {
+            Ok(()) => self.app.clear_error(ErrorSource::Render),
+            // Outdated/timeout surfaces are routine around resizes and
+            // recover on the next frame.
+            Err(e)
+                if matches!(
+                    e.downcast_ref::<wgpu::SurfaceError>(),
+                    Some(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Timeout)
+                ) => {}
+            Err(e) => self.app.report_error(ErrorSource::Render, e.to_string()),
+        }
+    }
+
+    // Resized can trail ScaleFactorChanged by a frame; reconcile here so the
+    // surface, egui's screen rect and the video quad all use the same size.
+    fn sync_surface_size(&mut self, window: &Window) {
+        if let Some(render) = self.render.as_mut()
+            && let Some(size) = surface_resize(
+                (render.config.width, render.config.height),
+                window.inner_size(),
+            )
+        {
+            render.resize(size);
+        }
     }
 
     fn update_target_capture_size(&mut self) {
         let Some(window) = self.window.as_ref() else { return };
-        let monitor = window.current_monitor();
+        let monitor = self.target_monitor(window);
         let size = monitor.map(|m| m.size());
         self.app
             .set_target_capture_size(size.map(|s| (s.width, s.height)));
     }
 
     fn apply_fullscreen(&mut self, window: &Window) {
-        let aspect = self.app.aspect_correction_enabled();
+        // Matching the capture's mode only keeps the source's own ratio.
+        let aspect = self.app.aspect_mode() == AspectMode::Auto;
+        let rotation = self.app.rotation();
+        let monitor = self.app.fullscreen_monitor().map(str::to_string);
+        let mode_match = self.app.mode_match();
         if let Some(request) = self.app.take_fullscreen_request() {
             if request {
-                if aspect {
-                    if let Some(mode) = self.match_capture_mode(window) {
-                        window.set_fullscreen(Some(winit::window::Fullscreen::Exclusive(mode)));
-                        self.fullscreen_exclusive = true;
-                    } else {
-                        window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(
-                            window.current_monitor(),
-                        )));
-                        self.fullscreen_exclusive = false;
-                    }
-                } else {
-                    window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(
-                        window.current_monitor(),
-                    )));
-                    self.fullscreen_exclusive = false;
-                }
+                self.enter_fullscreen(window, aspect);
                 self.fullscreen_aspect = Some(aspect);
+                self.fullscreen_rotation = rotation;
+                self.fullscreen_monitor = monitor;
+                self.fullscreen_mode_match = mode_match;
             } else {
                 window.set_fullscreen(None);
                 self.fullscreen_aspect = None;
                 self.fullscreen_exclusive = false;
             }
             self.app.set_fullscreen_state(request);
-        } else if self.app.is_fullscreen() && self.fullscreen_aspect != Some(aspect) {
+        } else if self.app.is_fullscreen()
+            && (self.fullscreen_aspect != Some(aspect)
+                || self.fullscreen_rotation != rotation
+                || self.fullscreen_monitor != monitor
+                || self.fullscreen_mode_match != mode_match)
+        {
             self.fullscreen_aspect = Some(aspect);
-            if aspect {
-                if let Some(mode) = self.match_capture_mode(window) {
-                    window.set_fullscreen(Some(winit::window::Fullscreen::Exclusive(mode)));
-                    self.fullscreen_exclusive = true;
-                } else {
-                    window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(
-                        window.current_monitor(),
-                    )));
-                    self.fullscreen_exclusive = false;
-                }
+            self.fullscreen_rotation = rotation;
+            self.fullscreen_monitor = monitor;
+            self.fullscreen_mode_match = mode_match;
+            self.enter_fullscreen(window, aspect);
+        }
+    }
+
+    fn enter_fullscreen(&mut self, window: &Window, aspect: bool) {
+        let monitor = self.target_monitor(window);
+        if aspect && let Some(mode) = self.match_capture_mode(monitor.as_ref()) {
+            window.set_fullscreen(Some(winit::window::Fullscreen::Exclusive(mode)));
+            self.fullscreen_exclusive = true;
+        } else {
+            window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(monitor)));
+            self.fullscreen_exclusive = false;
+        }
+    }
+
+    // The monitor picked for fullscreen, or the window's own when none is
+    // picked or the picked one is no longer connected.
+    fn target_monitor(&self, window: &Window) -> Option<MonitorHandle> {
+        self.app
+            .fullscreen_monitor()
+            .and_then(|name| {
+                window
+                    .available_monitors()
+                    .enumerate()
+                    .find(|(i, m)| monitor_label(*i, m) == name)
+                    .map(|(_, m)| m)
+            })
+            .or_else(|| window.current_monitor())
+    }
+
+    fn update_monitors(&mut self) {
+        let Some(window) = self.window.as_ref() else {
+            return;
+        };
+        let labels = window
+            .available_monitors()
+            .enumerate()
+            .map(|(i, m)| monitor_label(i, &m))
+            .collect();
+        self.app.set_monitors(labels);
+    }
+
+    fn apply_window_mode(&mut self, window: &Window) {
+        if let Some(title) = self.app.take_title_request() {
+            window.set_title(&title);
+        }
+        if let Some(on_top) = self.app.take_always_on_top_request() {
+            let level = if on_top {
+                WindowLevel::AlwaysOnTop
             } else {
-                window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(
-                    window.current_monitor(),
-                )));
-                self.fullscreen_exclusive = false;
+                WindowLevel::Normal
+            };
+            window.set_window_level(level);
+        }
+        if let Some(compact) = self.app.take_compact_request() {
+            if compact {
+                if self.compact_restore_size.is_none() {
+                    self.compact_restore_size = Some(window.inner_size());
+                }
+                let _ = window.request_inner_size(self.compact_size(window));
+            } else if let Some(size) = self.compact_restore_size.take() {
+                let _ = window.request_inner_size(size);
             }
         }
+        if self.app.take_fit_window_request() {
+            self.fit_window(window);
+        }
     }
 
-    fn apply_keep_awake(&mut self) {
+    // The largest window of the picture's shape that fits the monitor with
+    // some room to spare, centred on it. The resize comes back as Resized,
+    // which updates the surface.
+    fn fit_window(&self, window: &Window) {
+        let (Some(aspect), Some(monitor)) = (self.app.picture_aspect(), window.current_monitor())
+        else {
+            return;
+        };
+        let inner = window.inner_size();
+        let outer = window.outer_size();
+        let border_w = outer.width.saturating_sub(inner.width);
+        let border_h = outer.height.saturating_sub(inner.height);
+        let bounds = monitor.size();
+        let (width, height) = fit_size(
+            aspect,
+            (
+                (bounds.width * 9 / 10).saturating_sub(border_w),
+                (bounds.height * 9 / 10).saturating_sub(border_h),
+            ),
+        );
+        let _ = window.request_inner_size(PhysicalSize::new(width, height));
+        let origin = monitor.position();
+        window.set_outer_position(PhysicalPosition::new(
+            origin.x + bounds.width.saturating_sub(width + border_w) as i32 / 2,
+            origin.y + bounds.height.saturating_sub(height + border_h) as i32 / 2,
+        ));
+    }
+
+    fn compact_size(&self, window: &Window) -> PhysicalSize<u32> {
+        let monitor_w = window
+            .current_monitor()
+            .map(|m| m.size().width)
+            .unwrap_or(1920);
+        let width = (monitor_w / 4).max(320);
+        let (w, h) = self
+            .app
+            .display_size()
+            .filter(|&(w, h)| w > 0 && h > 0)
+            .unwrap_or((16, 9));
+        PhysicalSize::new(width, (width as u64 * h as u64 / w as u64) as u32)
+    }
+
+    fn apply_keep_awake(&mut self, window: &Window) {
         if let Some(request) = self.app.take_keep_awake_request() {
             if request {
                 if self.keep_awake.is_none() {
-                    self.keep_awake = platform::KeepAwake::new();
+                    self.keep_awake = keep_awake::KeepAwake::new(window);
                 }
             } else {
                 self.keep_awake = None;
@@ -143,18 +397,29 @@ impl MainState {
         }
     }
 
-    fn match_capture_mode(&self, window: &Window) -> Option<VideoModeHandle> {
-        let (w, h) = self.app.capture_size()?;
-        let monitor = window.current_monitor()?;
-        monitor
+    fn match_capture_mode(&self, monitor: Option<&MonitorHandle>) -> Option<VideoModeHandle> {
+        // A rotated picture fills the screen in the swapped orientation.
+        let capture = self.app.display_size()?;
+        let preference = self.app.mode_match();
+        let fps = self.app.capture_fps();
+        monitor?
             .video_modes()
-            .find(|mode| mode.size().width == w && mode.size().height == h)
+            .filter_map(|mode| {
+                let size = mode.size();
+                let score = mode_score((size.width, size.height), capture, preference)?;
+                Some((
+                    (score, refresh_score(mode.refresh_rate_millihertz(), fps)),
+                    mode,
+                ))
+            })
+            .min_by_key(|(score, _)| *score)
+            .map(|(_, mode)| mode)
     }
 }
 
 impl ApplicationHandler for MainState {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        event_loop.set_control_flow(ControlFlow::Poll);
+        event_loop.set_control_flow(ControlFlow::Wait);
         let monitor = event_loop.primary_monitor();
         let window_attrs = if let Some(monitor) = monitor {
             Window::default_attributes()
@@ -171,7 +436,9 @@ impl ApplicationHandler for MainState {
                 return;
             }
         };
-        let render = match pollster::block_on(render::RenderState::new(window.clone())) {
+        let wide_gamut = self.app.wide_gamut();
+        let render = match pollster::block_on(render::RenderState::new(window.clone(), wide_gamut))
+        {
             Ok(r) => r,
             Err(e) => {
                 eprintln!("{e}");
@@ -196,8 +463,11 @@ impl ApplicationHandler for MainState {
         self.render = Some(render);
         self.egui_state = Some(egui_state);
         self.egui_renderer = Some(egui_renderer);
+        self.update_monitors();
         self.update_target_capture_size();
-        self.apply_keep_awake();
+        if let Some(window) = self.window.clone() {
+            self.apply_keep_awake(&window);
+        }
     }
 
     fn window_event(
@@ -214,6 +484,7 @@ impl ApplicationHandler for MainState {
         }
         if let WindowEvent::CursorMoved { position, .. } = event {
             self.app.set_mouse_y(position.y as f32);
+            self.cursor = position;
         }
         let response = egui_state.on_window_event(window.as_ref(), &event);
         if response.repaint {
@@ -223,25 +494,93 @@ impl ApplicationHandler for MainState {
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
-                        logical_key: Key::Named(NamedKey::F3),
+                        logical_key,
                         state: ElementState::Pressed,
+                        repeat: false,
                         ..
                     },
                 ..
-            } => {
-                self.app.toggle_stats();
+            } if self.app.is_rebinding() => {
+                let binding = match logical_key {
+                    Key::Named(NamedKey::Escape) => None,
+                    key => keybind::binding_name(&key),
+                };
+                self.app.finish_rebinding(binding);
+                window.request_redraw();
+            }
+            // Keys like Space also activate a focused egui widget; leave them to
+            // egui then.
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key,
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } if !response.consumed && self.run_hotkey(&logical_key) => {
+                window.request_redraw();
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(key),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if self.app.is_crop_adjust() => {
+                let step = if self.modifiers.shift_key() { 10 } else { 1 };
+                match key {
+                    NamedKey::ArrowLeft => self.app.nudge_crop(-step, 0),
+                    NamedKey::ArrowRight => self.app.nudge_crop(step, 0),
+                    NamedKey::ArrowUp => self.app.nudge_crop(0, -step),
+                    NamedKey::ArrowDown => self.app.nudge_crop(0, step),
+                    NamedKey::Tab => self.app.cycle_crop_edge(),
+                    _ => {}
+                }
                 window.request_redraw();
             }
+            // Double-clicking the picture toggles fullscreen like a media
+            // player; clicks on the panel or an open popup are egui's.
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let now = Instant::now();
+                let over_ui = response.consumed
+                    || self.egui_ctx.is_pointer_over_area()
+                    || egui::Popup::is_any_open(&self.egui_ctx);
+                if over_ui {
+                    self.last_click = None;
+                } else if is_double_click(self.last_click, now, self.cursor) {
+                    self.last_click = None;
+                    self.app.toggle_fullscreen();
+                    window.request_redraw();
+                } else {
+                    self.last_click = Some((now, self.cursor));
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
             WindowEvent::Resized(size) => {
                 if let Some(render) = self.render.as_mut() {
                     render.resize(size);
                 }
+                self.update_window_hidden(window.as_ref());
+            }
+            WindowEvent::Occluded(occluded) => {
+                self.occluded = occluded;
+                self.update_window_hidden(window.as_ref());
             }
             WindowEvent::ScaleFactorChanged { .. } => {
-                if let Some(render) = self.render.as_mut() {
-                    render.resize(window.inner_size());
-                }
+                self.sync_surface_size(window.as_ref());
+                self.update_monitors();
                 self.update_target_capture_size();
+                window.request_redraw();
             }
             WindowEvent::Moved { .. } => {
                 self.update_target_capture_size();
@@ -253,16 +592,272 @@ impl ApplicationHandler for MainState {
         }
     }
 
-    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
-        if let Some(window) = self.window.as_ref() {
+    // A new frame is on the capture channel.
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, _event: ()) {
+        match self.app.frame_interval() {
+            // The newest frame is shown once the interval is up; any that
+            // arrive before then are dropped. Input still redraws at once.
+            Some(interval) => {
+                self.next_redraw = self.next_redraw.min(self.last_redraw + interval);
+            }
+            None => {
+                if let Some(window) = self.window.as_ref() {
+                    window.request_redraw();
+                }
+            }
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(window) = self.window.as_ref() else {
+            return;
+        };
+        if Instant::now() >= self.next_redraw {
+            // Wait for the redraw itself, which sets the next deadline;
+            // a hidden window may never get it.
             window.request_redraw();
+            event_loop.set_control_flow(ControlFlow::Wait);
+        } else {
+            event_loop.set_control_flow(ControlFlow::WaitUntil(self.next_redraw));
         }
     }
 }
 
+// A third click counts as the first of a new pair, since the caller clears
+// `last` once a double click fires.
+fn is_double_click(
+    last: Option<(Instant, PhysicalPosition<f64>)>,
+    now: Instant,
+    position: PhysicalPosition<f64>,
+) -> bool {
+    last.is_some_and(|(time, at)| {
+        now.duration_since(time) <= DOUBLE_CLICK_TIME
+            && (position.x - at.x).hypot(position.y - at.y) <= DOUBLE_CLICK_DISTANCE
+    })
+}
+
+// The size to reconfigure the surface to, if the window no longer matches
+// it. Physical sizes already include the scale factor, and egui reads the
+// scale from the window every frame, so a scale change on its own needs
+// nothing. A minimized window reports zero, which the surface can't take.
+fn surface_resize(surface: (u32, u32), inner: PhysicalSize<u32>) -> Option<PhysicalSize<u32>> {
+    let changed = (inner.width, inner.height) != surface;
+    (changed && inner.width > 0 && inner.height > 0).then_some(inner)
+}
+
+// Largest size of the given width-over-height ratio within `bounds`.
+fn fit_size(aspect: f32, bounds: (u32, u32)) -> (u32, u32) {
+    let (w, h) = (bounds.0 as f32, bounds.1 as f32);
+    if w / h > aspect {
+        ((h * aspect).round() as u32, bounds.1)
+    } else {
+        (bounds.0, (w / aspect).round() as u32)
+    }
+}
+
+// Lower is a better fit; None rules the mode out. Larger modes must keep the
+// capture's shape so the picture still fills the screen without stretching.
+fn mode_score(mode: (u32, u32), capture: (u32, u32), preference: ModeMatch) -> Option<u64> {
+    let (w, h) = (mode.0 as u64, mode.1 as u64);
+    let (cw, ch) = (capture.0 as u64, capture.1 as u64);
+    if (w, h) == (cw, ch) {
+        return Some(0);
+    }
+    let fits = w >= cw && h >= ch && w * ch == h * cw;
+    (preference == ModeMatch::Nearest && fits).then(|| w * h - cw * ch)
+}
+
+// Lower is a better fit. A refresh that's a whole multiple of the capture
+// rate shows every frame for the same number of refreshes, so those come
+// first, then the closest rate; ties go to the faster refresh.
+fn refresh_score(refresh_mhz: u32, fps: Option<u32>) -> (bool, u32, std::cmp::Reverse<u32>) {
+    let fastest = std::cmp::Reverse(refresh_mhz);
+    let Some(fps) = fps.filter(|&fps| fps > 0) else {
+        return (false, 0, fastest);
+    };
+    let fps_mhz = fps * 1000;
+    let multiple = ((refresh_mhz + fps_mhz / 2) / fps_mhz).max(1);
+    // Per-frame drift, so 59.94 Hz still counts as a multiple of 60 fps.
+    let drift = refresh_mhz.abs_diff(multiple * fps_mhz) / multiple;
+    if drift <= 100 {
+        (false, drift, fastest)
+    } else {
+        (true, refresh_mhz.abs_diff(fps_mhz), fastest)
+    }
+}
+
+// Monitors without a name are told apart by position.
+fn monitor_label(index: usize, monitor: &MonitorHandle) -> String {
+    monitor
+        .name()
+        .unwrap_or_else(|| format!("Monitor {}", index + 1))
+}
+
+const USAGE: &str = "usage: CaptureCardGaming [--video <id-or-name>] [--audio <id-or-name>] [--fullscreen] [--bench <seconds>] [--shader <file.wgsl>]";
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<LaunchOptions> {
+    let mut launch = LaunchOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--video" | "--audio" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow!("{arg} needs a device id or name\n{USAGE}"))?;
+                if arg == "--video" {
+                    launch.video = Some(value);
+                } else {
+                    launch.audio = Some(value);
+                }
+            }
+            "--fullscreen" => launch.fullscreen = true,
+            "--bench" => {
+                // try_from rejects what Duration can't hold, such as "inf".
+                let duration = args
+                    .next()
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .and_then(|s| Duration::try_from_secs_f64(s).ok())
+                    .filter(|d| !d.is_zero())
+                    .ok_or_else(|| anyhow!("--bench needs a number of seconds\n{USAGE}"))?;
+                launch.bench = Some(duration);
+            }
+            "--shader" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--shader needs a path to a WGSL file\n{USAGE}"))?;
+                launch.shader = Some(path.into());
+            }
+            _ => return Err(anyhow!("Unknown argument {arg:?}\n{USAGE}")),
+        }
+    }
+    Ok(launch)
+}
+
 fn main() -> Result<()> {
+    let launch = parse_args(std::env::args().skip(1))?;
+    if let Some(duration) = launch.bench {
+        return bench::run(launch.video.as_deref(), duration);
+    }
     let event_loop = EventLoop::new()?;
-    let mut state = MainState::new()?;
+    let mut state = MainState::new(launch, event_loop.create_proxy())?;
     event_loop.run_app(&mut state)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<LaunchOptions> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn launch_arguments() {
+        let launch = parse(&[
+            "--video",
+            "/dev/video2",
+            "--audio",
+            "usb capture",
+            "--fullscreen",
+        ])
+        .unwrap();
+        assert_eq!(launch.video.as_deref(), Some("/dev/video2"));
+        assert_eq!(launch.audio.as_deref(), Some("usb capture"));
+        assert!(launch.fullscreen);
+        assert_eq!(parse(&[]).unwrap(), LaunchOptions::default());
+        assert!(parse(&["--video"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+        assert_eq!(
+            parse(&["--bench", "5"]).unwrap().bench,
+            Some(Duration::from_secs(5))
+        );
+        assert!(parse(&["--bench", "soon"]).is_err());
+        assert!(parse(&["--bench", "inf"]).is_err());
+        assert!(parse(&["--bench", "1e30"]).is_err());
+        assert!(parse(&["--bench", "0"]).is_err());
+        assert_eq!(
+            parse(&["--shader", "crt.wgsl"]).unwrap().shader,
+            Some("crt.wgsl".into())
+        );
+        assert!(parse(&["--shader"]).is_err());
+    }
+
+    #[test]
+    fn exclusive_mode_scores() {
+        let capture = (1920, 1080);
+        assert_eq!(mode_score((1920, 1080), capture, ModeMatch::Exact), Some(0));
+        assert_eq!(mode_score((2560, 1440), capture, ModeMatch::Exact), None);
+        let larger = mode_score((2560, 1440), capture, ModeMatch::Nearest).unwrap();
+        let largest = mode_score((3840, 2160), capture, ModeMatch::Nearest).unwrap();
+        assert!(0 < larger && larger < largest);
+        assert_eq!(mode_score((2560, 1600), capture, ModeMatch::Nearest), None);
+        assert_eq!(mode_score((1280, 720), capture, ModeMatch::Nearest), None);
+    }
+
+    #[test]
+    fn refresh_prefers_multiples_of_capture_rate() {
+        let pick = |rates: &[u32], fps| {
+            *rates
+                .iter()
+                .min_by_key(|&&r| refresh_score(r, fps))
+                .unwrap()
+        };
+        assert_eq!(pick(&[144_000, 60_000, 75_000], Some(60)), 60_000);
+        assert_eq!(pick(&[144_000, 120_000, 60_000], Some(60)), 120_000);
+        assert_eq!(pick(&[59_940, 75_000], Some(60)), 59_940);
+        assert_eq!(pick(&[75_000, 144_000], Some(60)), 75_000);
+        assert_eq!(pick(&[60_000, 144_000], None), 144_000);
+    }
+
+    #[test]
+    fn surface_follows_the_window_across_scale_changes() {
+        // Moving from a 1x to a 2x monitor: the same logical window doubles
+        // in physical pixels, possibly before Resized arrives.
+        assert_eq!(
+            surface_resize((1280, 720), PhysicalSize::new(2560, 1440)),
+            Some(PhysicalSize::new(2560, 1440))
+        );
+        // Moving back.
+        assert_eq!(
+            surface_resize((2560, 1440), PhysicalSize::new(1280, 720)),
+            Some(PhysicalSize::new(1280, 720))
+        );
+        // A scale change that kept the physical size.
+        assert_eq!(
+            surface_resize((1280, 720), PhysicalSize::new(1280, 720)),
+            None
+        );
+        // Minimized.
+        assert_eq!(surface_resize((1280, 720), PhysicalSize::new(0, 0)), None);
+    }
+
+    #[test]
+    fn fit_size_keeps_aspect_within_bounds() {
+        assert_eq!(fit_size(16.0 / 9.0, (1000, 1000)), (1000, 563));
+        assert_eq!(fit_size(16.0 / 9.0, (1920, 1000)), (1778, 1000));
+        assert_eq!(fit_size(4.0 / 3.0, (1600, 1200)), (1600, 1200));
+    }
+
+    #[test]
+    fn double_clicks_need_time_and_place() {
+        let start = Instant::now();
+        let at = |x| PhysicalPosition::new(x, 100.0);
+        let first = Some((start, at(100.0)));
+        assert!(is_double_click(
+            first,
+            start + Duration::from_millis(300),
+            at(104.0)
+        ));
+        assert!(!is_double_click(
+            first,
+            start + Duration::from_millis(600),
+            at(100.0)
+        ));
+        assert!(!is_double_click(
+            first,
+            start + Duration::from_millis(300),
+            at(120.0)
+        ));
+        assert!(!is_double_click(None, start, at(100.0)));
+    }
+}
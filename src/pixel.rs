@@ -1,4 +1,12 @@
-#[cfg(test)]
+use std::io::Cursor;
+
+use anyhow::{Result, anyhow};
+use jpeg_decoder::{Decoder, PixelFormat};
+
+use crate::types::VideoFormat;
+
+// CPU conversions to RGBA, BT.601 limited range. The viewer converts on the
+// GPU; these serve as the shaders' reference and for consumers without one.
 #[inline]
 fn clamp(v: i32) -> u8 {
     if v < 0 {
@@ -10,7 +18,6 @@ fn clamp(v: i32) -> u8 {
     }
 }
 
-#[cfg(test)]
 #[inline]
 fn yuv_to_rgb(y: u8, u: u8, v: u8) -> (u8, u8, u8) {
     let c = y as i32 - 16;
@@ -22,8 +29,22 @@ fn yuv_to_rgb(y: u8, u: u8, v: u8) -> (u8, u8, u8) {
     (clamp(r), clamp(g), clamp(b))
 }
 
-#[cfg(test)]
 pub fn yuyv_to_rgba(width: u32, height: u32, stride: usize, src: &[u8]) -> Vec<u8> {
+    packed_422_to_rgba(width, height, stride, src, [0, 1, 2, 3])
+}
+
+pub fn uyvy_to_rgba(width: u32, height: u32, stride: usize, src: &[u8]) -> Vec<u8> {
+    packed_422_to_rgba(width, height, stride, src, [1, 0, 3, 2])
+}
+
+// `order` gives the byte offsets of Y0, U, Y1 and V within each pixel pair.
+fn packed_422_to_rgba(
+    width: u32,
+    height: u32,
+    stride: usize,
+    src: &[u8],
+    order: [usize; 4],
+) -> Vec<u8> {
     let mut out = vec![0u8; (width * height * 4) as usize];
     let mut di = 0usize;
     let w = width as usize;
@@ -31,27 +52,24 @@ pub fn yuyv_to_rgba(width: u32, height: u32, stride: usize, src: &[u8]) -> Vec<u
         let row = &src[y * stride..];
         for x in (0..w).step_by(2) {
             let i = x * 2;
-            let y0 = row[i];
-            let u = row[i + 1];
-            let y1 = row[i + 2];
-            let v = row[i + 3];
+            let y0 = row[i + order[0]];
+            let u = row[i + order[1]];
+            let y1 = row[i + order[2]];
+            let v = row[i + order[3]];
             let (r0, g0, b0) = yuv_to_rgb(y0, u, v);
             let (r1, g1, b1) = yuv_to_rgb(y1, u, v);
-            out[di] = r0;
-            out[di + 1] = g0;
-            out[di + 2] = b0;
-            out[di + 3] = 255;
-            out[di + 4] = r1;
-            out[di + 5] = g1;
-            out[di + 6] = b1;
-            out[di + 7] = 255;
-            di += 8;
+            out[di..di + 4].copy_from_slice(&[r0, g0, b0, 255]);
+            di += 4;
+            // An odd width leaves the last pair's second pixel off the frame.
+            if x + 1 < w {
+                out[di..di + 4].copy_from_slice(&[r1, g1, b1, 255]);
+                di += 4;
+            }
         }
     }
     out
 }
 
-#[cfg(test)]
 pub fn nv12_to_rgba(
     width: u32,
     height: u32,
@@ -83,13 +101,20 @@ pub fn nv12_to_rgba(
     out
 }
 
-#[cfg(any(target_os = "windows", test))]
-pub fn bgra_to_rgba(width: u32, height: u32, stride: usize, src: &[u8]) -> Vec<u8> {
+pub fn bgra_to_rgba(
+    width: u32,
+    height: u32,
+    stride: usize,
+    bottom_up: bool,
+    src: &[u8],
+) -> Vec<u8> {
     let mut out = vec![0u8; (width * height * 4) as usize];
     let w = width as usize;
+    let h = height as usize;
     let mut di = 0usize;
-    for y in 0..height as usize {
-        let row = &src[y * stride..];
+    for y in 0..h {
+        let src_y = if bottom_up { h - 1 - y } else { y };
+        let row = &src[src_y * stride..];
         for x in 0..w {
             let i = x * 4;
             out[di] = row[i + 2];
@@ -102,6 +127,184 @@ pub fn bgra_to_rgba(width: u32, height: u32, stride: usize, src: &[u8]) -> Vec<u
     out
 }
 
+// Average luma in 0..=1 over a sparse grid; cheap enough to run per frame
+// on the capture thread. For NV12, I420 and P010 `data` is the Y plane.
+pub fn mean_luma(
+    format: VideoFormat,
+    width: u32,
+    height: u32,
+    stride: usize,
+    data: &[u8],
+) -> Option<f32> {
+    const STEP: usize = 8;
+    let bytes_per_px = match format {
+        VideoFormat::Rgba => 4,
+        VideoFormat::Yuyv | VideoFormat::Uyvy | VideoFormat::P010 | VideoFormat::Rgb565 => 2,
+        VideoFormat::Nv12 | VideoFormat::I420 | VideoFormat::Grey => 1,
+    };
+    let mut sum = 0u64;
+    let mut count = 0u64;
+    for y in (0..height as usize).step_by(STEP) {
+        let Some(row) = data.get(y * stride..) else {
+            break;
+        };
+        for x in (0..width as usize).step_by(STEP) {
+            let i = x * bytes_per_px;
+            let Some(&px) = row.get(i) else { break };
+            let luma = match format {
+                VideoFormat::Rgba => match row.get(i..i + 3) {
+                    Some(&[r, g, b]) => (r as u32 * 54 + g as u32 * 183 + b as u32 * 19) >> 8,
+                    _ => break,
+                },
+                VideoFormat::Rgb565 => match row.get(i..i + 2) {
+                    Some(&[lo, hi]) => {
+                        let bits = u16::from_le_bytes([lo, hi]) as u32;
+                        let (r, g, b) =
+                            ((bits >> 11) << 3, ((bits >> 5) & 63) << 2, (bits & 31) << 3);
+                        (r * 54 + g * 183 + b * 19) >> 8
+                    }
+                    _ => break,
+                },
+                VideoFormat::Uyvy | VideoFormat::P010 => match row.get(i + 1) {
+                    Some(&hi) => hi as u32,
+                    None => break,
+                },
+                _ => px as u32,
+            };
+            sum += luma as u64;
+            count += 1;
+        }
+    }
+    (count > 0).then(|| sum as f32 / count as f32 / 255.0)
+}
+
+// Keeps the high byte of each 16-bit sample; used when the GPU can't sample
+// 16-bit normalized textures.
+pub fn p010_to_8bit(samples_per_row: usize, rows: usize, stride: usize, src: &[u8]) -> Vec<u8> {
+    let mut out = vec![0u8; samples_per_row * rows];
+    for (y, dst) in out.chunks_exact_mut(samples_per_row.max(1)).enumerate() {
+        let Some(row) = src.get(y * stride..) else {
+            break;
+        };
+        for (d, s) in dst.iter_mut().zip(row.chunks_exact(2)) {
+            *d = s[1];
+        }
+    }
+    out
+}
+
+pub fn rgb24_to_rgba(
+    width: u32,
+    height: u32,
+    stride: usize,
+    bottom_up: bool,
+    src: &[u8],
+) -> Vec<u8> {
+    packed_24_to_rgba(width, height, stride, bottom_up, src, [0, 1, 2])
+}
+
+pub fn bgr24_to_rgba(
+    width: u32,
+    height: u32,
+    stride: usize,
+    bottom_up: bool,
+    src: &[u8],
+) -> Vec<u8> {
+    packed_24_to_rgba(width, height, stride, bottom_up, src, [2, 1, 0])
+}
+
+// 3-byte pixels to RGBA. `order` gives the byte offsets of R, G and B within
+// a pixel; rows missing from a short buffer stay black.
+fn packed_24_to_rgba(
+    width: u32,
+    height: u32,
+    stride: usize,
+    bottom_up: bool,
+    src: &[u8],
+    [r, g, b]: [usize; 3],
+) -> Vec<u8> {
+    let w = width as usize;
+    let h = height as usize;
+    let mut out = vec![0u8; w * h * 4];
+    for (y, dst) in out.chunks_exact_mut(w * 4).enumerate() {
+        let src_y = if bottom_up { h - 1 - y } else { y };
+        let Some(row) = src.get(src_y * stride..) else {
+            continue;
+        };
+        for (d, px) in dst.chunks_exact_mut(4).zip(row.chunks_exact(3)) {
+            d.copy_from_slice(&[px[r], px[g], px[b], 255]);
+        }
+    }
+    out
+}
+
+// RGB565 with red in the high bits, little-endian, as the GPU path unpacks
+// it; kept as the reference for the shader.
+pub fn rgb565_to_rgba(width: u32, height: u32, stride: usize, src: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height as usize {
+        let row = &src[y * stride..];
+        for px in row.chunks_exact(2).take(width as usize) {
+            let bits = u16::from_le_bytes([px[0], px[1]]) as u32;
+            let expand = |v: u32, max: u32| ((v * 255 + max / 2) / max) as u8;
+            out.extend_from_slice(&[
+                expand(bits >> 11, 31),
+                expand((bits >> 5) & 63, 63),
+                expand(bits & 31, 31),
+                255,
+            ]);
+        }
+    }
+    out
+}
+
+fn l8_to_rgba(pixels: &[u8], pixel_count: usize) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(pixel_count * 4);
+    for &v in &pixels[..pixel_count] {
+        rgba.extend_from_slice(&[v, v, v, 255]);
+    }
+    rgba
+}
+
+// Software MJPEG decode to tightly packed RGBA; returns the decoded size.
+pub fn decode_mjpeg(data: &[u8]) -> Result<(u32, u32, Vec<u8>)> {
+    let mut decoder = Decoder::new(Cursor::new(data));
+    let pixels = decoder.decode()?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| anyhow!("Missing MJPEG info"))?;
+    let width = info.width as u32;
+    let height = info.height as u32;
+    let pixel_count = (width as usize)
+        .checked_mul(height as usize)
+        .ok_or_else(|| anyhow!("MJPEG size overflow"))?;
+    let rgba = match info.pixel_format {
+        PixelFormat::RGB24 => {
+            let expected = pixel_count
+                .checked_mul(3)
+                .ok_or_else(|| anyhow!("MJPEG size overflow"))?;
+            if pixels.len() < expected {
+                return Err(anyhow!("MJPEG RGB size mismatch"));
+            }
+            rgb24_to_rgba(
+                width,
+                height,
+                width as usize * 3,
+                false,
+                &pixels[..expected],
+            )
+        }
+        PixelFormat::L8 => {
+            if pixels.len() < pixel_count {
+                return Err(anyhow!("MJPEG L8 size mismatch"));
+            }
+            l8_to_rgba(&pixels[..pixel_count], pixel_count)
+        }
+        _ => return Err(anyhow!("Unsupported MJPEG pixel format")),
+    };
+    Ok((width, height, rgba))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,6 +315,52 @@ mod tests {
         let out = yuyv_to_rgba(2, 1, 4, &src);
         assert_eq!(&out[0..4], &[0, 0, 0, 255]);
         assert_eq!(&out[4..8], &[255, 255, 255, 255]);
+        // An odd width drops the last pair's second pixel.
+        let src = [16u8, 128, 235, 128, 235, 128, 16, 128];
+        let out = yuyv_to_rgba(3, 1, 8, &src);
+        assert_eq!(out, [0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn uyvy_black_white() {
+        let src = [128u8, 16, 128, 235];
+        let out = uyvy_to_rgba(2, 1, 4, &src);
+        assert_eq!(&out[0..4], &[0, 0, 0, 255]);
+        assert_eq!(&out[4..8], &[255, 255, 255, 255]);
+        let uyvy = [128u8, 16, 128, 16];
+        let luma = mean_luma(VideoFormat::Uyvy, 2, 1, 4, &uyvy).unwrap();
+        assert!((luma - 16.0 / 255.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn grey_broadcast() {
+        assert_eq!(l8_to_rgba(&[0, 200], 2), [0, 0, 0, 255, 200, 200, 200, 255]);
+        let grey = [51u8, 51, 0, 0];
+        let luma = mean_luma(VideoFormat::Grey, 2, 1, 4, &grey).unwrap();
+        assert!((luma - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn packed_rgb_orders() {
+        let src = [1u8, 2, 3, 4, 5, 6, 0, 0];
+        assert_eq!(
+            rgb24_to_rgba(1, 2, 4, false, &src),
+            [1, 2, 3, 255, 5, 6, 0, 255]
+        );
+        assert_eq!(
+            bgr24_to_rgba(2, 1, 8, false, &src),
+            [3, 2, 1, 255, 6, 5, 4, 255]
+        );
+        assert_eq!(
+            rgb24_to_rgba(1, 2, 4, true, &src),
+            [5, 6, 0, 255, 1, 2, 3, 255]
+        );
+        // Pure red, green and blue.
+        let rgb565 = [0x00, 0xf8, 0xe0, 0x07, 0x1f, 0x00];
+        assert_eq!(
+            rgb565_to_rgba(3, 1, 6, &rgb565),
+            [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255]
+        );
     }
 
     #[test]
@@ -124,7 +373,35 @@ mod tests {
     #[test]
     fn bgra_swap() {
         let src = [10u8, 20, 30, 255];
-        let out = bgra_to_rgba(1, 1, 4, &src);
+        let out = bgra_to_rgba(1, 1, 4, false, &src);
         assert_eq!(&out[0..4], &[30, 20, 10, 255]);
     }
+
+    #[test]
+    fn bgra_row_order() {
+        let src = [1u8, 2, 3, 255, 4, 5, 6, 255];
+        let top_down = bgra_to_rgba(1, 2, 4, false, &src);
+        assert_eq!(top_down, [3, 2, 1, 255, 6, 5, 4, 255]);
+        let bottom_up = bgra_to_rgba(1, 2, 4, true, &src);
+        assert_eq!(bottom_up, [6, 5, 4, 255, 3, 2, 1, 255]);
+    }
+
+    #[test]
+    fn luma_of_flat_frames() {
+        let white = vec![255u8; 16 * 16 * 4];
+        let luma = mean_luma(VideoFormat::Rgba, 16, 16, 64, &white).unwrap();
+        assert!(luma > 0.99);
+        let black_y = vec![16u8; 16 * 16];
+        let luma = mean_luma(VideoFormat::Nv12, 16, 16, 16, &black_y).unwrap();
+        assert!((luma - 16.0 / 255.0).abs() < 1e-6);
+        assert!(mean_luma(VideoFormat::Yuyv, 16, 16, 32, &[]).is_none());
+    }
+
+    #[test]
+    fn p010_high_bytes() {
+        // Two rows of two samples, stride padded to 6 bytes.
+        let src = [0x40, 0x10, 0xc0, 0xeb, 0, 0, 0x00, 0x80, 0xff, 0xff, 0, 0];
+        assert_eq!(p010_to_8bit(2, 2, 6, &src), [0x10, 0xeb, 0x80, 0xff]);
+        assert_eq!(p010_to_8bit(2, 2, 6, &src[..6]), [0x10, 0xeb, 0, 0]);
+    }
 }
@@ -1,45 +1,41 @@
+use std::collections::HashMap;
+use std::os::fd::AsRawFd;
+use std::path::Path;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
     Arc,
+    atomic::{AtomicBool, Ordering},
 };
-use std::collections::HashMap;
-use std::io::Cursor;
-use std::process::{Child, Command, Stdio};
 use std::thread::JoinHandle;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use anyhow::{anyhow, Result};
-use crossbeam_channel::Sender;
-use crossbeam_channel::Receiver;
+use anyhow::{Result, anyhow};
+use crossbeam_channel::{Receiver, Sender, TrySendError};
 use gstreamer as gst;
 use gstreamer::prelude::*;
-use jpeg_decoder::{Decoder, PixelFormat};
-use gstreamer_app::AppSink;
+use gstreamer_app::{AppSink, AppSrc};
 use gstreamer_video::{
-    VideoColorMatrix as GstColorMatrix,
-    VideoColorRange as GstColorRange,
-    VideoFormat as GstVideoFormat,
-    VideoInfo as GstVideoInfo,
+    VideoColorMatrix as GstColorMatrix, VideoColorRange as GstColorRange,
+    VideoFormat as GstVideoFormat, VideoFrameFlags, VideoInfo as GstVideoInfo,
+    VideoInterlaceMode as GstInterlaceMode, VideoMeta, VideoTransferFunction as GstTransfer,
 };
 use v4l::buffer::Type;
 use v4l::capability::Flags;
+use v4l::control::{Control, Flags as ControlFlags, Type as ControlType, Value as ControlValue};
 use v4l::device::Device;
-use v4l::format::FourCC;
+use v4l::format::{FieldOrder, FourCC};
 use v4l::frameinterval::FrameIntervalEnum;
 use v4l::io::mmap::Stream as MmapStream;
-use v4l::io::traits::CaptureStream;
+use v4l::io::traits::{CaptureStream, Stream as _};
 use v4l::video::Capture;
+use v4l::video::capture::Parameters;
 
+use crate::pixel;
 use crate::types::{
-    ColorInfo,
-    ColorMatrix,
-    ColorRange,
-    DeviceInfo,
-    FrameData,
-    VideoFormat,
-    VideoFrame,
+    ColorInfo, ColorMatrix, ColorRange, ColorTransfer, Deinterlace, DeviceInfo, FrameData,
+    VideoFormat, VideoFrame,
 };
-use super::{CaptureStats, VideoInfo};
+
+use super::{FrameSink, ReadFailures, VideoControl, VideoInfo};
 
 pub fn list_video_devices() -> Result<Vec<DeviceInfo>> {
     let mut raw = Vec::new();
@@ -54,8 +50,8 @@ pub fn list_video_devices() -> Result<Vec<DeviceInfo>> {
             Err(_) => continue,
         };
         let flags = caps.capabilities;
-        let capture = flags.contains(Flags::VIDEO_CAPTURE)
-            || flags.contains(Flags::VIDEO_CAPTURE_MPLANE);
+        let capture =
+            flags.contains(Flags::VIDEO_CAPTURE) || flags.contains(Flags::VIDEO_CAPTURE_MPLANE);
         if !capture || !flags.contains(Flags::STREAMING) {
             continue;
         }
@@ -87,6 +83,45 @@ pub fn list_video_devices() -> Result<Vec<DeviceInfo>> {
     Ok(out)
 }
 
+// Signals on `tx` whenever a V4L2 node is added or removed. The thread ends
+// once the receiver is gone.
+pub fn watch_video_devices(tx: Sender<()>) -> Result<()> {
+    let socket = udev::MonitorBuilder::new()?
+        .match_subsystem("video4linux")?
+        .listen()?;
+    std::thread::Builder::new()
+        .name("udev-monitor".to_string())
+        .spawn(move || {
+            let mut fd = libc::pollfd {
+                fd: socket.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            loop {
+                // The socket is non-blocking, so wait for it to turn readable.
+                if unsafe { libc::poll(&mut fd, 1, -1) } < 0
+                    && std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted
+                {
+                    return;
+                }
+                let changed = socket
+                    .iter()
+                    .filter(|e| {
+                        matches!(
+                            e.event_type(),
+                            udev::EventType::Add | udev::EventType::Remove
+                        )
+                    })
+                    .count()
+                    > 0;
+                if changed && matches!(tx.try_send(()), Err(TrySendError::Disconnected(_))) {
+                    return;
+                }
+            }
+        })?;
+    Ok(())
+}
+
 fn max_fps(dev: &Device, fourcc: FourCC, width: u32, height: u32) -> Option<f64> {
     let intervals = dev.enum_frameintervals(fourcc, width, height).ok()?;
     let mut best: Option<f64> = None;
@@ -106,6 +141,21 @@ fn max_fps(dev: &Device, fourcc: FourCC, width: u32, height: u32) -> Option<f64>
     best
 }
 
+fn frame_rates(dev: &Device, fourcc: FourCC, width: u32, height: u32) -> Vec<u32> {
+    let Ok(intervals) = dev.enum_frameintervals(fourcc, width, height) else {
+        return Vec::new();
+    };
+    intervals
+        .into_iter()
+        .flat_map(|interval| match interval.interval {
+            FrameIntervalEnum::Discrete(f) => vec![f],
+            FrameIntervalEnum::Stepwise(s) => vec![s.min, s.max],
+        })
+        .filter(|f| f.numerator != 0)
+        .map(|f| (f.denominator as f64 / f.numerator as f64).round() as u32)
+        .collect()
+}
+
 #[derive(Clone, Copy)]
 struct FormatChoice {
     fourcc: FourCC,
@@ -116,10 +166,21 @@ struct FormatChoice {
 
 fn format_rank(fourcc: FourCC) -> u8 {
     if fourcc == FourCC::new(b"NV12") {
+        6
+    } else if fourcc == FourCC::new(b"P010") {
+        5
+    } else if fourcc == FourCC::new(b"YUYV") || fourcc == FourCC::new(b"UYVY") {
+        4
+    } else if fourcc == FourCC::new(b"YU12") || fourcc == FourCC::new(b"YV12") {
         3
-    } else if fourcc == FourCC::new(b"YUYV") {
+    } else if fourcc == FourCC::new(b"MJPG")
+        || fourcc == FourCC::new(b"RGBP")
+        || fourcc == FourCC::new(b"RGB3")
+        || fourcc == FourCC::new(b"BGR3")
+    {
         2
-    } else if fourcc == FourCC::new(b"MJPG") {
+    } else if fourcc == FourCC::new(b"GREY") {
+        // Any color format wins over monochrome.
         1
     } else {
         0
@@ -178,14 +239,156 @@ fn best_choice_for_fourcc(dev: &Device, fourcc: FourCC) -> Option<FormatChoice>
     best
 }
 
+pub fn list_video_modes(id: &str) -> Result<Vec<VideoInfo>> {
+    let dev = Device::with_path(id).map_err(|e| open_error(id, e))?;
+    let mut out = Vec::new();
+    for desc in dev.enum_formats()? {
+        if format_rank(desc.fourcc) == 0 {
+            continue;
+        }
+        let Ok(sizes) = dev.enum_framesizes(desc.fourcc) else {
+            continue;
+        };
+        for size in sizes {
+            for d in size.size.to_discrete() {
+                out.push(VideoInfo {
+                    width: d.width,
+                    height: d.height,
+                    format: desc.fourcc.to_string(),
+                    fps: max_fps(&dev, desc.fourcc, d.width, d.height)
+                        .map(|v| v.round().max(1.0) as u32),
+                    deinterlace: false,
+                });
+            }
+        }
+    }
+    out.sort_by(|a, b| {
+        (b.width * b.height, b.fps, format_rank(fourcc_of(&b.format))).cmp(&(
+            a.width * a.height,
+            a.fps,
+            format_rank(fourcc_of(&a.format)),
+        ))
+    });
+    out.dedup();
+    Ok(out)
+}
+
+pub fn list_frame_rates(id: &str, mode: Option<&VideoInfo>) -> Result<Vec<u32>> {
+    let dev = Device::with_path(id).map_err(|e| open_error(id, e))?;
+    let mut out = Vec::new();
+    for desc in dev.enum_formats()? {
+        if format_rank(desc.fourcc) == 0 {
+            continue;
+        }
+        if let Some(mode) = mode
+            && fourcc_of(&mode.format) != desc.fourcc
+        {
+            continue;
+        }
+        let Ok(sizes) = dev.enum_framesizes(desc.fourcc) else {
+            continue;
+        };
+        for d in sizes.into_iter().flat_map(|s| s.size.to_discrete()) {
+            if let Some(mode) = mode
+                && (mode.width, mode.height) != (d.width, d.height)
+            {
+                continue;
+            }
+            out.extend(frame_rates(&dev, desc.fourcc, d.width, d.height));
+        }
+    }
+    out.sort_unstable_by(|a, b| b.cmp(a));
+    out.dedup();
+    Ok(out)
+}
+
+// V4L2_CID_BRIGHTNESS, _CONTRAST, _SATURATION and _HUE.
+const PICTURE_CONTROLS: [u32; 4] = [0x0098_0900, 0x0098_0901, 0x0098_0902, 0x0098_0903];
+
+pub fn list_video_controls(id: &str) -> Result<Vec<VideoControl>> {
+    let dev = Device::with_path(id).map_err(|e| open_error(id, e))?;
+    let mut out = Vec::new();
+    for desc in dev.query_controls()? {
+        if !PICTURE_CONTROLS.contains(&desc.id)
+            || desc.typ != ControlType::Integer
+            || desc.flags.intersects(
+                ControlFlags::DISABLED | ControlFlags::READ_ONLY | ControlFlags::INACTIVE,
+            )
+        {
+            continue;
+        }
+        let Ok(Control {
+            value: ControlValue::Integer(value),
+            ..
+        }) = dev.control(desc.id)
+        else {
+            continue;
+        };
+        out.push(VideoControl {
+            id: desc.id,
+            name: desc.name,
+            min: desc.minimum,
+            max: desc.maximum,
+            step: (desc.step as i64).max(1),
+            default: desc.default,
+            value,
+        });
+    }
+    out.sort_by_key(|c| c.id);
+    Ok(out)
+}
+
+pub fn set_video_control(id: &str, control: u32, value: i64) -> Result<()> {
+    let dev = Device::with_path(id).map_err(|e| open_error(id, e))?;
+    dev.set_control(Control {
+        id: control,
+        value: ControlValue::Integer(value),
+    })?;
+    Ok(())
+}
+
+fn fourcc_of(name: &str) -> FourCC {
+    match name.as_bytes().try_into() {
+        Ok(bytes) => FourCC::new(&bytes),
+        Err(_) => FourCC::new(b"\0\0\0\0"),
+    }
+}
+
+fn set_mode(dev: &Device, mode: &VideoInfo) -> Result<(v4l::Format, Option<u32>)> {
+    let fourcc = fourcc_of(&mode.format);
+    let set = dev.set_format(&v4l::Format::new(mode.width, mode.height, fourcc))?;
+    if set.width != mode.width || set.height != mode.height || set.fourcc != fourcc {
+        return Err(anyhow!(
+            "Device rejected {}x{} {}",
+            mode.width,
+            mode.height,
+            mode.format
+        ));
+    }
+    Ok((set, mode.fps))
+}
+
 fn select_format(dev: &Device, max_size: Option<(u32, u32)>) -> Result<(v4l::Format, Option<u32>)> {
     let current = dev.format()?;
     let yuyv = FourCC::new(b"YUYV");
+    let uyvy = FourCC::new(b"UYVY");
     let nv12 = FourCC::new(b"NV12");
     let mjpg = FourCC::new(b"MJPG");
+    // V4L2 calls I420 "YU12".
+    let i420 = FourCC::new(b"YU12");
+    let yv12 = FourCC::new(b"YV12");
+    let p010 = FourCC::new(b"P010");
+    let grey = FourCC::new(b"GREY");
+    // V4L2's RGB565, RGB24 and BGR24.
+    let rgb565 = FourCC::new(b"RGBP");
+    let rgb24 = FourCC::new(b"RGB3");
+    let bgr24 = FourCC::new(b"BGR3");
     let supported = dev.enum_formats()?;
     let mut choices = Vec::new();
-    for fourcc in [nv12, yuyv, mjpg] {
+    let formats = [
+        nv12, p010, yuyv, uyvy, i420, yv12, mjpg, rgb565, rgb24, bgr24, grey,
+    ];
+    for fourcc in formats {
         if supported.iter().any(|f| f.fourcc == fourcc) {
             if let Some(choice) = best_choice_for_fourcc(dev, fourcc) {
                 choices.push(choice);
@@ -228,7 +431,7 @@ fn select_format(dev: &Device, max_size: Option<(u32, u32)>) -> Result<(v4l::For
             return Ok((set, fps));
         }
     }
-    if current.fourcc == yuyv || current.fourcc == nv12 || current.fourcc == mjpg {
+    if formats.contains(&current.fourcc) {
         return Ok((current, None));
     }
     Err(anyhow!("Unsupported pixel format: {}", current.fourcc))
@@ -237,82 +440,159 @@ fn select_format(dev: &Device, max_size: Option<(u32, u32)>) -> Result<(v4l::For
 pub fn spawn_capture(
     id: &str,
     max_size: Option<(u32, u32)>,
-    tx: Sender<VideoFrame>,
-    drop_rx: Receiver<VideoFrame>,
+    mode: Option<&VideoInfo>,
+    fps: Option<u32>,
+    deinterlace: Deinterlace,
+    sink: FrameSink,
     stop: Arc<AtomicBool>,
-    stats: Arc<CaptureStats>,
 ) -> Result<(JoinHandle<()>, VideoInfo)> {
-    let mut dev = Device::with_path(id)?;
-    let (fmt, _fps) = select_format(&dev, max_size)?;
+    if super::is_network_source(id) {
+        return spawn_capture_network(id, deinterlace, sink, stop);
+    }
+    let mut dev = Device::with_path(id).map_err(|e| open_error(id, e))?;
+    let flags = dev.query_caps()?.capabilities;
+    if !flags.contains(Flags::VIDEO_CAPTURE) && flags.contains(Flags::VIDEO_CAPTURE_MPLANE) {
+        // v4l only speaks the single-plane API; v4l2src handles MPLANE.
+        drop(dev);
+        return spawn_capture_mplane(id, max_size, deinterlace, sink, stop);
+    }
+    let pinned = mode.is_some() || fps.is_some();
+    let (fmt, format_fps) = match mode {
+        Some(mode) => set_mode(&dev, mode)?,
+        None => select_format(&dev, max_size)?,
+    };
+    // The driver snaps to the nearest interval it supports for this mode;
+    // otherwise report whatever interval it's already running at.
+    let params = match fps {
+        Some(fps) => dev.set_params(&Parameters::with_fps(fps)).ok(),
+        None => dev.params().ok(),
+    };
+    let fps = params
+        .filter(|p| p.interval.numerator != 0)
+        .map(|p| (p.interval.denominator as f64 / p.interval.numerator as f64).round() as u32)
+        .or(format_fps);
     if fmt.fourcc == FourCC::new(b"MJPG") {
         if let Some(decoder) = mjpeg_hw_decoder() {
             drop(dev);
-            if let Ok((handle, info)) = spawn_capture_gst(
-                id,
-                fmt,
-                decoder,
-                tx.clone(),
-                drop_rx.clone(),
-                stop.clone(),
-                stats.clone(),
-            ) {
+            if let Ok((handle, info)) =
+                spawn_capture_gst(id, fmt, fps, decoder, sink.clone(), stop.clone())
+            {
                 return Ok((handle, info));
             }
-            dev = Device::with_path(id)?;
+            dev = Device::with_path(id).map_err(|e| open_error(id, e))?;
             let _ = dev.set_format(&fmt);
         }
     }
+    // The single-planar format only has the luma stride, leaving
+    // chroma_stride to guess the rest. Drivers that also speak the
+    // multi-planar API report each plane's stride there, which v4l2src passes
+    // on, so NV12 goes that way when the mode is left to the platform anyway.
+    let nv12 = fmt.fourcc == FourCC::new(b"NV12");
+    if nv12 && flags.contains(Flags::VIDEO_CAPTURE_MPLANE) && !pinned {
+        drop(dev);
+        if let Ok(started) =
+            spawn_capture_mplane(id, max_size, deinterlace, sink.clone(), stop.clone())
+        {
+            return Ok(started);
+        }
+        dev = Device::with_path(id).map_err(|e| open_error(id, e))?;
+        let _ = dev.set_format(&fmt);
+    }
     let width = fmt.width;
     let height = fmt.height;
     let fourcc = fmt.fourcc;
+    let interlaced = !matches!(fmt.field_order, FieldOrder::Progressive | FieldOrder::Any);
     let info = VideoInfo {
         width,
         height,
         format: format!("{fourcc}"),
-        fps: None,
+        fps,
+        deinterlace: deinterlace.applies(interlaced),
     };
     let stride = if fmt.stride == 0 {
         match fourcc {
-            f if f == FourCC::new(b"YUYV") => width * 2,
+            f if f == FourCC::new(b"YUYV")
+                || f == FourCC::new(b"UYVY")
+                || f == FourCC::new(b"P010")
+                || f == FourCC::new(b"RGBP") =>
+            {
+                width * 2
+            }
+            f if f == FourCC::new(b"RGB3") || f == FourCC::new(b"BGR3") => width * 3,
             _ => width,
         }
     } else {
         fmt.stride
     } as usize;
+    let chroma_row = width.div_ceil(2) as usize * 2;
+    let uv_stride = match fourcc {
+        f if f == FourCC::new(b"NV12") => {
+            chroma_stride(stride, height, fmt.size as usize, chroma_row)
+        }
+        f if f == FourCC::new(b"P010") => {
+            chroma_stride(stride, height, fmt.size as usize, chroma_row * 2)
+        }
+        _ => stride,
+    };
+    // Allocate buffers before spawning so a device already streaming in
+    // another process is reported instead of leaving a silent dead thread.
+    let mut stream = match MmapStream::with_buffers(&dev, Type::VideoCapture, 1) {
+        Ok(s) => s,
+        Err(_) => {
+            MmapStream::with_buffers(&dev, Type::VideoCapture, 2).map_err(|e| open_error(id, e))?
+        }
+    };
     let handle = std::thread::Builder::new()
         .name("v4l-capture".to_string())
         .spawn(move || {
-            let mut stream = match MmapStream::with_buffers(&dev, Type::VideoCapture, 1) {
-                Ok(s) => s,
-                Err(_) => match MmapStream::with_buffers(&dev, Type::VideoCapture, 2) {
-                    Ok(s) => s,
-                    Err(_) => return,
-                },
-            };
+            let mut failures = ReadFailures::new();
             while !stop.load(Ordering::Relaxed) {
-                let stats_on = stats.enabled();
+                if sink.is_paused() {
+                    // STREAMOFF hands the buffers back; next() queues them
+                    // again and restarts the stream.
+                    let _ = stream.stop();
+                    sink.wait_while_paused(&stop);
+                    continue;
+                }
+                let stats_on = sink.stats.enabled();
                 let (data, meta) = match stream.next() {
-                    Ok(v) => v,
-                    Err(_) => continue,
+                    Ok(v) => {
+                        failures.ok();
+                        v
+                    }
+                    Err(_) => {
+                        if failures.failed() {
+                            sink.mark_lost();
+                            break;
+                        }
+                        continue;
+                    }
                 };
+                let captured_at = Instant::now();
                 let used = meta.bytesused as usize;
                 let slice = &data[..used.min(data.len())];
-                if !drop_rx.is_empty() {
+                if sink.is_backed_up() {
                     if stats_on {
-                        stats.on_drop_enabled();
+                        sink.stats.on_drop_enabled();
                     }
                     continue;
                 }
                 let t0 = if stats_on { Some(Instant::now()) } else { None };
-                let frame = if fourcc == FourCC::new(b"YUYV") {
+                let frame = if fourcc == FourCC::new(b"YUYV") || fourcc == FourCC::new(b"UYVY") {
                     VideoFrame {
                         width,
                         height,
-                        format: VideoFormat::Yuyv,
+                        format: if fourcc == FourCC::new(b"UYVY") {
+                            VideoFormat::Uyvy
+                        } else {
+                            VideoFormat::Yuyv
+                        },
                         stride,
                         uv_stride: 0,
+                        plane_offsets: None,
                         color: ColorInfo::default_for_size(width),
                         data: FrameData::Owned(slice.to_vec()),
+                        captured_at,
                     }
                 } else if fourcc == FourCC::new(b"NV12") {
                     VideoFrame {
@@ -320,20 +600,97 @@ pub fn spawn_capture(
                         height,
                         format: VideoFormat::Nv12,
                         stride,
-                        uv_stride: stride,
+                        uv_stride,
+                        plane_offsets: None,
                         color: ColorInfo::default_for_size(width),
                         data: FrameData::Owned(slice.to_vec()),
+                        captured_at,
+                    }
+                } else if fourcc == FourCC::new(b"RGBP") {
+                    VideoFrame {
+                        width,
+                        height,
+                        format: VideoFormat::Rgb565,
+                        stride,
+                        uv_stride: 0,
+                        plane_offsets: None,
+                        color: ColorInfo::default_for_size(width),
+                        data: FrameData::Owned(slice.to_vec()),
+                        captured_at,
+                    }
+                } else if fourcc == FourCC::new(b"RGB3") || fourcc == FourCC::new(b"BGR3") {
+                    let rgba = if fourcc == FourCC::new(b"RGB3") {
+                        pixel::rgb24_to_rgba(width, height, stride, false, slice)
+                    } else {
+                        pixel::bgr24_to_rgba(width, height, stride, false, slice)
+                    };
+                    VideoFrame {
+                        width,
+                        height,
+                        format: VideoFormat::Rgba,
+                        stride: (width * 4) as usize,
+                        uv_stride: 0,
+                        plane_offsets: None,
+                        color: ColorInfo::default_for_size(width),
+                        data: FrameData::Owned(rgba),
+                        captured_at,
+                    }
+                } else if fourcc == FourCC::new(b"GREY") {
+                    VideoFrame {
+                        width,
+                        height,
+                        format: VideoFormat::Grey,
+                        stride,
+                        uv_stride: 0,
+                        plane_offsets: None,
+                        color: ColorInfo::default_for_size(width),
+                        data: FrameData::Owned(slice.to_vec()),
+                        captured_at,
+                    }
+                } else if fourcc == FourCC::new(b"P010") {
+                    VideoFrame {
+                        width,
+                        height,
+                        format: VideoFormat::P010,
+                        stride,
+                        uv_stride,
+                        plane_offsets: None,
+                        color: ColorInfo::default_for_size(width),
+                        data: FrameData::Owned(slice.to_vec()),
+                        captured_at,
+                    }
+                } else if fourcc == FourCC::new(b"YU12") || fourcc == FourCC::new(b"YV12") {
+                    let uv_stride = stride / 2;
+                    let y_bytes = stride * height as usize;
+                    let c_bytes = uv_stride * height.div_ceil(2) as usize;
+                    VideoFrame {
+                        width,
+                        height,
+                        format: VideoFormat::I420,
+                        stride,
+                        uv_stride,
+                        // YV12 stores V before U.
+                        plane_offsets: (fourcc == FourCC::new(b"YV12")).then_some([
+                            0,
+                            y_bytes + c_bytes,
+                            y_bytes,
+                        ]),
+                        color: ColorInfo::default_for_size(width),
+                        data: FrameData::Owned(slice.to_vec()),
+                        captured_at,
                     }
                 } else if fourcc == FourCC::new(b"MJPG") {
-                    match decode_mjpeg(slice) {
+                    match pixel::decode_mjpeg(slice) {
                         Ok((w, h, rgba)) => VideoFrame {
                             width: w,
                             height: h,
                             format: VideoFormat::Rgba,
                             stride: (w * 4) as usize,
                             uv_stride: 0,
+                            plane_offsets: None,
                             color: ColorInfo::default_for_size(w),
                             data: FrameData::Owned(rgba),
+                            captured_at,
                         },
                         Err(_) => continue,
                     }
@@ -341,88 +698,36 @@ pub fn spawn_capture(
                     continue;
                 };
                 if let Some(t0) = t0 {
-                    stats.on_frame_enabled(t0.elapsed().as_micros() as u64);
-                }
-                if let Err(err) = tx.try_send(frame) {
-                    let frame = err.into_inner();
-                    let _ = drop_rx.try_recv();
-                    if stats_on {
-                        stats.on_drop_enabled();
-                    }
-                    let _ = tx.try_send(frame);
+                    sink.stats.on_frame_enabled(t0.elapsed().as_micros() as u64);
                 }
+                sink.deliver(frame, stats_on);
             }
         })?;
     Ok((handle, info))
 }
 
-fn rgb24_to_rgba(pixels: &[u8], pixel_count: usize) -> Vec<u8> {
-    let mut rgba = Vec::with_capacity(pixel_count * 4);
-    // Safety: we set the length then write every byte.
-    unsafe {
-        rgba.set_len(pixel_count * 4);
-        let mut src = pixels.as_ptr();
-        let mut dst = rgba.as_mut_ptr();
-        for _ in 0..pixel_count {
-            *dst = *src;
-            *dst.add(1) = *src.add(1);
-            *dst.add(2) = *src.add(2);
-            *dst.add(3) = 255;
-            src = src.add(3);
-            dst = dst.add(4);
-        }
-    }
-    rgba
-}
-
-fn l8_to_rgba(pixels: &[u8], pixel_count: usize) -> Vec<u8> {
-    let mut rgba = Vec::with_capacity(pixel_count * 4);
-    // Safety: we set the length then write every byte.
-    unsafe {
-        rgba.set_len(pixel_count * 4);
-        let mut src = pixels.as_ptr();
-        let mut dst = rgba.as_mut_ptr();
-        for _ in 0..pixel_count {
-            let v = *src;
-            *dst = v;
-            *dst.add(1) = v;
-            *dst.add(2) = v;
-            *dst.add(3) = 255;
-            src = src.add(1);
-            dst = dst.add(4);
-        }
-    }
-    rgba
-}
-
-fn decode_mjpeg(data: &[u8]) -> Result<(u32, u32, Vec<u8>)> {
-    let mut decoder = Decoder::new(Cursor::new(data));
-    let pixels = decoder.decode()?;
-    let info = decoder.info().ok_or_else(|| anyhow!("Missing MJPEG info"))?;
-    let width = info.width as u32;
-    let height = info.height as u32;
-    let pixel_count = (width as usize)
-        .checked_mul(height as usize)
-        .ok_or_else(|| anyhow!("MJPEG size overflow"))?;
-    let rgba = match info.pixel_format {
-        PixelFormat::RGB24 => {
-            let expected = pixel_count
-                .checked_mul(3)
-                .ok_or_else(|| anyhow!("MJPEG size overflow"))?;
-            if pixels.len() < expected {
-                return Err(anyhow!("MJPEG RGB size mismatch"));
-            }
-            rgb24_to_rgba(&pixels[..expected], pixel_count)
-        }
-        PixelFormat::L8 => {
-            if pixels.len() < pixel_count {
-                return Err(anyhow!("MJPEG L8 size mismatch"));
-            }
-            l8_to_rgba(&pixels[..pixel_count], pixel_count)
+// Single-planar NV12 and P010 carry no chroma stride of their own; the
+// chroma rows nominally match the luma stride, but some drivers pad them
+// differently. sizeimage covers both planes, so when what follows the luma
+// plane splits evenly into rows between one chroma row and the luma stride,
+// that's the stride the driver used.
+fn chroma_stride(stride: usize, height: u32, size: usize, row_bytes: usize) -> usize {
+    let rows = height.div_ceil(2) as usize;
+    let rest = size.saturating_sub(stride * height as usize);
+    match rest.checked_div(rows) {
+        Some(uv_stride) if rest % rows == 0 && (row_bytes..=stride).contains(&uv_stride) => {
+            uv_stride
         }
-        _ => return Err(anyhow!("Unsupported MJPEG pixel format")),
-    };
-    Ok((width, height, rgba))
+        _ => stride,
+    }
+}
+
+fn open_error(id: &str, e: std::io::Error) -> anyhow::Error {
+    if e.kind() == std::io::ErrorKind::ResourceBusy {
+        anyhow!("{id} is busy (in use by another application or instance)")
+    } else {
+        anyhow!("{id}: {e}")
+    }
 }
 
 fn color_info_from_gst(info: &GstVideoInfo, source_fourcc: FourCC) -> ColorInfo {
@@ -444,6 +749,13 @@ fn color_info_from_gst(info: &GstVideoInfo, source_fourcc: FourCC) -> ColorInfo
         }
         _ => out.matrix,
     };
+    out.transfer = match colorimetry.transfer() {
+        GstTransfer::Smpte2084 => ColorTransfer::Pq,
+        GstTransfer::AribStdB67 => ColorTransfer::Hlg,
+        GstTransfer::Srgb => ColorTransfer::Srgb,
+        GstTransfer::Gamma10 => ColorTransfer::Linear,
+        _ => ColorTransfer::Bt709,
+    };
     out
 }
 
@@ -492,7 +804,10 @@ fn mjpeg_pipeline_variants(
     variants
 }
 
-fn launch_pipeline(pipeline_str: &str) -> Result<(gst::Pipeline, AppSink)> {
+fn launch_pipeline(
+    pipeline_str: &str,
+    timeout: gst::ClockTime,
+) -> Result<(gst::Pipeline, AppSink)> {
     let pipeline = gst::parse::launch(&pipeline_str)?
         .downcast::<gst::Pipeline>()
         .map_err(|_| anyhow!("GStreamer pipeline type"))?;
@@ -502,7 +817,7 @@ fn launch_pipeline(pipeline_str: &str) -> Result<(gst::Pipeline, AppSink)> {
         .downcast::<AppSink>()
         .map_err(|_| anyhow!("GStreamer appsink type"))?;
     pipeline.set_state(gst::State::Playing)?;
-    let (state_res, state, _) = pipeline.state(gst::ClockTime::from_mseconds(500));
+    let (state_res, state, _) = pipeline.state(timeout);
     if state_res.is_err() || state != gst::State::Playing {
         let _ = pipeline.set_state(gst::State::Null);
         return Err(anyhow!("GStreamer failed to play"));
@@ -510,6 +825,83 @@ fn launch_pipeline(pipeline_str: &str) -> Result<(gst::Pipeline, AppSink)> {
     Ok((pipeline, appsink))
 }
 
+// `deinterlace` passes progressive buffers straight through in auto mode,
+// so it can sit in the pipeline whether or not the source is interlaced.
+fn deinterlace_element(mode: Deinterlace) -> &'static str {
+    if gst::ElementFactory::find("deinterlace").is_none() {
+        return "";
+    }
+    match mode {
+        Deinterlace::Auto => "deinterlace mode=auto method=yadif ! ",
+        Deinterlace::On => "deinterlace mode=interlaced method=yadif ! ",
+        Deinterlace::Off => "",
+    }
+}
+
+// Whether the negotiated frames still need the renderer's deinterlacer,
+// i.e. no deinterlace element was available to handle them.
+fn renderer_deinterlace(mode: Deinterlace, negotiated: Option<&GstVideoInfo>) -> bool {
+    let interlaced =
+        negotiated.is_some_and(|i| i.interlace_mode() != GstInterlaceMode::Progressive);
+    deinterlace_element(mode).is_empty() && mode.applies(interlaced)
+}
+
+// Variable-rate caps report 0/1, which leaves the rate unknown.
+fn caps_fps(info: &GstVideoInfo) -> Option<u32> {
+    let rate = info.fps();
+    (rate.numer() > 0 && rate.denom() > 0)
+        .then(|| (rate.numer() as f64 / rate.denom() as f64).round().max(1.0) as u32)
+}
+
+fn network_pipeline(url: &str, deinterlace: Deinterlace) -> Result<String> {
+    if url.chars().any(|c| c.is_whitespace() || c == '"') {
+        return Err(anyhow!("Invalid network URL"));
+    }
+    let source = if url.starts_with("rtsp://") || url.starts_with("rtsps://") {
+        format!("rtspsrc location=\"{url}\" latency=0 ! decodebin")
+    } else if url.starts_with("srt://") {
+        format!("srtsrc uri=\"{url}\" ! decodebin")
+    } else {
+        format!("uridecodebin uri=\"{url}\"")
+    };
+    let queue = "queue leaky=downstream max-size-buffers=1 max-size-time=0 max-size-bytes=0";
+    let appsink =
+        "appsink name=sink max-buffers=1 drop=true sync=false async=false enable-last-sample=false";
+    let deinterlace = deinterlace_element(deinterlace);
+    Ok(format!(
+        "{source} ! {queue} ! videoconvert ! video/x-raw,format=NV12 ! {deinterlace}{appsink}"
+    ))
+}
+
+fn mplane_pipeline_variants(
+    device: &str,
+    max_size: Option<(u32, u32)>,
+    deinterlace: Deinterlace,
+) -> Vec<String> {
+    let base = format!("v4l2src device={device} io-mode=2 do-timestamp=true");
+    // Exported dmabufs let the renderer import NV12 without a copy; drivers
+    // that can't export fail this variant and fall back to mmap.
+    let dmabuf = format!("v4l2src device={device} io-mode=4 do-timestamp=true");
+    let queue = "queue leaky=downstream max-size-buffers=1 max-size-time=0 max-size-bytes=0";
+    let deinterlace = deinterlace_element(deinterlace);
+    let appsink =
+        "appsink name=sink max-buffers=1 drop=true sync=false async=false enable-last-sample=false";
+    let size = max_size
+        .map(|(w, h)| format!(",width=(int)[1,{w}],height=(int)[1,{h}]"))
+        .unwrap_or_default();
+    vec![
+        format!("{dmabuf} ! video/x-raw,format=NV12{size} ! {queue} ! {deinterlace}{appsink}"),
+        format!("{base} ! video/x-raw,format=NV12{size} ! {queue} ! {deinterlace}{appsink}"),
+        format!("{base} ! video/x-raw,format=YUY2{size} ! {queue} ! {deinterlace}{appsink}"),
+        format!("{base} ! video/x-raw,format=UYVY{size} ! {queue} ! {deinterlace}{appsink}"),
+        format!("{base} ! video/x-raw,format=I420{size} ! {queue} ! {deinterlace}{appsink}"),
+        format!("{base} ! video/x-raw,format=YV12{size} ! {queue} ! {deinterlace}{appsink}"),
+        format!(
+            "{base} ! video/x-raw{size} ! {queue} ! videoconvert ! video/x-raw,format=NV12 ! {deinterlace}{appsink}"
+        ),
+    ]
+}
+
 fn build_mjpeg_pipeline(
     device: &str,
     width: u32,
@@ -518,7 +910,7 @@ fn build_mjpeg_pipeline(
 ) -> Result<(gst::Pipeline, AppSink)> {
     let mut last_err = None;
     for pipeline_str in mjpeg_pipeline_variants(device, width, height, decoder) {
-        match launch_pipeline(&pipeline_str) {
+        match launch_pipeline(&pipeline_str, gst::ClockTime::from_mseconds(500)) {
             Ok(ok) => return Ok(ok),
             Err(e) => last_err = Some(e),
         }
@@ -529,11 +921,10 @@ fn build_mjpeg_pipeline(
 fn spawn_capture_gst(
     id: &str,
     fmt: v4l::Format,
+    fps: Option<u32>,
     decoder: &str,
-    tx: Sender<VideoFrame>,
-    drop_rx: Receiver<VideoFrame>,
+    sink: FrameSink,
     stop: Arc<AtomicBool>,
-    stats: Arc<CaptureStats>,
 ) -> Result<(JoinHandle<()>, VideoInfo)> {
     gst::init()?;
     let width = fmt.width;
@@ -547,22 +938,167 @@ fn spawn_capture_gst(
         width,
         height,
         format: format!("{}", fmt.fourcc),
-        fps: None,
+        fps,
+        deinterlace: false,
+    };
+    let handle = spawn_appsink_loop(pipeline, appsink, source_fourcc, sink, stop)?;
+    Ok((handle, info))
+}
+
+fn spawn_capture_mplane(
+    id: &str,
+    max_size: Option<(u32, u32)>,
+    deinterlace: Deinterlace,
+    sink: FrameSink,
+    stop: Arc<AtomicBool>,
+) -> Result<(JoinHandle<()>, VideoInfo)> {
+    gst::init()?;
+    let mut last_err = None;
+    let mut launched = None;
+    for pipeline_str in mplane_pipeline_variants(id, max_size, deinterlace) {
+        match launch_pipeline(&pipeline_str, gst::ClockTime::from_mseconds(500)) {
+            Ok(ok) => {
+                launched = Some(ok);
+                break;
+            }
+            Err(e) => last_err = Some(e),
+        }
+    }
+    let (pipeline, appsink) =
+        launched.ok_or_else(|| last_err.unwrap_or_else(|| anyhow!("GStreamer failed to play")))?;
+    // Size, format and interlacing all come from the caps; without them
+    // there's nothing sound to report.
+    let negotiated = match wait_for_caps(&pipeline, &appsink, Duration::from_secs(2)) {
+        Ok(Some(negotiated)) => negotiated,
+        Ok(None) => {
+            let _ = pipeline.set_state(gst::State::Null);
+            return Err(anyhow!("Multi-planar capture negotiated no format"));
+        }
+        Err(e) => {
+            let _ = pipeline.set_state(gst::State::Null);
+            return Err(e);
+        }
     };
+    let source_fourcc = match negotiated.format() {
+        GstVideoFormat::Yuy2 => FourCC::new(b"YUYV"),
+        GstVideoFormat::Uyvy => FourCC::new(b"UYVY"),
+        GstVideoFormat::I420 => FourCC::new(b"YU12"),
+        GstVideoFormat::Yv12 => FourCC::new(b"YV12"),
+        _ => FourCC::new(b"NV12"),
+    };
+    let info = VideoInfo {
+        width: negotiated.width(),
+        height: negotiated.height(),
+        format: negotiated.format().to_str().to_string(),
+        fps: caps_fps(&negotiated),
+        deinterlace: renderer_deinterlace(deinterlace, Some(&negotiated)),
+    };
+    let handle = spawn_appsink_loop(pipeline, appsink, source_fourcc, sink, stop)?;
+    Ok((handle, info))
+}
+
+fn spawn_capture_network(
+    url: &str,
+    deinterlace: Deinterlace,
+    sink: FrameSink,
+    stop: Arc<AtomicBool>,
+) -> Result<(JoinHandle<()>, VideoInfo)> {
+    gst::init()?;
+    // Network sources can take a while to connect and negotiate.
+    let (pipeline, appsink) = launch_pipeline(
+        &network_pipeline(url, deinterlace)?,
+        gst::ClockTime::from_seconds(5),
+    )?;
+    let negotiated = match wait_for_caps(&pipeline, &appsink, Duration::from_secs(5)) {
+        Ok(negotiated) => negotiated,
+        Err(e) => {
+            let _ = pipeline.set_state(gst::State::Null);
+            return Err(e);
+        }
+    };
+    let (width, height) = negotiated
+        .as_ref()
+        .map(|i| (i.width(), i.height()))
+        .unwrap_or((0, 0));
+    let info = VideoInfo {
+        width,
+        height,
+        format: "NV12".to_string(),
+        fps: negotiated.as_ref().and_then(caps_fps),
+        deinterlace: renderer_deinterlace(deinterlace, negotiated.as_ref()),
+    };
+    let handle = spawn_appsink_loop(pipeline, appsink, FourCC::new(b"NV12"), sink, stop)?;
+    Ok((handle, info))
+}
+
+// Live sources reach Playing before anything is negotiated, so the caps
+// only turn up with the first frame. None if that takes longer than
+// `timeout`; the stream may still start later.
+fn wait_for_caps(
+    pipeline: &gst::Pipeline,
+    appsink: &AppSink,
+    timeout: Duration,
+) -> Result<Option<GstVideoInfo>> {
+    let pad = appsink
+        .static_pad("sink")
+        .ok_or_else(|| anyhow!("GStreamer appsink pad missing"))?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(caps) = pad.current_caps() {
+            return Ok(GstVideoInfo::from_caps(&caps).ok());
+        }
+        if pipeline_failed(pipeline) {
+            return Err(anyhow!("GStreamer stream failed to start"));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+// An unplugged v4l2src (or a dropped network stream) posts an error and
+// stops producing without ever reaching EOS.
+fn pipeline_failed(pipeline: &gst::Pipeline) -> bool {
+    pipeline
+        .bus()
+        .and_then(|bus| bus.pop_filtered(&[gst::MessageType::Error]))
+        .is_some()
+}
+
+fn spawn_appsink_loop(
+    pipeline: gst::Pipeline,
+    appsink: AppSink,
+    source_fourcc: FourCC,
+    sink: FrameSink,
+    stop: Arc<AtomicBool>,
+) -> Result<JoinHandle<()>> {
     let handle = std::thread::Builder::new()
         .name("gst-capture".to_string())
         .spawn(move || {
             let mut gst_info: Option<GstVideoInfo> = None;
             let mut color_info: Option<ColorInfo> = None;
             while !stop.load(Ordering::Relaxed) {
-                let stats_on = stats.enabled();
-                let sample = match appsink.pull_sample() {
-                    Ok(s) => s,
-                    Err(_) => continue,
+                if sink.is_paused() {
+                    let _ = pipeline.set_state(gst::State::Paused);
+                    sink.wait_while_paused(&stop);
+                    let _ = pipeline.set_state(gst::State::Playing);
+                    continue;
+                }
+                let stats_on = sink.stats.enabled();
+                // Time out so a stalled source can't block stop().
+                let sample = match appsink.try_pull_sample(gst::ClockTime::from_mseconds(100)) {
+                    Some(s) => s,
+                    None if appsink.is_eos() || pipeline_failed(&pipeline) => {
+                        sink.mark_lost();
+                        break;
+                    }
+                    None => continue,
                 };
-                if !drop_rx.is_empty() {
+                let captured_at = Instant::now();
+                if sink.is_backed_up() {
                     if stats_on {
-                        stats.on_drop_enabled();
+                        sink.stats.on_drop_enabled();
                     }
                     continue;
                 }
@@ -588,26 +1124,43 @@ fn spawn_capture_gst(
                     }
                 };
                 let t0 = if stats_on { Some(Instant::now()) } else { None };
-                let (format, stride, uv_stride) = match info.format() {
+                let (strides, offsets) = match buffer.meta::<VideoMeta>() {
+                    Some(meta) => (meta.stride().to_vec(), meta.offset().to_vec()),
+                    None => (info.stride().to_vec(), info.offset().to_vec()),
+                };
+                let (format, stride, uv_stride, plane_offsets) = match info.format() {
                     GstVideoFormat::Nv12 => (
                         VideoFormat::Nv12,
-                        info.stride()[0] as usize,
-                        info.stride()[1] as usize,
+                        strides[0] as usize,
+                        strides[1] as usize,
+                        Some([offsets[0], offsets[1], 0]),
                     ),
-                    GstVideoFormat::Yuy2 => (
-                        VideoFormat::Yuyv,
-                        info.stride()[0] as usize,
-                        0,
+                    GstVideoFormat::P01010le => (
+                        VideoFormat::P010,
+                        strides[0] as usize,
+                        strides[1] as usize,
+                        Some([offsets[0], offsets[1], 0]),
                     ),
-                    GstVideoFormat::Rgba => (
-                        VideoFormat::Rgba,
-                        info.stride()[0] as usize,
-                        0,
+                    GstVideoFormat::I420 => (
+                        VideoFormat::I420,
+                        strides[0] as usize,
+                        strides[1] as usize,
+                        Some([offsets[0], offsets[1], offsets[2]]),
                     ),
+                    // Plane 1 is V for YV12.
+                    GstVideoFormat::Yv12 => (
+                        VideoFormat::I420,
+                        strides[0] as usize,
+                        strides[2] as usize,
+                        Some([offsets[0], offsets[2], offsets[1]]),
+                    ),
+                    GstVideoFormat::Yuy2 => (VideoFormat::Yuyv, strides[0] as usize, 0, None),
+                    GstVideoFormat::Uyvy => (VideoFormat::Uyvy, strides[0] as usize, 0, None),
+                    GstVideoFormat::Rgba => (VideoFormat::Rgba, strides[0] as usize, 0, None),
                     _ => continue,
                 };
                 if let Some(t0) = t0 {
-                    stats.on_frame_enabled(t0.elapsed().as_micros() as u64);
+                    sink.stats.on_frame_enabled(t0.elapsed().as_micros() as u64);
                 }
                 let frame = VideoFrame {
                     width: info.width(),
@@ -615,48 +1168,177 @@ fn spawn_capture_gst(
                     format,
                     stride,
                     uv_stride,
+                    plane_offsets,
                     color,
                     data: FrameData::Gst(buffer),
+                    captured_at,
                 };
-                if let Err(err) = tx.try_send(frame) {
-                    let frame = err.into_inner();
-                    let _ = drop_rx.try_recv();
-                    if stats_on {
-                        stats.on_drop_enabled();
-                    }
-                    let _ = tx.try_send(frame);
-                }
+                sink.deliver(frame, stats_on);
             }
             let _ = pipeline.set_state(gst::State::Null);
         })?;
-    Ok((handle, info))
+    Ok(handle)
 }
 
-pub struct KeepAwake {
-    child: Child,
+const RECORD_PIPELINE: &str = "appsrc name=src is-live=true format=time ! queue \
+    ! videoconvert ! x264enc tune=zerolatency speed-preset=veryfast ! h264parse \
+    ! mp4mux ! filesink name=file";
+
+// Every capture path delivers VideoFrames, so one appsrc branch serves v4l,
+// mplane and decoded MJPG alike; GStreamer-backed frames are handed over
+// without copying their memory.
+pub fn record_video(
+    path: &Path,
+    fps: Option<u32>,
+    rx: Receiver<(Instant, VideoFrame)>,
+) -> Result<()> {
+    gst::init()?;
+    let (start, first) = rx.recv().map_err(|_| anyhow!("No frames were recorded"))?;
+    let (format, width, height) = (first.format, first.width, first.height);
+    let mut builder = GstVideoInfo::builder(gst_video_format(format), width, height);
+    if let Some(fps) = fps {
+        builder = builder.fps(gst::Fraction::new(fps as i32, 1));
+    }
+    let caps = builder.build()?.to_caps()?;
+    let pipeline = gst::parse::launch(RECORD_PIPELINE)?
+        .downcast::<gst::Pipeline>()
+        .map_err(|_| anyhow!("GStreamer pipeline type"))?;
+    let appsrc = pipeline
+        .by_name("src")
+        .ok_or_else(|| anyhow!("GStreamer appsrc missing"))?
+        .downcast::<AppSrc>()
+        .map_err(|_| anyhow!("GStreamer appsrc type"))?;
+    appsrc.set_caps(Some(&caps));
+    pipeline
+        .by_name("file")
+        .ok_or_else(|| anyhow!("GStreamer filesink missing"))?
+        .set_property("location", path.to_string_lossy().to_string());
+    pipeline.set_state(gst::State::Playing)?;
+    let frames = std::iter::once((start, first)).chain(rx);
+    for (at, frame) in frames {
+        // Caps are fixed for the file; the capture restarts (and the
+        // recording stops) whenever the mode changes.
+        if frame.format != format || frame.width != width || frame.height != height {
+            continue;
+        }
+        let buffer = recording_buffer(frame, at.duration_since(start))?;
+        if appsrc.push_buffer(buffer).is_err() {
+            break;
+        }
+    }
+    // mp4mux only writes its index once EOS reaches it.
+    let _ = appsrc.end_of_stream();
+    let bus = pipeline
+        .bus()
+        .ok_or_else(|| anyhow!("GStreamer bus missing"))?;
+    let msg = bus.timed_pop_filtered(
+        gst::ClockTime::from_seconds(5),
+        &[gst::MessageType::Eos, gst::MessageType::Error],
+    );
+    let _ = pipeline.set_state(gst::State::Null);
+    match msg.as_ref().map(|m| m.view()) {
+        Some(gst::MessageView::Error(err)) => Err(anyhow!("Recording failed: {}", err.error())),
+        Some(_) => Ok(()),
+        None => Err(anyhow!("Recording did not finish writing")),
+    }
 }
 
-impl KeepAwake {
-    pub fn new() -> Option<Self> {
-        let child = Command::new("systemd-inhibit")
-            .arg("--what=idle:sleep")
-            .arg("--mode=block")
-            .arg("--who=CaptureCardGaming")
-            .arg("--why=CaptureCardGaming")
-            .arg("sleep")
-            .arg("infinity")
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-            .ok()?;
-        Some(Self { child })
+fn gst_video_format(format: VideoFormat) -> GstVideoFormat {
+    match format {
+        VideoFormat::Rgba => GstVideoFormat::Rgba,
+        VideoFormat::Yuyv => GstVideoFormat::Yuy2,
+        VideoFormat::Uyvy => GstVideoFormat::Uyvy,
+        VideoFormat::Nv12 => GstVideoFormat::Nv12,
+        VideoFormat::I420 => GstVideoFormat::I420,
+        VideoFormat::P010 => GstVideoFormat::P01010le,
+        VideoFormat::Grey => GstVideoFormat::Gray8,
+        VideoFormat::Rgb565 => GstVideoFormat::Rgb16,
     }
 }
 
-impl Drop for KeepAwake {
-    fn drop(&mut self) {
-        let _ = self.child.kill();
-        let _ = self.child.wait();
+// Wraps a frame in a buffer whose VideoMeta carries the frame's own strides
+// and plane offsets, so nothing has to be repacked.
+fn recording_buffer(frame: VideoFrame, pts: std::time::Duration) -> Result<gst::Buffer> {
+    let y_bytes = frame.stride * frame.height as usize;
+    let c_bytes = frame.uv_stride * frame.height.div_ceil(2) as usize;
+    let (mut offsets, strides) = match frame.format {
+        VideoFormat::Rgba
+        | VideoFormat::Yuyv
+        | VideoFormat::Uyvy
+        | VideoFormat::Grey
+        | VideoFormat::Rgb565 => (vec![0], vec![frame.stride as i32]),
+        VideoFormat::Nv12 | VideoFormat::P010 => {
+            let [y, uv, _] = frame.plane_offsets.unwrap_or([0, y_bytes, 0]);
+            (
+                vec![y, uv],
+                vec![frame.stride as i32, frame.uv_stride as i32],
+            )
+        }
+        VideoFormat::I420 => {
+            let [y, u, v] = frame
+                .plane_offsets
+                .unwrap_or([0, y_bytes, y_bytes + c_bytes]);
+            (
+                vec![y, u, v],
+                vec![
+                    frame.stride as i32,
+                    frame.uv_stride as i32,
+                    frame.uv_stride as i32,
+                ],
+            )
+        }
+    };
+    let mut buffer = match frame.data {
+        FrameData::Owned(data) => gst::Buffer::from_mut_slice(data),
+        FrameData::Planes(planes) => {
+            let mut data = Vec::new();
+            for (offset, plane) in offsets.iter_mut().zip(&planes) {
+                *offset = data.len();
+                data.extend_from_slice(plane);
+            }
+            gst::Buffer::from_mut_slice(data)
+        }
+        // Shares the memory but drops the capture-side metas.
+        FrameData::Gst(buffer) => buffer.copy_region(gst::BufferCopyFlags::MEMORY, ..)?,
+    };
+    let buffer_ref = buffer.make_mut();
+    buffer_ref.set_pts(gst::ClockTime::from_nseconds(pts.as_nanos() as u64));
+    VideoMeta::add_full(
+        buffer_ref,
+        VideoFrameFlags::empty(),
+        gst_video_format(frame.format),
+        frame.width,
+        frame.height,
+        &offsets,
+        &strides,
+    )?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chroma_stride_from_sizeimage() {
+        let luma = 1920 * 1080;
+        // Both planes at the luma stride.
+        assert_eq!(chroma_stride(1920, 1080, luma * 3 / 2, 1920), 1920);
+        // Padded luma rows with the chroma rows packed.
+        assert_eq!(
+            chroma_stride(2048, 1080, 2048 * 1080 + 1920 * 540, 1920),
+            1920
+        );
+        // Rounded up to a page, which doesn't split into rows.
+        let paged = (luma * 3 / 2).next_multiple_of(4096);
+        assert_eq!(chroma_stride(1920, 1080, paged, 1920), 1920);
+        // Nothing or too little after the luma plane.
+        assert_eq!(chroma_stride(1920, 1080, 0, 1920), 1920);
+        assert_eq!(chroma_stride(1920, 1080, luma + 540, 1920), 1920);
+        // Odd heights round the chroma rows up.
+        assert_eq!(
+            chroma_stride(2048, 1081, 2048 * 1081 + 1920 * 541, 1920),
+            1920
+        );
     }
 }
@@ -1,42 +1,64 @@
+use std::path::Path;
+use std::sync::atomic::AtomicU64;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
     Arc,
+    atomic::{AtomicBool, Ordering},
 };
-use std::sync::atomic::AtomicU64;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
-use crossbeam_channel::{bounded, Receiver};
+use anyhow::{Result, anyhow};
+use crossbeam_channel::{Receiver, Sender, bounded};
 
-use crate::types::{DeviceInfo, VideoFrame};
+#[cfg(target_os = "linux")]
+use crate::types::FrameData;
+use crate::types::{Deinterlace, DeviceInfo, FramePolicy, VideoFrame};
 
 #[cfg(target_os = "linux")]
 mod linux;
 #[cfg(target_os = "windows")]
 mod windows;
 
-#[cfg(target_os = "linux")]
-pub use linux::KeepAwake;
-#[cfg(target_os = "windows")]
-pub use windows::KeepAwake;
-#[cfg(not(any(target_os = "linux", target_os = "windows")))]
-pub struct KeepAwake;
-#[cfg(not(any(target_os = "linux", target_os = "windows")))]
-impl KeepAwake {
-    pub fn new() -> Option<Self> {
-        None
-    }
+pub const NETWORK_SOURCES_SUPPORTED: bool = cfg!(target_os = "linux");
+
+pub fn is_network_source(id: &str) -> bool {
+    id.contains("://")
 }
 
+pub type FrameCallback = Arc<dyn Fn(&VideoFrame) + Send + Sync>;
+
+// Called once a frame is ready on the receiver, to wake whoever draws them.
+pub type FrameWaker = Arc<dyn Fn() + Send + Sync>;
+
 pub struct VideoCapture {
     pub rx: Receiver<VideoFrame>,
     pub info: VideoInfo,
     pub stats: Arc<CaptureStats>,
     stop: Arc<AtomicBool>,
+    lost: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    smooth: Arc<AtomicBool>,
     thread: Option<JoinHandle<()>>,
 }
 
 impl VideoCapture {
+    // Captures start out low latency.
+    pub fn set_frame_policy(&self, policy: FramePolicy) {
+        self.smooth
+            .store(policy == FramePolicy::Smooth, Ordering::Relaxed);
+    }
+
+    // Stops the stream without closing the device, so resuming is quick.
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    // Set by the capture thread when the device stopped delivering (e.g. it
+    // was unplugged); the thread has exited and the capture must be reopened.
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+
     pub fn stop(&mut self) {
         if let Some(handle) = self.thread.take() {
             self.stop.store(true, Ordering::Relaxed);
@@ -51,12 +73,26 @@ impl Drop for VideoCapture {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct VideoInfo {
     pub width: u32,
     pub height: u32,
     pub format: String,
     pub fps: Option<u32>,
+    // Frames arrive interlaced and still need deinterlacing by the renderer.
+    pub deinterlace: bool,
+}
+
+// A hardware picture adjustment (brightness, contrast, ...) on the device.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoControl {
+    pub id: u32,
+    pub name: String,
+    pub min: i64,
+    pub max: i64,
+    pub step: i64,
+    pub default: i64,
+    pub value: i64,
 }
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -66,21 +102,19 @@ pub struct StatsSnapshot {
     pub decode_us: u64,
 }
 
+#[derive(Default)]
 pub struct CaptureStats {
     enabled: AtomicBool,
     frames: AtomicU64,
     drops: AtomicU64,
     decode_us: AtomicU64,
+    // Every frame handed over, counted whether or not stats are enabled.
+    delivered: AtomicU64,
 }
 
 impl CaptureStats {
     pub fn new() -> Self {
-        Self {
-            enabled: AtomicBool::new(false),
-            frames: AtomicU64::new(0),
-            drops: AtomicU64::new(0),
-            decode_us: AtomicU64::new(0),
-        }
+        Self::default()
     }
 
     pub fn set_enabled(&self, enabled: bool) {
@@ -106,6 +140,10 @@ impl CaptureStats {
         self.drops.fetch_add(1, Ordering::Relaxed);
     }
 
+    pub fn delivered(&self) -> u64 {
+        self.delivered.load(Ordering::Relaxed)
+    }
+
     pub fn snapshot(&self) -> StatsSnapshot {
         StatsSnapshot {
             frames: self.frames.load(Ordering::Relaxed),
@@ -115,6 +153,121 @@ impl CaptureStats {
     }
 }
 
+// Shared delivery end of a capture thread: the render channel plus the
+// optional embedder callback, invoked on the capture thread.
+#[derive(Clone)]
+struct FrameSink {
+    tx: Sender<VideoFrame>,
+    drop_rx: Receiver<VideoFrame>,
+    stats: Arc<CaptureStats>,
+    on_frame: Option<FrameCallback>,
+    waker: Option<FrameWaker>,
+    lost: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    smooth: Arc<AtomicBool>,
+}
+
+impl FrameSink {
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    // Parks the capture thread until it's resumed or stopped.
+    fn wait_while_paused(&self, stop: &AtomicBool) {
+        while self.is_paused() && !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    fn mark_lost(&self) {
+        self.lost.store(true, Ordering::Relaxed);
+    }
+
+    fn is_smooth(&self) -> bool {
+        self.smooth.load(Ordering::Relaxed)
+    }
+
+    // Whether the next frame would only be dropped, so it needn't be
+    // decoded. Low latency never drops the incoming frame.
+    fn is_backed_up(&self) -> bool {
+        self.is_smooth() && self.tx.is_full()
+    }
+
+    fn deliver(&self, frame: VideoFrame, stats_on: bool) {
+        self.stats.delivered.fetch_add(1, Ordering::Relaxed);
+        if let Some(cb) = self.on_frame.as_ref() {
+            cb(&frame);
+        }
+        // Queued pool buffers would leave the pipeline's pool dry, so smooth
+        // queues copies of them instead. A frame that won't fit is dropped
+        // without being copied.
+        let frame = if self.is_smooth() && !self.tx.is_full() {
+            copy_pool_buffer(frame)
+        } else {
+            frame
+        };
+        // Low latency keeps only the newest frame, so anything still queued
+        // is replaced.
+        if !self.is_smooth() {
+            while self.drop_rx.try_recv().is_ok() {
+                if stats_on {
+                    self.stats.on_drop_enabled();
+                }
+            }
+        }
+        if self.tx.try_send(frame).is_err() && stats_on {
+            self.stats.on_drop_enabled();
+        }
+        if let Some(wake) = self.waker.as_ref() {
+            wake();
+        }
+    }
+}
+
+// Frames the smooth policy lets queue up.
+const SMOOTH_QUEUE: usize = 3;
+
+// Copies a frame out of its GStreamer buffer so holding it doesn't keep a
+// pool buffer from the pipeline. Other frames, and buffers that can't be
+// mapped, are passed on as they are.
+pub fn copy_pool_buffer(frame: VideoFrame) -> VideoFrame {
+    #[cfg(target_os = "linux")]
+    if let FrameData::Gst(buffer) = &frame.data
+        && let Ok(map) = buffer.map_readable()
+    {
+        let data = FrameData::Owned(map.as_slice().to_vec());
+        drop(map);
+        return VideoFrame { data, ..frame };
+    }
+    frame
+}
+
+const LOST_AFTER: Duration = Duration::from_secs(1);
+
+// Consecutive read failures on a capture thread. Reads that keep failing
+// for longer than LOST_AFTER mean the device is gone.
+struct ReadFailures {
+    since: Option<Instant>,
+}
+
+impl ReadFailures {
+    fn new() -> Self {
+        Self { since: None }
+    }
+
+    fn ok(&mut self) {
+        self.since = None;
+    }
+
+    // Records a failure and backs off briefly; true once the device should
+    // be considered lost.
+    fn failed(&mut self) -> bool {
+        let since = *self.since.get_or_insert_with(Instant::now);
+        std::thread::sleep(Duration::from_millis(10));
+        since.elapsed() > LOST_AFTER
+    }
+}
+
 pub fn list_video_devices() -> Result<Vec<DeviceInfo>> {
     #[cfg(target_os = "linux")]
     {
@@ -128,32 +281,177 @@ pub fn list_video_devices() -> Result<Vec<DeviceInfo>> {
     Ok(Vec::new())
 }
 
-pub fn start_video_capture(id: &str, max_size: Option<(u32, u32)>) -> Result<VideoCapture> {
-    let (tx, rx) = bounded(1);
-    let drop_rx = rx.clone();
+// Fires when capture devices come or go, so the lists can be refreshed right
+// away. None where the platform has no notifications or they failed to set
+// up; polling still covers those.
+pub fn watch_video_devices() -> Option<Receiver<()>> {
+    // One pending signal is enough; a burst of events needs a single rescan.
+    let (tx, rx) = bounded::<()>(1);
+    #[cfg(target_os = "linux")]
+    {
+        return linux::watch_video_devices(tx).ok().map(|()| rx);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        return windows::watch_video_devices(tx).ok().map(|()| rx);
+    }
+    #[allow(unreachable_code)]
+    {
+        drop((tx, rx));
+        None
+    }
+}
+
+// Modes the device offers natively, largest first. Network sources have none.
+pub fn list_video_modes(id: &str) -> Result<Vec<VideoInfo>> {
+    if is_network_source(id) {
+        return Ok(Vec::new());
+    }
+    #[cfg(target_os = "linux")]
+    {
+        return linux::list_video_modes(id);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        return windows::list_video_modes(id);
+    }
+    #[allow(unreachable_code)]
+    Ok(Vec::new())
+}
+
+// Frame rates offered for `mode`, or for any mode when None, fastest first.
+pub fn list_frame_rates(id: &str, mode: Option<&VideoInfo>) -> Result<Vec<u32>> {
+    if is_network_source(id) {
+        return Ok(Vec::new());
+    }
+    #[cfg(target_os = "linux")]
+    {
+        return linux::list_frame_rates(id, mode);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        return windows::list_frame_rates(id, mode);
+    }
+    #[allow(unreachable_code)]
+    Ok(Vec::new())
+}
+
+// Encodes frames to an H.264 MP4 at `path` until `rx` disconnects. Each
+// frame carries the instant it was captured, which sets its timestamp.
+pub fn record_video(
+    path: &Path,
+    fps: Option<u32>,
+    rx: Receiver<(Instant, VideoFrame)>,
+) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        return linux::record_video(path, fps, rx);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        return windows::record_video(path, fps, rx);
+    }
+    #[allow(unreachable_code)]
+    Err(anyhow!("Recording is not supported on this platform"))
+}
+
+// Standard picture controls the device supports, plus camera controls such
+// as exposure and focus on Windows; empty where the platform or source has
+// none. Controls go through their own handle, so they work while a capture
+// is running.
+pub fn list_video_controls(id: &str) -> Result<Vec<VideoControl>> {
+    if is_network_source(id) {
+        return Ok(Vec::new());
+    }
+    #[cfg(target_os = "linux")]
+    {
+        return linux::list_video_controls(id);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        return windows::list_video_controls(id);
+    }
+    #[allow(unreachable_code)]
+    Ok(Vec::new())
+}
+
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "windows")),
+    allow(unused_variables)
+)]
+pub fn set_video_control(id: &str, control: u32, value: i64) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        return linux::set_video_control(id, control, value);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        return windows::set_video_control(id, control, value);
+    }
+    #[allow(unreachable_code)]
+    Err(anyhow!("Video controls are not supported on this platform"))
+}
+
+// `mode` pins one of the entries from list_video_modes; otherwise the
+// platform picks the best mode within `max_size`. `fps` requests a frame
+// rate on top of either.
+pub fn start_video_capture(
+    id: &str,
+    max_size: Option<(u32, u32)>,
+    mode: Option<&VideoInfo>,
+    fps: Option<u32>,
+    deinterlace: Deinterlace,
+    on_frame: Option<FrameCallback>,
+    waker: Option<FrameWaker>,
+) -> Result<VideoCapture> {
+    if is_network_source(id) && !NETWORK_SOURCES_SUPPORTED {
+        return Err(anyhow!(
+            "Network sources need GStreamer and are only supported on Linux"
+        ));
+    }
+    let (tx, rx) = bounded(SMOOTH_QUEUE);
     let stop = Arc::new(AtomicBool::new(false));
     let stats = Arc::new(CaptureStats::new());
+    let lost = Arc::new(AtomicBool::new(false));
+    let paused = Arc::new(AtomicBool::new(false));
+    let smooth = Arc::new(AtomicBool::new(false));
+    let sink = FrameSink {
+        tx,
+        drop_rx: rx.clone(),
+        stats: stats.clone(),
+        on_frame,
+        waker,
+        lost: lost.clone(),
+        paused: paused.clone(),
+        smooth: smooth.clone(),
+    };
     #[cfg(target_os = "linux")]
     {
         let (thread, info) =
-            linux::spawn_capture(id, max_size, tx, drop_rx, stop.clone(), stats.clone())?;
+            linux::spawn_capture(id, max_size, mode, fps, deinterlace, sink, stop.clone())?;
         return Ok(VideoCapture {
             rx,
             info,
             stats,
             stop,
+            lost,
+            paused,
+            smooth,
             thread: Some(thread),
         });
     }
     #[cfg(target_os = "windows")]
     {
         let (thread, info) =
-            windows::spawn_capture(id, max_size, tx, drop_rx, stop.clone(), stats.clone())?;
+            windows::spawn_capture(id, max_size, mode, fps, deinterlace, sink, stop.clone())?;
         return Ok(VideoCapture {
             rx,
             info,
             stats,
             stop,
+            lost,
+            paused,
+            smooth,
             thread: Some(thread),
         });
     }
@@ -165,9 +463,76 @@ pub fn start_video_capture(id: &str, max_size: Option<(u32, u32)>) -> Result<Vid
             height: 0,
             format: "Unknown".to_string(),
             fps: None,
+            deinterlace: false,
         },
         stats,
         stop,
+        lost,
+        paused,
+        smooth,
         thread: None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ColorInfo, FrameData, VideoFormat};
+
+    fn sink(smooth: bool) -> (FrameSink, Receiver<VideoFrame>) {
+        let (tx, rx) = bounded(SMOOTH_QUEUE);
+        let sink = FrameSink {
+            tx,
+            drop_rx: rx.clone(),
+            stats: Arc::new(CaptureStats::new()),
+            on_frame: None,
+            waker: None,
+            lost: Arc::default(),
+            paused: Arc::default(),
+            smooth: Arc::new(AtomicBool::new(smooth)),
+        };
+        (sink, rx)
+    }
+
+    // Told apart by width.
+    fn frame(width: u32) -> VideoFrame {
+        VideoFrame {
+            width,
+            height: 1,
+            format: VideoFormat::Rgba,
+            stride: width as usize * 4,
+            uv_stride: 0,
+            plane_offsets: None,
+            color: ColorInfo::default(),
+            data: FrameData::Owned(vec![0; width as usize * 4]),
+            captured_at: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn low_latency_keeps_the_newest_frame() {
+        let (sink, rx) = sink(false);
+        for width in 1..=5 {
+            sink.deliver(frame(width), true);
+        }
+        assert!(!sink.is_backed_up());
+        assert_eq!(rx.try_iter().map(|f| f.width).collect::<Vec<_>>(), [5]);
+        assert_eq!(sink.stats.snapshot().drops, 4);
+        assert_eq!(sink.stats.delivered(), 5);
+    }
+
+    #[test]
+    fn smooth_keeps_the_oldest_frames_until_full() {
+        let (sink, rx) = sink(true);
+        for width in 1..=5 {
+            sink.deliver(frame(width), true);
+        }
+        assert!(sink.is_backed_up());
+        assert_eq!(
+            rx.try_iter().map(|f| f.width).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert_eq!(sink.stats.snapshot().drops, 2);
+        assert_eq!(sink.stats.delivered(), 5);
+    }
+}
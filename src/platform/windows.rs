@@ -1,23 +1,44 @@
+use std::cell::Cell;
+use std::path::Path;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
     Arc,
+    atomic::{AtomicBool, Ordering},
 };
 use std::thread::JoinHandle;
+use std::time::Instant;
 
-use anyhow::{anyhow, Result};
-use crossbeam_channel::{Receiver, Sender};
-use windows::core::{GUID, PWSTR};
+use anyhow::{Result, anyhow};
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+use windows::Win32::Foundation::{ERROR_SHARING_VIOLATION, HANDLE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::Media::DirectShow::{
+    CameraControl_Exposure, CameraControl_Flags_Manual, CameraControl_Focus, CameraControl_Iris,
+    CameraControl_Pan, CameraControl_Roll, CameraControl_Tilt, CameraControl_Zoom,
+    CameraControlProperty, IAMCameraControl, IAMVideoProcAmp, VideoProcAmp_BacklightCompensation,
+    VideoProcAmp_Brightness, VideoProcAmp_ColorEnable, VideoProcAmp_Contrast,
+    VideoProcAmp_Flags_Manual, VideoProcAmp_Gain, VideoProcAmp_Gamma, VideoProcAmp_Hue,
+    VideoProcAmp_Saturation, VideoProcAmp_Sharpness, VideoProcAmp_WhiteBalance,
+    VideoProcAmpProperty,
+};
 use windows::Win32::Media::MediaFoundation::*;
 use windows::Win32::System::Com::{
-    CoInitializeEx, CoTaskMemFree, CoUninitialize, COINIT_MULTITHREADED,
+    COINIT_MULTITHREADED, CoInitializeEx, CoTaskMemFree, CoUninitialize,
 };
-use windows::Win32::System::Power::{
-    SetThreadExecutionState, ES_CONTINUOUS, ES_DISPLAY_REQUIRED, ES_SYSTEM_REQUIRED,
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::core::{GUID, HRESULT, HSTRING, Interface, PWSTR, w};
+
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DBT_DEVICEARRIVAL, DBT_DEVICEREMOVECOMPLETE, DBT_DEVTYP_DEVICEINTERFACE,
+    DEV_BROADCAST_DEVICEINTERFACE_W, DEVICE_NOTIFY_WINDOW_HANDLE, DefWindowProcW, DestroyWindow,
+    DispatchMessageW, GetMessageW, HDEVNOTIFY, HWND_MESSAGE, MSG, RegisterClassW,
+    RegisterDeviceNotificationW, UnregisterDeviceNotification, WINDOW_EX_STYLE, WINDOW_STYLE,
+    WM_DEVICECHANGE, WNDCLASSW,
 };
 
+use super::{FrameSink, ReadFailures, VideoControl, VideoInfo};
 use crate::pixel;
-use crate::types::{ColorInfo, DeviceInfo, FrameData, VideoFormat, VideoFrame};
-use super::{CaptureStats, VideoInfo};
+use crate::types::{
+    ColorInfo, ColorTransfer, Deinterlace, DeviceInfo, FrameData, VideoFormat, VideoFrame,
+};
 
 pub fn list_video_devices() -> Result<Vec<DeviceInfo>> {
     let _com = ComInit::new()?;
@@ -31,7 +52,10 @@ pub fn list_video_devices() -> Result<Vec<DeviceInfo>> {
         let mut out = Vec::new();
         for act in slice.iter().flatten() {
             let name = get_string(act, &MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME)?;
-            let id = get_string(act, &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK)?;
+            let id = get_string(
+                act,
+                &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK,
+            )?;
             out.push(DeviceInfo { id, name });
         }
         CoTaskMemFree(Some(activates as _));
@@ -39,16 +63,416 @@ pub fn list_video_devices() -> Result<Vec<DeviceInfo>> {
     }
 }
 
+// KSCATEGORY_CAPTURE: capture card and webcam interfaces, plus their audio
+// capture endpoints.
+const CAPTURE_INTERFACE_CLASS: GUID = GUID::from_u128(0x65e8773d_8f56_11d0_a3b9_00a0c9223196);
+
+thread_local! {
+    // Set by the notification window's procedure, which runs on the watcher
+    // thread while it dispatches messages.
+    static DEVICES_CHANGED: Cell<bool> = const { Cell::new(false) };
+}
+
+// Signals on `tx` whenever a capture device interface arrives or goes away.
+// The thread ends once the receiver is gone.
+pub fn watch_video_devices(tx: Sender<()>) -> Result<()> {
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    std::thread::Builder::new()
+        .name("device-notify".to_string())
+        .spawn(move || {
+            // The window and its notification belong to this thread, which
+            // pumps their messages.
+            let (window, notify) = match notify_window() {
+                Ok(registered) => registered,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            let _ = ready_tx.send(Ok(()));
+            let mut msg = MSG::default();
+            while unsafe { GetMessageW(&mut msg, None, 0, 0) }.as_bool() {
+                unsafe { DispatchMessageW(&msg) };
+                if DEVICES_CHANGED.replace(false)
+                    && matches!(tx.try_send(()), Err(TrySendError::Disconnected(_)))
+                {
+                    break;
+                }
+            }
+            unsafe {
+                let _ = UnregisterDeviceNotification(notify);
+                let _ = DestroyWindow(window);
+            }
+        })?;
+    ready_rx
+        .recv()
+        .map_err(|_| anyhow!("Device notification thread failed"))?
+}
+
+// Message-only window registered for capture interface notifications.
+fn notify_window() -> Result<(HWND, HDEVNOTIFY)> {
+    unsafe {
+        let instance = GetModuleHandleW(None)?.into();
+        let class_name = w!("CaptureCardGamingDeviceNotify");
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(notify_proc),
+            hInstance: instance,
+            lpszClassName: class_name,
+            ..Default::default()
+        };
+        if RegisterClassW(&class) == 0 {
+            return Err(anyhow!("Failed to register the device notification window"));
+        }
+        let window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            class_name,
+            None,
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            Some(HWND_MESSAGE),
+            None,
+            Some(instance),
+            None,
+        )?;
+        let filter = DEV_BROADCAST_DEVICEINTERFACE_W {
+            dbcc_size: std::mem::size_of::<DEV_BROADCAST_DEVICEINTERFACE_W>() as u32,
+            dbcc_devicetype: DBT_DEVTYP_DEVICEINTERFACE.0,
+            dbcc_classguid: CAPTURE_INTERFACE_CLASS,
+            ..Default::default()
+        };
+        match RegisterDeviceNotificationW(
+            HANDLE(window.0),
+            std::ptr::from_ref(&filter).cast(),
+            DEVICE_NOTIFY_WINDOW_HANDLE,
+        ) {
+            Ok(notify) => Ok((window, notify)),
+            Err(e) => {
+                let _ = DestroyWindow(window);
+                Err(e.into())
+            }
+        }
+    }
+}
+
+unsafe extern "system" fn notify_proc(
+    window: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_DEVICECHANGE
+        && matches!(
+            wparam.0 as u32,
+            DBT_DEVICEARRIVAL | DBT_DEVICEREMOVECOMPLETE
+        )
+    {
+        DEVICES_CHANGED.set(true);
+    }
+    unsafe { DefWindowProcW(window, msg, wparam, lparam) }
+}
+
+pub fn list_video_modes(id: &str) -> Result<Vec<VideoInfo>> {
+    let _com = ComInit::new()?;
+    mf_startup()?;
+    let reader = create_source_reader(id, false)?;
+    let stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+    let mut out: Vec<VideoInfo> = Vec::new();
+    for index in 0.. {
+        let Ok(mt) = (unsafe { reader.GetNativeMediaType(stream, index) }) else {
+            break;
+        };
+        let Ok(subtype) = (unsafe { mt.GetGUID(&MF_MT_SUBTYPE) }) else {
+            continue;
+        };
+        let Some(format) = subtype_name(subtype) else {
+            continue;
+        };
+        let Ok(size) = (unsafe { mt.GetUINT64(&MF_MT_FRAME_SIZE) }) else {
+            continue;
+        };
+        let (width, height) = ((size >> 32) as u32, size as u32);
+        let fps = unsafe { mt.GetUINT64(&MF_MT_FRAME_RATE) }
+            .ok()
+            .and_then(rate_to_fps);
+        // Native types repeat per frame rate; keep the fastest of each.
+        match out
+            .iter_mut()
+            .find(|m| m.width == width && m.height == height && m.format == format)
+        {
+            Some(existing) => existing.fps = existing.fps.max(fps),
+            None => out.push(VideoInfo {
+                width,
+                height,
+                format: format.to_string(),
+                fps,
+                deinterlace: false,
+            }),
+        }
+    }
+    out.sort_by(|a, b| (b.width * b.height, b.fps).cmp(&(a.width * a.height, a.fps)));
+    Ok(out)
+}
+
+pub fn list_frame_rates(id: &str, mode: Option<&VideoInfo>) -> Result<Vec<u32>> {
+    let _com = ComInit::new()?;
+    mf_startup()?;
+    let reader = create_source_reader(id, false)?;
+    let stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+    let mut out = Vec::new();
+    for index in 0.. {
+        let Ok(mt) = (unsafe { reader.GetNativeMediaType(stream, index) }) else {
+            break;
+        };
+        if let Some(mode) = mode {
+            let Ok(subtype) = (unsafe { mt.GetGUID(&MF_MT_SUBTYPE) }) else {
+                continue;
+            };
+            let Ok(size) = (unsafe { mt.GetUINT64(&MF_MT_FRAME_SIZE) }) else {
+                continue;
+            };
+            if subtype_name(subtype) != Some(mode.format.as_str())
+                || size != ((mode.width as u64) << 32) | mode.height as u64
+            {
+                continue;
+            }
+        }
+        let Ok(rate) = (unsafe { mt.GetUINT64(&MF_MT_FRAME_RATE) }) else {
+            continue;
+        };
+        if let Some(fps) = rate_to_fps(rate) {
+            out.push(fps);
+        }
+    }
+    out.sort_unstable_by(|a, b| b.cmp(a));
+    out.dedup();
+    Ok(out)
+}
+
+const PROC_AMP_CONTROLS: [(VideoProcAmpProperty, &str); 10] = [
+    (VideoProcAmp_Brightness, "Brightness"),
+    (VideoProcAmp_Contrast, "Contrast"),
+    (VideoProcAmp_Hue, "Hue"),
+    (VideoProcAmp_Saturation, "Saturation"),
+    (VideoProcAmp_Sharpness, "Sharpness"),
+    (VideoProcAmp_Gamma, "Gamma"),
+    (VideoProcAmp_ColorEnable, "Color Enable"),
+    (VideoProcAmp_WhiteBalance, "White Balance"),
+    (VideoProcAmp_BacklightCompensation, "Backlight Compensation"),
+    (VideoProcAmp_Gain, "Gain"),
+];
+
+const CAMERA_CONTROLS: [(CameraControlProperty, &str); 7] = [
+    (CameraControl_Pan, "Pan"),
+    (CameraControl_Tilt, "Tilt"),
+    (CameraControl_Roll, "Roll"),
+    (CameraControl_Zoom, "Zoom"),
+    (CameraControl_Exposure, "Exposure"),
+    (CameraControl_Iris, "Iris"),
+    (CameraControl_Focus, "Focus"),
+];
+
+// Camera control ids sit above the proc amp ones so both share one id space.
+const CAMERA_CONTROL_BASE: u32 = 0x100;
+
+// IAMVideoProcAmp and IAMCameraControl have the same shape; only their
+// property and flag types differ.
+trait DeviceControls {
+    // (min, max, step, default); None for controls that can't be set by hand.
+    fn range(&self, property: i32) -> Option<(i32, i32, i32, i32)>;
+    fn value(&self, property: i32) -> Option<i32>;
+    fn set(&self, property: i32, value: i32) -> Result<()>;
+}
+
+impl DeviceControls for IAMVideoProcAmp {
+    fn range(&self, property: i32) -> Option<(i32, i32, i32, i32)> {
+        let (mut min, mut max, mut step, mut default, mut caps) = (0, 0, 0, 0, 0);
+        unsafe {
+            self.GetRange(
+                property,
+                &mut min,
+                &mut max,
+                &mut step,
+                &mut default,
+                &mut caps,
+            )
+        }
+        .ok()?;
+        (caps & VideoProcAmp_Flags_Manual.0 != 0).then_some((min, max, step, default))
+    }
+
+    fn value(&self, property: i32) -> Option<i32> {
+        let (mut value, mut flags) = (0, 0);
+        unsafe { self.Get(property, &mut value, &mut flags) }.ok()?;
+        Some(value)
+    }
+
+    fn set(&self, property: i32, value: i32) -> Result<()> {
+        unsafe { self.Set(property, value, VideoProcAmp_Flags_Manual.0) }?;
+        Ok(())
+    }
+}
+
+impl DeviceControls for IAMCameraControl {
+    fn range(&self, property: i32) -> Option<(i32, i32, i32, i32)> {
+        let (mut min, mut max, mut step, mut default, mut caps) = (0, 0, 0, 0, 0);
+        unsafe {
+            self.GetRange(
+                property,
+                &mut min,
+                &mut max,
+                &mut step,
+                &mut default,
+                &mut caps,
+            )
+        }
+        .ok()?;
+        (caps & CameraControl_Flags_Manual.0 != 0).then_some((min, max, step, default))
+    }
+
+    fn value(&self, property: i32) -> Option<i32> {
+        let (mut value, mut flags) = (0, 0);
+        unsafe { self.Get(property, &mut value, &mut flags) }.ok()?;
+        Some(value)
+    }
+
+    fn set(&self, property: i32, value: i32) -> Result<()> {
+        unsafe { self.Set(property, value, CameraControl_Flags_Manual.0) }?;
+        Ok(())
+    }
+}
+
+fn read_controls(
+    controls: &impl DeviceControls,
+    properties: impl IntoIterator<Item = (i32, &'static str)>,
+    base: u32,
+    out: &mut Vec<VideoControl>,
+) {
+    for (property, name) in properties {
+        let Some((min, max, step, default)) = controls.range(property) else {
+            continue;
+        };
+        if min >= max {
+            continue;
+        }
+        let Some(value) = controls.value(property) else {
+            continue;
+        };
+        out.push(VideoControl {
+            id: base + property as u32,
+            name: name.to_string(),
+            min: min.into(),
+            max: max.into(),
+            step: i64::from(step).max(1),
+            default: default.into(),
+            value: value.into(),
+        });
+    }
+}
+
+// Controls go through their own activation of the device, which works next
+// to a running capture. Unsupported ones are left out.
+pub fn list_video_controls(id: &str) -> Result<Vec<VideoControl>> {
+    let _com = ComInit::new()?;
+    mf_startup()?;
+    let source = activate_source(id)?;
+    let mut out = Vec::new();
+    if let Ok(proc_amp) = source.cast::<IAMVideoProcAmp>() {
+        let properties = PROC_AMP_CONTROLS.map(|(p, name)| (p.0, name));
+        read_controls(&proc_amp, properties, 0, &mut out);
+    }
+    if let Ok(camera) = source.cast::<IAMCameraControl>() {
+        let properties = CAMERA_CONTROLS.map(|(p, name)| (p.0, name));
+        read_controls(&camera, properties, CAMERA_CONTROL_BASE, &mut out);
+    }
+    let _ = unsafe { source.Shutdown() };
+    Ok(out)
+}
+
+pub fn set_video_control(id: &str, control: u32, value: i64) -> Result<()> {
+    let _com = ComInit::new()?;
+    mf_startup()?;
+    let source = activate_source(id)?;
+    let value = i32::try_from(value)?;
+    let result = match control.checked_sub(CAMERA_CONTROL_BASE) {
+        Some(property) => source
+            .cast::<IAMCameraControl>()
+            .map_err(anyhow::Error::from)
+            .and_then(|camera| camera.set(property as i32, value)),
+        None => source
+            .cast::<IAMVideoProcAmp>()
+            .map_err(anyhow::Error::from)
+            .and_then(|proc_amp| proc_amp.set(control as i32, value)),
+    };
+    let _ = unsafe { source.Shutdown() };
+    result
+}
+
+// MF_MT_FRAME_RATE packs numerator and denominator into one UINT64.
+fn rate_to_fps(rate: u64) -> Option<u32> {
+    let (num, den) = ((rate >> 32) as u32, rate as u32);
+    (den != 0).then(|| (num as f64 / den as f64).round() as u32)
+}
+
+// Exact native rate behind a rounded fps (e.g. 60000/1001 for 60), so the
+// reader doesn't reject the type over a near miss.
+fn native_frame_rate(reader: &IMFSourceReader, fps: u32, frame_size: Option<u64>) -> u64 {
+    let stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+    for index in 0.. {
+        let Ok(mt) = (unsafe { reader.GetNativeMediaType(stream, index) }) else {
+            break;
+        };
+        if let Some(size) = frame_size
+            && unsafe { mt.GetUINT64(&MF_MT_FRAME_SIZE) }.ok() != Some(size)
+        {
+            continue;
+        }
+        if let Ok(rate) = unsafe { mt.GetUINT64(&MF_MT_FRAME_RATE) }
+            && rate_to_fps(rate) == Some(fps)
+        {
+            return rate;
+        }
+    }
+    ((fps as u64) << 32) | 1
+}
+
+fn subtype_name(subtype: GUID) -> Option<&'static str> {
+    if subtype == MFVideoFormat_NV12 {
+        Some("NV12")
+    } else if subtype == MFVideoFormat_P010 {
+        Some("P010")
+    } else if subtype == MFVideoFormat_YUY2 {
+        Some("YUY2")
+    } else if subtype == MFVideoFormat_UYVY {
+        Some("UYVY")
+    } else if subtype == MFVideoFormat_RGB32 {
+        Some("RGB32")
+    } else if subtype == MFVideoFormat_RGB24 {
+        Some("RGB24")
+    } else if subtype == MFVideoFormat_RGB565 {
+        Some("RGB565")
+    } else if subtype == MFVideoFormat_L8 {
+        Some("L8")
+    } else if subtype == MFVideoFormat_MJPG {
+        Some("MJPG")
+    } else {
+        None
+    }
+}
+
 pub fn spawn_capture(
     id: &str,
-    _max_size: Option<(u32, u32)>,
-    tx: Sender<VideoFrame>,
-    drop_rx: Receiver<VideoFrame>,
+    max_size: Option<(u32, u32)>,
+    mode: Option<&VideoInfo>,
+    fps: Option<u32>,
+    deinterlace: Deinterlace,
+    sink: FrameSink,
     stop: Arc<AtomicBool>,
-    stats: Arc<CaptureStats>,
 ) -> Result<(JoinHandle<()>, VideoInfo)> {
     let id = id.to_string();
-    let (reader, width, height, subtype, stride) = {
+    let (reader, cfg) = {
         if ComInit::new().is_err() {
             return Err(anyhow!("COM init failed"));
         }
@@ -57,7 +481,7 @@ pub fn spawn_capture(
         let mut out = None;
         for enable_processing in [false, true] {
             match create_source_reader(&id, enable_processing) {
-                Ok(reader) => match configure_reader(&reader) {
+                Ok(reader) => match configure_reader(&reader, max_size, mode, fps) {
                     Ok(cfg) => {
                         out = Some((reader, cfg));
                         break;
@@ -67,24 +491,35 @@ pub fn spawn_capture(
                 Err(e) => last_err = Some(e),
             }
         }
-        let (reader, (width, height, subtype, stride)) = out
-            .ok_or_else(|| last_err.unwrap_or_else(|| anyhow!("No supported media type")))?;
-        (reader, width, height, subtype, stride)
-    };
-    let format = if subtype == MFVideoFormat_NV12 {
-        "NV12"
-    } else if subtype == MFVideoFormat_YUY2 {
-        "YUY2"
-    } else if subtype == MFVideoFormat_RGB32 {
-        "RGB32"
-    } else {
-        "Unknown"
+        match out {
+            Some(out) => out,
+            None => {
+                let err = last_err.unwrap_or_else(|| anyhow!("No supported media type"));
+                return Err(match err.downcast_ref::<windows::core::Error>() {
+                    Some(e) if is_device_busy(e.code()) => {
+                        anyhow!("Device is busy (in use by another application or instance)")
+                    }
+                    _ => err,
+                });
+            }
+        }
     };
+    let ReaderConfig {
+        width,
+        height,
+        subtype,
+        stride,
+        bottom_up,
+        fps,
+        color,
+        interlaced,
+    } = cfg;
     let info = VideoInfo {
         width,
         height,
-        format: format.to_string(),
-        fps: None,
+        format: subtype_name(subtype).unwrap_or("Unknown").to_string(),
+        fps,
+        deinterlace: deinterlace.applies(interlaced),
     };
     let handle = std::thread::Builder::new()
         .name("mf-capture".to_string())
@@ -95,99 +530,429 @@ pub fn spawn_capture(
             if mf_startup().is_err() {
                 return;
             }
+            let mut failures = ReadFailures::new();
             loop {
                 if stop.load(Ordering::Relaxed) {
                     break;
                 }
-                let stats_on = stats.enabled();
+                // The reader only pulls from the device on ReadSample; drop
+                // what it had buffered so resuming doesn't show stale frames.
+                if sink.is_paused() {
+                    sink.wait_while_paused(&stop);
+                    let _ = reader.Flush(MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32);
+                    continue;
+                }
+                let stats_on = sink.stats.enabled();
                 let mut flags = 0u32;
                 let mut sample = None;
-                if reader
-                    .ReadSample(
-                        MF_SOURCE_READER_FIRST_VIDEO_STREAM,
-                        0,
-                        None,
-                        Some(&mut flags),
-                        None,
-                        Some(&mut sample),
-                    )
-                    .is_err()
-                {
+                if let Err(e) = reader.ReadSample(
+                    MF_SOURCE_READER_FIRST_VIDEO_STREAM,
+                    0,
+                    None,
+                    Some(&mut flags),
+                    None,
+                    Some(&mut sample),
+                ) {
+                    if is_device_busy(e.code()) {
+                        break;
+                    }
+                    if failures.failed() {
+                        sink.mark_lost();
+                        break;
+                    }
                     continue;
                 }
-                if flags & MF_SOURCE_READERF_ENDOFSTREAM.0 as u32 != 0 {
+                failures.ok();
+                let ended = MF_SOURCE_READERF_ENDOFSTREAM.0 | MF_SOURCE_READERF_ERROR.0;
+                if flags & ended as u32 != 0 {
+                    sink.mark_lost();
                     break;
                 }
                 let Some(sample) = sample else { continue };
-                if !drop_rx.is_empty() {
+                let captured_at = Instant::now();
+                if sink.is_backed_up() {
                     if stats_on {
-                        stats.on_drop_enabled();
+                        sink.stats.on_drop_enabled();
                     }
                     continue;
                 }
-                let buffer = match sample.ConvertToContiguousBuffer() {
-                    Ok(b) => b,
-                    Err(_) => continue,
+                let t0 = if stats_on {
+                    Some(std::time::Instant::now())
+                } else {
+                    None
                 };
-                let mut data_ptr = std::ptr::null_mut();
-                let mut len = 0u32;
-                if buffer.Lock(&mut data_ptr, None, Some(&mut len)).is_err() {
-                    continue;
-                }
-                let data = std::slice::from_raw_parts(data_ptr, len as usize);
-                let t0 = if stats_on { Some(std::time::Instant::now()) } else { None };
-                let frame = if subtype == MFVideoFormat_NV12 {
-                    VideoFrame {
-                        width,
-                        height,
-                        format: VideoFormat::Nv12,
-                        stride: stride as usize,
-                        uv_stride: stride as usize,
-                        color: ColorInfo::default_for_size(width),
-                        data: FrameData::Owned(data.to_vec()),
-                    }
-                } else if subtype == MFVideoFormat_YUY2 {
-                    VideoFrame {
-                        width,
-                        height,
-                        format: VideoFormat::Yuyv,
-                        stride: stride as usize,
-                        uv_stride: 0,
-                        color: ColorInfo::default_for_size(width),
-                        data: FrameData::Owned(data.to_vec()),
-                    }
-                } else if subtype == MFVideoFormat_RGB32 {
-                    let rgba = pixel::bgra_to_rgba(width, height, stride as usize, data);
-                    VideoFrame {
-                        width,
-                        height,
-                        format: VideoFormat::Rgba,
-                        stride: (width * 4) as usize,
-                        uv_stride: 0,
-                        color: ColorInfo::default_for_size(width),
-                        data: FrameData::Owned(rgba),
+                let frame = match read_2d_frame(&sample, width, height, subtype, color, captured_at)
+                {
+                    Some(frame) => frame,
+                    None => {
+                        let buffer = match sample.ConvertToContiguousBuffer() {
+                            Ok(b) => b,
+                            Err(_) => continue,
+                        };
+                        let mut data_ptr = std::ptr::null_mut();
+                        let mut len = 0u32;
+                        if buffer.Lock(&mut data_ptr, None, Some(&mut len)).is_err() {
+                            continue;
+                        }
+                        let data = std::slice::from_raw_parts(data_ptr, len as usize);
+                        let frame = if subtype == MFVideoFormat_NV12
+                            || subtype == MFVideoFormat_P010
+                        {
+                            VideoFrame {
+                                width,
+                                height,
+                                format: if subtype == MFVideoFormat_P010 {
+                                    VideoFormat::P010
+                                } else {
+                                    VideoFormat::Nv12
+                                },
+                                stride: stride as usize,
+                                uv_stride: stride as usize,
+                                plane_offsets: None,
+                                color,
+                                data: FrameData::Owned(data.to_vec()),
+                                captured_at,
+                            }
+                        } else if subtype == MFVideoFormat_YUY2 || subtype == MFVideoFormat_UYVY {
+                            VideoFrame {
+                                width,
+                                height,
+                                format: if subtype == MFVideoFormat_UYVY {
+                                    VideoFormat::Uyvy
+                                } else {
+                                    VideoFormat::Yuyv
+                                },
+                                stride: stride as usize,
+                                uv_stride: 0,
+                                plane_offsets: None,
+                                color,
+                                data: FrameData::Owned(data.to_vec()),
+                                captured_at,
+                            }
+                        } else if subtype == MFVideoFormat_RGB32 {
+                            let rgba = pixel::bgra_to_rgba(
+                                width,
+                                height,
+                                stride as usize,
+                                bottom_up,
+                                data,
+                            );
+                            VideoFrame {
+                                width,
+                                height,
+                                format: VideoFormat::Rgba,
+                                stride: (width * 4) as usize,
+                                uv_stride: 0,
+                                plane_offsets: None,
+                                color,
+                                data: FrameData::Owned(rgba),
+                                captured_at,
+                            }
+                        } else if subtype == MFVideoFormat_RGB24 {
+                            // Stored B, G, R like RGB32.
+                            let rgba = pixel::bgr24_to_rgba(
+                                width,
+                                height,
+                                stride as usize,
+                                bottom_up,
+                                data,
+                            );
+                            VideoFrame {
+                                width,
+                                height,
+                                format: VideoFormat::Rgba,
+                                stride: (width * 4) as usize,
+                                uv_stride: 0,
+                                plane_offsets: None,
+                                color,
+                                data: FrameData::Owned(rgba),
+                                captured_at,
+                            }
+                        } else if subtype == MFVideoFormat_RGB565 {
+                            let row_bytes = stride as usize;
+                            let mut rows: Vec<&[u8]> =
+                                data.chunks(row_bytes).take(height as usize).collect();
+                            if bottom_up {
+                                rows.reverse();
+                            }
+                            VideoFrame {
+                                width,
+                                height,
+                                format: VideoFormat::Rgb565,
+                                stride: row_bytes,
+                                uv_stride: 0,
+                                plane_offsets: None,
+                                color,
+                                data: FrameData::Owned(rows.concat()),
+                                captured_at,
+                            }
+                        } else if subtype == MFVideoFormat_L8 {
+                            VideoFrame {
+                                width,
+                                height,
+                                format: VideoFormat::Grey,
+                                stride: stride as usize,
+                                uv_stride: 0,
+                                plane_offsets: None,
+                                color,
+                                data: FrameData::Owned(data.to_vec()),
+                                captured_at,
+                            }
+                        } else if subtype == MFVideoFormat_MJPG {
+                            match pixel::decode_mjpeg(data) {
+                                Ok((w, h, rgba)) => VideoFrame {
+                                    width: w,
+                                    height: h,
+                                    format: VideoFormat::Rgba,
+                                    stride: (w * 4) as usize,
+                                    uv_stride: 0,
+                                    plane_offsets: None,
+                                    color,
+                                    data: FrameData::Owned(rgba),
+                                    captured_at,
+                                },
+                                Err(_) => {
+                                    let _ = buffer.Unlock();
+                                    continue;
+                                }
+                            }
+                        } else {
+                            let _ = buffer.Unlock();
+                            continue;
+                        };
+                        let _ = buffer.Unlock();
+                        frame
                     }
-                } else {
-                    let _ = buffer.Unlock();
-                    continue;
                 };
-                let _ = buffer.Unlock();
                 if let Some(t0) = t0 {
-                    stats.on_frame_enabled(t0.elapsed().as_micros() as u64);
-                }
-                if let Err(err) = tx.try_send(frame) {
-                    let frame = err.into_inner();
-                    let _ = drop_rx.try_recv();
-                    if stats_on {
-                        stats.on_drop_enabled();
-                    }
-                    let _ = tx.try_send(frame);
+                    sink.stats.on_frame_enabled(t0.elapsed().as_micros() as u64);
                 }
+                sink.deliver(frame, stats_on);
             }
         })?;
     Ok((handle, info))
 }
 
+// Reads NV12/P010/YUY2/UYVY/L8 straight from a 2D buffer using its real pitch, which
+// avoids the repack ConvertToContiguousBuffer does for padded surfaces.
+fn read_2d_frame(
+    sample: &IMFSample,
+    width: u32,
+    height: u32,
+    subtype: GUID,
+    color: ColorInfo,
+    captured_at: Instant,
+) -> Option<VideoFrame> {
+    let (format, rows) = if subtype == MFVideoFormat_NV12 {
+        (VideoFormat::Nv12, height as usize * 3 / 2)
+    } else if subtype == MFVideoFormat_P010 {
+        (VideoFormat::P010, height as usize * 3 / 2)
+    } else if subtype == MFVideoFormat_YUY2 {
+        (VideoFormat::Yuyv, height as usize)
+    } else if subtype == MFVideoFormat_UYVY {
+        (VideoFormat::Uyvy, height as usize)
+    } else if subtype == MFVideoFormat_L8 {
+        (VideoFormat::Grey, height as usize)
+    } else {
+        return None;
+    };
+    unsafe {
+        if sample.GetBufferCount().ok()? != 1 {
+            return None;
+        }
+        let buffer = sample
+            .GetBufferByIndex(0)
+            .ok()?
+            .cast::<IMF2DBuffer2>()
+            .ok()?;
+        let mut scanline0 = std::ptr::null_mut();
+        let mut pitch = 0i32;
+        let mut start = std::ptr::null_mut();
+        let mut len = 0u32;
+        buffer
+            .Lock2DSize(
+                MF2DBuffer_LockFlags_Read,
+                &mut scanline0,
+                &mut pitch,
+                &mut start,
+                &mut len,
+            )
+            .ok()?;
+        let offset = scanline0.offset_from(start) as usize;
+        let needed = pitch.max(0) as usize * rows;
+        let data = if pitch > 0 && offset + needed <= len as usize {
+            let bytes = std::slice::from_raw_parts(scanline0, needed);
+            if !matches!(
+                format,
+                VideoFormat::Yuyv | VideoFormat::Uyvy | VideoFormat::Grey
+            ) {
+                let (y, uv) = bytes.split_at(pitch as usize * height as usize);
+                Some(FrameData::Planes(vec![y.to_vec(), uv.to_vec()]))
+            } else {
+                Some(FrameData::Owned(bytes.to_vec()))
+            }
+        } else {
+            None
+        };
+        let _ = buffer.Unlock2D();
+        Some(VideoFrame {
+            width,
+            height,
+            format,
+            stride: pitch as usize,
+            uv_stride: if matches!(
+                format,
+                VideoFormat::Yuyv | VideoFormat::Uyvy | VideoFormat::Grey
+            ) {
+                0
+            } else {
+                pitch as usize
+            },
+            plane_offsets: None,
+            color,
+            data: data?,
+            captured_at,
+        })
+    }
+}
+
+pub fn record_video(
+    path: &Path,
+    fps: Option<u32>,
+    rx: Receiver<(Instant, VideoFrame)>,
+) -> Result<()> {
+    let _com = ComInit::new()?;
+    mf_startup()?;
+    let (start, first) = rx.recv().map_err(|_| anyhow!("No frames were recorded"))?;
+    let (format, width, height) = (first.format, first.width, first.height);
+    let fps = fps.unwrap_or(60).max(1);
+    let frame_size = ((width as u64) << 32) | height as u64;
+    let frame_rate = ((fps as u64) << 32) | 1;
+    let (subtype, stride, _) = recording_bytes(first.clone());
+    unsafe {
+        let mut attrs = None;
+        MFCreateAttributes(&mut attrs, 1)?;
+        let attrs = attrs.ok_or_else(|| anyhow!("No attributes"))?;
+        attrs.SetUINT32(&MF_READWRITE_ENABLE_HARDWARE_TRANSFORMS, 1)?;
+        let writer =
+            MFCreateSinkWriterFromURL(&HSTRING::from(path), None::<&IMFByteStream>, &attrs)?;
+
+        let output = MFCreateMediaType()?;
+        output.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)?;
+        output.SetGUID(&MF_MT_SUBTYPE, &MFVideoFormat_H264)?;
+        // Roughly 16 Mbit/s at 1080p60, scaled with pixel rate.
+        let bitrate = (width as u64 * height as u64 * fps as u64 / 8).min(u32::MAX as u64) as u32;
+        output.SetUINT32(&MF_MT_AVG_BITRATE, bitrate)?;
+        output.SetUINT32(&MF_MT_INTERLACE_MODE, MFVideoInterlace_Progressive.0 as u32)?;
+        output.SetUINT64(&MF_MT_FRAME_SIZE, frame_size)?;
+        output.SetUINT64(&MF_MT_FRAME_RATE, frame_rate)?;
+        output.SetUINT64(&MF_MT_PIXEL_ASPECT_RATIO, (1 << 32) | 1)?;
+        let stream = writer.AddStream(&output)?;
+
+        let input = MFCreateMediaType()?;
+        input.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)?;
+        input.SetGUID(&MF_MT_SUBTYPE, &subtype)?;
+        input.SetUINT32(&MF_MT_INTERLACE_MODE, MFVideoInterlace_Progressive.0 as u32)?;
+        input.SetUINT32(&MF_MT_DEFAULT_STRIDE, stride)?;
+        input.SetUINT64(&MF_MT_FRAME_SIZE, frame_size)?;
+        input.SetUINT64(&MF_MT_FRAME_RATE, frame_rate)?;
+        input.SetUINT64(&MF_MT_PIXEL_ASPECT_RATIO, (1 << 32) | 1)?;
+        writer.SetInputMediaType(stream, &input, None)?;
+        writer.BeginWriting()?;
+
+        let duration = 10_000_000 / fps as i64;
+        let frames = std::iter::once((start, first)).chain(rx);
+        for (at, frame) in frames {
+            if frame.format != format || frame.width != width || frame.height != height {
+                continue;
+            }
+            let (_, _, data) = recording_bytes(frame);
+            let time = (at.duration_since(start).as_nanos() / 100) as i64;
+            write_sample(&writer, stream, &data, time, duration)?;
+        }
+        // Writes the MP4 index; without it the file is unplayable.
+        writer.Finalize()?;
+    }
+    Ok(())
+}
+
+// Flattens a frame into one top-down buffer in a subtype the H.264 encoder
+// (or the sink writer's converter) accepts, with its stride.
+fn recording_bytes(frame: VideoFrame) -> (GUID, u32, Vec<u8>) {
+    let data = match frame.data {
+        FrameData::Owned(data) => data,
+        FrameData::Planes(planes) => planes.concat(),
+    };
+    match frame.format {
+        VideoFormat::Nv12 => (MFVideoFormat_NV12, frame.stride as u32, data),
+        VideoFormat::I420 => (MFVideoFormat_I420, frame.stride as u32, data),
+        VideoFormat::Yuyv => (MFVideoFormat_YUY2, frame.stride as u32, data),
+        VideoFormat::Uyvy => (MFVideoFormat_UYVY, frame.stride as u32, data),
+        VideoFormat::Rgba => {
+            let mut data = data;
+            for px in data.chunks_exact_mut(4) {
+                px.swap(0, 2);
+            }
+            (MFVideoFormat_RGB32, frame.stride as u32, data)
+        }
+        // The encoders only take 8-bit input.
+        VideoFormat::P010 => {
+            let width = frame.width as usize;
+            let y_bytes = (frame.stride * frame.height as usize).min(data.len());
+            let (y, uv) = data.split_at(y_bytes);
+            let mut out = pixel::p010_to_8bit(width, frame.height as usize, frame.stride, y);
+            out.extend(pixel::p010_to_8bit(
+                width,
+                frame.height.div_ceil(2) as usize,
+                frame.uv_stride,
+                uv,
+            ));
+            (MFVideoFormat_NV12, width as u32, out)
+        }
+        VideoFormat::Rgb565 => (MFVideoFormat_RGB565, frame.stride as u32, data),
+        // Sent as NV12 with neutral chroma.
+        VideoFormat::Grey => {
+            let y_bytes = frame.stride * frame.height as usize;
+            let mut out = data;
+            out.resize(y_bytes, 0);
+            out.resize(
+                y_bytes + frame.stride * frame.height.div_ceil(2) as usize,
+                128,
+            );
+            (MFVideoFormat_NV12, frame.stride as u32, out)
+        }
+    }
+}
+
+fn write_sample(
+    writer: &IMFSinkWriter,
+    stream: u32,
+    data: &[u8],
+    time: i64,
+    duration: i64,
+) -> Result<()> {
+    unsafe {
+        let buffer = MFCreateMemoryBuffer(data.len() as u32)?;
+        let mut ptr = std::ptr::null_mut();
+        buffer.Lock(&mut ptr, None, None)?;
+        std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+        buffer.Unlock()?;
+        buffer.SetCurrentLength(data.len() as u32)?;
+        let sample = MFCreateSample()?;
+        sample.AddBuffer(&buffer)?;
+        sample.SetSampleTime(time)?;
+        sample.SetSampleDuration(duration)?;
+        writer.WriteSample(stream, &sample)?;
+    }
+    Ok(())
+}
+
+fn is_device_busy(code: HRESULT) -> bool {
+    code == MF_E_VIDEO_RECORDING_DEVICE_PREEMPTED
+        || code == MF_E_HW_MFT_FAILED_START_STREAMING
+        || code == HRESULT::from_win32(ERROR_SHARING_VIOLATION.0)
+}
+
 fn mf_startup() -> Result<()> {
     unsafe {
         MFStartup(MF_VERSION, MFSTARTUP_LITE).or_else(|e| {
@@ -225,7 +990,7 @@ fn get_string(attrs: &IMFAttributes, key: &GUID) -> Result<String> {
     }
 }
 
-fn create_source_reader(id: &str, enable_processing: bool) -> Result<IMFSourceReader> {
+fn activate_source(id: &str) -> Result<IMFMediaSource> {
     unsafe {
         let attrs = create_attrs()?;
         let mut activates = std::ptr::null_mut();
@@ -241,7 +1006,13 @@ fn create_source_reader(id: &str, enable_processing: bool) -> Result<IMFSourceRe
             }
         }
         CoTaskMemFree(Some(activates as _));
-        let source = source.ok_or_else(|| anyhow!("Device not found"))?;
+        source.ok_or_else(|| anyhow!("Device not found"))
+    }
+}
+
+fn create_source_reader(id: &str, enable_processing: bool) -> Result<IMFSourceReader> {
+    let source = activate_source(id)?;
+    unsafe {
         let mut reader_attrs = None;
         MFCreateAttributes(&mut reader_attrs, 3)?;
         let reader_attrs = reader_attrs.ok_or_else(|| anyhow!("No reader attrs"))?;
@@ -255,15 +1026,87 @@ fn create_source_reader(id: &str, enable_processing: bool) -> Result<IMFSourceRe
     }
 }
 
-fn configure_reader(reader: &IMFSourceReader) -> Result<(u32, u32, GUID, u32)> {
+struct ReaderConfig {
+    width: u32,
+    height: u32,
+    subtype: GUID,
+    stride: u32,
+    bottom_up: bool,
+    fps: Option<u32>,
+    color: ColorInfo,
+    interlaced: bool,
+}
+
+// Largest native frame size that fits within max_size, if the device lists any.
+fn preferred_frame_size(reader: &IMFSourceReader, max_size: (u32, u32)) -> Option<u64> {
+    let stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+    let mut best: Option<(u32, u32)> = None;
+    for index in 0.. {
+        let Ok(mt) = (unsafe { reader.GetNativeMediaType(stream, index) }) else {
+            break;
+        };
+        let Ok(size) = (unsafe { mt.GetUINT64(&MF_MT_FRAME_SIZE) }) else {
+            continue;
+        };
+        let (w, h) = ((size >> 32) as u32, size as u32);
+        if w > max_size.0 || h > max_size.1 {
+            continue;
+        }
+        if best.is_none_or(|(bw, bh)| w * h > bw * bh) {
+            best = Some((w, h));
+        }
+    }
+    best.map(|(w, h)| ((w as u64) << 32) | h as u64)
+}
+
+fn configure_reader(
+    reader: &IMFSourceReader,
+    max_size: Option<(u32, u32)>,
+    mode: Option<&VideoInfo>,
+    fps: Option<u32>,
+) -> Result<ReaderConfig> {
+    let frame_size = match mode {
+        Some(mode) => Some(((mode.width as u64) << 32) | mode.height as u64),
+        None => max_size.and_then(|max| preferred_frame_size(reader, max)),
+    };
+    let frame_rate = fps.map(|fps| native_frame_rate(reader, fps, frame_size));
+    let mut subtypes = vec![
+        MFVideoFormat_NV12,
+        MFVideoFormat_YUY2,
+        MFVideoFormat_UYVY,
+        MFVideoFormat_P010,
+        MFVideoFormat_RGB32,
+        MFVideoFormat_RGB24,
+        MFVideoFormat_RGB565,
+        MFVideoFormat_L8,
+        // Last resort for MJPEG-only cards without a decoder MF can insert;
+        // decoded on the CPU.
+        MFVideoFormat_MJPG,
+    ];
+    // A pinned mode in an uncompressed format goes first; a pinned MJPG mode
+    // only pins the size so the reader's own decoder is still preferred.
+    if let Some(pos) = mode.and_then(|mode| {
+        subtypes
+            .iter()
+            .position(|&s| s != MFVideoFormat_MJPG && subtype_name(s) == Some(mode.format.as_str()))
+    }) {
+        let preferred = subtypes.remove(pos);
+        subtypes.insert(0, preferred);
+    }
     unsafe {
         let mut chosen = None;
-        for subtype in [MFVideoFormat_NV12, MFVideoFormat_YUY2, MFVideoFormat_RGB32] {
+        for subtype in subtypes {
             let mut mt = None;
             MFCreateMediaType(&mut mt)?;
             let mt = mt.ok_or_else(|| anyhow!("No media type"))?;
             mt.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)?;
             mt.SetGUID(&MF_MT_SUBTYPE, &subtype)?;
+            if let Some(size) = frame_size {
+                mt.SetUINT64(&MF_MT_FRAME_SIZE, size)?;
+            }
+            if let Some(rate) = frame_rate {
+                mt.SetUINT64(&MF_MT_FRAME_RATE, rate)?;
+            }
             if reader
                 .SetCurrentMediaType(MF_SOURCE_READER_FIRST_VIDEO_STREAM, None, &mt)
                 .is_ok()
@@ -280,18 +1123,62 @@ fn configure_reader(reader: &IMFSourceReader) -> Result<(u32, u32, GUID, u32)> {
         let height = size as u32;
         let mut subtype = GUID::default();
         mt.GetGUID(&MF_MT_SUBTYPE, &mut subtype)?;
-        let mut stride = 0u32;
-        if mt.GetUINT32(&MF_MT_DEFAULT_STRIDE, &mut stride).is_err() {
-            stride = if subtype == MFVideoFormat_NV12 {
-                width
-            } else if subtype == MFVideoFormat_YUY2 {
-                width * 2
-            } else {
-                width * 4
-            };
-        }
-        Ok((width, height, subtype, stride))
+        // The stride attribute is a signed value stored as UINT32; a negative
+        // stride marks a bottom-up image (first row in memory is the bottom).
+        let signed_stride = match mt.GetUINT32(&MF_MT_DEFAULT_STRIDE) {
+            Ok(v) => v as i32,
+            Err(_) => match MFGetStrideForBitmapInfoHeader(subtype.data1, width) {
+                Ok(v) => v,
+                Err(_) => {
+                    if subtype == MFVideoFormat_NV12 || subtype == MFVideoFormat_L8 {
+                        width as i32
+                    } else if subtype == MFVideoFormat_YUY2
+                        || subtype == MFVideoFormat_UYVY
+                        || subtype == MFVideoFormat_P010
+                        || subtype == MFVideoFormat_RGB565
+                    {
+                        (width * 2) as i32
+                    } else if subtype == MFVideoFormat_RGB24 {
+                        (width * 3) as i32
+                    } else {
+                        (width * 4) as i32
+                    }
+                }
+            },
+        };
+        Ok(ReaderConfig {
+            width,
+            height,
+            subtype,
+            stride: signed_stride.unsigned_abs(),
+            bottom_up: signed_stride < 0,
+            fps: mt.GetUINT64(&MF_MT_FRAME_RATE).ok().and_then(rate_to_fps),
+            color: color_info_from_media_type(&mt, width),
+            interlaced: mt.GetUINT32(&MF_MT_INTERLACE_MODE).is_ok_and(|mode| {
+                let mode = MFVideoInterlaceMode(mode as i32);
+                mode != MFVideoInterlace_Progressive && mode != MFVideoInterlace_Unknown
+            }),
+        })
+    }
+}
+
+fn color_info_from_media_type(mt: &IMFMediaType, width: u32) -> ColorInfo {
+    let mut out = ColorInfo::default_for_size(width);
+    if let Ok(transfer) = unsafe { mt.GetUINT32(&MF_MT_TRANSFER_FUNCTION) } {
+        let transfer = MFVideoTransferFunction(transfer as i32);
+        out.transfer = if transfer == MFVideoTransFunc_2084 {
+            ColorTransfer::Pq
+        } else if transfer == MFVideoTransFunc_HLG {
+            ColorTransfer::Hlg
+        } else if transfer == MFVideoTransFunc_sRGB {
+            ColorTransfer::Srgb
+        } else if transfer == MFVideoTransFunc_10 {
+            ColorTransfer::Linear
+        } else {
+            ColorTransfer::Bt709
+        };
     }
+    out
 }
 
 struct ComInit;
@@ -311,25 +1198,3 @@ impl Drop for ComInit {
         unsafe { CoUninitialize() }
     }
 }
-
-pub struct KeepAwake;
-
-impl KeepAwake {
-    pub fn new() -> Option<Self> {
-        let flags = ES_CONTINUOUS | ES_SYSTEM_REQUIRED | ES_DISPLAY_REQUIRED;
-        let ok = unsafe { SetThreadExecutionState(flags) };
-        if ok.0 == 0 {
-            None
-        } else {
-            Some(Self)
-        }
-    }
-}
-
-impl Drop for KeepAwake {
-    fn drop(&mut self) {
-        unsafe {
-            SetThreadExecutionState(ES_CONTINUOUS);
-        }
-    }
-}
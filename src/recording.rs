@@ -0,0 +1,115 @@
+use std::path::PathBuf;
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, Ordering},
+};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use anyhow::Result;
+use crossbeam_channel::{Receiver, Sender, bounded, unbounded};
+
+use crate::platform::{self, FrameCallback};
+use crate::screenshot;
+use crate::types::VideoFrame;
+
+// Frames waiting for the encoder before new ones are dropped, so a slow
+// encoder never stalls the capture thread.
+const QUEUE_LEN: usize = 8;
+
+type FrameQueue = Arc<Mutex<Option<Sender<(Instant, VideoFrame)>>>>;
+
+// Encodes captured frames to an MP4 on a separate thread. Frames are copied
+// off the capture thread only while a recording runs; otherwise the callback
+// is a single atomic load, leaving the preview path untouched.
+pub struct Recorder {
+    active: Arc<AtomicBool>,
+    queue: FrameQueue,
+    thread: Option<JoinHandle<()>>,
+    result_tx: Sender<Result<PathBuf>>,
+    result_rx: Receiver<Result<PathBuf>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        let (result_tx, result_rx) = unbounded();
+        Self {
+            active: Arc::new(AtomicBool::new(false)),
+            queue: Arc::new(Mutex::new(None)),
+            thread: None,
+            result_tx,
+            result_rx,
+        }
+    }
+
+    pub fn callback(&self) -> FrameCallback {
+        let active = self.active.clone();
+        let queue = self.queue.clone();
+        Arc::new(move |frame: &VideoFrame| {
+            if !active.load(Ordering::Relaxed) {
+                return;
+            }
+            let Ok(queue) = queue.lock() else { return };
+            if let Some(tx) = queue.as_ref()
+                && !tx.is_full()
+            {
+                // The encoder can fall behind, so pool buffers are copied
+                // rather than held in the queue.
+                let frame = platform::copy_pool_buffer(frame.clone());
+                let _ = tx.try_send((Instant::now(), frame));
+            }
+        })
+    }
+
+    pub fn start(&mut self, fps: Option<u32>) -> Result<PathBuf> {
+        self.stop();
+        let dir = screenshot::user_dir("XDG_VIDEOS_DIR", "Videos");
+        std::fs::create_dir_all(&dir)?;
+        let path = screenshot::unique_path(&dir, "mp4");
+        let (tx, rx) = bounded(QUEUE_LEN);
+        let active = self.active.clone();
+        let result_tx = self.result_tx.clone();
+        let out = path.clone();
+        let thread = std::thread::Builder::new()
+            .name("recorder".to_string())
+            .spawn(move || {
+                let result = platform::record_video(&out, fps, rx).map(|_| out);
+                // An encoder that gave up shouldn't keep being fed copies.
+                active.store(false, Ordering::Relaxed);
+                let _ = result_tx.send(result);
+            })?;
+        if let Ok(mut queue) = self.queue.lock() {
+            *queue = Some(tx);
+        }
+        self.active.store(true, Ordering::Relaxed);
+        self.thread = Some(thread);
+        Ok(path)
+    }
+
+    // Closing the queue lets the encoder drain what's left and finalize the
+    // file; waits for that so the MP4 is complete once this returns.
+    pub fn stop(&mut self) {
+        self.active.store(false, Ordering::Relaxed);
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.take();
+        }
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    // Outcome of a finished recording: the file written, or why it failed.
+    pub fn take_result(&self) -> Option<Result<PathBuf>> {
+        self.result_rx.try_recv().ok()
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -1,12 +1,19 @@
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-use anyhow::{anyhow, Result};
+use anyhow::{Result, anyhow};
 use bytemuck::{Pod, Zeroable};
 use wgpu::util::DeviceExt;
 use winit::dpi::PhysicalSize;
 use winit::window::Window;
 
-use crate::types::{ColorInfo, FrameData, VideoFormat, VideoFrame};
+use crate::clock::Corner;
+use crate::pixel;
+use crate::types::{ColorInfo, ColorMatrix, FrameData, VideoFormat, VideoFrame};
+use crate::view::{
+    AspectMode, CropRect, CrtMask, CrtSettings, ImageAdjust, Rotation, ScaleFilter, ScaleMode,
+};
 
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
@@ -25,10 +32,82 @@ struct ColorParams {
     m_gv: f32,
     m_bu: f32,
     srgb_output: f32,
+    clamp_output: f32,
+    gamma: f32,
+    brightness: f32,
+    contrast: f32,
+    saturation: f32,
+    is_hdr: f32,
+    transfer: f32,
+    // Luma at or above which zebra stripes are drawn; 0 turns them off.
+    zebra: f32,
+    // Stripe offset in periods, advanced each frame to animate them.
+    zebra_phase: f32,
+    // Output quantization step spread by ordered dithering; 0 turns it off.
+    dither: f32,
+    // Source primaries to map into Display P3; see GAMUT_* in the shaders.
+    gamut: f32,
+    // Frame width in pixels. Packed 4:2:2 textures hold pixel pairs, so for
+    // an odd width they're a pixel wider than the frame.
+    source_width: f32,
     _pad: f32,
 }
 
-fn color_params_from_info(color: ColorInfo, output_is_srgb: bool) -> ColorParams {
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct SharpenParams {
+    strength: f32,
+    _pad: [f32; 3],
+}
+
+// Window-sized target an earlier pass draws into, plus the bind group the
+// next pass reads it through.
+struct OffscreenTarget {
+    size: (u32, u32),
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+// Uniforms handed to a custom post-process shader; see POST_SHADER_PRELUDE.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct PostParams {
+    resolution: [f32; 2],
+    source_size: [f32; 2],
+    video_rect: [f32; 4],
+    time: f32,
+    _pad: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CrtParams {
+    scanlines: f32,
+    mask: f32,
+    mask_strength: f32,
+    bloom: f32,
+    curvature: f32,
+    _pad: [f32; 3],
+}
+
+// A user-supplied WGSL fragment shader, recompiled when its file changes.
+struct PostShader {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    checked_at: Instant,
+    // Last version that compiled; a broken edit leaves it running.
+    pipeline: Option<wgpu::RenderPipeline>,
+}
+
+const POST_SHADER_POLL: Duration = Duration::from_millis(250);
+
+fn color_params_from_info(
+    color: ColorInfo,
+    output_is_srgb: bool,
+    clamp_limited: bool,
+    gamma: f32,
+    adjust: ImageAdjust,
+) -> ColorParams {
     let (y_offset, y_scale) = match color.range {
         crate::types::ColorRange::Limited => (-16.0 / 255.0, 1.164_383_6),
         crate::types::ColorRange::Full => (0.0, 1.0),
@@ -53,6 +132,13 @@ fn color_params_from_info(color: ColorInfo, output_is_srgb: bool) -> ColorParams
             (1.402, 0.344, 0.714, 1.772)
         }
     };
+    // The BT.2020 rows above only give the right RGB for SDR-graded content;
+    // PQ/HLG still needs decoding and tone mapping in the shader.
+    let is_hdr = color.matrix == crate::types::ColorMatrix::Bt2020
+        && matches!(
+            color.transfer,
+            crate::types::ColorTransfer::Pq | crate::types::ColorTransfer::Hlg
+        );
     ColorParams {
         y_offset,
         y_scale,
@@ -61,10 +147,139 @@ fn color_params_from_info(color: ColorInfo, output_is_srgb: bool) -> ColorParams
         m_gv,
         m_bu,
         srgb_output: if output_is_srgb { 1.0 } else { 0.0 },
+        clamp_output: if clamp_limited || color.range == crate::types::ColorRange::Full {
+            1.0
+        } else {
+            0.0
+        },
+        gamma,
+        brightness: adjust.brightness,
+        contrast: adjust.contrast,
+        saturation: adjust.saturation,
+        is_hdr: if is_hdr { 1.0 } else { 0.0 },
+        transfer: match color.transfer {
+            crate::types::ColorTransfer::Bt709 => 0.0,
+            crate::types::ColorTransfer::Pq => 1.0,
+            crate::types::ColorTransfer::Hlg => 2.0,
+            crate::types::ColorTransfer::Srgb => 3.0,
+            crate::types::ColorTransfer::Linear => 4.0,
+        },
+        zebra: 0.0,
+        zebra_phase: 0.0,
+        dither: 0.0,
+        gamut: 0.0,
+        source_width: 0.0,
         _pad: 0.0,
     }
 }
 
+// Wide gamut output maps SDR sources from their primaries (taken from the
+// matrix) into Display P3; HDR sources are always BT.2020.
+fn output_gamut(matrix: ColorMatrix, wide_gamut: bool) -> f32 {
+    match (wide_gamut, matrix) {
+        (false, _) => 0.0,
+        (true, ColorMatrix::Bt2020) => 2.0,
+        (true, _) => 1.0,
+    }
+}
+
+// Without vsync the lowest-latency mode the surface offers wins. Fifo is
+// the one mode every surface must support.
+fn pick_present_mode(available: &[wgpu::PresentMode], vsync: bool) -> wgpu::PresentMode {
+    if vsync {
+        return wgpu::PresentMode::Fifo;
+    }
+    [
+        wgpu::PresentMode::Immediate,
+        wgpu::PresentMode::Mailbox,
+        wgpu::PresentMode::FifoRelaxed,
+    ]
+    .into_iter()
+    .find(|mode| available.contains(mode))
+    .unwrap_or(wgpu::PresentMode::Fifo)
+}
+
+// Clear values are linear; an sRGB surface encodes them on write, so the
+// picked sRGB color has to be decoded first to come out unchanged.
+fn clear_color(rgb: [u8; 3], srgb_target: bool) -> wgpu::Color {
+    let channel = |c: u8| {
+        let c = c as f64 / 255.0;
+        if !srgb_target {
+            c
+        } else if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    wgpu::Color {
+        r: channel(rgb[0]),
+        g: channel(rgb[1]),
+        b: channel(rgb[2]),
+        a: 1.0,
+    }
+}
+
+fn nv12_planes<'a>(frame: &VideoFrame, planes: &[&'a [u8]]) -> (&'a [u8], &'a [u8]) {
+    let y_bytes = frame.stride * frame.height as usize;
+    let uv_bytes = frame.uv_stride * frame.height.div_ceil(2) as usize;
+    let slice = |data: &'a [u8], start: usize, len: usize| {
+        let start = start.min(data.len());
+        &data[start..start + len.min(data.len() - start)]
+    };
+    if let [y, uv, ..] = planes {
+        return (slice(y, 0, y_bytes), slice(uv, 0, uv_bytes));
+    }
+    let data = planes[0];
+    let [y_offset, uv_offset, _] = frame.plane_offsets.unwrap_or([0, y_bytes, 0]);
+    (
+        slice(data, y_offset, y_bytes),
+        slice(data, uv_offset, uv_bytes),
+    )
+}
+
+// Whether either plane ends before the picture does. Only the last row may
+// stop short of a full stride.
+fn nv12_truncated(frame: &VideoFrame, planes: &[&[u8]]) -> bool {
+    let sample = if frame.format == VideoFormat::P010 {
+        2
+    } else {
+        1
+    };
+    let needed =
+        |stride: usize, rows: u32, row: usize| stride * (rows as usize).saturating_sub(1) + row;
+    let uv_row = frame.width.div_ceil(2) as usize * 2 * sample;
+    let (y, uv) = nv12_planes(frame, planes);
+    y.len() < needed(frame.stride, frame.height, frame.width as usize * sample)
+        || uv.len() < needed(frame.uv_stride, frame.height.div_ceil(2), uv_row)
+}
+
+fn i420_planes<'a>(frame: &VideoFrame, planes: &[&'a [u8]]) -> (&'a [u8], &'a [u8], &'a [u8]) {
+    let y_bytes = frame.stride * frame.height as usize;
+    let c_bytes = frame.uv_stride * frame.height.div_ceil(2) as usize;
+    let slice = |data: &'a [u8], start: usize, len: usize| {
+        let start = start.min(data.len());
+        &data[start..start + len.min(data.len() - start)]
+    };
+    if let [y, u, v, ..] = planes {
+        return (
+            slice(y, 0, y_bytes),
+            slice(u, 0, c_bytes),
+            slice(v, 0, c_bytes),
+        );
+    }
+    let data = planes[0];
+    let [y_offset, u_offset, v_offset] =
+        frame
+            .plane_offsets
+            .unwrap_or([0, y_bytes, y_bytes + c_bytes]);
+    (
+        slice(data, y_offset, y_bytes),
+        slice(data, u_offset, c_bytes),
+        slice(data, v_offset, c_bytes),
+    )
+}
+
 const VERTICES: [Vertex; 4] = [
     Vertex {
         pos: [-1.0, -1.0],
@@ -86,21 +301,122 @@ const VERTICES: [Vertex; 4] = [
 
 const INDICES: [u16; 6] = [0, 1, 2, 2, 3, 0];
 
+// Longest side of the downscaled scope readback.
+const SCOPE_SIZE: u32 = 256;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SurfaceDiagnostics {
+    pub format: wgpu::TextureFormat,
+    pub present_mode: wgpu::PresentMode,
+    pub frame_latency: u32,
+    pub width: u32,
+    pub height: u32,
+    // Whether the shaders decode sRGB before writing, forced or not.
+    pub srgb_output: bool,
+}
+
+// Placement of the picture-in-picture preview; `size` is its width as a
+// fraction of the window's.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PipLayout {
+    pub corner: Corner,
+    pub size: f32,
+}
+
 pub struct RenderState {
     surface: wgpu::Surface<'static>,
     device: wgpu::Device,
     queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
+    present_modes: Vec<wgpu::PresentMode>,
     size: PhysicalSize<u32>,
     pipeline_rgba: wgpu::RenderPipeline,
+    pipeline_grey: wgpu::RenderPipeline,
+    pipeline_rgb565: wgpu::RenderPipeline,
     pipeline_yuyv: wgpu::RenderPipeline,
+    pipeline_uyvy: wgpu::RenderPipeline,
     pipeline_nv12: wgpu::RenderPipeline,
+    pipeline_i420: wgpu::RenderPipeline,
+    pipeline_p010: wgpu::RenderPipeline,
+    // Catmull-Rom variants; packed 4:2:2 has none and stays on the linear path.
+    pipeline_rgba_cubic: wgpu::RenderPipeline,
+    pipeline_grey_cubic: wgpu::RenderPipeline,
+    pipeline_nv12_cubic: wgpu::RenderPipeline,
+    pipeline_i420_cubic: wgpu::RenderPipeline,
+    pipeline_p010_cubic: wgpu::RenderPipeline,
+    // Line-blend deinterlacing variants for the YUV formats; RGBA only comes
+    // from decoded MJPEG and RGB32, which are progressive.
+    pipeline_yuyv_deinterlace: wgpu::RenderPipeline,
+    pipeline_uyvy_deinterlace: wgpu::RenderPipeline,
+    pipeline_nv12_deinterlace: wgpu::RenderPipeline,
+    pipeline_i420_deinterlace: wgpu::RenderPipeline,
+    pipeline_p010_deinterlace: wgpu::RenderPipeline,
+    deinterlace: bool,
+    sharpen_pipeline: wgpu::RenderPipeline,
+    sharpen_bind_group_layout: wgpu::BindGroupLayout,
+    sharpen_buffer: wgpu::Buffer,
+    sharpen_target: Option<OffscreenTarget>,
+    sharpen: f32,
+    post_bind_group_layout: wgpu::BindGroupLayout,
+    post_sampler: wgpu::Sampler,
+    post_buffer: wgpu::Buffer,
+    post_target: Option<OffscreenTarget>,
+    post_shader: Option<PostShader>,
+    post_epoch: Instant,
+    crt_pipeline: wgpu::RenderPipeline,
+    crt_buffer: wgpu::Buffer,
+    crt_bind_group: wgpu::BindGroup,
+    crt: Option<CrtSettings>,
     bind_group_layout: wgpu::BindGroupLayout,
     nv12_bind_group_layout: wgpu::BindGroupLayout,
+    i420_bind_group_layout: wgpu::BindGroupLayout,
     sampler: wgpu::Sampler,
-    vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     num_indices: u32,
+    video: VideoLayer,
+    // Second capture drawn over a corner of the window.
+    pip: Option<VideoLayer>,
+    pip_layout: Option<PipLayout>,
+    blank_video: bool,
+    // R16Unorm/Rg16Unorm need an optional feature; without it P010 is
+    // reduced to 8-bit NV12 on the CPU.
+    p010_native: bool,
+    // Vulkan can import dmabuf-backed frames; cleared after the first
+    // import that fails so the copy path isn't slowed by retries.
+    #[cfg(target_os = "linux")]
+    dmabuf_import: bool,
+    output_is_srgb: bool,
+    // Forces the shaders' sRGB decode on or off for sources whose RGB
+    // doesn't match what the surface format implies.
+    srgb_output_override: Option<bool>,
+    // Dithering only helps when the surface rounds to 8 bits per channel.
+    output_is_8bit: bool,
+    dither: bool,
+    wide_gamut: bool,
+    // Letterbox and clear color.
+    background: wgpu::Color,
+    clamp_limited: bool,
+    output_gamma: f32,
+    image_adjust: ImageAdjust,
+    zebra: Option<f32>,
+    zebra_epoch: Instant,
+    aspect_mode: AspectMode,
+    scale_mode: ScaleMode,
+    scale_filter: ScaleFilter,
+    crop: CropRect,
+    rotation: Rotation,
+    // Where the video quad lands on the surface, in pixels (left, top,
+    // right, bottom).
+    video_rect: [f32; 4],
+    // Cropped and rotated picture size in source pixels.
+    source_size: [f32; 2],
+    staging: Vec<u8>,
+}
+
+// Everything one video source needs on the GPU: its textures and bind
+// groups for each frame format, color uniforms and quad. The pipelines are
+// shared, so the main picture and the PiP preview each own one of these.
+struct VideoLayer {
     video_texture: wgpu::Texture,
     video_view: wgpu::TextureView,
     video_bind_group: wgpu::BindGroup,
@@ -109,365 +425,150 @@ pub struct RenderState {
     nv12_y_view: wgpu::TextureView,
     nv12_uv_view: wgpu::TextureView,
     nv12_bind_group: wgpu::BindGroup,
+    // I420 reuses the NV12 Y texture and the chroma texture (as R8, holding
+    // U); V gets its own.
+    i420_v_texture: wgpu::Texture,
+    i420_v_view: wgpu::TextureView,
+    i420_bind_group: wgpu::BindGroup,
     video_size: (u32, u32),
+    // The textures still hold the 1x1 placeholder until this is set.
+    has_frame: bool,
     video_format: VideoFormat,
-    output_is_srgb: bool,
+    // The NV12 textures alias an imported dmabuf and can't be written to.
+    nv12_imported: bool,
+    color_info: ColorInfo,
     color_params: ColorParams,
     color_buffer: wgpu::Buffer,
-    aspect_correct: bool,
-    staging: Vec<u8>,
+    vertex_buffer: wgpu::Buffer,
 }
 
-impl RenderState {
-    pub fn device(&self) -> &wgpu::Device {
-        &self.device
+// The renderer state a layer builds its bind groups from and uploads
+// through.
+struct LayerContext<'a> {
+    device: &'a wgpu::Device,
+    queue: &'a wgpu::Queue,
+    bind_group_layout: &'a wgpu::BindGroupLayout,
+    nv12_bind_group_layout: &'a wgpu::BindGroupLayout,
+    i420_bind_group_layout: &'a wgpu::BindGroupLayout,
+    sampler: &'a wgpu::Sampler,
+    // R16Unorm/Rg16Unorm need an optional feature; without it P010 is
+    // reduced to 8-bit NV12 on the CPU.
+    p010_native: bool,
+    #[cfg(target_os = "linux")]
+    dmabuf_import: &'a mut bool,
+    staging: &'a mut Vec<u8>,
+}
+
+impl LayerContext<'_> {
+    fn write_texture_padded(
+        &mut self,
+        texture: &wgpu::Texture,
+        width: u32,
+        height: u32,
+        bytes_per_row: u32,
+        data: &[u8],
+    ) {
+        let (data, padded_stride) = pack_rows(
+            data,
+            height,
+            bytes_per_row,
+            wgpu::COPY_BYTES_PER_ROW_ALIGNMENT,
+            self.staging,
+        );
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            data,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_stride),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
     }
+}
 
-    pub async fn new(window: Arc<Window>) -> Result<Self> {
-        let size = window.inner_size();
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
-        let surface = instance.create_surface(window)?;
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .map_err(|e| anyhow!("{e:?}"))?;
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: None,
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
-                ..Default::default()
-            })
-            .await?;
-        let caps = surface.get_capabilities(&adapter);
-        let format = caps
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(caps.formats[0]);
-        let present_mode = if caps.present_modes.contains(&wgpu::PresentMode::Immediate) {
-            wgpu::PresentMode::Immediate
-        } else if caps.present_modes.contains(&wgpu::PresentMode::Mailbox) {
-            wgpu::PresentMode::Mailbox
-        } else if caps.present_modes.contains(&wgpu::PresentMode::FifoRelaxed) {
-            wgpu::PresentMode::FifoRelaxed
-        } else {
-            wgpu::PresentMode::Fifo
-        };
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format,
-            width: size.width.max(1),
-            height: size.height.max(1),
-            present_mode,
-            alpha_mode: caps.alpha_modes[0],
-            view_formats: vec![format],
-            desired_maximum_frame_latency: 1,
-        };
-        surface.configure(&device, &config);
-        let output_is_srgb = format.is_srgb();
-        let color_params = color_params_from_info(ColorInfo::default(), output_is_srgb);
-        let color_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("color_params"),
-            contents: bytemuck::bytes_of(&color_params),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-        let bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("video_bind_group_layout"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                ],
+impl VideoLayer {
+    fn new(ctx: &LayerContext, color_params: ColorParams) -> Self {
+        let color_buffer = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("color_params"),
+                contents: bytemuck::bytes_of(&color_params),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             });
-        let nv12_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("nv12_bind_group_layout"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 3,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                ],
+        let vertex_buffer = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("video_vertex_buffer"),
+                contents: bytemuck::cast_slice(&VERTICES),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             });
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("video_shader"),
-            source: wgpu::ShaderSource::Wgsl(VIDEO_SHADER.into()),
-        });
-        let nv12_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("nv12_shader"),
-            source: wgpu::ShaderSource::Wgsl(NV12_SHADER.into()),
-        });
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("video_pipeline_layout"),
-            bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[],
+        let (video_texture, video_view) =
+            create_video_texture(ctx.device, 1, 1, wgpu::TextureFormat::Rgba8Unorm);
+        let video_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("video_bind_group"),
+            layout: ctx.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&video_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(ctx.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: color_buffer.as_entire_binding(),
+                },
+            ],
         });
-        let pipeline_nv12_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("nv12_pipeline_layout"),
-            bind_group_layouts: &[&nv12_bind_group_layout],
-            push_constant_ranges: &[],
+        let (nv12_y_texture, nv12_y_view) =
+            create_video_texture(ctx.device, 1, 1, wgpu::TextureFormat::R8Unorm);
+        let (nv12_uv_texture, nv12_uv_view) =
+            create_video_texture(ctx.device, 1, 1, wgpu::TextureFormat::Rg8Unorm);
+        let nv12_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("nv12_bind_group"),
+            layout: ctx.nv12_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&nv12_y_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&nv12_uv_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(ctx.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: color_buffer.as_entire_binding(),
+                },
+            ],
         });
-        let pipeline_rgba = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("video_pipeline_rgba"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<Vertex>() as u64,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &[
-                        wgpu::VertexAttribute {
-                            offset: 0,
-                            shader_location: 0,
-                            format: wgpu::VertexFormat::Float32x2,
-                        },
-                        wgpu::VertexAttribute {
-                            offset: 8,
-                            shader_location: 1,
-                            format: wgpu::VertexFormat::Float32x2,
-                        },
-                    ],
-                }],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None,
-        });
-        let pipeline_yuyv = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("video_pipeline_yuyv"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<Vertex>() as u64,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &[
-                        wgpu::VertexAttribute {
-                            offset: 0,
-                            shader_location: 0,
-                            format: wgpu::VertexFormat::Float32x2,
-                        },
-                        wgpu::VertexAttribute {
-                            offset: 8,
-                            shader_location: 1,
-                            format: wgpu::VertexFormat::Float32x2,
-                        },
-                    ],
-                }],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_yuyv"),
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None,
-        });
-        let pipeline_nv12 = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("video_pipeline_nv12"),
-            layout: Some(&pipeline_nv12_layout),
-            vertex: wgpu::VertexState {
-                module: &nv12_shader,
-                entry_point: Some("vs_main"),
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<Vertex>() as u64,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &[
-                        wgpu::VertexAttribute {
-                            offset: 0,
-                            shader_location: 0,
-                            format: wgpu::VertexFormat::Float32x2,
-                        },
-                        wgpu::VertexAttribute {
-                            offset: 8,
-                            shader_location: 1,
-                            format: wgpu::VertexFormat::Float32x2,
-                        },
-                    ],
-                }],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &nv12_shader,
-                entry_point: Some("fs_nv12"),
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None,
-        });
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("video_vertex_buffer"),
-            contents: bytemuck::cast_slice(&VERTICES),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("video_index_buffer"),
-            contents: bytemuck::cast_slice(&INDICES),
-            usage: wgpu::BufferUsages::INDEX,
-        });
-        let (video_texture, video_view) = create_video_texture(&device, 1, 1, wgpu::TextureFormat::Rgba8Unorm);
-        let video_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("video_bind_group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&video_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: color_buffer.as_entire_binding(),
-                },
-            ],
-        });
-        let (nv12_y_texture, nv12_y_view) =
-            create_video_texture(&device, 1, 1, wgpu::TextureFormat::R8Unorm);
-        let (nv12_uv_texture, nv12_uv_view) =
-            create_video_texture(&device, 1, 1, wgpu::TextureFormat::Rg8Unorm);
-        let nv12_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("nv12_bind_group"),
-            layout: &nv12_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&nv12_y_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&nv12_uv_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: color_buffer.as_entire_binding(),
-                },
-            ],
-        });
-        Ok(Self {
-            surface,
-            device,
-            queue,
-            config,
-            size,
-            pipeline_rgba,
-            pipeline_yuyv,
-            pipeline_nv12,
-            bind_group_layout,
-            nv12_bind_group_layout,
-            sampler,
-            vertex_buffer,
-            index_buffer,
-            num_indices: INDICES.len() as u32,
+        let (i420_v_texture, i420_v_view) =
+            create_video_texture(ctx.device, 1, 1, wgpu::TextureFormat::R8Unorm);
+        let i420_bind_group = create_i420_bind_group(
+            ctx.device,
+            ctx.i420_bind_group_layout,
+            [&nv12_y_view, &nv12_uv_view, &i420_v_view],
+            ctx.sampler,
+            &color_buffer,
+        );
+        Self {
             video_texture,
             video_view,
             video_bind_group,
@@ -476,95 +577,179 @@ impl RenderState {
             nv12_y_view,
             nv12_uv_view,
             nv12_bind_group,
+            i420_v_texture,
+            i420_v_view,
+            i420_bind_group,
             video_size: (1, 1),
+            has_frame: false,
             video_format: VideoFormat::Rgba,
-            output_is_srgb,
+            nv12_imported: false,
+            color_info: ColorInfo::default(),
             color_params,
             color_buffer,
-            aspect_correct: true,
-            staging: Vec::new(),
-        })
-    }
-
-    pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
-            self.update_vertices();
+            vertex_buffer,
         }
     }
 
-    pub fn set_aspect_correction(&mut self, enabled: bool) {
-        if self.aspect_correct != enabled {
-            self.aspect_correct = enabled;
-            self.update_vertices();
+    fn set_color_params(&mut self, queue: &wgpu::Queue, color: ColorInfo, mut params: ColorParams) {
+        self.color_info = color;
+        params.source_width = self.video_size.0 as f32;
+        if params != self.color_params {
+            self.color_params = params;
+            queue.write_buffer(&self.color_buffer, 0, bytemuck::bytes_of(&params));
         }
     }
 
-    pub fn update_frame(&mut self, frame: &VideoFrame) {
-        self.update_color_params(frame.color);
-        match &frame.data {
-            FrameData::Owned(data) => self.upload_frame(frame, data),
+    // False when the frame had to be skipped.
+    fn update_frame(&mut self, ctx: &mut LayerContext, frame: &VideoFrame) -> bool {
+        // Some sources hand over an empty frame while they settle; there's
+        // no texture to make from it.
+        if frame.width == 0 || frame.height == 0 {
+            return false;
+        }
+        let shown = match &frame.data {
+            FrameData::Owned(data) => self.upload_frame(ctx, frame, &[data]),
+            FrameData::Planes(planes) => {
+                let planes: Vec<&[u8]> = planes.iter().map(Vec::as_slice).collect();
+                self.upload_frame(ctx, frame, &planes)
+            }
             #[cfg(target_os = "linux")]
             FrameData::Gst(buffer) => {
-                if let Ok(map) = buffer.map_readable() {
-                    self.upload_frame(frame, map.as_slice());
+                if *ctx.dmabuf_import
+                    && frame.format == VideoFormat::Nv12
+                    && crate::dmabuf::is_dmabuf(buffer)
+                {
+                    if self.import_dmabuf(ctx, frame, buffer) {
+                        self.has_frame = true;
+                        return true;
+                    }
+                    *ctx.dmabuf_import = false;
                 }
+                buffer
+                    .map_readable()
+                    .is_ok_and(|map| self.upload_frame(ctx, frame, &[map.as_slice()]))
             }
+        };
+        if shown {
+            // The frame may have changed width.
+            self.set_color_params(ctx.queue, self.color_info, self.color_params);
         }
+        self.has_frame |= shown;
+        shown
     }
 
-    fn update_color_params(&mut self, color: ColorInfo) {
-        let params = color_params_from_info(color, self.output_is_srgb);
-        if params != self.color_params {
-            self.color_params = params;
-            self.queue
-                .write_buffer(&self.color_buffer, 0, bytemuck::bytes_of(&params));
-        }
+    // Samples the frame straight from its dmabuf; false leaves it to the
+    // copy path.
+    #[cfg(target_os = "linux")]
+    fn import_dmabuf(
+        &mut self,
+        ctx: &LayerContext,
+        frame: &VideoFrame,
+        buffer: &gstreamer::Buffer,
+    ) -> bool {
+        let Some((y_tex, uv_tex)) = crate::dmabuf::import_nv12(ctx.device, frame, buffer) else {
+            return false;
+        };
+        self.video_size = (frame.width, frame.height);
+        self.video_format = VideoFormat::Nv12;
+        let y_view = y_tex.create_view(&wgpu::TextureViewDescriptor::default());
+        let uv_view = uv_tex.create_view(&wgpu::TextureViewDescriptor::default());
+        self.set_nv12_textures(ctx, (y_tex, y_view), (uv_tex, uv_view));
+        self.nv12_imported = true;
+        true
+    }
+
+    fn set_nv12_textures(
+        &mut self,
+        ctx: &LayerContext,
+        (y_tex, y_view): (wgpu::Texture, wgpu::TextureView),
+        (uv_tex, uv_view): (wgpu::Texture, wgpu::TextureView),
+    ) {
+        self.nv12_y_texture = y_tex;
+        self.nv12_uv_texture = uv_tex;
+        self.nv12_y_view = y_view;
+        self.nv12_uv_view = uv_view;
+        self.nv12_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("nv12_bind_group"),
+            layout: ctx.nv12_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&self.nv12_y_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&self.nv12_uv_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(ctx.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.color_buffer.as_entire_binding(),
+                },
+            ],
+        });
     }
 
-    fn upload_frame(&mut self, frame: &VideoFrame, data: &[u8]) {
+    fn upload_frame(
+        &mut self,
+        ctx: &mut LayerContext,
+        frame: &VideoFrame,
+        planes: &[&[u8]],
+    ) -> bool {
+        // Uploading what's there would leave the previous frame's chroma in
+        // the texture, which shows as a green picture.
+        if matches!(frame.format, VideoFormat::Nv12 | VideoFormat::P010)
+            && nv12_truncated(frame, planes)
+        {
+            return false;
+        }
+        if frame.format == VideoFormat::P010 && !ctx.p010_native {
+            return self.upload_p010_as_nv12(ctx, frame, planes);
+        }
+        let Some(&data) = planes.first() else {
+            return false;
+        };
         let size_changed = self.video_size != (frame.width, frame.height);
         let format_changed = self.video_format != frame.format;
         self.video_format = frame.format;
         self.video_size = (frame.width, frame.height);
-        if size_changed {
-            self.update_vertices();
-        }
         match frame.format {
-            VideoFormat::Rgba => {
+            VideoFormat::Rgba | VideoFormat::Grey | VideoFormat::Rgb565 => {
                 if size_changed || format_changed {
-                    let (tex, view) = create_video_texture(
-                        &self.device,
-                        frame.width,
-                        frame.height,
-                        wgpu::TextureFormat::Rgba8Unorm,
-                    );
+                    let texture_format = match frame.format {
+                        VideoFormat::Grey => wgpu::TextureFormat::R8Unorm,
+                        VideoFormat::Rgb565 => wgpu::TextureFormat::Rg8Unorm,
+                        _ => wgpu::TextureFormat::Rgba8Unorm,
+                    };
+                    let (tex, view) =
+                        create_video_texture(ctx.device, frame.width, frame.height, texture_format);
                     self.video_texture = tex;
                     self.video_view = view;
-                    self.video_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                        label: Some("video_bind_group"),
-                        layout: &self.bind_group_layout,
-                        entries: &[
-                            wgpu::BindGroupEntry {
-                                binding: 0,
-                                resource: wgpu::BindingResource::TextureView(&self.video_view),
-                            },
-                            wgpu::BindGroupEntry {
-                                binding: 1,
-                                resource: wgpu::BindingResource::Sampler(&self.sampler),
-                            },
-                            wgpu::BindGroupEntry {
-                                binding: 2,
-                                resource: self.color_buffer.as_entire_binding(),
-                            },
-                        ],
-                    });
+                    self.video_bind_group =
+                        ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                            label: Some("video_bind_group"),
+                            layout: ctx.bind_group_layout,
+                            entries: &[
+                                wgpu::BindGroupEntry {
+                                    binding: 0,
+                                    resource: wgpu::BindingResource::TextureView(&self.video_view),
+                                },
+                                wgpu::BindGroupEntry {
+                                    binding: 1,
+                                    resource: wgpu::BindingResource::Sampler(ctx.sampler),
+                                },
+                                wgpu::BindGroupEntry {
+                                    binding: 2,
+                                    resource: self.color_buffer.as_entire_binding(),
+                                },
+                            ],
+                        });
                 }
                 let texture = self.video_texture.clone();
-                self.write_texture_padded(
+                ctx.write_texture_padded(
                     &texture,
                     frame.width,
                     frame.height,
@@ -572,96 +757,139 @@ impl RenderState {
                     data,
                 );
             }
-            VideoFormat::Yuyv => {
+            VideoFormat::Yuyv | VideoFormat::Uyvy => {
                 if size_changed || format_changed {
                     let (tex, view) = create_video_texture(
-                        &self.device,
-                        frame.width,
+                        ctx.device,
+                        frame.width.div_ceil(2),
                         frame.height,
-                        wgpu::TextureFormat::Rg8Unorm,
+                        wgpu::TextureFormat::Rgba8Unorm,
                     );
                     self.video_texture = tex;
                     self.video_view = view;
-                    self.video_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                        label: Some("video_bind_group"),
-                        layout: &self.bind_group_layout,
-                        entries: &[
-                            wgpu::BindGroupEntry {
-                                binding: 0,
-                                resource: wgpu::BindingResource::TextureView(&self.video_view),
-                            },
-                            wgpu::BindGroupEntry {
-                                binding: 1,
-                                resource: wgpu::BindingResource::Sampler(&self.sampler),
-                            },
-                            wgpu::BindGroupEntry {
-                                binding: 2,
-                                resource: self.color_buffer.as_entire_binding(),
-                            },
-                        ],
-                    });
+                    self.video_bind_group =
+                        ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                            label: Some("video_bind_group"),
+                            layout: ctx.bind_group_layout,
+                            entries: &[
+                                wgpu::BindGroupEntry {
+                                    binding: 0,
+                                    resource: wgpu::BindingResource::TextureView(&self.video_view),
+                                },
+                                wgpu::BindGroupEntry {
+                                    binding: 1,
+                                    resource: wgpu::BindingResource::Sampler(ctx.sampler),
+                                },
+                                wgpu::BindGroupEntry {
+                                    binding: 2,
+                                    resource: self.color_buffer.as_entire_binding(),
+                                },
+                            ],
+                        });
                 }
                 let texture = self.video_texture.clone();
-                self.write_texture_padded(
+                ctx.write_texture_padded(
                     &texture,
-                    frame.width,
+                    frame.width.div_ceil(2),
                     frame.height,
                     frame.stride as u32,
                     data,
                 );
             }
             VideoFormat::Nv12 => {
+                let uv_width = frame.width.div_ceil(2);
+                let uv_height = frame.height.div_ceil(2);
+                if size_changed || format_changed || self.nv12_imported {
+                    let y = create_video_texture(
+                        ctx.device,
+                        frame.width,
+                        frame.height,
+                        wgpu::TextureFormat::R8Unorm,
+                    );
+                    let uv = create_video_texture(
+                        ctx.device,
+                        uv_width,
+                        uv_height,
+                        wgpu::TextureFormat::Rg8Unorm,
+                    );
+                    self.set_nv12_textures(ctx, y, uv);
+                    self.nv12_imported = false;
+                }
+                let (y_data, uv_data) = nv12_planes(frame, planes);
+                let y_texture = self.nv12_y_texture.clone();
+                ctx.write_texture_padded(
+                    &y_texture,
+                    frame.width,
+                    frame.height,
+                    frame.stride as u32,
+                    y_data,
+                );
+                let uv_texture = self.nv12_uv_texture.clone();
+                ctx.write_texture_padded(
+                    &uv_texture,
+                    uv_width,
+                    uv_height,
+                    frame.uv_stride as u32,
+                    uv_data,
+                );
+            }
+            VideoFormat::P010 => {
                 let uv_width = frame.width.div_ceil(2);
                 let uv_height = frame.height.div_ceil(2);
                 if size_changed || format_changed {
-                    let (y_tex, y_view) =
-                        create_video_texture(&self.device, frame.width, frame.height, wgpu::TextureFormat::R8Unorm);
-                    let (uv_tex, uv_view) =
-                        create_video_texture(&self.device, uv_width, uv_height, wgpu::TextureFormat::Rg8Unorm);
+                    let (y_tex, y_view) = create_video_texture(
+                        ctx.device,
+                        frame.width,
+                        frame.height,
+                        wgpu::TextureFormat::R16Unorm,
+                    );
+                    let (uv_tex, uv_view) = create_video_texture(
+                        ctx.device,
+                        uv_width,
+                        uv_height,
+                        wgpu::TextureFormat::Rg16Unorm,
+                    );
                     self.nv12_y_texture = y_tex;
                     self.nv12_uv_texture = uv_tex;
                     self.nv12_y_view = y_view;
                     self.nv12_uv_view = uv_view;
-                    self.nv12_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                        label: Some("nv12_bind_group"),
-                        layout: &self.nv12_bind_group_layout,
-                        entries: &[
-                            wgpu::BindGroupEntry {
-                                binding: 0,
-                                resource: wgpu::BindingResource::TextureView(&self.nv12_y_view),
-                            },
-                            wgpu::BindGroupEntry {
-                                binding: 1,
-                                resource: wgpu::BindingResource::TextureView(&self.nv12_uv_view),
-                            },
-                            wgpu::BindGroupEntry {
-                                binding: 2,
-                                resource: wgpu::BindingResource::Sampler(&self.sampler),
-                            },
-                            wgpu::BindGroupEntry {
-                                binding: 3,
-                                resource: self.color_buffer.as_entire_binding(),
-                            },
-                        ],
-                    });
+                    self.nv12_bind_group =
+                        ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                            label: Some("p010_bind_group"),
+                            layout: ctx.nv12_bind_group_layout,
+                            entries: &[
+                                wgpu::BindGroupEntry {
+                                    binding: 0,
+                                    resource: wgpu::BindingResource::TextureView(&self.nv12_y_view),
+                                },
+                                wgpu::BindGroupEntry {
+                                    binding: 1,
+                                    resource: wgpu::BindingResource::TextureView(
+                                        &self.nv12_uv_view,
+                                    ),
+                                },
+                                wgpu::BindGroupEntry {
+                                    binding: 2,
+                                    resource: wgpu::BindingResource::Sampler(ctx.sampler),
+                                },
+                                wgpu::BindGroupEntry {
+                                    binding: 3,
+                                    resource: self.color_buffer.as_entire_binding(),
+                                },
+                            ],
+                        });
                 }
-                let data_len = data.len();
-                let y_bytes = (frame.stride * frame.height as usize).min(data_len);
-                let y_data = &data[..y_bytes];
+                let (y_data, uv_data) = nv12_planes(frame, planes);
                 let y_texture = self.nv12_y_texture.clone();
-                self.write_texture_padded(
+                ctx.write_texture_padded(
                     &y_texture,
                     frame.width,
                     frame.height,
                     frame.stride as u32,
                     y_data,
                 );
-                let uv_bytes = frame.uv_stride * uv_height as usize;
-                let uv_start = y_bytes;
-                let uv_len = uv_bytes.min(data_len.saturating_sub(uv_start));
-                let uv_data = &data[uv_start..uv_start + uv_len];
                 let uv_texture = self.nv12_uv_texture.clone();
-                self.write_texture_padded(
+                ctx.write_texture_padded(
                     &uv_texture,
                     uv_width,
                     uv_height,
@@ -669,41 +897,1185 @@ impl RenderState {
                     uv_data,
                 );
             }
+            VideoFormat::I420 => {
+                let uv_width = frame.width.div_ceil(2);
+                let uv_height = frame.height.div_ceil(2);
+                if size_changed || format_changed {
+                    let (y_tex, y_view) = create_video_texture(
+                        ctx.device,
+                        frame.width,
+                        frame.height,
+                        wgpu::TextureFormat::R8Unorm,
+                    );
+                    let (u_tex, u_view) = create_video_texture(
+                        ctx.device,
+                        uv_width,
+                        uv_height,
+                        wgpu::TextureFormat::R8Unorm,
+                    );
+                    let (v_tex, v_view) = create_video_texture(
+                        ctx.device,
+                        uv_width,
+                        uv_height,
+                        wgpu::TextureFormat::R8Unorm,
+                    );
+                    self.nv12_y_texture = y_tex;
+                    self.nv12_uv_texture = u_tex;
+                    self.i420_v_texture = v_tex;
+                    self.nv12_y_view = y_view;
+                    self.nv12_uv_view = u_view;
+                    self.i420_v_view = v_view;
+                    self.i420_bind_group = create_i420_bind_group(
+                        ctx.device,
+                        ctx.i420_bind_group_layout,
+                        [&self.nv12_y_view, &self.nv12_uv_view, &self.i420_v_view],
+                        ctx.sampler,
+                        &self.color_buffer,
+                    );
+                }
+                let (y_data, u_data, v_data) = i420_planes(frame, planes);
+                let y_texture = self.nv12_y_texture.clone();
+                ctx.write_texture_padded(
+                    &y_texture,
+                    frame.width,
+                    frame.height,
+                    frame.stride as u32,
+                    y_data,
+                );
+                for (texture, data) in [
+                    (self.nv12_uv_texture.clone(), u_data),
+                    (self.i420_v_texture.clone(), v_data),
+                ] {
+                    ctx.write_texture_padded(
+                        &texture,
+                        uv_width,
+                        uv_height,
+                        frame.uv_stride as u32,
+                        data,
+                    );
+                }
+            }
         }
+        true
     }
 
-    pub fn render(
+    fn upload_p010_as_nv12(
         &mut self,
-        window: &Window,
-        egui_renderer: &mut egui_wgpu::Renderer,
-        textures_delta: &egui::TexturesDelta,
-        clipped_primitives: &[egui::ClippedPrimitive],
-        pixels_per_point: f32,
-    ) -> Result<()> {
-        let output = self.surface.get_current_texture()?;
-        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder =
-            self.device
-                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("render_encoder"),
-                });
-        let screen_descriptor = egui_wgpu::ScreenDescriptor {
-            size_in_pixels: [self.config.width, self.config.height],
-            pixels_per_point,
+        ctx: &mut LayerContext,
+        frame: &VideoFrame,
+        planes: &[&[u8]],
+    ) -> bool {
+        let (y_data, uv_data) = nv12_planes(frame, planes);
+        let width = frame.width as usize;
+        let uv_width = frame.width.div_ceil(2) as usize * 2;
+        let y = pixel::p010_to_8bit(width, frame.height as usize, frame.stride, y_data);
+        let uv = pixel::p010_to_8bit(
+            uv_width,
+            frame.height.div_ceil(2) as usize,
+            frame.uv_stride,
+            uv_data,
+        );
+        let nv12 = VideoFrame {
+            width: frame.width,
+            height: frame.height,
+            format: VideoFormat::Nv12,
+            stride: width,
+            uv_stride: uv_width,
+            plane_offsets: None,
+            color: frame.color,
+            data: FrameData::Owned(Vec::new()),
+            captured_at: frame.captured_at,
         };
-        for (id, image_delta) in &textures_delta.set {
-            egui_renderer.update_texture(&self.device, &self.queue, *id, image_delta);
-        }
-        let has_ui = !clipped_primitives.is_empty();
-        if has_ui {
-            egui_renderer.update_buffers(
-                &self.device,
-                &self.queue,
-                &mut encoder,
-                clipped_primitives,
+        self.upload_frame(ctx, &nv12, &[&y, &uv])
+    }
+
+    // Re-binds the current textures, e.g. after the sampler was replaced.
+    fn rebuild_bind_groups(&mut self, ctx: &LayerContext) {
+        self.video_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("video_bind_group"),
+            layout: ctx.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&self.video_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(ctx.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.color_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        self.nv12_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("nv12_bind_group"),
+            layout: ctx.nv12_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&self.nv12_y_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&self.nv12_uv_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(ctx.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.color_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        self.i420_bind_group = create_i420_bind_group(
+            ctx.device,
+            ctx.i420_bind_group_layout,
+            [&self.nv12_y_view, &self.nv12_uv_view, &self.i420_v_view],
+            ctx.sampler,
+            &self.color_buffer,
+        );
+    }
+}
+
+impl RenderState {
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    // `wide_gamut` prefers a 10-bit surface and maps colors into Display
+    // P3, for wide-gamut monitors that show surfaces unmanaged.
+    pub async fn new(window: Arc<Window>, wide_gamut: bool) -> Result<Self> {
+        let size = window.inner_size();
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let surface = instance.create_surface(window)?;
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: false,
+            })
+            .await
+            .map_err(|e| anyhow!("{e:?}"))?;
+        let p010_native = adapter
+            .features()
+            .contains(wgpu::Features::TEXTURE_FORMAT_16BIT_NORM);
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: None,
+                required_features: if p010_native {
+                    wgpu::Features::TEXTURE_FORMAT_16BIT_NORM
+                } else {
+                    wgpu::Features::empty()
+                },
+                required_limits: wgpu::Limits::default(),
+                ..Default::default()
+            })
+            .await?;
+        let caps = surface.get_capabilities(&adapter);
+        let wide_format = caps
+            .formats
+            .iter()
+            .copied()
+            .find(|&f| wide_gamut && f == wgpu::TextureFormat::Rgb10a2Unorm);
+        let format = wide_format
+            .or_else(|| caps.formats.iter().copied().find(|f| f.is_srgb()))
+            .unwrap_or(caps.formats[0]);
+        let present_modes = caps.present_modes.clone();
+        let present_mode = pick_present_mode(&present_modes, false);
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode,
+            alpha_mode: caps.alpha_modes[0],
+            view_formats: vec![format],
+            desired_maximum_frame_latency: 1,
+        };
+        surface.configure(&device, &config);
+        let output_is_srgb = format.is_srgb();
+        let mut color_params = color_params_from_info(
+            ColorInfo::default(),
+            output_is_srgb,
+            true,
+            1.0,
+            ImageAdjust::default(),
+        );
+        color_params.gamut = output_gamut(ColorInfo::default().matrix, wide_gamut);
+        let bind_group_layout = create_video_bind_group_layout(&device);
+        let nv12_bind_group_layout = create_nv12_bind_group_layout(&device);
+        let i420_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("i420_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let sampler = create_sampler(&device, wgpu::FilterMode::Linear);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("video_shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                format!("{COLOR_SHADER_PRELUDE}\n{VIDEO_SHADER}").into(),
+            ),
+        });
+        let nv12_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("nv12_shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                format!("{COLOR_SHADER_PRELUDE}\n{NV12_SHADER}").into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("video_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline_nv12_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("nv12_pipeline_layout"),
+            bind_group_layouts: &[&nv12_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline_i420_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("i420_pipeline_layout"),
+            bind_group_layouts: &[&i420_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline_rgba = create_video_pipeline(
+            &device,
+            "video_pipeline_rgba",
+            &pipeline_layout,
+            &shader,
+            "fs_main",
+            format,
+        );
+        let pipeline_yuyv = create_video_pipeline(
+            &device,
+            "video_pipeline_yuyv",
+            &pipeline_layout,
+            &shader,
+            "fs_yuyv",
+            format,
+        );
+        let pipeline_uyvy = create_video_pipeline(
+            &device,
+            "video_pipeline_uyvy",
+            &pipeline_layout,
+            &shader,
+            "fs_uyvy",
+            format,
+        );
+        let pipeline_nv12 = create_video_pipeline(
+            &device,
+            "video_pipeline_nv12",
+            &pipeline_nv12_layout,
+            &nv12_shader,
+            "fs_nv12",
+            format,
+        );
+        let pipeline_i420 = create_video_pipeline(
+            &device,
+            "video_pipeline_i420",
+            &pipeline_i420_layout,
+            &nv12_shader,
+            "fs_i420",
+            format,
+        );
+        let pipeline_p010 = create_video_pipeline(
+            &device,
+            "video_pipeline_p010",
+            &pipeline_nv12_layout,
+            &nv12_shader,
+            "fs_p010",
+            format,
+        );
+        let pipeline_rgba_cubic = create_video_pipeline(
+            &device,
+            "video_pipeline_rgba_cubic",
+            &pipeline_layout,
+            &shader,
+            "fs_main_cubic",
+            format,
+        );
+        let pipeline_grey = create_video_pipeline(
+            &device,
+            "video_pipeline_grey",
+            &pipeline_layout,
+            &shader,
+            "fs_grey",
+            format,
+        );
+        let pipeline_rgb565 = create_video_pipeline(
+            &device,
+            "video_pipeline_rgb565",
+            &pipeline_layout,
+            &shader,
+            "fs_rgb565",
+            format,
+        );
+        let pipeline_grey_cubic = create_video_pipeline(
+            &device,
+            "video_pipeline_grey_cubic",
+            &pipeline_layout,
+            &shader,
+            "fs_grey_cubic",
+            format,
+        );
+        let pipeline_nv12_cubic = create_video_pipeline(
+            &device,
+            "video_pipeline_nv12_cubic",
+            &pipeline_nv12_layout,
+            &nv12_shader,
+            "fs_nv12_cubic",
+            format,
+        );
+        let pipeline_i420_cubic = create_video_pipeline(
+            &device,
+            "video_pipeline_i420_cubic",
+            &pipeline_i420_layout,
+            &nv12_shader,
+            "fs_i420_cubic",
+            format,
+        );
+        let pipeline_p010_cubic = create_video_pipeline(
+            &device,
+            "video_pipeline_p010_cubic",
+            &pipeline_nv12_layout,
+            &nv12_shader,
+            "fs_p010_cubic",
+            format,
+        );
+        let pipeline_yuyv_deinterlace = create_video_pipeline(
+            &device,
+            "video_pipeline_yuyv_deinterlace",
+            &pipeline_layout,
+            &shader,
+            "fs_yuyv_deinterlace",
+            format,
+        );
+        let pipeline_uyvy_deinterlace = create_video_pipeline(
+            &device,
+            "video_pipeline_uyvy_deinterlace",
+            &pipeline_layout,
+            &shader,
+            "fs_uyvy_deinterlace",
+            format,
+        );
+        let pipeline_nv12_deinterlace = create_video_pipeline(
+            &device,
+            "video_pipeline_nv12_deinterlace",
+            &pipeline_nv12_layout,
+            &nv12_shader,
+            "fs_nv12_deinterlace",
+            format,
+        );
+        let pipeline_i420_deinterlace = create_video_pipeline(
+            &device,
+            "video_pipeline_i420_deinterlace",
+            &pipeline_i420_layout,
+            &nv12_shader,
+            "fs_i420_deinterlace",
+            format,
+        );
+        let pipeline_p010_deinterlace = create_video_pipeline(
+            &device,
+            "video_pipeline_p010_deinterlace",
+            &pipeline_nv12_layout,
+            &nv12_shader,
+            "fs_p010_deinterlace",
+            format,
+        );
+        let sharpen_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("sharpen_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let sharpen_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("sharpen_shader"),
+            source: wgpu::ShaderSource::Wgsl(SHARPEN_SHADER.into()),
+        });
+        let sharpen_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("sharpen_pipeline_layout"),
+            bind_group_layouts: &[&sharpen_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let sharpen_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("sharpen_pipeline"),
+            layout: Some(&sharpen_layout),
+            vertex: wgpu::VertexState {
+                module: &sharpen_shader,
+                entry_point: Some("vs_fullscreen"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &sharpen_shader,
+                entry_point: Some("fs_sharpen"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let sharpen_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("sharpen_params"),
+            contents: bytemuck::bytes_of(&SharpenParams {
+                strength: 0.0,
+                _pad: [0.0; 3],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let post_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("post_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let post_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("post_params"),
+            contents: bytemuck::bytes_of(&PostParams::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let post_sampler = create_sampler(&device, wgpu::FilterMode::Linear);
+        let crt_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("crt_bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let crt_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("crt_params"),
+            contents: bytemuck::bytes_of(&CrtParams::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let crt_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("crt_bind_group"),
+            layout: &crt_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: crt_buffer.as_entire_binding(),
+            }],
+        });
+        let crt_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("crt_shader"),
+            source: wgpu::ShaderSource::Wgsl(format!("{CRT_SHADER}\n{POST_SHADER_PRELUDE}").into()),
+        });
+        let crt_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("crt_pipeline_layout"),
+            bind_group_layouts: &[&post_bind_group_layout, &crt_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let crt_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("crt_pipeline"),
+            layout: Some(&crt_layout),
+            vertex: wgpu::VertexState {
+                module: &crt_shader,
+                entry_point: Some("vs_post"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &crt_shader,
+                entry_point: Some("fs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("video_index_buffer"),
+            contents: bytemuck::cast_slice(&INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        #[cfg(target_os = "linux")]
+        let mut dmabuf_import = crate::dmabuf::supported(&device);
+        let mut staging = Vec::new();
+        let video = VideoLayer::new(
+            &LayerContext {
+                device: &device,
+                queue: &queue,
+                bind_group_layout: &bind_group_layout,
+                nv12_bind_group_layout: &nv12_bind_group_layout,
+                i420_bind_group_layout: &i420_bind_group_layout,
+                sampler: &sampler,
+                p010_native,
+                #[cfg(target_os = "linux")]
+                dmabuf_import: &mut dmabuf_import,
+                staging: &mut staging,
+            },
+            color_params,
+        );
+        Ok(Self {
+            surface,
+            device,
+            queue,
+            config,
+            present_modes,
+            size,
+            pipeline_rgba,
+            pipeline_grey,
+            pipeline_rgb565,
+            pipeline_yuyv,
+            pipeline_uyvy,
+            pipeline_nv12,
+            pipeline_i420,
+            pipeline_p010,
+            pipeline_rgba_cubic,
+            pipeline_grey_cubic,
+            pipeline_nv12_cubic,
+            pipeline_i420_cubic,
+            pipeline_p010_cubic,
+            pipeline_yuyv_deinterlace,
+            pipeline_uyvy_deinterlace,
+            pipeline_nv12_deinterlace,
+            pipeline_i420_deinterlace,
+            pipeline_p010_deinterlace,
+            deinterlace: false,
+            sharpen_pipeline,
+            sharpen_bind_group_layout,
+            sharpen_buffer,
+            sharpen_target: None,
+            sharpen: 0.0,
+            post_bind_group_layout,
+            post_sampler,
+            post_buffer,
+            post_target: None,
+            post_shader: None,
+            post_epoch: Instant::now(),
+            crt_pipeline,
+            crt_buffer,
+            crt_bind_group,
+            crt: None,
+            bind_group_layout,
+            nv12_bind_group_layout,
+            i420_bind_group_layout,
+            sampler,
+            index_buffer,
+            num_indices: INDICES.len() as u32,
+            video,
+            pip: None,
+            pip_layout: None,
+            blank_video: false,
+            p010_native,
+            #[cfg(target_os = "linux")]
+            dmabuf_import,
+            output_is_srgb,
+            srgb_output_override: None,
+            output_is_8bit: matches!(
+                format,
+                wgpu::TextureFormat::Bgra8Unorm
+                    | wgpu::TextureFormat::Bgra8UnormSrgb
+                    | wgpu::TextureFormat::Rgba8Unorm
+                    | wgpu::TextureFormat::Rgba8UnormSrgb
+            ),
+            dither: false,
+            wide_gamut,
+            background: wgpu::Color::BLACK,
+            clamp_limited: true,
+            output_gamma: 1.0,
+            image_adjust: ImageAdjust::default(),
+            zebra: None,
+            zebra_epoch: Instant::now(),
+            aspect_mode: AspectMode::Auto,
+            scale_mode: ScaleMode::Fit,
+            scale_filter: ScaleFilter::Linear,
+            crop: CropRect::default(),
+            rotation: Rotation::Deg0,
+            video_rect: [0.0; 4],
+            source_size: [1.0; 2],
+            staging,
+        })
+    }
+
+    pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.size = new_size;
+            self.config.width = new_size.width;
+            self.config.height = new_size.height;
+            self.surface.configure(&self.device, &self.config);
+            self.update_vertices();
+        }
+    }
+
+    pub fn set_aspect_mode(&mut self, mode: AspectMode) {
+        if self.aspect_mode != mode {
+            self.aspect_mode = mode;
+            self.update_vertices();
+        }
+    }
+
+    pub fn set_vsync(&mut self, vsync: bool) {
+        let mode = pick_present_mode(&self.present_modes, vsync);
+        if self.config.present_mode != mode {
+            self.config.present_mode = mode;
+            self.surface.configure(&self.device, &self.config);
+        }
+    }
+
+    pub fn set_background(&mut self, rgb: [u8; 3]) {
+        self.background = clear_color(rgb, self.output_is_srgb);
+    }
+
+    pub fn set_frame_latency(&mut self, latency: u32) {
+        let latency = latency.clamp(1, 3);
+        if self.config.desired_maximum_frame_latency != latency {
+            self.config.desired_maximum_frame_latency = latency;
+            self.surface.configure(&self.device, &self.config);
+        }
+    }
+
+    pub fn diagnostics(&self) -> SurfaceDiagnostics {
+        SurfaceDiagnostics {
+            format: self.config.format,
+            present_mode: self.config.present_mode,
+            frame_latency: self.config.desired_maximum_frame_latency,
+            width: self.config.width,
+            height: self.config.height,
+            srgb_output: self.srgb_output(),
+        }
+    }
+
+    pub fn video_rect(&self) -> [f32; 4] {
+        self.video_rect
+    }
+
+    // False until the main source's first frame has been uploaded.
+    pub fn has_frame(&self) -> bool {
+        self.video.has_frame
+    }
+
+    // Leaves just the background where the main picture would be.
+    pub fn set_blank_video(&mut self, blank: bool) {
+        self.blank_video = blank;
+    }
+
+    pub fn set_scale_mode(&mut self, mode: ScaleMode) {
+        if self.scale_mode != mode {
+            let was_integer = self.scale_mode == ScaleMode::Integer;
+            self.scale_mode = mode;
+            if was_integer != (mode == ScaleMode::Integer) {
+                let filter = if mode == ScaleMode::Integer {
+                    wgpu::FilterMode::Nearest
+                } else {
+                    wgpu::FilterMode::Linear
+                };
+                self.sampler = create_sampler(&self.device, filter);
+                let (ctx, video, pip) = self.layers();
+                video.rebuild_bind_groups(&ctx);
+                if let Some(pip) = pip {
+                    pip.rebuild_bind_groups(&ctx);
+                }
+            }
+            self.update_vertices();
+        }
+    }
+
+    pub fn set_crop(&mut self, crop: CropRect) {
+        if self.crop != crop {
+            self.crop = crop;
+            self.update_vertices();
+        }
+    }
+
+    // 0 disables the pass entirely; 1 is the strongest sharpening.
+    pub fn set_sharpen(&mut self, strength: f32) {
+        let strength = strength.clamp(0.0, 1.0);
+        if self.sharpen != strength {
+            self.sharpen = strength;
+            let params = SharpenParams {
+                strength,
+                _pad: [0.0; 3],
+            };
+            self.queue
+                .write_buffer(&self.sharpen_buffer, 0, bytemuck::bytes_of(&params));
+            if strength == 0.0 {
+                self.sharpen_target = None;
+            }
+        }
+    }
+
+    // None turns the built-in CRT pass off, and with it all of its cost.
+    pub fn set_crt(&mut self, crt: Option<CrtSettings>) {
+        if self.crt == crt {
+            return;
+        }
+        self.crt = crt;
+        if let Some(crt) = crt {
+            let params = CrtParams {
+                scanlines: crt.scanlines,
+                mask: match crt.mask {
+                    CrtMask::None => 0.0,
+                    CrtMask::ApertureGrille => 1.0,
+                    CrtMask::ShadowMask => 2.0,
+                },
+                mask_strength: crt.mask_strength,
+                bloom: crt.bloom,
+                curvature: if crt.curvature { 1.0 } else { 0.0 },
+                _pad: [0.0; 3],
+            };
+            self.queue
+                .write_buffer(&self.crt_buffer, 0, bytemuck::bytes_of(&params));
+        } else if self.post_shader.is_none() {
+            self.post_target = None;
+        }
+    }
+
+    // Runs the WGSL file at `path` as a last pass over the finished picture,
+    // or drops it with None. The file is checked for changes every
+    // POST_SHADER_POLL; each (re)compile reports its result, as does
+    // dropping a loaded shader so its errors can be cleared. A custom
+    // shader takes the place of the CRT pass.
+    pub fn set_post_shader(&mut self, path: Option<&Path>) -> Option<Result<()>> {
+        let Some(path) = path else {
+            self.post_shader.take()?;
+            if self.crt.is_none() {
+                self.post_target = None;
+            }
+            return Some(Ok(()));
+        };
+        let modified = || std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        match self.post_shader.as_mut().filter(|p| p.path == path) {
+            Some(post) => {
+                if post.checked_at.elapsed() < POST_SHADER_POLL {
+                    return None;
+                }
+                post.checked_at = Instant::now();
+                let modified = modified();
+                if modified == post.modified {
+                    return None;
+                }
+                post.modified = modified;
+            }
+            None => {
+                self.post_shader = Some(PostShader {
+                    path: path.to_path_buf(),
+                    modified: modified(),
+                    checked_at: Instant::now(),
+                    pipeline: None,
+                });
+            }
+        }
+        let result = self.compile_post_shader(path);
+        let post = self.post_shader.as_mut()?;
+        Some(result.map(|pipeline| post.pipeline = Some(pipeline)))
+    }
+
+    // Validation errors are caught here rather than by wgpu's default
+    // handler, which would abort on a typo.
+    fn compile_post_shader(&self, path: &Path) -> Result<wgpu::RenderPipeline> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {e}", path.display()))?;
+        // Appended so line numbers in errors match the user's file.
+        let source = format!("{source}\n{POST_SHADER_PRELUDE}");
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("post_shader"),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+        let layout = self
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("post_pipeline_layout"),
+                bind_group_layouts: &[&self.post_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let pipeline = self
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("post_pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_post"),
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: self.config.format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+        match pollster::block_on(self.device.pop_error_scope()) {
+            Some(e) => Err(anyhow!("{}: {e}", path.display())),
+            None => Ok(pipeline),
+        }
+    }
+
+    pub fn set_scale_filter(&mut self, filter: ScaleFilter) {
+        self.scale_filter = filter;
+    }
+
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        if self.rotation != rotation {
+            self.rotation = rotation;
+            self.update_vertices();
+        }
+    }
+
+    pub fn set_deinterlace(&mut self, enabled: bool) {
+        self.deinterlace = enabled;
+    }
+
+    // False when the frame was skipped, e.g. a truncated buffer.
+    pub fn update_frame(&mut self, frame: &VideoFrame) -> bool {
+        self.update_color_params(frame.color);
+        let size = self.video.video_size;
+        let (mut ctx, video, _) = self.layers();
+        let shown = video.update_frame(&mut ctx, frame);
+        if self.video.video_size != size {
+            self.update_vertices();
+        }
+        shown
+    }
+
+    // None drops the preview's textures along with it.
+    pub fn set_pip(&mut self, layout: Option<PipLayout>) {
+        if layout.is_none() {
+            self.pip = None;
+        }
+        self.pip_layout = layout;
+    }
+
+    pub fn update_pip_frame(&mut self, frame: &VideoFrame) {
+        if self.pip_layout.is_none() {
+            return;
+        }
+        let params = self.layer_color_params(frame.color, false);
+        if self.pip.is_none() {
+            let (ctx, _, _) = self.layers();
+            let layer = VideoLayer::new(&ctx, params);
+            self.pip = Some(layer);
+        }
+        let (mut ctx, _, Some(pip)) = self.layers() else {
+            return;
+        };
+        pip.set_color_params(ctx.queue, frame.color, params);
+        pip.update_frame(&mut ctx, frame);
+    }
+
+    // Splits out the shared state the layers upload through from the layers
+    // themselves.
+    fn layers(&mut self) -> (LayerContext<'_>, &mut VideoLayer, Option<&mut VideoLayer>) {
+        let ctx = LayerContext {
+            device: &self.device,
+            queue: &self.queue,
+            bind_group_layout: &self.bind_group_layout,
+            nv12_bind_group_layout: &self.nv12_bind_group_layout,
+            i420_bind_group_layout: &self.i420_bind_group_layout,
+            sampler: &self.sampler,
+            p010_native: self.p010_native,
+            #[cfg(target_os = "linux")]
+            dmabuf_import: &mut self.dmabuf_import,
+            staging: &mut self.staging,
+        };
+        (ctx, &mut self.video, self.pip.as_mut())
+    }
+
+    pub fn set_clamp_limited_range(&mut self, enabled: bool) {
+        if self.clamp_limited != enabled {
+            self.clamp_limited = enabled;
+            self.update_color_params(self.video.color_info);
+        }
+    }
+
+    pub fn set_output_gamma(&mut self, gamma: f32) {
+        if self.output_gamma != gamma {
+            self.output_gamma = gamma;
+            self.update_color_params(self.video.color_info);
+        }
+    }
+
+    // Refreshed every frame while enabled so the stripes keep moving.
+    pub fn set_zebra(&mut self, threshold: Option<f32>) {
+        if self.zebra.is_some() || threshold.is_some() {
+            self.zebra = threshold;
+            self.update_color_params(self.video.color_info);
+        }
+    }
+
+    pub fn set_srgb_output(&mut self, forced: Option<bool>) {
+        if self.srgb_output_override != forced {
+            self.srgb_output_override = forced;
+            self.update_color_params(self.video.color_info);
+        }
+    }
+
+    fn srgb_output(&self) -> bool {
+        self.srgb_output_override.unwrap_or(self.output_is_srgb)
+    }
+
+    pub fn set_dither(&mut self, enabled: bool) {
+        if self.dither != enabled {
+            self.dither = enabled;
+            self.update_color_params(self.video.color_info);
+        }
+    }
+
+    pub fn set_image_adjust(&mut self, adjust: ImageAdjust) {
+        if self.image_adjust != adjust {
+            self.image_adjust = adjust;
+            self.update_color_params(self.video.color_info);
+        }
+    }
+
+    fn update_color_params(&mut self, color: ColorInfo) {
+        let params = self.layer_color_params(color, true);
+        self.video.set_color_params(&self.queue, color, params);
+        if let Some(color) = self.pip.as_ref().map(|pip| pip.color_info) {
+            let params = self.layer_color_params(color, false);
+            if let Some(pip) = &mut self.pip {
+                pip.set_color_params(&self.queue, color, params);
+            }
+        }
+    }
+
+    // Zebra stripes are a check on the main picture and stay off the PiP.
+    fn layer_color_params(&self, color: ColorInfo, main: bool) -> ColorParams {
+        let mut params = color_params_from_info(
+            color,
+            self.srgb_output(),
+            self.clamp_limited,
+            self.output_gamma,
+            self.image_adjust,
+        );
+        if let Some(threshold) = self.zebra.filter(|_| main) {
+            params.zebra = threshold;
+            params.zebra_phase = (self.zebra_epoch.elapsed().as_secs_f32() * 2.0).fract();
+        }
+        if self.dither && self.output_is_8bit {
+            params.dither = 1.0 / 255.0;
+        }
+        params.gamut = output_gamut(color.matrix, self.wide_gamut);
+        params
+    }
+
+    pub fn render(
+        &mut self,
+        window: &Window,
+        egui_renderer: &mut egui_wgpu::Renderer,
+        textures_delta: &egui::TexturesDelta,
+        clipped_primitives: &[egui::ClippedPrimitive],
+        pixels_per_point: f32,
+    ) -> Result<()> {
+        let output = self.surface.get_current_texture()?;
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder =
+            self.device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("render_encoder"),
+                });
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [self.config.width, self.config.height],
+            pixels_per_point,
+        };
+        for (id, image_delta) in &textures_delta.set {
+            egui_renderer.update_texture(&self.device, &self.queue, *id, image_delta);
+        }
+        self.update_pip_vertices();
+        let has_ui = !clipped_primitives.is_empty();
+        if has_ui {
+            egui_renderer.update_buffers(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                clipped_primitives,
                 &screen_descriptor,
             );
         }
+        // With sharpening or a post shader on, the video goes to an offscreen
+        // target first and the later passes filter it onto the surface, the
+        // post shader last; otherwise it's drawn straight to the surface.
+        let sharpen = self.sharpen > 0.0;
+        if sharpen {
+            self.ensure_sharpen_target();
+        }
+        let post = self.crt.is_some()
+            || self
+                .post_shader
+                .as_ref()
+                .is_some_and(|p| p.pipeline.is_some());
+        if post {
+            self.ensure_post_target();
+            let params = PostParams {
+                resolution: [self.config.width as f32, self.config.height as f32],
+                source_size: self.source_size,
+                video_rect: self.video_rect,
+                time: self.post_epoch.elapsed().as_secs_f32(),
+                _pad: [0.0; 3],
+            };
+            self.queue
+                .write_buffer(&self.post_buffer, 0, bytemuck::bytes_of(&params));
+        }
+        let sharpen_target = self.sharpen_target.as_ref().filter(|_| sharpen);
+        let post_pass = self.post_target.as_ref().zip(self.post_pipeline());
+        if let Some(target) = sharpen_target.or(post_pass.map(|(target, _)| target)) {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("video_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.background),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.draw_video(&self.video, true, &mut rpass);
+        }
+        if let (Some(source), Some((target, _))) = (sharpen_target, post_pass) {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("sharpen_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.background),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(&self.sharpen_pipeline);
+            rpass.set_bind_group(0, &source.bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("render_pass"),
@@ -711,7 +2083,7 @@ impl RenderState {
                     view: &view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        load: wgpu::LoadOp::Clear(self.background),
                         store: wgpu::StoreOp::Store,
                     },
                     depth_slice: None,
@@ -720,23 +2092,27 @@ impl RenderState {
                 timestamp_writes: None,
                 occlusion_query_set: None,
             });
-            match self.video_format {
-                VideoFormat::Rgba => {
-                    rpass.set_pipeline(&self.pipeline_rgba);
-                    rpass.set_bind_group(0, &self.video_bind_group, &[]);
+            match (post_pass, sharpen_target) {
+                (Some((target, (pipeline, extra))), _) => {
+                    rpass.set_pipeline(pipeline);
+                    rpass.set_bind_group(0, &target.bind_group, &[]);
+                    if let Some(bind_group) = extra {
+                        rpass.set_bind_group(1, bind_group, &[]);
+                    }
+                    rpass.draw(0..3, 0..1);
                 }
-                VideoFormat::Yuyv => {
-                    rpass.set_pipeline(&self.pipeline_yuyv);
-                    rpass.set_bind_group(0, &self.video_bind_group, &[]);
-                }
-                VideoFormat::Nv12 => {
-                    rpass.set_pipeline(&self.pipeline_nv12);
-                    rpass.set_bind_group(0, &self.nv12_bind_group, &[]);
+                (None, Some(target)) => {
+                    rpass.set_pipeline(&self.sharpen_pipeline);
+                    rpass.set_bind_group(0, &target.bind_group, &[]);
+                    rpass.draw(0..3, 0..1);
                 }
+                (None, None) => self.draw_video(&self.video, true, &mut rpass),
+            }
+            // The preview goes over the finished picture, so the effects and
+            // screenshots only ever see the main source.
+            if let Some(pip) = self.pip.as_ref().filter(|_| self.pip_layout.is_some()) {
+                self.draw_video(pip, false, &mut rpass);
             }
-            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            rpass.draw_indexed(0..self.num_indices, 0, 0..1);
             if has_ui {
                 let mut rpass = rpass.forget_lifetime();
                 egui_renderer.render(&mut rpass, clipped_primitives, &screen_descriptor);
@@ -776,7 +2152,184 @@ fn create_video_texture(
     (texture, view)
 }
 
-const VIDEO_SHADER: &str = r#"
+fn create_video_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("video_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    })
+}
+
+fn create_nv12_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("nv12_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    })
+}
+
+fn create_video_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+    fs_entry: &str,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module,
+            entry_point: Some("vs_main"),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<Vertex>() as u64,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &[
+                    wgpu::VertexAttribute {
+                        offset: 0,
+                        shader_location: 0,
+                        format: wgpu::VertexFormat::Float32x2,
+                    },
+                    wgpu::VertexAttribute {
+                        offset: 8,
+                        shader_location: 1,
+                        format: wgpu::VertexFormat::Float32x2,
+                    },
+                ],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module,
+            entry_point: Some(fs_entry),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+fn create_sampler(device: &wgpu::Device, filter: wgpu::FilterMode) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: filter,
+        min_filter: filter,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    })
+}
+
+fn create_i420_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    [y_view, u_view, v_view]: [&wgpu::TextureView; 3],
+    sampler: &wgpu::Sampler,
+    color_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("i420_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(y_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(u_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: color_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(v_view),
+            },
+        ],
+    })
+}
+
+// Color conversion and output helpers shared by the video and NV12
+// shaders, which each add their own textures and bind `color` after it.
+const COLOR_SHADER_PRELUDE: &str = r#"
 struct VsOut {
     @builtin(position) pos: vec4<f32>,
     @location(0) uv: vec2<f32>,
@@ -790,27 +2343,174 @@ struct ColorParams {
     m_gv: f32,
     m_bu: f32,
     srgb_output: f32,
-    _pad: f32,
+    clamp_output: f32,
+    gamma: f32,
+    brightness: f32,
+    contrast: f32,
+    saturation: f32,
+    is_hdr: f32,
+    transfer: f32,
+    zebra: f32,
+    zebra_phase: f32,
+    dither: f32,
+    gamut: f32,
+    source_width: f32,
+    _pad0: f32,
 };
 
-@group(0) @binding(0) var video_tex: texture_2d<f32>;
-@group(0) @binding(1) var video_sampler: sampler;
-@group(0) @binding(2) var<uniform> color: ColorParams;
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    let cutoff = vec3<f32>(0.04045);
+    let low = c / 12.92;
+    let high = pow((c + vec3<f32>(0.055)) / 1.055, vec3<f32>(2.4));
+    return select(low, high, c > cutoff);
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    let cutoff = vec3<f32>(0.0031308);
+    let low = c * 12.92;
+    let high = 1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - vec3<f32>(0.055);
+    return select(low, high, c > cutoff);
+}
+
+const TRANSFER_HLG: u32 = 2u;
+const TRANSFER_LINEAR: u32 = 4u;
+const GAMUT_OFF: u32 = 0u;
+const GAMUT_BT2020: u32 = 2u;
+
+// Linear light relative to SDR reference white (203 nits).
+fn pq_eotf(e: vec3<f32>) -> vec3<f32> {
+    let m1 = 0.1593017578125;
+    let m2 = 78.84375;
+    let c1 = 0.8359375;
+    let c2 = 18.8515625;
+    let c3 = 18.6875;
+    let p = pow(max(e, vec3<f32>(0.0)), vec3<f32>(1.0 / m2));
+    let y = pow(max(p - vec3<f32>(c1), vec3<f32>(0.0)) / (vec3<f32>(c2) - c3 * p), vec3<f32>(1.0 / m1));
+    return y * (10000.0 / 203.0);
+}
+
+// HLG inverse OETF plus the reference OOTF for a 1000 nit display.
+fn hlg_eotf(e: vec3<f32>) -> vec3<f32> {
+    let a = 0.17883277;
+    let b = 0.28466892;
+    let c = 0.55991073;
+    let e0 = max(e, vec3<f32>(0.0));
+    let low = e0 * e0 / 3.0;
+    let high = (exp((e0 - vec3<f32>(c)) / a) + vec3<f32>(b)) / 12.0;
+    let scene = select(low, high, e0 > vec3<f32>(0.5));
+    return pow(scene, vec3<f32>(1.2)) * (1000.0 / 203.0);
+}
+
+// Decodes PQ/HLG, moves BT.2020 primaries into BT.709 (or Display P3 for
+// wide gamut output) and tone maps with extended Reinhard, returning
+// sRGB-encoded SDR.
+fn tone_map_hdr(rgb: vec3<f32>) -> vec3<f32> {
+    var lin: vec3<f32>;
+    if u32(color.transfer + 0.5) == TRANSFER_HLG {
+        lin = hlg_eotf(rgb);
+    } else {
+        lin = pq_eotf(rgb);
+    }
+    if u32(color.gamut + 0.5) != GAMUT_OFF {
+        lin = bt2020_to_p3(lin);
+    } else {
+        let to_709 = mat3x3<f32>(
+            vec3<f32>(1.6605, -0.1246, -0.0182),
+            vec3<f32>(-0.5876, 1.1329, -0.1006),
+            vec3<f32>(-0.0728, -0.0083, 1.1187),
+        );
+        lin = to_709 * lin;
+    }
+    lin = max(lin, vec3<f32>(0.0));
+    let white = 1000.0 / 203.0;
+    let mapped = lin * (vec3<f32>(1.0) + lin / (white * white)) / (vec3<f32>(1.0) + lin);
+    return linear_to_srgb(min(mapped, vec3<f32>(1.0)));
+}
 
-fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
-    let cutoff = vec3<f32>(0.04045);
-    let low = c / 12.92;
-    let high = pow((c + vec3<f32>(0.055)) / 1.055, vec3<f32>(2.4));
-    return select(low, high, c > cutoff);
+fn bt2020_to_p3(lin: vec3<f32>) -> vec3<f32> {
+    let m = mat3x3<f32>(
+        vec3<f32>(1.3436, -0.0653, 0.0028),
+        vec3<f32>(-0.2822, 1.0758, -0.0196),
+        vec3<f32>(-0.0614, -0.0105, 1.0168),
+    );
+    return m * lin;
+}
+
+// SDR primaries into Display P3, which shares the sRGB transfer curve.
+fn sdr_to_p3(rgb: vec3<f32>) -> vec3<f32> {
+    var lin = srgb_to_linear(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)));
+    if u32(color.gamut + 0.5) == GAMUT_BT2020 {
+        lin = bt2020_to_p3(lin);
+    } else {
+        let bt709_to_p3 = mat3x3<f32>(
+            vec3<f32>(0.8225, 0.0332, 0.0171),
+            vec3<f32>(0.1775, 0.9668, 0.0724),
+            vec3<f32>(0.0, 0.0, 0.9105),
+        );
+        lin = bt709_to_p3 * lin;
+    }
+    return linear_to_srgb(clamp(lin, vec3<f32>(0.0), vec3<f32>(1.0)));
 }
 
-fn apply_output_color(rgb: vec3<f32>) -> vec3<f32> {
+fn apply_output_color(rgb_in: vec3<f32>, frag: vec2<f32>) -> vec3<f32> {
+    var rgb = rgb_in;
+    // Everything below works on sRGB-encoded values, so bring PQ/HLG and
+    // linear sources into that space first. BT.709 and sRGB already are.
+    if color.is_hdr > 0.5 {
+        rgb = tone_map_hdr(rgb);
+    } else {
+        if u32(color.transfer + 0.5) == TRANSFER_LINEAR {
+            rgb = linear_to_srgb(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)));
+        }
+        if u32(color.gamut + 0.5) != GAMUT_OFF {
+            rgb = sdr_to_p3(rgb);
+        }
+    }
+    rgb = (rgb - vec3<f32>(0.5)) * color.contrast + vec3<f32>(0.5 + color.brightness);
+    if color.saturation != 1.0 {
+        let luma = dot(rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+        rgb = mix(vec3<f32>(luma), rgb, color.saturation);
+    }
+    if color.gamma != 1.0 {
+        rgb = pow(max(rgb, vec3<f32>(0.0)), vec3<f32>(color.gamma));
+    }
+    // Diagonal zebra stripes over anything at or above the threshold,
+    // judged on the final display luma.
+    if color.zebra > 0.0 {
+        let luma = dot(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(0.2126, 0.7152, 0.0722));
+        let stripe = fract((frag.x + frag.y) / 16.0 - color.zebra_phase) < 0.5;
+        if luma >= color.zebra && stripe {
+            rgb = vec3<f32>(0.0);
+        }
+    }
+    // Ordered dither in the encoded domain, so banding in gradients breaks
+    // up before the surface rounds to 8 bits.
+    if color.dither > 0.0 {
+        rgb += vec3<f32>(bayer8(vec2<u32>(frag)) * color.dither);
+    }
     if color.srgb_output > 0.5 {
         return srgb_to_linear(rgb);
     }
     return rgb;
 }
 
+// 8x8 Bayer threshold centred on zero, from interleaving the bits of x ^ y
+// and y in reverse order.
+fn bayer8(p: vec2<u32>) -> f32 {
+    let y = p.y & 7u;
+    let v = (p.x & 7u) ^ y;
+    let m = ((v & 1u) << 5u) | ((y & 1u) << 4u) | ((v & 2u) << 2u) | ((y & 2u) << 1u)
+        | ((v & 4u) >> 1u) | ((y & 4u) >> 2u);
+    return (f32(m) + 0.5) / 64.0 - 0.5;
+}
+
+fn clamp_rgb(rgb: vec3<f32>) -> vec3<f32> {
+    if color.clamp_output > 0.5 {
+        return clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    }
+    return rgb;
+}
+
 @vertex
 fn vs_main(@location(0) pos: vec2<f32>, @location(1) uv: vec2<f32>) -> VsOut {
     var out: VsOut;
@@ -819,139 +2519,760 @@ fn vs_main(@location(0) pos: vec2<f32>, @location(1) uv: vec2<f32>) -> VsOut {
     return out;
 }
 
+// Catmull-Rom over a 4x4 texel neighbourhood; sharper than bilinear when
+// upscaling, at 16 loads per pixel.
+fn catmull_rom_weights(t: f32) -> vec4<f32> {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    return vec4<f32>(
+        -0.5 * t3 + t2 - 0.5 * t,
+        1.5 * t3 - 2.5 * t2 + 1.0,
+        -1.5 * t3 + 2.0 * t2 + 0.5 * t,
+        0.5 * t3 - 0.5 * t2,
+    );
+}
+
+fn sample_cubic(tex: texture_2d<f32>, uv: vec2<f32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(tex));
+    let pos = uv * vec2<f32>(size) - 0.5;
+    let base = vec2<i32>(floor(pos));
+    let f = fract(pos);
+    let wx = catmull_rom_weights(f.x);
+    let wy = catmull_rom_weights(f.y);
+    var sum = vec4<f32>(0.0);
+    for (var j = 0; j < 4; j++) {
+        var row = vec4<f32>(0.0);
+        for (var i = 0; i < 4; i++) {
+            let p = clamp(base + vec2<i32>(i - 1, j - 1), vec2<i32>(0), size - 1);
+            row += textureLoad(tex, p, 0) * wx[i];
+        }
+        sum += row * wy[j];
+    }
+    return sum;
+}
+"#;
+
+const VIDEO_SHADER: &str = r#"
+@group(0) @binding(0) var video_tex: texture_2d<f32>;
+@group(0) @binding(1) var video_sampler: sampler;
+@group(0) @binding(2) var<uniform> color: ColorParams;
+
 @fragment
 fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
     let src = textureSample(video_tex, video_sampler, in.uv);
-    let rgb = apply_output_color(src.rgb);
+    let rgb = apply_output_color(src.rgb, in.pos.xy);
     return vec4<f32>(rgb, src.a);
 }
 
+// Single-channel luma, taken as full range and spread to all three
+// channels like pixel::l8_to_rgba.
 @fragment
-fn fs_yuyv(in: VsOut) -> @location(0) vec4<f32> {
-    let tex_size = textureDimensions(video_tex);
-    let x = clamp(i32(floor(in.uv.x * f32(tex_size.x))), 0, i32(tex_size.x) - 1);
-    let y = clamp(i32(floor(in.uv.y * f32(tex_size.y))), 0, i32(tex_size.y) - 1);
-    let even = (x & 1) == 0;
-    let x_prev = max(x - 1, 0);
-    let x_next = min(x + 1, i32(tex_size.x) - 1);
-    let cur = textureLoad(video_tex, vec2<i32>(x, y), 0).rg;
-    let other = textureLoad(video_tex, vec2<i32>(select(x_prev, x_next, even), y), 0).rg;
-    let yv = cur.r;
-    let u = select(other.g, cur.g, even);
-    let v = select(cur.g, other.g, even);
+fn fs_grey(in: VsOut) -> @location(0) vec4<f32> {
+    let l = textureSample(video_tex, video_sampler, in.uv).r;
+    return vec4<f32>(apply_output_color(vec3<f32>(l), in.pos.xy), 1.0);
+}
+
+@fragment
+fn fs_grey_cubic(in: VsOut) -> @location(0) vec4<f32> {
+    let l = clamp(sample_cubic(video_tex, in.uv).r, 0.0, 1.0);
+    return vec4<f32>(apply_output_color(vec3<f32>(l), in.pos.xy), 1.0);
+}
+
+// RGB565 in an Rg8 texture, low byte in .r. The bits are rebuilt from the
+// two normalized bytes and each pixel is loaded on its own, since filtering
+// the packed bytes would mix bit fields.
+@fragment
+fn fs_rgb565(in: VsOut) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(video_tex));
+    let p = clamp(vec2<i32>(floor(in.uv * vec2<f32>(size))), vec2<i32>(0), size - 1);
+    let bytes = vec2<u32>(round(textureLoad(video_tex, p, 0).rg * 255.0));
+    let bits = bytes.x | (bytes.y << 8u);
+    let r = f32(bits >> 11u) / 31.0;
+    let g = f32((bits >> 5u) & 63u) / 63.0;
+    let b = f32(bits & 31u) / 31.0;
+    return vec4<f32>(apply_output_color(vec3<f32>(r, g, b), in.pos.xy), 1.0);
+}
+
+// The kernel overshoots around edges; clamp before the output transfer.
+@fragment
+fn fs_main_cubic(in: VsOut) -> @location(0) vec4<f32> {
+    let src = clamp(sample_cubic(video_tex, in.uv), vec4<f32>(0.0), vec4<f32>(1.0));
+    let rgb = apply_output_color(src.rgb, in.pos.xy);
+    return vec4<f32>(rgb, src.a);
+}
+
+// Packed 4:2:2 in an Rgba8 texture, one texel per pixel pair so a single
+// load yields both lumas and the pair's shared chroma: YUYV texels read
+// (Y0, U, Y1, V), UYVY texels (U, Y0, V, Y1). With `blend` set the luma is
+// deinterlaced by mixing each line with its neighbours from the other field
+// ([1 2 1] / 4), which removes combing at the cost of vertical detail. Only
+// luma is blended; chroma is already subsampled or averaged enough.
+fn load_422(texel: vec2<i32>, uyvy: bool) -> vec4<f32> {
+    let t = textureLoad(video_tex, texel, 0);
+    return select(t, t.yxwz, uyvy);
+}
+
+fn shade_422(in: VsOut, uyvy: bool, blend: bool) -> vec4<f32> {
+    let uv = in.uv;
+    let tex_size = vec2<i32>(textureDimensions(video_tex));
+    let width = clamp(i32(color.source_width), 1, tex_size.x * 2);
+    let x = clamp(i32(floor(uv.x * f32(width))), 0, width - 1);
+    let y = clamp(i32(floor(uv.y * f32(tex_size.y))), 0, tex_size.y - 1);
+    let odd = (x & 1) == 1;
+    let pair = load_422(vec2<i32>(x >> 1, y), uyvy);
+    var yv = select(pair.x, pair.z, odd);
+    if blend {
+        let above = load_422(vec2<i32>(x >> 1, max(y - 1, 0)), uyvy);
+        let below = load_422(vec2<i32>(x >> 1, min(y + 1, tex_size.y - 1)), uyvy);
+        yv = yv * 0.5 + (select(above.x, above.z, odd) + select(below.x, below.z, odd)) * 0.25;
+    }
     let c = (yv + color.y_offset) * color.y_scale;
+    let d = pair.y - 0.5;
+    let e = pair.w - 0.5;
+    let r = c + color.m_rv * e;
+    let g = c - color.m_gu * d - color.m_gv * e;
+    let b = c + color.m_bu * d;
+    let rgb = apply_output_color(clamp_rgb(vec3<f32>(r, g, b)), in.pos.xy);
+    return vec4<f32>(rgb, 1.0);
+}
+
+@fragment
+fn fs_yuyv(in: VsOut) -> @location(0) vec4<f32> {
+    return shade_422(in, false, false);
+}
+
+@fragment
+fn fs_uyvy(in: VsOut) -> @location(0) vec4<f32> {
+    return shade_422(in, true, false);
+}
+
+@fragment
+fn fs_yuyv_deinterlace(in: VsOut) -> @location(0) vec4<f32> {
+    return shade_422(in, false, true);
+}
+
+@fragment
+fn fs_uyvy_deinterlace(in: VsOut) -> @location(0) vec4<f32> {
+    return shade_422(in, true, true);
+}
+"#;
+
+const NV12_SHADER: &str = r#"
+@group(0) @binding(0) var y_tex: texture_2d<f32>;
+@group(0) @binding(1) var uv_tex: texture_2d<f32>;
+@group(0) @binding(2) var nv_sampler: sampler;
+@group(0) @binding(3) var<uniform> color: ColorParams;
+// Only bound for I420, where uv_tex holds U alone.
+@group(0) @binding(4) var v_tex: texture_2d<f32>;
+
+@fragment
+fn fs_nv12(in: VsOut) -> @location(0) vec4<f32> {
+    let y = textureSample(y_tex, nv_sampler, in.uv).r;
+    let uv = textureSample(uv_tex, nv_sampler, in.uv).rg;
+    let c = (y + color.y_offset) * color.y_scale;
+    let d = uv.x - 0.5;
+    let e = uv.y - 0.5;
+    let r = c + color.m_rv * e;
+    let g = c - color.m_gu * d - color.m_gv * e;
+    let b = c + color.m_bu * d;
+    let rgb = apply_output_color(clamp_rgb(vec3<f32>(r, g, b)), in.pos.xy);
+    return vec4<f32>(rgb, 1.0);
+}
+
+// P010 samples sit in the top 10 bits, so full scale is 0xffc0 rather
+// than 0xffff once normalized.
+@fragment
+fn fs_p010(in: VsOut) -> @location(0) vec4<f32> {
+    let scale = 65535.0 / 65472.0;
+    let y = textureSample(y_tex, nv_sampler, in.uv).r * scale;
+    let uv = textureSample(uv_tex, nv_sampler, in.uv).rg * scale;
+    let c = (y + color.y_offset) * color.y_scale;
+    let d = uv.x - 0.5;
+    let e = uv.y - 0.5;
+    let r = c + color.m_rv * e;
+    let g = c - color.m_gu * d - color.m_gv * e;
+    let b = c + color.m_bu * d;
+    let rgb = apply_output_color(clamp_rgb(vec3<f32>(r, g, b)), in.pos.xy);
+    return vec4<f32>(rgb, 1.0);
+}
+
+@fragment
+fn fs_i420(in: VsOut) -> @location(0) vec4<f32> {
+    let y = textureSample(y_tex, nv_sampler, in.uv).r;
+    let u = textureSample(uv_tex, nv_sampler, in.uv).r;
+    let v = textureSample(v_tex, nv_sampler, in.uv).r;
+    let c = (y + color.y_offset) * color.y_scale;
     let d = u - 0.5;
     let e = v - 0.5;
     let r = c + color.m_rv * e;
     let g = c - color.m_gu * d - color.m_gv * e;
     let b = c + color.m_bu * d;
-    let rgb = apply_output_color(clamp(vec3<f32>(r, g, b), vec3<f32>(0.0), vec3<f32>(1.0)));
+    let rgb = apply_output_color(clamp_rgb(vec3<f32>(r, g, b)), in.pos.xy);
     return vec4<f32>(rgb, 1.0);
 }
+
+fn yuv_to_rgb(y: f32, u: f32, v: f32, frag: vec2<f32>) -> vec4<f32> {
+    let c = (y + color.y_offset) * color.y_scale;
+    let d = u - 0.5;
+    let e = v - 0.5;
+    let r = c + color.m_rv * e;
+    let g = c - color.m_gu * d - color.m_gv * e;
+    let b = c + color.m_bu * d;
+    let rgb = clamp(vec3<f32>(r, g, b), vec3<f32>(0.0), vec3<f32>(1.0));
+    return vec4<f32>(apply_output_color(rgb, frag), 1.0);
+}
+
+// Detail lives in luma, so only Y gets the cubic filter; chroma stays
+// bilinear. The result is always clamped since the kernel overshoots.
+@fragment
+fn fs_nv12_cubic(in: VsOut) -> @location(0) vec4<f32> {
+    let y = sample_cubic(y_tex, in.uv).r;
+    let uv = textureSample(uv_tex, nv_sampler, in.uv).rg;
+    return yuv_to_rgb(y, uv.x, uv.y, in.pos.xy);
+}
+
+@fragment
+fn fs_p010_cubic(in: VsOut) -> @location(0) vec4<f32> {
+    let scale = 65535.0 / 65472.0;
+    let y = sample_cubic(y_tex, in.uv).r * scale;
+    let uv = textureSample(uv_tex, nv_sampler, in.uv).rg * scale;
+    return yuv_to_rgb(y, uv.x, uv.y, in.pos.xy);
+}
+
+@fragment
+fn fs_i420_cubic(in: VsOut) -> @location(0) vec4<f32> {
+    let y = sample_cubic(y_tex, in.uv).r;
+    let u = textureSample(uv_tex, nv_sampler, in.uv).r;
+    let v = textureSample(v_tex, nv_sampler, in.uv).r;
+    return yuv_to_rgb(y, u, v, in.pos.xy);
+}
+
+// Linear blend of each Y line with its neighbours from the other field,
+// see shade_422.
+fn sample_luma_blend(uv: vec2<f32>) -> f32 {
+    let dy = vec2<f32>(0.0, 1.0 / f32(textureDimensions(y_tex).y));
+    let cur = textureSample(y_tex, nv_sampler, uv).r;
+    let above = textureSample(y_tex, nv_sampler, uv - dy).r;
+    let below = textureSample(y_tex, nv_sampler, uv + dy).r;
+    return cur * 0.5 + (above + below) * 0.25;
+}
+
+@fragment
+fn fs_nv12_deinterlace(in: VsOut) -> @location(0) vec4<f32> {
+    let y = sample_luma_blend(in.uv);
+    let uv = textureSample(uv_tex, nv_sampler, in.uv).rg;
+    return yuv_to_rgb(y, uv.x, uv.y, in.pos.xy);
+}
+
+@fragment
+fn fs_p010_deinterlace(in: VsOut) -> @location(0) vec4<f32> {
+    let scale = 65535.0 / 65472.0;
+    let y = sample_luma_blend(in.uv) * scale;
+    let uv = textureSample(uv_tex, nv_sampler, in.uv).rg * scale;
+    return yuv_to_rgb(y, uv.x, uv.y, in.pos.xy);
+}
+
+@fragment
+fn fs_i420_deinterlace(in: VsOut) -> @location(0) vec4<f32> {
+    let y = sample_luma_blend(in.uv);
+    let u = textureSample(uv_tex, nv_sampler, in.uv).r;
+    let v = textureSample(v_tex, nv_sampler, in.uv).r;
+    return yuv_to_rgb(y, u, v, in.pos.xy);
+}
 "#;
 
-const NV12_SHADER: &str = r#"
-struct VsOut {
+// Contrast-adaptive sharpening after AMD's CAS: the sharpening weight
+// shrinks where the 3x3 cross is already near black or white, which keeps
+// it from ringing on hard edges.
+const SHARPEN_SHADER: &str = r#"
+struct SharpenParams {
+    strength: f32,
+    _pad0: f32,
+    _pad1: f32,
+    _pad2: f32,
+};
+
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> params: SharpenParams;
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+    let p = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+    return vec4<f32>(p * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn load(p: vec2<i32>) -> vec3<f32> {
+    let max_p = vec2<i32>(textureDimensions(src_tex)) - 1;
+    return textureLoad(src_tex, clamp(p, vec2<i32>(0), max_p), 0).rgb;
+}
+
+@fragment
+fn fs_sharpen(@builtin(position) frag: vec4<f32>) -> @location(0) vec4<f32> {
+    let p = vec2<i32>(frag.xy);
+    let b = load(p + vec2<i32>(0, -1));
+    let d = load(p + vec2<i32>(-1, 0));
+    let e = load(p);
+    let f = load(p + vec2<i32>(1, 0));
+    let h = load(p + vec2<i32>(0, 1));
+    let mn = min(min(min(b, d), min(f, h)), e);
+    let mx = max(max(max(b, d), max(f, h)), e);
+    let amp = sqrt(clamp(min(mn, 1.0 - mx) / max(mx, vec3<f32>(1e-5)), vec3<f32>(0.0), vec3<f32>(1.0)));
+    let w = amp * (-1.0 / mix(8.0, 5.0, params.strength));
+    let rgb = (e + (b + d + f + h) * w) / (1.0 + 4.0 * w);
+    return vec4<f32>(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+}
+"#;
+
+// Declarations appended to a custom post shader. The shader supplies
+// `@fragment fn fs_main(in: PostIn) -> @location(0) vec4<f32>` and samples
+// the finished window-sized picture through src_tex/src_sampler at in.uv.
+// resolution is the surface size, source_size the cropped picture in source
+// pixels, video_rect where it sits on the surface (left, top, right,
+// bottom) and time the seconds since startup.
+const POST_SHADER_PRELUDE: &str = r#"
+struct PostParams {
+    resolution: vec2<f32>,
+    source_size: vec2<f32>,
+    video_rect: vec4<f32>,
+    time: f32,
+    _pad0: f32,
+    _pad1: f32,
+    _pad2: f32,
+};
+
+struct PostIn {
     @builtin(position) pos: vec4<f32>,
     @location(0) uv: vec2<f32>,
 };
 
-struct ColorParams {
-    y_offset: f32,
-    y_scale: f32,
-    m_rv: f32,
-    m_gu: f32,
-    m_gv: f32,
-    m_bu: f32,
-    srgb_output: f32,
-    _pad: f32,
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: PostParams;
+
+@vertex
+fn vs_post(@builtin(vertex_index) i: u32) -> PostIn {
+    let p = vec2<f32>(f32((i << 1u) & 2u), f32(i & 2u));
+    var out: PostIn;
+    out.pos = vec4<f32>(p * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(p.x, 1.0 - p.y);
+    return out;
+}
+"#;
+
+// Scanlines, phosphor mask, bloom and optional barrel curvature, worked
+// out per screen pixel from where it falls on the source picture.
+// Scanlines fade in as the picture is scaled up, since below 2x there's no
+// room to draw them without beating against the source lines.
+const CRT_SHADER: &str = r#"
+struct CrtParams {
+    scanlines: f32,
+    mask: f32,
+    mask_strength: f32,
+    bloom: f32,
+    curvature: f32,
+    _pad0: f32,
+    _pad1: f32,
+    _pad2: f32,
 };
 
-@group(0) @binding(0) var y_tex: texture_2d<f32>;
-@group(0) @binding(1) var uv_tex: texture_2d<f32>;
-@group(0) @binding(2) var nv_sampler: sampler;
-@group(0) @binding(3) var<uniform> color: ColorParams;
+@group(1) @binding(0) var<uniform> crt: CrtParams;
 
-fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
-    let cutoff = vec3<f32>(0.04045);
-    let low = c / 12.92;
-    let high = pow((c + vec3<f32>(0.055)) / 1.055, vec3<f32>(2.4));
-    return select(low, high, c > cutoff);
+const MASK_APERTURE: u32 = 1u;
+const MASK_SHADOW: u32 = 2u;
+
+fn phosphor_mask(pos: vec2<f32>) -> vec3<f32> {
+    let kind = u32(crt.mask + 0.5);
+    if kind != MASK_APERTURE && kind != MASK_SHADOW {
+        return vec3<f32>(1.0);
+    }
+    var x = u32(pos.x);
+    if kind == MASK_SHADOW && (u32(pos.y) / 2u) % 2u == 1u {
+        x += 2u;
+    }
+    var m = vec3<f32>(1.0 - crt.mask_strength);
+    m[x % 3u] = 1.0;
+    // Win back part of the light the dark stripes take away.
+    return m / (1.0 - crt.mask_strength / 3.0);
 }
 
-fn apply_output_color(rgb: vec3<f32>) -> vec3<f32> {
-    if color.srgb_output > 0.5 {
-        return srgb_to_linear(rgb);
+@fragment
+fn fs_main(in: PostIn) -> @location(0) vec4<f32> {
+    let rect = params.video_rect;
+    let size = rect.zw - rect.xy;
+    var p = (in.pos.xy - rect.xy) / size;
+    if any(p < vec2<f32>(0.0)) || any(p > vec2<f32>(1.0)) {
+        return textureSampleLevel(src_tex, src_sampler, in.uv, 0.0);
+    }
+    if crt.curvature > 0.5 {
+        var c = p * 2.0 - 1.0;
+        let bend = abs(c.yx) / vec2<f32>(5.0, 4.0);
+        c += c * bend * bend;
+        p = c * 0.5 + 0.5;
+    }
+    // Bent past the tube's edge.
+    let outside = any(p < vec2<f32>(0.0)) || any(p > vec2<f32>(1.0));
+    let uv = (rect.xy + p * size) / params.resolution;
+    var rgb = textureSampleLevel(src_tex, src_sampler, uv, 0.0).rgb;
+    if crt.bloom > 0.0 {
+        let texel = size / params.source_size / params.resolution;
+        var glow = vec3<f32>(0.0);
+        for (var i = 0; i < 8; i++) {
+            let a = f32(i) * 0.7853982;
+            let offset = vec2<f32>(cos(a), sin(a)) * texel * 1.5;
+            glow += textureSampleLevel(src_tex, src_sampler, uv + offset, 0.0).rgb;
+        }
+        glow /= 8.0;
+        rgb += glow * glow * crt.bloom;
+    }
+    let zoom = size.y / params.source_size.y;
+    let strength = crt.scanlines * clamp(zoom - 1.0, 0.0, 1.0);
+    if strength > 0.0 {
+        let line = 0.5 - 0.5 * cos(6.2831853 * fract(p.y * params.source_size.y));
+        rgb *= mix(1.0, line, strength) / (1.0 - 0.5 * strength);
+    }
+    rgb *= phosphor_mask(in.pos.xy);
+    if outside {
+        rgb = vec3<f32>(0.0);
+    }
+    return vec4<f32>(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+}
+"#;
+
+impl RenderState {
+    // The custom shader, else the CRT pass with its extra bind group.
+    fn post_pipeline(&self) -> Option<(&wgpu::RenderPipeline, Option<&wgpu::BindGroup>)> {
+        match self.post_shader.as_ref().and_then(|p| p.pipeline.as_ref()) {
+            Some(pipeline) => Some((pipeline, None)),
+            None => self
+                .crt
+                .map(|_| (&self.crt_pipeline, Some(&self.crt_bind_group))),
+        }
+    }
+
+    fn draw_video(&self, layer: &VideoLayer, main: bool, rpass: &mut wgpu::RenderPass<'_>) {
+        if !layer.has_frame || (main && self.blank_video) {
+            return;
+        }
+        self.bind_video_pipeline(layer, main, rpass);
+        rpass.set_vertex_buffer(0, layer.vertex_buffer.slice(..));
+        rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+
+    // (Re)creates the offscreen targets whenever the surface size changed.
+    fn ensure_sharpen_target(&mut self) {
+        let size = (self.config.width, self.config.height);
+        if self.sharpen_target.as_ref().is_some_and(|t| t.size == size) {
+            return;
+        }
+        let view = self.create_offscreen_view("sharpen_source");
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sharpen_bind_group"),
+            layout: &self.sharpen_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.sharpen_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        self.sharpen_target = Some(OffscreenTarget {
+            size,
+            view,
+            bind_group,
+        });
+    }
+
+    fn ensure_post_target(&mut self) {
+        let size = (self.config.width, self.config.height);
+        if self.post_target.as_ref().is_some_and(|t| t.size == size) {
+            return;
+        }
+        let view = self.create_offscreen_view("post_source");
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("post_bind_group"),
+            layout: &self.post_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.post_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.post_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        self.post_target = Some(OffscreenTarget {
+            size,
+            view,
+            bind_group,
+        });
+    }
+
+    fn create_offscreen_view(&self, label: &str) -> wgpu::TextureView {
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: self.config.width,
+                height: self.config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    // The PiP preview is small enough that it always takes the plain
+    // linear pipelines.
+    fn bind_video_pipeline(
+        &self,
+        layer: &VideoLayer,
+        main: bool,
+        rpass: &mut wgpu::RenderPass<'_>,
+    ) {
+        // Integer scaling wants hard pixel edges, which the cubic path
+        // would soften again.
+        let cubic = main
+            && self.scale_filter == ScaleFilter::CatmullRom
+            && self.scale_mode != ScaleMode::Integer;
+        let pick = |linear, sharp| if cubic { sharp } else { linear };
+        // Deinterlacing takes priority over the cubic filter, which would
+        // otherwise sharpen the combing it is meant to hide.
+        let deinterlace = main && self.deinterlace;
+        let yuv = |linear, sharp, blend| {
+            if deinterlace {
+                blend
+            } else {
+                pick(linear, sharp)
+            }
+        };
+        match layer.video_format {
+            VideoFormat::Rgba => {
+                rpass.set_pipeline(pick(&self.pipeline_rgba, &self.pipeline_rgba_cubic));
+                rpass.set_bind_group(0, &layer.video_bind_group, &[]);
+            }
+            VideoFormat::Grey => {
+                rpass.set_pipeline(pick(&self.pipeline_grey, &self.pipeline_grey_cubic));
+                rpass.set_bind_group(0, &layer.video_bind_group, &[]);
+            }
+            VideoFormat::Rgb565 => {
+                rpass.set_pipeline(&self.pipeline_rgb565);
+                rpass.set_bind_group(0, &layer.video_bind_group, &[]);
+            }
+            VideoFormat::Yuyv => {
+                rpass.set_pipeline(yuv(
+                    &self.pipeline_yuyv,
+                    &self.pipeline_yuyv,
+                    &self.pipeline_yuyv_deinterlace,
+                ));
+                rpass.set_bind_group(0, &layer.video_bind_group, &[]);
+            }
+            VideoFormat::Uyvy => {
+                rpass.set_pipeline(yuv(
+                    &self.pipeline_uyvy,
+                    &self.pipeline_uyvy,
+                    &self.pipeline_uyvy_deinterlace,
+                ));
+                rpass.set_bind_group(0, &layer.video_bind_group, &[]);
+            }
+            VideoFormat::Nv12 => {
+                rpass.set_pipeline(yuv(
+                    &self.pipeline_nv12,
+                    &self.pipeline_nv12_cubic,
+                    &self.pipeline_nv12_deinterlace,
+                ));
+                rpass.set_bind_group(0, &layer.nv12_bind_group, &[]);
+            }
+            VideoFormat::P010 => {
+                rpass.set_pipeline(yuv(
+                    &self.pipeline_p010,
+                    &self.pipeline_p010_cubic,
+                    &self.pipeline_p010_deinterlace,
+                ));
+                rpass.set_bind_group(0, &layer.nv12_bind_group, &[]);
+            }
+            VideoFormat::I420 => {
+                rpass.set_pipeline(yuv(
+                    &self.pipeline_i420,
+                    &self.pipeline_i420_cubic,
+                    &self.pipeline_i420_deinterlace,
+                ));
+                rpass.set_bind_group(0, &layer.i420_bind_group, &[]);
+            }
+        }
+    }
+
+    // Renders the current video frame, cropped, at its native size without
+    // the UI and reads it back as RGBA8.
+    pub fn capture_frame(&mut self) -> Result<image::RgbaImage> {
+        let (width, height) = self
+            .output_size()
+            .ok_or_else(|| anyhow!("No video frame to capture"))?;
+        self.read_back_video(width, height)
     }
-    return rgb;
-}
 
-@vertex
-fn vs_main(@location(0) pos: vec2<f32>, @location(1) uv: vec2<f32>) -> VsOut {
-    var out: VsOut;
-    out.pos = vec4<f32>(pos, 0.0, 1.0);
-    out.uv = uv;
-    return out;
-}
+    // Small copy of the current picture for the level scopes; only its
+    // distribution matters, so a few hundred pixels across is plenty.
+    pub fn scope_frame(&mut self) -> Result<image::RgbaImage> {
+        let (width, height) = self
+            .output_size()
+            .ok_or_else(|| anyhow!("No video frame to scope"))?;
+        let scale = (SCOPE_SIZE as f32 / width.max(height) as f32).min(1.0);
+        let width = ((width as f32 * scale).round() as u32).max(1);
+        let height = ((height as f32 * scale).round() as u32).max(1);
+        self.read_back_video(width, height)
+    }
 
-@fragment
-fn fs_nv12(in: VsOut) -> @location(0) vec4<f32> {
-    let y = textureSample(y_tex, nv_sampler, in.uv).r;
-    let uv = textureSample(uv_tex, nv_sampler, in.uv).rg;
-    let c = (y + color.y_offset) * color.y_scale;
-    let d = uv.x - 0.5;
-    let e = uv.y - 0.5;
-    let r = c + color.m_rv * e;
-    let g = c - color.m_gu * d - color.m_gv * e;
-    let b = c + color.m_bu * d;
-    let rgb = apply_output_color(clamp(vec3<f32>(r, g, b), vec3<f32>(0.0), vec3<f32>(1.0)));
-    return vec4<f32>(rgb, 1.0);
-}
-"#;
+    pub fn color_info(&self) -> ColorInfo {
+        self.video.color_info
+    }
 
-impl RenderState {
-    fn write_texture_padded(
-        &mut self,
-        texture: &wgpu::Texture,
-        width: u32,
-        height: u32,
-        bytes_per_row: u32,
-        data: &[u8],
-    ) {
-        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
-        let expected = (bytes_per_row * height) as usize;
-        let needs_pad = bytes_per_row % align != 0;
-        let needs_fill = data.len() < expected;
-        let (data, padded_stride) = if !needs_pad && !needs_fill {
-            self.staging.clear();
-            (data, bytes_per_row)
+    // Cropped and rotated picture size at the source's resolution.
+    fn output_size(&self) -> Option<(u32, u32)> {
+        let crop = self
+            .crop
+            .clamped(self.video.video_size.0, self.video.video_size.1);
+        let width = self
+            .video
+            .video_size
+            .0
+            .saturating_sub(crop.left + crop.right);
+        let height = self
+            .video
+            .video_size
+            .1
+            .saturating_sub(crop.top + crop.bottom);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        if self.rotation.swaps_axes() {
+            Some((height, width))
         } else {
-            let padded_stride = bytes_per_row.div_ceil(align) * align;
-            self.staging
-                .resize((padded_stride * height) as usize, 0);
-            for y in 0..height as usize {
-                let row_start = y * bytes_per_row as usize;
-                if row_start >= data.len() {
-                    break;
-                }
-                let row_end = (row_start + bytes_per_row as usize).min(data.len());
-                let dst = &mut self.staging[y * padded_stride as usize..][..row_end - row_start];
-                dst.copy_from_slice(&data[row_start..row_end]);
-            }
-            (self.staging.as_slice(), padded_stride)
+            Some((width, height))
+        }
+    }
+
+    // Draws the video alone into a `width`x`height` target and reads it
+    // back, without the letterbox or UI.
+    fn read_back_video(&mut self, width: u32, height: u32) -> Result<image::RgbaImage> {
+        let crop = self
+            .crop
+            .clamped(self.video.video_size.0, self.video.video_size.1);
+        let (swap_rb, packed_10bit) = match self.config.format {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => (true, false),
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => (false, false),
+            wgpu::TextureFormat::Rgb10a2Unorm => (false, true),
+            other => return Err(anyhow!("Readback unsupported for surface format {other:?}")),
         };
-        self.queue.write_texture(
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("readback_texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let full_w = self.video.video_size.0 as f32;
+        let full_h = self.video.video_size.1 as f32;
+        let uvs = quad_uvs(
+            (crop.left as f32 / full_w, 1.0 - crop.right as f32 / full_w),
+            (crop.top as f32 / full_h, 1.0 - crop.bottom as f32 / full_h),
+            self.rotation,
+        );
+        let vertices = [
+            Vertex {
+                pos: [-1.0, -1.0],
+                uv: uvs[0],
+            },
+            Vertex {
+                pos: [1.0, -1.0],
+                uv: uvs[1],
+            },
+            Vertex {
+                pos: [1.0, 1.0],
+                uv: uvs[2],
+            },
+            Vertex {
+                pos: [-1.0, 1.0],
+                uv: uvs[3],
+            },
+        ];
+        let vertex_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("readback_vertex_buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+        let unpadded_stride = width * 4;
+        let padded_stride = aligned_stride(unpadded_stride, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback_readback"),
+            size: u64::from(padded_stride) * u64::from(height),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("readback_encoder"),
+            });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("readback_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.background),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.bind_video_pipeline(&self.video, true, &mut rpass);
+            rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+        }
+        encoder.copy_texture_to_buffer(
             wgpu::TexelCopyTextureInfo {
-                texture,
+                texture: &texture,
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            data,
-            wgpu::TexelCopyBufferLayout {
-                offset: 0,
-                bytes_per_row: Some(padded_stride),
-                rows_per_image: Some(height),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_stride),
+                    rows_per_image: Some(height),
+                },
             },
             wgpu::Extent3d {
                 width,
@@ -959,6 +3280,80 @@ impl RenderState {
                 depth_or_array_layers: 1,
             },
         );
+        // Zebra stripes are a viewing aid; keep them out of the readback.
+        // Buffer writes land at the start of the next submit, so the
+        // stripes come back for the following on-screen frame.
+        let zebra = self.video.color_params.zebra > 0.0;
+        if zebra {
+            let params = ColorParams {
+                zebra: 0.0,
+                ..self.video.color_params
+            };
+            self.queue
+                .write_buffer(&self.video.color_buffer, 0, bytemuck::bytes_of(&params));
+        }
+        self.queue.submit(Some(encoder.finish()));
+        if zebra {
+            let params = bytemuck::bytes_of(&self.video.color_params);
+            self.queue.write_buffer(&self.video.color_buffer, 0, params);
+        }
+
+        let slice = readback.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        self.device.poll(wgpu::PollType::wait_indefinitely())?;
+        rx.recv()??;
+        let mut pixels = Vec::with_capacity((unpadded_stride * height) as usize);
+        {
+            let mapped = slice.get_mapped_range();
+            for row in mapped.chunks_exact(padded_stride as usize) {
+                pixels.extend_from_slice(&row[..unpadded_stride as usize]);
+            }
+        }
+        readback.unmap();
+        if swap_rb {
+            for px in pixels.chunks_exact_mut(4) {
+                px.swap(0, 2);
+            }
+        }
+        // Keeps the top 8 of each 10-bit channel.
+        if packed_10bit {
+            for px in pixels.chunks_exact_mut(4) {
+                let v = u32::from_le_bytes([px[0], px[1], px[2], px[3]]);
+                for (i, shift) in [0, 10, 20].into_iter().enumerate() {
+                    px[i] = ((v >> shift & 0x3ff) >> 2) as u8;
+                }
+            }
+        }
+        // Alpha isn't meaningful for video; keep the PNG opaque.
+        for px in pixels.chunks_exact_mut(4) {
+            px[3] = 255;
+        }
+        image::RgbaImage::from_raw(width, height, pixels)
+            .ok_or_else(|| anyhow!("Readback buffer size mismatch"))
+    }
+
+    fn update_pip_vertices(&self) {
+        let (Some(pip), Some(layout)) = (&self.pip, self.pip_layout) else {
+            return;
+        };
+        let window = (self.size.width as f32, self.size.height as f32);
+        if window.0 <= 0.0 || window.1 <= 0.0 {
+            return;
+        }
+        let video = (pip.video_size.0 as f32, pip.video_size.1 as f32);
+        let [left, top, right, bottom] = pip_rect(window, video, layout);
+        let x = |px: f32| px / window.0 * 2.0 - 1.0;
+        let y = |px: f32| 1.0 - px / window.1 * 2.0;
+        let corners = [[left, bottom], [right, bottom], [right, top], [left, top]];
+        let mut vertices = VERTICES;
+        for (vertex, [px, py]) in vertices.iter_mut().zip(corners) {
+            vertex.pos = [x(px), y(py)];
+        }
+        self.queue
+            .write_buffer(&pip.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
     }
 
     fn update_vertices(&mut self) {
@@ -967,14 +3362,31 @@ impl RenderState {
         if window_w <= 0.0 || window_h <= 0.0 {
             return;
         }
-        let (sx, sy) = if self.aspect_correct {
-            let video_w = self.video_size.0 as f32;
-            let video_h = self.video_size.1 as f32;
-            if video_w <= 0.0 || video_h <= 0.0 {
-                return;
+        let crop = self
+            .crop
+            .clamped(self.video.video_size.0, self.video.video_size.1);
+        let full_w = self.video.video_size.0 as f32;
+        let full_h = self.video.video_size.1 as f32;
+        let mut video_w = full_w - (crop.left + crop.right) as f32;
+        let mut video_h = full_h - (crop.top + crop.bottom) as f32;
+        // Forced ratios describe the picture before rotation.
+        let ratio = self.aspect_mode.ratio(video_w / video_h);
+        if self.rotation.swaps_axes() {
+            std::mem::swap(&mut video_w, &mut video_h);
+        }
+        let has_video = video_w > 0.0 && video_h > 0.0;
+        let ratio = ratio.map(|r| {
+            if self.rotation.swaps_axes() {
+                1.0 / r
+            } else {
+                r
             }
+        });
+        if !has_video && (ratio.is_some() || self.scale_mode != ScaleMode::Fit) {
+            return;
+        }
+        let (mut sx, mut sy) = if let Some(video_aspect) = ratio {
             let window_aspect = window_w / window_h;
-            let video_aspect = video_w / video_h;
             if window_aspect >= video_aspect {
                 (video_aspect / window_aspect, 1.0)
             } else {
@@ -983,25 +3395,697 @@ impl RenderState {
         } else {
             (1.0, 1.0)
         };
+        let (mut ox, mut oy) = (0.0, 0.0);
+        if self.scale_mode == ScaleMode::NoUpscale {
+            // Clamp each axis to the source's native pixel size; with aspect
+            // correction both axes shrink by the same factor.
+            sx = sx.min(video_w / window_w);
+            sy = sy.min(video_h / window_h);
+        }
+        // A window smaller than the source keeps the Fit size computed above.
+        if self.scale_mode == ScaleMode::Integer
+            && let Some(factor) = integer_scale((window_w, window_h), (video_w, video_h))
+        {
+            let w = video_w * factor;
+            let h = video_h * factor;
+            sx = w / window_w;
+            sy = h / window_h;
+            // Centering can land the edges on half pixels; nudge the quad
+            // up-left onto whole pixels so each source pixel covers exactly
+            // `factor` screen pixels.
+            ox = -((window_w - w) / 2.0).fract() * 2.0 / window_w;
+            oy = ((window_h - h) / 2.0).fract() * 2.0 / window_h;
+        }
+        self.source_size = [video_w, video_h];
+        self.video_rect = [
+            (ox - sx + 1.0) / 2.0 * window_w,
+            (1.0 - oy - sy) / 2.0 * window_h,
+            (ox + sx + 1.0) / 2.0 * window_w,
+            (1.0 - oy + sy) / 2.0 * window_h,
+        ];
+        let uvs = if has_video {
+            quad_uvs(
+                (crop.left as f32 / full_w, 1.0 - crop.right as f32 / full_w),
+                (crop.top as f32 / full_h, 1.0 - crop.bottom as f32 / full_h),
+                self.rotation,
+            )
+        } else {
+            quad_uvs((0.0, 1.0), (0.0, 1.0), self.rotation)
+        };
         let vertices = [
             Vertex {
-                pos: [-sx, -sy],
-                uv: [0.0, 1.0],
+                pos: [ox - sx, oy - sy],
+                uv: uvs[0],
             },
             Vertex {
-                pos: [sx, -sy],
-                uv: [1.0, 1.0],
+                pos: [ox + sx, oy - sy],
+                uv: uvs[1],
             },
             Vertex {
-                pos: [sx, sy],
-                uv: [1.0, 0.0],
+                pos: [ox + sx, oy + sy],
+                uv: uvs[2],
             },
             Vertex {
-                pos: [-sx, sy],
-                uv: [0.0, 0.0],
+                pos: [ox - sx, oy + sy],
+                uv: uvs[3],
             },
         ];
-        self.queue
-            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.queue.write_buffer(
+            &self.video.vertex_buffer,
+            0,
+            bytemuck::cast_slice(&vertices),
+        );
+    }
+}
+
+// Texture coordinates for the quad corners (bottom-left, bottom-right,
+// top-right, top-left). Rotating the picture clockwise shifts which source
+// corner lands on each screen corner.
+fn quad_uvs((u0, u1): (f32, f32), (v0, v1): (f32, f32), rotation: Rotation) -> [[f32; 2]; 4] {
+    let corners = [[u0, v1], [u1, v1], [u1, v0], [u0, v0]];
+    std::array::from_fn(|i| corners[(i + rotation.quarter_turns()) % 4])
+}
+
+// Where the PiP preview lands in `window`, in pixels (left, top, right,
+// bottom): `layout.size` of the window's width, shrunk to fit its height,
+// with the source's aspect and a margin from the chosen corner.
+fn pip_rect(window: (f32, f32), video: (f32, f32), layout: PipLayout) -> [f32; 4] {
+    const MARGIN: f32 = 16.0;
+    let aspect = video.0 / video.1;
+    let mut w = window.0 * layout.size;
+    let mut h = w / aspect;
+    let max_h = (window.1 - 2.0 * MARGIN).max(1.0);
+    if h > max_h {
+        h = max_h;
+        w = h * aspect;
+    }
+    let left = match layout.corner {
+        Corner::TopLeft | Corner::BottomLeft => MARGIN,
+        Corner::TopRight | Corner::BottomRight => window.0 - MARGIN - w,
+    };
+    let top = match layout.corner {
+        Corner::TopLeft | Corner::TopRight => MARGIN,
+        Corner::BottomLeft | Corner::BottomRight => window.1 - MARGIN - h,
+    };
+    [left, top, left + w, top + h]
+}
+
+// Largest whole multiple of `video` that fits in `window`, if any.
+fn integer_scale(window: (f32, f32), video: (f32, f32)) -> Option<f32> {
+    let factor = (window.0 / video.0).min(window.1 / video.1).floor();
+    (factor >= 1.0).then_some(factor)
+}
+
+fn aligned_stride(bytes_per_row: u32, align: u32) -> u32 {
+    bytes_per_row.div_ceil(align) * align
+}
+
+// Returns the bytes to upload and their row stride. Data is passed through
+// untouched when the stride is already aligned and the buffer is complete;
+// otherwise rows are repacked into `staging` at an aligned stride.
+fn pack_rows<'a>(
+    data: &'a [u8],
+    height: u32,
+    bytes_per_row: u32,
+    align: u32,
+    staging: &'a mut Vec<u8>,
+) -> (&'a [u8], u32) {
+    let expected = (bytes_per_row * height) as usize;
+    let needs_pad = bytes_per_row % align != 0;
+    let needs_fill = data.len() < expected;
+    if !needs_pad && !needs_fill {
+        staging.clear();
+        return (data, bytes_per_row);
+    }
+    let padded_stride = aligned_stride(bytes_per_row, align);
+    staging.resize((padded_stride * height) as usize, 0);
+    for y in 0..height as usize {
+        let row_start = y * bytes_per_row as usize;
+        if row_start >= data.len() {
+            break;
+        }
+        let row_end = (row_start + bytes_per_row as usize).min(data.len());
+        let dst = &mut staging[y * padded_stride as usize..][..row_end - row_start];
+        dst.copy_from_slice(&data[row_start..row_end]);
+    }
+    (staging.as_slice(), padded_stride)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ColorMatrix, ColorRange, ColorTransfer};
+
+    #[test]
+    fn exact_fit_passes_through() {
+        let data = vec![7u8; 256 * 2];
+        let mut staging = vec![1, 2, 3];
+        let (out, stride) = pack_rows(&data, 2, 256, 256, &mut staging);
+        assert_eq!(stride, 256);
+        assert_eq!(out.as_ptr(), data.as_ptr());
+        assert!(staging.is_empty());
+    }
+
+    #[test]
+    fn unaligned_stride_is_padded() {
+        let data: Vec<u8> = (0..6).collect();
+        let mut staging = Vec::new();
+        let (out, stride) = pack_rows(&data, 2, 3, 4, &mut staging);
+        assert_eq!(stride, 4);
+        assert_eq!(out, [0, 1, 2, 0, 3, 4, 5, 0]);
+    }
+
+    #[test]
+    fn short_buffer_is_filled() {
+        // Aligned stride, but the last row and a half are missing.
+        let data = [1u8, 2, 3, 4, 5, 6];
+        let mut staging = Vec::new();
+        let (out, stride) = pack_rows(&data, 3, 4, 4, &mut staging);
+        assert_eq!(stride, 4);
+        assert_eq!(out, [1, 2, 3, 4, 5, 6, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn i420_plane_split() {
+        let mut frame = VideoFrame {
+            width: 4,
+            height: 2,
+            format: VideoFormat::I420,
+            stride: 4,
+            uv_stride: 2,
+            plane_offsets: None,
+            color: ColorInfo::default(),
+            data: FrameData::Owned(Vec::new()),
+            captured_at: std::time::Instant::now(),
+        };
+        let data = [0u8, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2];
+        assert_eq!(
+            i420_planes(&frame, &[&data]),
+            (&data[..8], &data[8..10], &data[10..])
+        );
+        // YV12: V plane first.
+        frame.plane_offsets = Some([0, 10, 8]);
+        assert_eq!(
+            i420_planes(&frame, &[&data]),
+            (&data[..8], &data[10..], &data[8..10])
+        );
+    }
+
+    #[test]
+    fn short_nv12_buffer_is_skipped() {
+        let frame = VideoFrame {
+            width: 4,
+            height: 4,
+            format: VideoFormat::Nv12,
+            stride: 4,
+            uv_stride: 4,
+            plane_offsets: None,
+            color: ColorInfo::default(),
+            data: FrameData::Owned(Vec::new()),
+            captured_at: std::time::Instant::now(),
+        };
+        let data = [0u8; 24];
+        assert!(!nv12_truncated(&frame, &[&data]));
+        // Cut off partway through the last chroma row.
+        assert!(nv12_truncated(&frame, &[&data[..22]]));
+        // Only luma arrived.
+        assert!(nv12_truncated(&frame, &[&data[..16]]));
+        assert!(nv12_truncated(&frame, &[&data[..16], &[]]));
+    }
+
+    #[test]
+    fn integer_scale_fits_whole_multiples() {
+        assert_eq!(integer_scale((1920.0, 1080.0), (320.0, 240.0)), Some(4.0));
+        assert_eq!(integer_scale((1280.0, 720.0), (640.0, 480.0)), Some(1.0));
+        assert_eq!(integer_scale((600.0, 400.0), (640.0, 480.0)), None);
+    }
+
+    #[test]
+    fn pip_sits_in_its_corner() {
+        let layout = PipLayout {
+            corner: Corner::BottomRight,
+            size: 0.25,
+        };
+        let rect = pip_rect((1920.0, 1080.0), (1280.0, 720.0), layout);
+        assert_eq!(rect, [1424.0, 794.0, 1904.0, 1064.0]);
+        // Too tall for the window: shrinks to fit its height instead.
+        let layout = PipLayout {
+            corner: Corner::TopLeft,
+            size: 0.5,
+        };
+        let rect = pip_rect((400.0, 232.0), (100.0, 100.0), layout);
+        assert_eq!(rect, [16.0, 16.0, 216.0, 216.0]);
+    }
+
+    #[test]
+    fn quarter_turn_moves_top_left_to_top_right() {
+        let uvs = quad_uvs((0.0, 1.0), (0.0, 1.0), Rotation::Deg90);
+        // Screen top-right shows the source's top-left.
+        assert_eq!(uvs[2], [0.0, 0.0]);
+        assert_eq!(uvs[0], [1.0, 1.0]);
+        assert_eq!(
+            quad_uvs((0.0, 1.0), (0.0, 1.0), Rotation::Deg180)[3],
+            [1.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn vsync_off_prefers_immediate() {
+        use wgpu::PresentMode::*;
+        assert_eq!(
+            pick_present_mode(&[Fifo, Mailbox, Immediate], false),
+            Immediate
+        );
+        assert_eq!(pick_present_mode(&[Fifo, Mailbox], false), Mailbox);
+        assert_eq!(pick_present_mode(&[Fifo], false), Fifo);
+        assert_eq!(pick_present_mode(&[Fifo, Immediate], true), Fifo);
+    }
+
+    #[test]
+    fn background_is_decoded_for_srgb_targets() {
+        let unorm = clear_color([0, 128, 255], false);
+        assert_eq!((unorm.r, unorm.b), (0.0, 1.0));
+        assert!((unorm.g - 128.0 / 255.0).abs() < 1e-9);
+        let srgb = clear_color([0, 128, 255], true);
+        assert_eq!((srgb.r, srgb.b), (0.0, 1.0));
+        assert!((srgb.g - 0.2159).abs() < 1e-3);
+    }
+
+    #[test]
+    fn hdr_needs_bt2020_and_an_hdr_transfer() {
+        let params = |matrix, transfer| {
+            let color = ColorInfo {
+                matrix,
+                range: ColorRange::Limited,
+                transfer,
+            };
+            color_params_from_info(color, true, true, 1.0, ImageAdjust::default()).is_hdr
+        };
+        assert_eq!(params(ColorMatrix::Bt2020, ColorTransfer::Pq), 1.0);
+        assert_eq!(params(ColorMatrix::Bt2020, ColorTransfer::Bt709), 0.0);
+        assert_eq!(params(ColorMatrix::Bt709, ColorTransfer::Hlg), 0.0);
+    }
+
+    // One plane to upload: bytes, size in texels and format.
+    type Plane<'a> = (&'a [u8], u32, u32, wgpu::TextureFormat);
+
+    // Renders a `width` x `height` frame through a real pipeline into an
+    // offscreen Rgba8Unorm target, one fragment per source pixel, and reads
+    // it back. Planes bind in order ahead of the sampler and color params,
+    // matching the video and NV12 layouts.
+    fn render_offscreen(
+        layout: fn(&wgpu::Device) -> wgpu::BindGroupLayout,
+        shader: &str,
+        fs_entry: &str,
+        color: ColorInfo,
+        planes: &[Plane<'_>],
+        size: (u32, u32),
+    ) -> Vec<u8> {
+        draw_offscreen(layout, shader, fs_entry, color, planes, size, 1).0
+    }
+
+    // As render_offscreen, drawing the frame `draws` times and also
+    // returning how long the GPU took over them.
+    fn draw_offscreen(
+        layout: fn(&wgpu::Device) -> wgpu::BindGroupLayout,
+        shader: &str,
+        fs_entry: &str,
+        color: ColorInfo,
+        planes: &[Plane<'_>],
+        (width, height): (u32, u32),
+        draws: u32,
+    ) -> (Vec<u8>, Duration) {
+        let (device, queue) = pollster::block_on(async {
+            let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions::default())
+                .await
+                .expect("no GPU adapter");
+            adapter
+                .request_device(&wgpu::DeviceDescriptor::default())
+                .await
+                .expect("no GPU device")
+        });
+        let layout = layout(&device);
+        let views: Vec<_> = planes
+            .iter()
+            .map(|&(data, w, h, format)| {
+                let (texture, view) = create_video_texture(&device, w, h, format);
+                let bytes_per_row = data.len() as u32 / h;
+                queue.write_texture(
+                    texture.as_image_copy(),
+                    data,
+                    wgpu::TexelCopyBufferLayout {
+                        offset: 0,
+                        bytes_per_row: Some(bytes_per_row),
+                        rows_per_image: Some(h),
+                    },
+                    texture.size(),
+                );
+                view
+            })
+            .collect();
+        // Nearest keeps subsampled chroma from blending across pixel pairs,
+        // which the CPU references don't do.
+        let sampler = create_sampler(&device, wgpu::FilterMode::Nearest);
+        let mut params = color_params_from_info(color, false, true, 1.0, ImageAdjust::default());
+        params.source_width = width as f32;
+        let color_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let mut entries: Vec<_> = views
+            .iter()
+            .enumerate()
+            .map(|(i, view)| wgpu::BindGroupEntry {
+                binding: i as u32,
+                resource: wgpu::BindingResource::TextureView(view),
+            })
+            .collect();
+        entries.push(wgpu::BindGroupEntry {
+            binding: views.len() as u32,
+            resource: wgpu::BindingResource::Sampler(&sampler),
+        });
+        entries.push(wgpu::BindGroupEntry {
+            binding: views.len() as u32 + 1,
+            resource: color_buffer.as_entire_binding(),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &layout,
+            entries: &entries,
+        });
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(format!("{COLOR_SHADER_PRELUDE}\n{shader}").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let pipeline =
+            create_video_pipeline(&device, "test", &pipeline_layout, &module, fs_entry, format);
+        let vertex = |x: f32, y: f32| Vertex {
+            pos: [x, y],
+            uv: [(x + 1.0) / 2.0, (1.0 - y) / 2.0],
+        };
+        let vertices = [
+            vertex(-1.0, -1.0),
+            vertex(1.0, -1.0),
+            vertex(1.0, 1.0),
+            vertex(-1.0, -1.0),
+            vertex(1.0, 1.0),
+            vertex(-1.0, 1.0),
+        ];
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let stride = aligned_stride(width * 4, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: u64::from(stride) * u64::from(height),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        device.poll(wgpu::PollType::wait_indefinitely()).unwrap();
+        let started = Instant::now();
+        for _ in 0..draws {
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &target_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                rpass.set_pipeline(&pipeline);
+                rpass.set_bind_group(0, &bind_group, &[]);
+                rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                rpass.draw(0..vertices.len() as u32, 0..1);
+            }
+            queue.submit(Some(encoder.finish()));
+        }
+        device.poll(wgpu::PollType::wait_indefinitely()).unwrap();
+        let elapsed = started.elapsed();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_texture_to_buffer(
+            target.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(stride),
+                    rows_per_image: Some(height),
+                },
+            },
+            target.size(),
+        );
+        queue.submit(Some(encoder.finish()));
+        let slice = readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("readback failed")
+        });
+        device.poll(wgpu::PollType::wait_indefinitely()).unwrap();
+        let mapped = slice.get_mapped_range();
+        let pixels = mapped
+            .chunks_exact(stride as usize)
+            .flat_map(|row| row[..(width * 4) as usize].to_vec())
+            .collect();
+        (pixels, elapsed)
+    }
+
+    // The shaders use float matrices, the CPU references integer ones.
+    fn assert_close(gpu: &[u8], cpu: &[u8], what: &str) {
+        assert_eq!(gpu.len(), cpu.len(), "{what}");
+        for (i, (g, c)) in gpu.iter().zip(cpu).enumerate() {
+            assert!(g.abs_diff(*c) <= 3, "{what}: byte {i} is {g}, expected {c}");
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn shaders_match_cpu_reference() {
+        let (w, h) = (8u32, 2u32);
+        let color = ColorInfo {
+            matrix: ColorMatrix::Bt601,
+            range: ColorRange::Limited,
+            transfer: ColorTransfer::Bt709,
+        };
+        let luma: Vec<u8> = [
+            16, 40, 80, 120, 160, 200, 235, 128, 128, 235, 200, 160, 120, 80, 40, 16,
+        ]
+        .into();
+        // Chroma is constant per frame so any nearest/centre rounding in the
+        // subsampled planes can't pick a different sample than the CPU does.
+        for (u, v) in [(128u8, 128u8), (90, 200), (220, 60)] {
+            let yuyv: Vec<u8> = luma
+                .chunks_exact(2)
+                .flat_map(|y| [y[0], u, y[1], v])
+                .collect();
+            let gpu = render_offscreen(
+                create_video_bind_group_layout,
+                VIDEO_SHADER,
+                "fs_yuyv",
+                color,
+                &[(&yuyv, w / 2, h, wgpu::TextureFormat::Rgba8Unorm)],
+                (w, h),
+            );
+            assert_close(
+                &gpu,
+                &pixel::yuyv_to_rgba(w, h, (w * 2) as usize, &yuyv),
+                "yuyv",
+            );
+
+            let uyvy: Vec<u8> = luma
+                .chunks_exact(2)
+                .flat_map(|y| [u, y[0], v, y[1]])
+                .collect();
+            let gpu = render_offscreen(
+                create_video_bind_group_layout,
+                VIDEO_SHADER,
+                "fs_uyvy",
+                color,
+                &[(&uyvy, w / 2, h, wgpu::TextureFormat::Rgba8Unorm)],
+                (w, h),
+            );
+            assert_close(
+                &gpu,
+                &pixel::uyvy_to_rgba(w, h, (w * 2) as usize, &uyvy),
+                "uyvy",
+            );
+
+            let chroma: Vec<u8> = [u, v].repeat((w / 2 * h / 2) as usize);
+            let nv12 = [luma.as_slice(), &chroma].concat();
+            let gpu = render_offscreen(
+                create_nv12_bind_group_layout,
+                NV12_SHADER,
+                "fs_nv12",
+                color,
+                &[
+                    (&luma, w, h, wgpu::TextureFormat::R8Unorm),
+                    (&chroma, w / 2, h / 2, wgpu::TextureFormat::Rg8Unorm),
+                ],
+                (w, h),
+            );
+            assert_close(
+                &gpu,
+                &pixel::nv12_to_rgba(w, h, w as usize, w as usize, &nv12),
+                "nv12",
+            );
+        }
+
+        let gpu = render_offscreen(
+            create_video_bind_group_layout,
+            VIDEO_SHADER,
+            "fs_grey",
+            color,
+            &[(&luma, w, h, wgpu::TextureFormat::R8Unorm)],
+            (w, h),
+        );
+        let grey: Vec<u8> = luma.iter().flat_map(|&l| [l, l, l, 255]).collect();
+        assert_close(&gpu, &grey, "grey");
+
+        let rgb565: Vec<u8> = (0..w * h * 2).map(|i| (i * 37 + 11) as u8).collect();
+        let gpu = render_offscreen(
+            create_video_bind_group_layout,
+            VIDEO_SHADER,
+            "fs_rgb565",
+            color,
+            &[(&rgb565, w, h, wgpu::TextureFormat::Rg8Unorm)],
+            (w, h),
+        );
+        assert_close(
+            &gpu,
+            &pixel::rgb565_to_rgba(w, h, (w * 2) as usize, &rgb565),
+            "rgb565",
+        );
+
+        let bgra: Vec<u8> = (0..w * h * 4).map(|i| (i * 7) as u8).collect();
+        let rgba = pixel::bgra_to_rgba(w, h, (w * 4) as usize, false, &bgra);
+        let gpu = render_offscreen(
+            create_video_bind_group_layout,
+            VIDEO_SHADER,
+            "fs_main",
+            ColorInfo {
+                range: ColorRange::Full,
+                ..color
+            },
+            &[(&rgba, w, h, wgpu::TextureFormat::Rgba8Unorm)],
+            (w, h),
+        );
+        let opaque: Vec<u8> = rgba
+            .chunks_exact(4)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect();
+        let gpu_opaque: Vec<u8> = gpu
+            .chunks_exact(4)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect();
+        assert_close(&gpu_opaque, &opaque, "rgba");
+
+        // An odd width still maps one fragment to each source pixel even
+        // though the texture holds a spare one.
+        let w = 7u32;
+        let yuyv: Vec<u8> = luma
+            .chunks_exact(2)
+            .flat_map(|y| [y[0], 90, y[1], 200])
+            .collect();
+        let gpu = render_offscreen(
+            create_video_bind_group_layout,
+            VIDEO_SHADER,
+            "fs_yuyv",
+            color,
+            &[(&yuyv, w.div_ceil(2), h, wgpu::TextureFormat::Rgba8Unorm)],
+            (w, h),
+        );
+        let cpu = pixel::yuyv_to_rgba(w, h, (w.div_ceil(2) * 4) as usize, &yuyv);
+        assert_close(&gpu, &cpu, "odd yuyv");
+    }
+
+    // The Rg8 layout 4:2:2 used before pixel pairs were packed into one
+    // Rgba8 texel; two loads per pixel. Kept to compare against.
+    const RG8_422_SHADER: &str = r#"
+@fragment
+fn fs_yuyv_rg8(in: VsOut) -> @location(0) vec4<f32> {
+    let tex_size = vec2<i32>(textureDimensions(video_tex));
+    let x = clamp(i32(floor(in.uv.x * f32(tex_size.x))), 0, tex_size.x - 1);
+    let y = clamp(i32(floor(in.uv.y * f32(tex_size.y))), 0, tex_size.y - 1);
+    let even = (x & 1) == 0;
+    let other_x = select(max(x - 1, 0), min(x + 1, tex_size.x - 1), even);
+    let cur = textureLoad(video_tex, vec2<i32>(x, y), 0).rg;
+    let other = textureLoad(video_tex, vec2<i32>(other_x, y), 0).rg;
+    let u = select(other.g, cur.g, even);
+    let v = select(cur.g, other.g, even);
+    let c = (cur.r + color.y_offset) * color.y_scale;
+    let d = u - 0.5;
+    let e = v - 0.5;
+    let r = c + color.m_rv * e;
+    let g = c - color.m_gu * d - color.m_gv * e;
+    let b = c + color.m_bu * d;
+    let rgb = apply_output_color(clamp_rgb(vec3<f32>(r, g, b)), in.pos.xy);
+    return vec4<f32>(rgb, 1.0);
+}
+"#;
+
+    // Run with --ignored --nocapture on the machine in question; compares
+    // the packed Rgba8 4:2:2 path against the Rg8 one at 4K.
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn bench_packed_422() {
+        let (w, h) = (3840u32, 2160u32);
+        let draws = 100;
+        let color = ColorInfo {
+            matrix: ColorMatrix::Bt709,
+            range: ColorRange::Limited,
+            transfer: ColorTransfer::Bt709,
+        };
+        let yuyv: Vec<u8> = (0..w * h * 2).map(|i| (i * 13 + i / 7) as u8).collect();
+        let rg8_shader = format!("{VIDEO_SHADER}{RG8_422_SHADER}");
+        let per_frame = |shader: &str, entry, texel_width, format| {
+            let planes = [(yuyv.as_slice(), texel_width, h, format)];
+            let layout = create_video_bind_group_layout;
+            let (pixels, elapsed) =
+                draw_offscreen(layout, shader, entry, color, &planes, (w, h), draws);
+            (pixels, elapsed.as_secs_f64() * 1000.0 / f64::from(draws))
+        };
+        let (packed, packed_ms) = per_frame(
+            VIDEO_SHADER,
+            "fs_yuyv",
+            w / 2,
+            wgpu::TextureFormat::Rgba8Unorm,
+        );
+        let (rg8, rg8_ms) = per_frame(&rg8_shader, "fs_yuyv_rg8", w, wgpu::TextureFormat::Rg8Unorm);
+        println!("4K yuyv: packed rgba8 {packed_ms:.3} ms/frame, rg8 {rg8_ms:.3} ms/frame");
+        assert_close(&packed, &rg8, "packed vs rg8");
     }
 }
@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::types::{FrameData, VideoFrame};
+
+// Memory cap for held frames. 4K NV12 is ~12 MB a frame, so this keeps a
+// second or two of it and far more at lower resolutions.
+pub const DEFAULT_BUDGET: usize = 512 * 1024 * 1024;
+
+// Rolling window of recently shown frames to step back through while
+// paused. Bounded by age and by the total bytes held; the oldest frames go
+// first.
+pub struct ReplayBuffer {
+    frames: VecDeque<VideoFrame>,
+    bytes: usize,
+    budget: usize,
+    window: Duration,
+}
+
+impl ReplayBuffer {
+    pub fn new(window: Duration, budget: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            bytes: 0,
+            budget,
+            window,
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    // A zero window turns the buffer off.
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+        self.evict();
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&VideoFrame> {
+        self.frames.get(index)
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.bytes = 0;
+    }
+
+    pub fn push(&mut self, frame: &VideoFrame) {
+        if self.window.is_zero() {
+            return;
+        }
+        let Some(frame) = owned_copy(frame) else {
+            return;
+        };
+        let size = frame_bytes(&frame);
+        if size > self.budget {
+            return;
+        }
+        self.bytes += size;
+        self.frames.push_back(frame);
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        let Some(newest) = self.frames.back().map(|f| f.captured_at) else {
+            return;
+        };
+        while let Some(oldest) = self.frames.front() {
+            let too_old = newest.duration_since(oldest.captured_at) > self.window;
+            if self.bytes <= self.budget && !too_old && !self.window.is_zero() {
+                break;
+            }
+            self.bytes -= frame_bytes(oldest);
+            self.frames.pop_front();
+        }
+    }
+}
+
+fn frame_bytes(frame: &VideoFrame) -> usize {
+    match &frame.data {
+        FrameData::Owned(data) => data.len(),
+        FrameData::Planes(planes) => planes.iter().map(Vec::len).sum(),
+        #[cfg(target_os = "linux")]
+        FrameData::Gst(buffer) => buffer.size(),
+    }
+}
+
+// GStreamer buffers are copied out so held frames don't starve the
+// capture's buffer pool; other data is cloned as is.
+fn owned_copy(frame: &VideoFrame) -> Option<VideoFrame> {
+    #[cfg(target_os = "linux")]
+    if let FrameData::Gst(buffer) = &frame.data {
+        let map = buffer.map_readable().ok()?;
+        return Some(VideoFrame {
+            width: frame.width,
+            height: frame.height,
+            format: frame.format,
+            stride: frame.stride,
+            uv_stride: frame.uv_stride,
+            plane_offsets: frame.plane_offsets,
+            color: frame.color,
+            data: FrameData::Owned(map.as_slice().to_vec()),
+            captured_at: frame.captured_at,
+        });
+    }
+    Some(frame.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    use crate::types::{ColorInfo, VideoFormat};
+
+    fn frame(bytes: usize, captured_at: Instant) -> VideoFrame {
+        VideoFrame {
+            width: 1,
+            height: 1,
+            format: VideoFormat::Rgba,
+            stride: 4,
+            uv_stride: 0,
+            plane_offsets: None,
+            color: ColorInfo::default(),
+            data: FrameData::Owned(vec![0; bytes]),
+            captured_at,
+        }
+    }
+
+    #[test]
+    fn drops_oldest_over_budget() {
+        let now = Instant::now();
+        let mut replay = ReplayBuffer::new(Duration::from_secs(10), 100);
+        for _ in 0..3 {
+            replay.push(&frame(40, now));
+        }
+        assert_eq!(replay.len(), 2);
+        replay.push(&frame(200, now));
+        assert_eq!(replay.len(), 2);
+    }
+
+    #[test]
+    fn drops_frames_outside_the_window() {
+        let start = Instant::now();
+        let mut replay = ReplayBuffer::new(Duration::from_secs(2), 1000);
+        for secs in 0..5 {
+            replay.push(&frame(1, start + Duration::from_secs(secs)));
+        }
+        assert_eq!(replay.len(), 3);
+        assert_eq!(
+            replay.get(0).unwrap().captured_at,
+            start + Duration::from_secs(2)
+        );
+        replay.set_window(Duration::ZERO);
+        assert!(replay.is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+use crate::types::ColorRange;
+
+// Counts per 8-bit level for red, green, blue and luma, binned by the code
+// values the source signal would carry.
+pub struct Histogram {
+    pub channels: [[u32; 256]; 4],
+    pub range: ColorRange,
+}
+
+impl Histogram {
+    // `rgba` is the rendered picture, which is always full range; limited
+    // sources are mapped back onto 16-235 so their levels read as sent.
+    pub fn from_rgba(rgba: &[u8], range: ColorRange) -> Self {
+        let mut channels = [[0u32; 256]; 4];
+        for px in rgba.chunks_exact(4) {
+            let (r, g, b) = (u32::from(px[0]), u32::from(px[1]), u32::from(px[2]));
+            let luma = ((2126 * r + 7152 * g + 722 * b + 5000) / 10000) as u8;
+            for (bins, value) in channels.iter_mut().zip([px[0], px[1], px[2], luma]) {
+                bins[usize::from(signal_level(value, range))] += 1;
+            }
+        }
+        Self { channels, range }
+    }
+
+    // Nominal black and white codes.
+    pub fn bounds(&self) -> (u8, u8) {
+        match self.range {
+            ColorRange::Limited => (16, 235),
+            ColorRange::Full => (0, 255),
+        }
+    }
+
+    // Tallest bin strictly between black and white, so a letterbox or a
+    // blown-out sky doesn't flatten the rest of the graph.
+    pub fn peak(&self) -> u32 {
+        let (black, white) = self.bounds();
+        let inner = usize::from(black) + 1..usize::from(white);
+        self.channels
+            .iter()
+            .flat_map(|bins| bins[inner.clone()].iter().copied())
+            .max()
+            .unwrap_or(0)
+            .max(1)
+    }
+}
+
+fn signal_level(value: u8, range: ColorRange) -> u8 {
+    match range {
+        ColorRange::Limited => (16 + (u32::from(value) * 219 + 127) / 255) as u8,
+        ColorRange::Full => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limited_sources_bin_into_studio_levels() {
+        let rgba = [0, 0, 0, 255, 255, 255, 255, 255, 255, 0, 0, 255];
+        let full = Histogram::from_rgba(&rgba, ColorRange::Full);
+        assert_eq!(full.channels[1][0], 2);
+        assert_eq!(full.channels[1][255], 1);
+        assert_eq!(full.channels[3][54], 1);
+        let limited = Histogram::from_rgba(&rgba, ColorRange::Limited);
+        assert_eq!(limited.channels[1][16], 2);
+        assert_eq!(limited.channels[1][235], 1);
+        assert_eq!(limited.channels[3][62], 1);
+        assert_eq!(limited.peak(), 1);
+    }
+}
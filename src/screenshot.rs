@@ -0,0 +1,94 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+
+pub fn save_png(image: &image::RgbaImage) -> Result<PathBuf> {
+    let dir = user_dir("XDG_PICTURES_DIR", "Pictures");
+    std::fs::create_dir_all(&dir)?;
+    let path = unique_path(&dir, "png");
+    image.save_with_format(&path, image::ImageFormat::Png)?;
+    Ok(path)
+}
+
+// A timestamped file in `dir` that doesn't exist yet; several files within
+// the same second get a numeric suffix.
+pub fn unique_path(dir: &Path, extension: &str) -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut path = dir.join(file_name(secs, 0, extension));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(file_name(secs, n, extension));
+        n += 1;
+    }
+    path
+}
+
+// `<user folder>/CaptureCardGaming`, e.g. ~/Pictures or %USERPROFILE%\Videos.
+#[cfg_attr(target_os = "windows", allow(unused_variables))]
+pub fn user_dir(xdg_var: &str, folder: &str) -> PathBuf {
+    #[cfg(target_os = "windows")]
+    let base = std::env::var_os("USERPROFILE").map(|h| PathBuf::from(h).join(folder));
+    #[cfg(not(target_os = "windows"))]
+    let base = std::env::var_os(xdg_var)
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(folder)));
+    base.unwrap_or_else(|| PathBuf::from("."))
+        .join("CaptureCardGaming")
+}
+
+// UTC timestamp, e.g. CaptureCardGaming-20240131-235959.png.
+fn file_name(unix_secs: u64, n: u32, extension: &str) -> String {
+    let days = (unix_secs / 86_400) as i64;
+    let rem = unix_secs % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    let suffix = if n > 0 {
+        format!("-{n}")
+    } else {
+        String::new()
+    };
+    format!(
+        "CaptureCardGaming-{year:04}{month:02}{day:02}-{:02}{:02}{:02}{suffix}.{extension}",
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+// Days since 1970-01-01 to a proleptic Gregorian date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamped_names() {
+        assert_eq!(
+            file_name(0, 0, "png"),
+            "CaptureCardGaming-19700101-000000.png"
+        );
+        assert_eq!(
+            file_name(951_782_400, 0, "png"),
+            "CaptureCardGaming-20000229-000000.png"
+        );
+        assert_eq!(
+            file_name(1_706_745_599, 2, "mp4"),
+            "CaptureCardGaming-20240131-235959-2.mp4"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+
+use crate::view::CropRect;
+
+// Small INI-style store: `[section]` headers followed by `key=value` lines.
+#[derive(Default)]
+pub struct Settings {
+    path: Option<PathBuf>,
+    sections: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let path = settings_path();
+        let text = path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            sections: parse(&text),
+        }
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.sections.get(section)?.get(key).map(String::as_str)
+    }
+
+    pub fn get_parsed<T: std::str::FromStr>(&self, section: &str, key: &str) -> Option<T> {
+        self.get(section, key)?.parse().ok()
+    }
+
+    pub fn set(&mut self, section: &str, key: &str, value: impl ToString) {
+        self.sections
+            .entry(section.to_string())
+            .or_default()
+            .insert(key.to_string(), value.to_string());
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("No settings directory"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("ini.tmp");
+        std::fs::write(&tmp, serialize(&self.sections))?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+// Colors are stored as `#rrggbb`.
+pub fn parse_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+pub fn format_color([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+// Crop insets are stored as `left,top,right,bottom`.
+pub fn parse_crop(value: &str) -> Option<CropRect> {
+    let mut parts = value.split(',').map(|v| v.trim().parse::<u32>().ok());
+    let crop = CropRect {
+        left: parts.next()??,
+        top: parts.next()??,
+        right: parts.next()??,
+        bottom: parts.next()??,
+    };
+    parts.next().is_none().then_some(crop)
+}
+
+pub fn format_crop(crop: CropRect) -> String {
+    format!("{},{},{},{}", crop.left, crop.top, crop.right, crop.bottom)
+}
+
+fn settings_path() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let base = std::env::var_os("APPDATA").map(PathBuf::from);
+    #[cfg(not(target_os = "windows"))]
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")));
+    base.map(|b| b.join("CaptureCardGaming").join("settings.ini"))
+}
+
+fn parse(text: &str) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut sections: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    let mut current = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = name.to_string();
+        } else if let Some((key, value)) = line.split_once('=') {
+            sections
+                .entry(current.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    sections
+}
+
+fn serialize(sections: &BTreeMap<String, BTreeMap<String, String>>) -> String {
+    let mut out = String::new();
+    for (name, values) in sections {
+        if !name.is_empty() {
+            out.push_str(&format!("[{name}]\n"));
+        }
+        for (key, value) in values {
+            out.push_str(&format!("{key}={value}\n"));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let text = "[audio:hw:1,0]\nmuted=true\n\n[window]\nscale = fit\n";
+        let sections = parse(text);
+        assert_eq!(sections["audio:hw:1,0"]["muted"], "true");
+        assert_eq!(sections["window"]["scale"], "fit");
+        assert_eq!(parse(&serialize(&sections)), sections);
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#20a0ff"), Some([0x20, 0xa0, 0xff]));
+        assert_eq!(format_color([0x20, 0xa0, 0xff]), "#20a0ff");
+        assert_eq!(parse_color("20a0ff"), None);
+        assert_eq!(parse_color("#20a0f"), None);
+        assert_eq!(parse_color("#20a0fg"), None);
+    }
+
+    #[test]
+    fn crops() {
+        let crop = CropRect {
+            left: 8,
+            top: 0,
+            right: 8,
+            bottom: 2,
+        };
+        assert_eq!(parse_crop(&format_crop(crop)), Some(crop));
+        assert_eq!(parse_crop("8,0,8"), None);
+        assert_eq!(parse_crop("8,0,8,2,1"), None);
+        assert_eq!(parse_crop("8,0,-8,2"), None);
+    }
+}
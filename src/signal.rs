@@ -0,0 +1,54 @@
+use std::time::{Duration, Instant};
+
+// Notices a capture that stays open but stops delivering frames, as some
+// cards do when the source is switched off with the cable still in. Goes by
+// the frame count rather than the picture, since a black frame is still a
+// signal.
+pub struct SignalWatch {
+    count: u64,
+    since: Instant,
+}
+
+impl SignalWatch {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            count: 0,
+            since: now,
+        }
+    }
+
+    // Starts the timeout over, for when frames are held back on purpose.
+    pub fn restart(&mut self, count: u64, now: Instant) {
+        self.count = count;
+        self.since = now;
+    }
+
+    // `count` is the capture's running frame total; true once it hasn't
+    // moved for `timeout`.
+    pub fn update(&mut self, count: u64, now: Instant, timeout: Duration) -> bool {
+        if count != self.count {
+            self.restart(count, now);
+        }
+        now.duration_since(self.since) >= timeout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stalls_until_frames_resume() {
+        let start = Instant::now();
+        let timeout = Duration::from_secs(2);
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut watch = SignalWatch::new(start);
+        assert!(!watch.update(0, at(1999), timeout));
+        assert!(watch.update(0, at(2000), timeout));
+        assert!(!watch.update(1, at(2500), timeout));
+        assert!(!watch.update(1, at(4000), timeout));
+        assert!(watch.update(1, at(4500), timeout));
+        watch.restart(1, at(5000));
+        assert!(!watch.update(1, at(6000), timeout));
+    }
+}
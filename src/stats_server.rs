@@ -0,0 +1,156 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+
+pub const PORT_ENV: &str = "CAPTURECARDGAMING_STATS_PORT";
+
+// Clients are served one at a time, so one that stalls mustn't hold the
+// endpoint for longer than this.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, Default)]
+pub struct StatsReport {
+    pub capturing: bool,
+    pub device: String,
+    pub width: u32,
+    pub height: u32,
+    pub format: String,
+    pub fps: f32,
+    pub drops_per_s: f32,
+    pub decode_us: u64,
+}
+
+impl StatsReport {
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"capturing\":{},\"device\":\"{}\",\"width\":{},\"height\":{},\"format\":\"{}\",\"fps\":{},\"drops_per_s\":{},\"decode_us\":{}}}",
+            self.capturing,
+            json_escape(&self.device),
+            self.width,
+            self.height,
+            json_escape(&self.format),
+            json_number(self.fps),
+            json_number(self.drops_per_s),
+            self.decode_us
+        )
+    }
+}
+
+// JSON has no NaN or infinity.
+fn json_number(value: f32) -> String {
+    if value.is_finite() {
+        format!("{value:.1}")
+    } else {
+        "null".to_string()
+    }
+}
+
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+pub struct StatsServer {
+    report: Arc<Mutex<StatsReport>>,
+}
+
+impl StatsServer {
+    pub fn from_env() -> Option<Result<Self>> {
+        let port = std::env::var(PORT_ENV).ok()?;
+        Some(
+            port.trim()
+                .parse::<u16>()
+                .map_err(|_| anyhow::anyhow!("{PORT_ENV}: invalid port {port:?}"))
+                .and_then(Self::start),
+        )
+    }
+
+    pub fn start(port: u16) -> Result<Self> {
+        // Localhost only; this is meant for tools running on the same machine.
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let report = Arc::new(Mutex::new(StatsReport::default()));
+        let shared = report.clone();
+        std::thread::Builder::new()
+            .name("stats-server".to_string())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let _ = handle_client(stream, &shared);
+                }
+            })?;
+        Ok(Self { report })
+    }
+
+    pub fn publish(&self, report: StatsReport) {
+        if let Ok(mut current) = self.report.lock() {
+            *current = report;
+        }
+    }
+}
+
+fn handle_client(mut stream: TcpStream, report: &Mutex<StatsReport>) -> Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+    let (status, body) = if path == "/" || path == "/stats" {
+        let json = report.lock().map(|r| r.to_json()).unwrap_or_default();
+        ("200 OK", json)
+    } else {
+        ("404 Not Found", "{\"error\":\"not found\"}".to_string())
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_json_escapes_strings() {
+        let report = StatsReport {
+            capturing: true,
+            device: "Cam \"Link\"".to_string(),
+            width: 1920,
+            height: 1080,
+            format: "NV12".to_string(),
+            fps: 59.94,
+            drops_per_s: 0.0,
+            decode_us: 120,
+        };
+        assert_eq!(
+            report.to_json(),
+            "{\"capturing\":true,\"device\":\"Cam \\\"Link\\\"\",\"width\":1920,\"height\":1080,\"format\":\"NV12\",\"fps\":59.9,\"drops_per_s\":0.0,\"decode_us\":120}"
+        );
+    }
+
+    #[test]
+    fn non_finite_rates_are_null() {
+        let report = StatsReport {
+            fps: f32::NAN,
+            drops_per_s: f32::INFINITY,
+            ..Default::default()
+        };
+        let json = report.to_json();
+        assert!(
+            json.contains("\"fps\":null,\"drops_per_s\":null,"),
+            "{json}"
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 #[derive(Clone, Debug)]
 pub struct DeviceInfo {
     pub id: String,
@@ -8,7 +10,46 @@ pub struct DeviceInfo {
 pub enum VideoFormat {
     Rgba,
     Yuyv,
+    // YUYV with luma and chroma bytes swapped (U0 Y0 V0 Y1).
+    Uyvy,
     Nv12,
+    // Three-plane 4:2:0 (Y, U, V). YV12 is carried as I420 with the chroma
+    // plane offsets swapped.
+    I420,
+    // NV12 layout with 16-bit little-endian samples, 10 significant bits
+    // in the high end.
+    P010,
+    // 8-bit luma only (V4L2 GREY), shown as grayscale.
+    Grey,
+    // 16-bit little-endian pixels, 5 bits red (high), 6 green, 5 blue.
+    Rgb565,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deinterlace {
+    Auto,
+    On,
+    Off,
+}
+
+impl Deinterlace {
+    pub fn applies(self, interlaced: bool) -> bool {
+        match self {
+            Deinterlace::Auto => interlaced,
+            Deinterlace::On => true,
+            Deinterlace::Off => false,
+        }
+    }
+}
+
+// What a capture does with frames its consumer hasn't taken yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FramePolicy {
+    // Only the newest frame waits; a new one replaces it.
+    LowLatency,
+    // A few frames queue up to be shown in order, evening out hiccups at
+    // the cost of latency. New frames are dropped while the queue is full.
+    Smooth,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -24,10 +65,22 @@ pub enum ColorRange {
     Full,
 }
 
+// Transfer function the source was encoded with. Unknown transfers are
+// reported as Bt709.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorTransfer {
+    Bt709,
+    Srgb,
+    Pq,
+    Hlg,
+    Linear,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct ColorInfo {
     pub matrix: ColorMatrix,
     pub range: ColorRange,
+    pub transfer: ColorTransfer,
 }
 
 impl ColorInfo {
@@ -40,6 +93,7 @@ impl ColorInfo {
         Self {
             matrix,
             range: ColorRange::Limited,
+            transfer: ColorTransfer::Bt709,
         }
     }
 }
@@ -49,13 +103,21 @@ impl Default for ColorInfo {
         Self {
             matrix: ColorMatrix::Bt709,
             range: ColorRange::Limited,
+            transfer: ColorTransfer::Bt709,
         }
     }
 }
 
+// Owned and Planes are copies the frame owns outright; Planes holds one
+// buffer per plane in the format's plane order. Gst is a reference to a
+// buffer from the capture pipeline's pool, possibly backed by a dmabuf: the
+// pool can't reuse it while any clone of the frame is alive, so holding on
+// to frames stalls the capture once the pool runs dry. Its bytes are read
+// with `map_readable` and laid out like Owned.
 #[derive(Clone, Debug)]
 pub enum FrameData {
     Owned(Vec<u8>),
+    Planes(Vec<Vec<u8>>),
     #[cfg(target_os = "linux")]
     Gst(gstreamer::Buffer),
 }
@@ -67,6 +129,13 @@ pub struct VideoFrame {
     pub format: VideoFormat,
     pub stride: usize,
     pub uv_stride: usize,
+    // Byte offsets of the Y and chroma planes within a single buffer, when
+    // they don't simply follow each other. NV12 and P010 use the first two (Y, UV),
+    // I420 all three (Y, U, V).
+    pub plane_offsets: Option<[usize; 3]>,
     pub color: ColorInfo,
     pub data: FrameData,
+    // When the capture thread received the frame; its age on upload is the
+    // latency the app itself adds.
+    pub captured_at: Instant,
 }
@@ -0,0 +1,146 @@
+// How the viewer shows the picture; none of this reaches the capture side.
+
+// Resampling used when the video is scaled to the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScaleFilter {
+    Linear,
+    CatmullRom,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScaleMode {
+    Fit,
+    NoUpscale,
+    // Largest whole multiple of the source size that fits, sampled nearest.
+    Integer,
+}
+
+// Picture shape on screen: the source's own ratio, a forced display ratio
+// for sources captured in the wrong frame (e.g. 4:3 letterboxed into 16:9),
+// or the whole window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AspectMode {
+    Auto,
+    Force4x3,
+    Force16x9,
+    Stretch,
+}
+
+impl AspectMode {
+    // Width over height to show a picture whose own ratio is `source`;
+    // None fills the window.
+    pub fn ratio(self, source: f32) -> Option<f32> {
+        match self {
+            AspectMode::Auto => Some(source),
+            AspectMode::Force4x3 => Some(4.0 / 3.0),
+            AspectMode::Force16x9 => Some(16.0 / 9.0),
+            AspectMode::Stretch => None,
+        }
+    }
+}
+
+// How closely a monitor mode must fit the capture for exclusive fullscreen:
+// only its exact size, or the smallest larger mode of the same shape.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModeMatch {
+    Exact,
+    Nearest,
+}
+
+// Clockwise rotation applied to the displayed video.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl Rotation {
+    pub fn quarter_turns(self) -> usize {
+        match self {
+            Rotation::Deg0 => 0,
+            Rotation::Deg90 => 1,
+            Rotation::Deg180 => 2,
+            Rotation::Deg270 => 3,
+        }
+    }
+
+    // 90/270 put the source's width along the screen's vertical axis.
+    pub fn swaps_axes(self) -> bool {
+        self.quarter_turns() % 2 == 1
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CropRect {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+impl CropRect {
+    // Keeps at least one visible source pixel on each axis.
+    pub fn clamped(self, width: u32, height: u32) -> Self {
+        let max_x = width.saturating_sub(1);
+        let max_y = height.saturating_sub(1);
+        let left = self.left.min(max_x);
+        let top = self.top.min(max_y);
+        Self {
+            left,
+            top,
+            right: self.right.min(max_x - left),
+            bottom: self.bottom.min(max_y - top),
+        }
+    }
+}
+
+// Software picture adjustments applied after YUV->RGB conversion.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageAdjust {
+    pub brightness: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+}
+
+impl Default for ImageAdjust {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrtMask {
+    None,
+    // Vertical RGB stripes, as on Trinitron tubes.
+    ApertureGrille,
+    // Triads staggered from row to row.
+    ShadowMask,
+}
+
+// Built-in CRT look drawn as a pass over the finished picture.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CrtSettings {
+    pub scanlines: f32,
+    pub mask: CrtMask,
+    pub mask_strength: f32,
+    pub bloom: f32,
+    pub curvature: bool,
+}
+
+impl Default for CrtSettings {
+    fn default() -> Self {
+        Self {
+            scanlines: 0.5,
+            mask: CrtMask::ApertureGrille,
+            mask_strength: 0.3,
+            bloom: 0.2,
+            curvature: false,
+        }
+    }
+}